@@ -13,9 +13,11 @@
  * program. If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::io::IsTerminal;
 use std::path::PathBuf;
 
 use clap::Args;
+use clap::ValueEnum;
 
 #[derive(Args, Debug)]
 #[command(visible_alias = "c")]
@@ -23,13 +25,74 @@ pub struct CompileArgs {
     #[arg(short, long, help = "Disable language features", value_delimiter = ',', num_args = 1.., value_name = "FEATURE")]
     pub disable_features: Vec<String>,
 
+    #[arg(short, long, help = "Enable language features", value_delimiter = ',', num_args = 1.., value_name = "FEATURE")]
+    pub enable_features: Vec<String>,
+
     #[arg(short, long, help = "Output file", value_name = "FILE")]
     pub output: Option<PathBuf>,
 
+    #[arg(long, help = "When to use colors in diagnostics", value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    #[arg(long, help = "Format of the diagnostics", value_enum, default_value_t = MessageFormat::Human)]
+    pub message_format: MessageFormat,
+
+    #[arg(long, help = "What to emit for each source file", value_enum, default_value_t = Emit::Bytecode)]
+    pub emit: Emit,
+
     #[arg(help = "Input source file(s)")]
     pub files: Vec<PathBuf>,
 }
 
+/// The format in which the diagnostics are printed.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum MessageFormat {
+    /// Human-readable messages, along with the source code they point to.
+    Human,
+
+    /// One JSON object per diagnostic, on a separate line.
+    Json,
+}
+
+/// What the compiler emits for each source file.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Emit {
+    /// Write the bytecode to a `.ykb` file next to the source file.
+    Bytecode,
+
+    /// Print the IR from which the bytecode is lowered to the standard output, instead of writing
+    /// the bytecode.
+    Ir,
+}
+
+/// When to use colors in the output.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ColorChoice {
+    /// Use colors only if the output is a terminal.
+    Auto,
+
+    /// Always use colors.
+    Always,
+
+    /// Never use colors.
+    Never,
+}
+
+impl ColorChoice {
+    /// Enable or disable colored output according to this choice.
+    pub fn apply(&self) {
+        match self {
+            ColorChoice::Auto => {
+                if !std::io::stderr().is_terminal() {
+                    colored::control::set_override(false);
+                }
+            }
+            ColorChoice::Always => colored::control::set_override(true),
+            ColorChoice::Never => colored::control::set_override(false),
+        }
+    }
+}
+
 #[derive(Args, Debug)]
 #[command(visible_alias = "d")]
 pub struct DisassembleArgs {
@@ -143,11 +143,13 @@ pub enum NodeType {
     BreakStmt,
     ContinueStmt,
     EmptyStmt,
+    ErrorStmt,
     AssignExpr,
     CompoundAssignExpr,
     BinaryExpr,
     UnaryExpr,
     FuncCallExpr,
+    LambdaExpr,
     MemberAccessExpr,
     IdentifierExpr,
     LiteralExpr,
@@ -212,6 +214,9 @@ def_node!(FuncDecl {
     name: IdentifierExpr,
     params: Vec<IdentifierExpr>,
     body: BlockStmt,
+
+    // the receiver of the function if it is a method, `None` otherwise
+    this: Option<IdentifierExpr>,
 });
 
 def_node!(DeclStmt {
@@ -231,10 +236,14 @@ def_enum!(Stmt {
     Break: BreakStmt,
     Continue: ContinueStmt,
     Empty: EmptyStmt,
+    Error: ErrorStmt,
 });
 
 def_node!(EmptyStmt {});
 
+// a statement or a declaration which could not be parsed, spanning the skipped source
+def_node!(ErrorStmt {});
+
 def_node!(BreakStmt {
     label: Option<IdentifierExpr>
 });
@@ -246,6 +255,9 @@ def_node!(ContinueStmt {
 def_node!(VarStmt {
     name: IdentifierExpr,
     initializer: Option<Expr>,
+
+    // whether the variable is declared with `const`, and cannot be reassigned
+    is_const: bool,
 });
 
 def_node!(BlockStmt {
@@ -291,6 +303,7 @@ def_enum!(Expr {
     Binary: Box<BinaryExpr>,
     Unary: Box<UnaryExpr>,
     FuncCall: Box<FuncCallExpr>,
+    Lambda: Box<LambdaExpr>,
     MemberAccess: Box<MemberAccessExpr>,
     Identifier: IdentifierExpr,
     Literal: LiteralExpr,
@@ -334,6 +347,11 @@ def_node!(FuncCallExpr {
     args: Vec<Expr>,
 });
 
+def_node!(LambdaExpr {
+    params: Vec<IdentifierExpr>,
+    body: BlockStmt,
+});
+
 def_node!(MemberAccessExpr {
     receiver: Expr,
     member: IdentifierExpr,
@@ -341,7 +359,10 @@ def_node!(MemberAccessExpr {
 
 def_node!(IdentifierExpr {
     name: String,
-    typ: IdentifierType
+    typ: IdentifierType,
+
+    // whether the variable declared by this identifier is captured by a closure
+    captured: bool,
 });
 
 impl IdentifierExpr {
@@ -606,11 +627,13 @@ impl_node!(ReturnStmt);
 impl_node!(VarStmt);
 impl_node!(BlockStmt);
 impl_node!(EmptyStmt);
+impl_node!(ErrorStmt);
 impl_node!(AssignExpr);
 impl_node!(CompoundAssignExpr);
 impl_node!(BinaryExpr);
 impl_node!(UnaryExpr);
 impl_node!(FuncCallExpr);
+impl_node!(LambdaExpr);
 impl_node!(MemberAccessExpr);
 impl_node!(IdentifierExpr);
 impl_node!(GroupingExpr);
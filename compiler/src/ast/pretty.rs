@@ -23,6 +23,7 @@ use crate::ast::ForStmt;
 use crate::ast::FuncCallExpr;
 use crate::ast::FuncDecl;
 use crate::ast::IfStmt;
+use crate::ast::LambdaExpr;
 use crate::ast::MemberAccessExpr;
 use crate::ast::PrintStmt;
 use crate::ast::Program;
@@ -96,6 +97,10 @@ impl<'a> ASTPrinter<'a> {
                 self.f.write_str("call ").unwrap();
                 self.visit_func_call_expr(func_call_expr, indent_level);
             }
+            Expr::Lambda(lambda_expr) => {
+                self.f.write_str("lambda ").unwrap();
+                self.visit_lambda_expr(lambda_expr, indent_level);
+            }
             Expr::MemberAccess(member_access_expr) => {
                 self.f.write_str("member ").unwrap();
                 self.visit_member_access_expr(member_access_expr, indent_level);
@@ -121,6 +126,19 @@ impl<'a> ASTPrinter<'a> {
         self.f.write_str(")").unwrap();
     }
 
+    fn print_params(&mut self, params: &Vec<IdentifierExpr>) {
+        self.f.write_str("(").unwrap();
+        let mut first = true;
+        for param in params {
+            if !first {
+                self.f.write_str(", ").unwrap();
+            }
+            first = false;
+            self.f.write_str(&param.name).unwrap();
+        }
+        self.f.write_str(") ").unwrap();
+    }
+
     fn print_decl(&mut self, decl: &mut Decl, indent_level: &mut usize) {
         self.f.write_str("(decl ").unwrap();
         match decl {
@@ -174,7 +192,9 @@ impl<'a> ASTPrinter<'a> {
                 self.visit_while_stmt(while_stmt, indent_level);
             }
             Stmt::Var(var_decl) => {
-                self.f.write_str("var ").unwrap();
+                self.f
+                    .write_str(if var_decl.is_const { "const " } else { "var " })
+                    .unwrap();
                 self.visit_var_stmt(var_decl, indent_level);
             }
             Stmt::Block(block_stmt) => {
@@ -190,6 +210,7 @@ impl<'a> ASTPrinter<'a> {
                 self.visit_continue_stmt(cont, indent_level);
             }
             Stmt::Empty(_) => {}
+            Stmt::Error(_) => self.f.write_str("error").unwrap(),
         }
         self.f.write_str(")").unwrap();
     }
@@ -240,16 +261,7 @@ impl<'a> ASTVisitor<usize, ()> for ASTPrinter<'a> {
         indent_level: &mut usize,
     ) -> Option<()> {
         self.f.write_str(&func_decl.name.name).unwrap();
-        self.f.write_str("(").unwrap();
-        let mut first = true;
-        for param in &func_decl.params {
-            if !first {
-                self.f.write_str(", ").unwrap();
-            }
-            first = false;
-            self.f.write_str(&param.name).unwrap();
-        }
-        self.f.write_str(") ").unwrap();
+        self.print_params(&func_decl.params);
         self.visit_block_stmt(&mut func_decl.body, &mut indent_level.add(1));
         None
     }
@@ -412,6 +424,16 @@ impl<'a> ASTVisitor<usize, ()> for ASTPrinter<'a> {
         None
     }
 
+    fn visit_lambda_expr(
+        &mut self,
+        lambda_expr: &mut LambdaExpr,
+        indent_level: &mut usize,
+    ) -> Option<()> {
+        self.print_params(&lambda_expr.params);
+        self.visit_block_stmt(&mut lambda_expr.body, &mut indent_level.add(1));
+        None
+    }
+
     fn visit_member_access_expr(
         &mut self,
         member_access_expr: &mut MemberAccessExpr,
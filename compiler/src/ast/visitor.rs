@@ -23,6 +23,7 @@ use crate::ast::FuncDecl;
 use crate::ast::GroupingExpr;
 use crate::ast::IdentifierExpr;
 use crate::ast::IfStmt;
+use crate::ast::LambdaExpr;
 use crate::ast::LiteralExpr;
 use crate::ast::MemberAccessExpr;
 use crate::ast::PrintStmt;
@@ -37,7 +38,7 @@ use crate::ast::{ArrayAccessExpr, Decl};
 use crate::ast::{ArrayExpr, ClassDecl};
 use crate::ast::{AssignExpr, BreakStmt, ContinueStmt};
 use crate::ast::{BinaryExpr, CompoundAssignExpr};
-use crate::ast::{BlockStmt, EmptyStmt};
+use crate::ast::{BlockStmt, EmptyStmt, ErrorStmt};
 
 /// ASTVisitor for visiting AST nodes. Methods in the visitor result an [Option<R>]. If the result
 /// is [Some], then the child nodes of the AST node will not be visited.
@@ -104,6 +105,7 @@ pub trait ASTVisitor<P, R> {
             Stmt::Break(br) => self.visit_break_stmt(br, p),
             Stmt::Continue(cont) => self.visit_continue_stmt(cont, p),
             Stmt::Empty(empty) => self.visit_empty_stmt(empty, p),
+            Stmt::Error(error) => self.visit_error_stmt(error, p),
         }
     }
 
@@ -117,6 +119,7 @@ pub trait ASTVisitor<P, R> {
             Expr::Binary(bin) => self.visit_binary_expr(bin, p),
             Expr::Unary(un) => self.visit_unary_expr(un, p),
             Expr::FuncCall(func) => self.visit_func_call_expr(func, p),
+            Expr::Lambda(lambda) => self.visit_lambda_expr(lambda, p),
             Expr::MemberAccess(acc) => self.visit_member_access_expr(acc, p),
             Expr::Identifier(exp) => self.visit_identifier_expr(exp, p),
             Expr::Literal(exp) => self.visit_literal_expr(exp, p),
@@ -342,6 +345,13 @@ pub trait ASTVisitor<P, R> {
         None
     }
 
+    fn visit_error_stmt(&mut self, error_stmt: &mut ErrorStmt, p: &mut P) -> Option<R> {
+        self.default_visit_error_stmt(error_stmt, p)
+    }
+    fn default_visit_error_stmt(&mut self, _error_stmt: &mut ErrorStmt, _p: &mut P) -> Option<R> {
+        None
+    }
+
     fn visit_assign_expr(&mut self, assign_expr: &mut AssignExpr, p: &mut P) -> Option<R> {
         self.default_visit_assign_expr(assign_expr, p)
     }
@@ -413,6 +423,21 @@ pub trait ASTVisitor<P, R> {
         None
     }
 
+    fn visit_lambda_expr(&mut self, lambda_expr: &mut LambdaExpr, p: &mut P) -> Option<R> {
+        self.default_visit_lambda_expr(lambda_expr, p)
+    }
+    fn default_visit_lambda_expr(&mut self, lambda_expr: &mut LambdaExpr, p: &mut P) -> Option<R> {
+        for i in 0..lambda_expr.params.len() {
+            let param = lambda_expr.params.get_mut(i).unwrap();
+            let r = self.visit_identifier_expr(param, p);
+            if r.is_some() {
+                return r;
+            }
+        }
+
+        self.visit_block_stmt(&mut lambda_expr.body, p)
+    }
+
     fn visit_member_access_expr(
         &mut self,
         member_access_expr: &mut MemberAccessExpr,
@@ -425,12 +450,12 @@ pub trait ASTVisitor<P, R> {
         member_access_expr: &mut MemberAccessExpr,
         p: &mut P,
     ) -> Option<R> {
-        let r = self.visit_identifier_expr(&mut member_access_expr.member, p);
+        let r = self.visit_expr(&mut member_access_expr.receiver, p);
         if r.is_some() {
             return r;
         }
 
-        self.visit_expr(&mut member_access_expr.receiver, p)
+        self.visit_identifier_expr(&mut member_access_expr.member, p)
     }
 
     fn visit_grouping_expr(&mut self, grouping: &mut GroupingExpr, _p: &mut P) -> Option<R> {
@@ -484,6 +509,11 @@ pub trait ASTVisitor<P, R> {
         array_expr: &mut ArrayAccessExpr,
         p: &mut P,
     ) -> Option<R> {
+        let r = self.visit_expr(array_expr.array.as_mut(), p);
+        if r.is_some() {
+            return r;
+        }
+
         self.visit_expr(array_expr.index.as_mut(), p)
     }
 }
@@ -521,11 +551,13 @@ impl_visitables!(
     BreakStmt,
     ContinueStmt,
     EmptyStmt,
+    ErrorStmt,
     AssignExpr,
     CompoundAssignExpr,
     BinaryExpr,
     UnaryExpr,
     FuncCallExpr,
+    LambdaExpr,
     MemberAccessExpr,
     GroupingExpr,
     IdentifierExpr,
@@ -26,10 +26,12 @@ pub const OP_SIZE: CodeSize = size_of::<OpSize>() as CodeSize;
 
 pub const CODE: &str = "Code";
 pub const SOURCE_FILE: &str = "SourceFile";
+pub const LINE_NUMBER_TABLE: &str = "LineNumberTable";
 
 castable_enum!(pub enum Attr {
     Code: Code,
     SourceFile: SourceFile,
+    LineNumberTable: LineNumberTable,
 });
 
 impl Attr {
@@ -37,6 +39,7 @@ impl Attr {
         match self {
             Attr::Code(_) => CODE,
             Attr::SourceFile(_) => SOURCE_FILE,
+            Attr::LineNumberTable(_) => LINE_NUMBER_TABLE,
         }
     }
 }
@@ -103,3 +106,84 @@ impl SourceFile {
         return SourceFile { name_index };
     }
 }
+
+/// The LineNumberTable attribute maps the instructions in the [Code] attribute with which it is
+/// declared to the lines in the source file.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct LineNumberTable {
+    entries: Vec<LineNumber>,
+}
+
+/// An entry in the [LineNumberTable]. The instructions starting at `start_pc`, up to the
+/// `start_pc` of the next entry, were generated for the source line `line`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct LineNumber {
+    pub start_pc: CodeSize,
+    pub line: u32,
+}
+
+impl LineNumberTable {
+    /// Create a new, empty [LineNumberTable].
+    pub fn new() -> Self {
+        return Self::with_entries(Vec::with_capacity(0));
+    }
+
+    /// Create a new [LineNumberTable] with the given entries. The entries must be sorted by
+    /// their `start_pc`.
+    pub fn with_entries(entries: Vec<LineNumber>) -> Self {
+        return LineNumberTable { entries };
+    }
+
+    /// Get the entries in this table.
+    pub fn entries(&self) -> &Vec<LineNumber> {
+        return &self.entries;
+    }
+
+    /// Returns whether this table is empty.
+    pub fn is_empty(&self) -> bool {
+        return self.entries.is_empty();
+    }
+
+    /// Map the instructions starting at `start_pc` to the given line.
+    pub fn push(&mut self, start_pc: CodeSize, line: u32) {
+        if let Some(last) = self.entries.last_mut() {
+            if last.line == line {
+                return;
+            }
+
+            if last.start_pc == start_pc {
+                last.line = line;
+                return;
+            }
+        }
+
+        self.entries.push(LineNumber { start_pc, line });
+    }
+
+    /// Update the entries after `len` bytes of instructions are removed at `from`.
+    pub fn remove_insns(&mut self, from: CodeSize, len: CodeSize) {
+        for entry in self.entries.iter_mut() {
+            if entry.start_pc >= from + len {
+                entry.start_pc -= len;
+            } else if entry.start_pc > from {
+                entry.start_pc = from;
+            }
+        }
+
+        // the entries which now start at the same address are mapped to the last of their lines
+        let entries = std::mem::take(&mut self.entries);
+        for entry in entries {
+            self.push(entry.start_pc, entry.line);
+        }
+    }
+
+    /// Get the line for the instruction at the given address.
+    pub fn line_at(&self, pc: CodeSize) -> Option<u32> {
+        return self
+            .entries
+            .iter()
+            .take_while(|entry| entry.start_pc <= pc)
+            .last()
+            .map(|entry| entry.line);
+    }
+}
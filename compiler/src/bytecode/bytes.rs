@@ -37,8 +37,13 @@ impl<R: Read> ByteInput<R> {
 
 impl<R: Read> ByteInput<R> {
     pub fn read_n_bytes(&mut self, count: usize) -> Result<Vec<u8>, Error> {
-        let mut out = vec![0u8; count];
-        let read = self.reader.read(&mut out)?;
+        // the count may come from a malformed file, so the bytes are not allocated up-front
+        let mut out = Vec::new();
+        let read = self
+            .reader
+            .by_ref()
+            .take(count as u64)
+            .read_to_end(&mut out)?;
         if read != count {
             return Err(Error::new(
                 ErrorKind::UnexpectedEof,
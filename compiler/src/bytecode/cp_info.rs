@@ -52,7 +52,7 @@ impl From<&str> for Utf8Info {
 
 impl Display for Utf8Info {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        return write!(f, "{}", String::from_utf8(self.bytes.clone()).unwrap());
+        return write!(f, "{}", String::from_utf8_lossy(&self.bytes));
     }
 }
 
@@ -13,26 +13,75 @@
  * program. If not, see <https://www.gnu.org/licenses/>.
  */
 
+use crate::bytecode::attrs::Attr;
+use crate::bytecode::attrs::Code;
+use crate::bytecode::attrs::LineNumberTable;
 use crate::bytecode::CpSize;
 
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum YKBDeclType {
     ClassDecl,
     FuncDecl,
 }
 
+impl YKBDeclType {
+    /// The tag which identifies a class declaration in a YKB file.
+    pub const CLASS: u8 = 0x00;
+
+    /// The tag which identifies a function declaration in a YKB file.
+    pub const FUNC: u8 = 0x01;
+
+    /// Get the tag for this declaration type.
+    pub fn tag(&self) -> u8 {
+        return match self {
+            YKBDeclType::ClassDecl => Self::CLASS,
+            YKBDeclType::FuncDecl => Self::FUNC,
+        };
+    }
+}
+
 pub trait YKBDecl {
     fn dtype(&self) -> &YKBDeclType;
     fn name_index(&self) -> &CpSize;
+
+    /// Get this declaration as a [FuncDecl], if it is one.
+    fn as_func(&self) -> Option<&FuncDecl> {
+        return None;
+    }
+
+    /// Get this declaration as a [ClassDecl], if it is one.
+    fn as_class(&self) -> Option<&ClassDecl> {
+        return None;
+    }
 }
 
+#[derive(Debug, PartialEq, Clone)]
 pub struct ClassDecl {
     pub name_index: CpSize,
-    // TODO(itsaky): Add the fields, methods, etc. here.
+
+    /// The constant pool index of the name of the superclass, or `0` if the class does not
+    /// have a superclass.
+    pub super_name_index: CpSize,
+
+    /// The indices of the function declarations of the methods of the class.
+    pub methods: Vec<u16>,
 }
 
 impl ClassDecl {
-    pub fn new(name_index: CpSize) -> ClassDecl {
-        return ClassDecl { name_index };
+    /// The name of the initializer method of classes.
+    pub const INIT_NAME: &'static str = "init";
+
+    pub fn new(name_index: CpSize, super_name_index: CpSize, methods: Vec<u16>) -> ClassDecl {
+        return ClassDecl {
+            name_index,
+            super_name_index,
+            methods,
+        };
+    }
+
+    /// Returns whether the class has a superclass.
+    pub fn has_super(&self) -> bool {
+        return self.super_name_index != 0;
     }
 }
 
@@ -43,16 +92,61 @@ impl YKBDecl for ClassDecl {
     fn name_index(&self) -> &CpSize {
         return &self.name_index;
     }
+    fn as_class(&self) -> Option<&ClassDecl> {
+        return Some(self);
+    }
 }
 
+#[derive(Debug, PartialEq, Clone)]
 pub struct FuncDecl {
     pub name_index: CpSize,
-    // TODO(itsaky): Add the params, body, etc. here.
+
+    /// The constant pool indices of the names of the parameters of the function.
+    pub param_names: Vec<CpSize>,
+
+    /// The variables captured by the function from the function enclosing it.
+    pub upvalues: Vec<UpvalueInfo>,
+
+    /// The attributes of the function. A function declaration always has exactly one
+    /// [Code] attribute.
+    pub attributes: Vec<Attr>,
 }
 
 impl FuncDecl {
-    pub fn new(name_index: CpSize) -> FuncDecl {
-        return FuncDecl { name_index };
+    pub fn new(
+        name_index: CpSize,
+        param_names: Vec<CpSize>,
+        upvalues: Vec<UpvalueInfo>,
+        attributes: Vec<Attr>,
+    ) -> FuncDecl {
+        return FuncDecl {
+            name_index,
+            param_names,
+            upvalues,
+            attributes,
+        };
+    }
+
+    /// Get the number of parameters of the function.
+    pub fn arity(&self) -> u8 {
+        return self.param_names.len() as u8;
+    }
+
+    /// Get the [Code] attribute of the function.
+    pub fn code(&self) -> &Code {
+        return self
+            .attributes
+            .iter()
+            .find_map(|attr| attr.Code())
+            .expect("A function declaration must have a Code attribute");
+    }
+
+    /// Get the [LineNumberTable] attribute of the function, if any.
+    pub fn line_numbers(&self) -> Option<&LineNumberTable> {
+        return self
+            .attributes
+            .iter()
+            .find_map(|attr| attr.LineNumberTable());
     }
 }
 
@@ -63,4 +157,24 @@ impl YKBDecl for FuncDecl {
     fn name_index(&self) -> &CpSize {
         return &self.name_index;
     }
+    fn as_func(&self) -> Option<&FuncDecl> {
+        return Some(self);
+    }
+}
+
+/// Information about a variable captured by a function.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct UpvalueInfo {
+    /// Whether the captured variable is a local variable of the enclosing function. If `false`,
+    /// the variable is one of the upvalues of the enclosing function.
+    pub is_local: bool,
+
+    /// The index of the local variable or the upvalue in the enclosing function.
+    pub index: u16,
+}
+
+impl UpvalueInfo {
+    pub fn new(is_local: bool, index: u16) -> UpvalueInfo {
+        return UpvalueInfo { is_local, index };
+    }
 }
@@ -17,12 +17,14 @@ use std::fmt::Display;
 use std::fmt::Write;
 use std::io::Read;
 
-use crate::bytecode::attrs;
 use crate::bytecode::attrs::Attr;
 use crate::bytecode::attrs::Code;
+use crate::bytecode::attrs::LineNumberTable;
 use crate::bytecode::bytes::AssertingByteConversions;
 use crate::bytecode::bytes::ByteInput;
 use crate::bytecode::cp_info::CpInfo;
+use crate::bytecode::decls::YKBDecl;
+use crate::bytecode::decls::YKBDeclType;
 use crate::bytecode::opcode::get_opcode;
 use crate::bytecode::opcode::OpCode;
 use crate::bytecode::ConstantEntry;
@@ -75,14 +77,14 @@ impl<'a, R: Read> YKBDisassembler<'a, R> {
         self.indent = 0;
         self.write("========= YKB =========");
 
-        let magic = self.r.read_magic_number().unwrap();
+        let magic = self.r.read_magic_number().map_err(|err| err.to_string())?;
         if MAGIC_NUMBER != magic {
             return Err(format!("Invalid magic number: {}", magic));
         }
 
         {
             self.linindent();
-            let version = self.r.read_version().unwrap();
+            let version = self.r.read_version().map_err(|err| err.to_string())?;
             self.write("major version: ");
             self.write1(&version.major_version().to_string());
             self.linindent();
@@ -91,9 +93,19 @@ impl<'a, R: Read> YKBDisassembler<'a, R> {
         }
 
         let mut constant_pool = ConstantPool::new();
-        let constant_pool_count = self.r.read_constant_pool(&mut constant_pool).unwrap();
+        let constant_pool_count = self
+            .r
+            .read_constant_pool(&mut constant_pool)
+            .map_err(|err| err.to_string())?;
 
-        let attrs = self.r.read_attrs(&constant_pool).unwrap();
+        let decls = self
+            .r
+            .read_decls(&constant_pool)
+            .map_err(|err| err.to_string())?;
+        let attrs = self
+            .r
+            .read_attrs(&constant_pool)
+            .map_err(|err| err.to_string())?;
 
         {
             self.linindent();
@@ -103,6 +115,14 @@ impl<'a, R: Read> YKBDisassembler<'a, R> {
             self.indent -= 1;
         }
 
+        {
+            self.linindent();
+            self.write("Declarations: ");
+            self.indent += 1;
+            self.write_decls(&decls, &constant_pool);
+            self.indent -= 1;
+        }
+
         {
             self.linindent();
             self.write("Attributes: ");
@@ -133,28 +153,107 @@ impl<'a, R: Read> YKBDisassembler<'a, R> {
         }
     }
 
+    fn write_decls(&mut self, decls: &Vec<Box<dyn YKBDecl>>, constant_pool: &ConstantPool) {
+        for (index, decl) in decls.iter().enumerate() {
+            self.linindent();
+            let name = constant_pool
+                .get(*decl.name_index())
+                .and_then(|entry| entry.as_utf8())
+                .map(|utf8| utf8.to_string())
+                .unwrap_or_default();
+
+            match decl.dtype() {
+                YKBDeclType::ClassDecl => {
+                    let class = decl.as_class().unwrap();
+                    self.write1(&format!("#{}: class {}", index, name));
+                    if class.has_super() {
+                        let super_name = constant_pool
+                            .get(class.super_name_index)
+                            .and_then(|entry| entry.as_utf8())
+                            .map(|utf8| utf8.to_string())
+                            .unwrap_or_default();
+                        self.write1(&format!(" : {}", super_name));
+                    }
+                    self.write(":");
+
+                    self.indent += 1;
+                    self.linindent();
+                    self.write("methods: ");
+                    self.indent += 1;
+                    for method in &class.methods {
+                        self.linindent();
+                        self.write1(&format!("#{}", method));
+                    }
+                    self.indent -= 2;
+                    self.linefeed();
+                }
+                YKBDeclType::FuncDecl => {
+                    let func = decl.as_func().unwrap();
+                    let params = func
+                        .param_names
+                        .iter()
+                        .map(|param| {
+                            constant_pool
+                                .get(*param)
+                                .and_then(|entry| entry.as_utf8())
+                                .map(|utf8| utf8.to_string())
+                                .unwrap_or_default()
+                        })
+                        .collect::<Vec<String>>()
+                        .join(", ");
+
+                    self.write1(&format!("#{}: fun {}({}):", index, name, params));
+                    self.indent += 1;
+                    self.linindent();
+                    self.write1(&format!("arity: {}", func.arity()));
+                    if !func.upvalues.is_empty() {
+                        self.linindent();
+                        self.write("upvalues: ");
+                        self.indent += 1;
+                        for (index, upvalue) in func.upvalues.iter().enumerate() {
+                            self.linindent();
+                            self.write1(&format!(
+                                "#{}: {} {}",
+                                index,
+                                if upvalue.is_local { "local" } else { "upvalue" },
+                                upvalue.index
+                            ));
+                        }
+                        self.indent -= 1;
+                    }
+                    self.write_attrs(&func.attributes, constant_pool);
+                    self.indent -= 1;
+                    self.linefeed();
+                }
+            }
+        }
+    }
+
     fn write_attrs(&mut self, attrs: &Vec<Attr>, constant_pool: &ConstantPool) {
+        // the line numbers are shown next to the instructions of the Code attribute
+        let line_numbers = attrs.iter().find_map(|attr| attr.LineNumberTable());
         for attr in attrs {
-            self.write_attr(attr, constant_pool);
+            self.write_attr(attr, line_numbers, constant_pool);
         }
     }
 
-    fn write_attr(&mut self, attr: &Attr, constant_pool: &ConstantPool) {
+    fn write_attr(
+        &mut self,
+        attr: &Attr,
+        line_numbers: Option<&LineNumberTable>,
+        constant_pool: &ConstantPool,
+    ) {
         self.linindent();
-        let attr_name = match attr {
-            Attr::Code(_) => attrs::CODE,
-            Attr::SourceFile(_) => attrs::SOURCE_FILE,
-        };
-
-        self.write(format!("{}: ", attr_name).as_str());
+        self.write(format!("{}: ", attr.name()).as_str());
 
         match attr {
-            Attr::Code(code) => {
-                self.write1(&format!("max_stack={}", code.max_stack()));
-                self.write1(&format!(" max_locals={}", code.max_locals()));
-
+            Attr::Code(code) => self.write_code_attr(code, line_numbers, constant_pool),
+            Attr::LineNumberTable(line_numbers) => {
                 self.indent += 1;
-                self.write_code(code, constant_pool);
+                for entry in line_numbers.entries() {
+                    self.linindent();
+                    self.write1(&format!("line {}: {}", entry.line, entry.start_pc));
+                }
                 self.indent -= 1;
             }
             Attr::SourceFile(file) => {
@@ -171,77 +270,134 @@ impl<'a, R: Read> YKBDisassembler<'a, R> {
         }
     }
 
-    fn write_code(&mut self, code: &Code, constant_pool: &ConstantPool) {
+    fn write_code_attr(
+        &mut self,
+        code: &Code,
+        line_numbers: Option<&LineNumberTable>,
+        constant_pool: &ConstantPool,
+    ) {
+        self.write1(&format!("max_stack={}", code.max_stack()));
+        self.write1(&format!(" max_locals={}", code.max_locals()));
+
+        self.indent += 1;
+        self.write_code(code, line_numbers, constant_pool);
+        self.indent -= 1;
+    }
+
+    fn write_code(
+        &mut self,
+        code: &Code,
+        line_numbers: Option<&LineNumberTable>,
+        constant_pool: &ConstantPool,
+    ) {
         let mut index: usize = 0;
         while index < code.instructions().len() {
-            let insns = code.instructions();
-            let opcode = get_opcode(insns[index]);
-
             self.linindent();
-            self.write1(&format!("{:>5}: {} ", index, opcode));
+            if let Some(line_numbers) = line_numbers {
+                let line = line_numbers
+                    .line_at(index.as_code_size())
+                    .map(|line| line.to_string())
+                    .unwrap_or_default();
+                self.write1(&format!("{:<6}", format!("L{}", line)));
+            }
 
-            index += 1;
+            let (insn, next) = disassemble_insn(code.instructions(), index, constant_pool);
+            self.write1(&insn);
+            index = next;
+        }
+    }
+}
 
-            match opcode {
-                OpCode::Pop
-                | OpCode::Nop
-                | OpCode::Halt
-                | OpCode::Add
-                | OpCode::Sub
-                | OpCode::Mult
-                | OpCode::Div
-                | OpCode::Print
-                | OpCode::BPush0
-                | OpCode::BPush1
-                | OpCode::Neg
-                | OpCode::Dup
-                | OpCode::ArrNew
-                | OpCode::ArrLd
-                | OpCode::ArrPut
-                | OpCode::Not => {}
-                OpCode::Ldc => {
-                    let const_index = (insns[index].as_u16()) << 8 | insns[index + 1] as u16;
-                    let constant = constant_pool.get(const_index).unwrap();
-                    self.write(&format!("#{:<10} // {}", const_index, constant));
-                    index += 2
-                }
-                OpCode::Load0 | OpCode::Load1 | OpCode::Load2 | OpCode::Load3 => {}
-                OpCode::Store0 | OpCode::Store1 | OpCode::Store2 | OpCode::Store3 => {}
+/// Disassemble the instruction at the given address in the instructions. Returns the
+/// disassembled instruction and the address of the next instruction.
+pub fn disassemble_insn(
+    insns: &[u8],
+    mut index: usize,
+    constant_pool: &ConstantPool,
+) -> (String, usize) {
+    let opcode = get_opcode(insns[index]);
+    let mut out = format!("{:>5}: {} ", index, opcode);
 
-                OpCode::Load | OpCode::Store => {
-                    self.write_16(insns, index);
-                    index += 2;
-                }
-                OpCode::IfEq
-                | OpCode::IfEqZ
-                | OpCode::IfNe
-                | OpCode::IfNeZ
-                | OpCode::IfLt
-                | OpCode::IfLtZ
-                | OpCode::IfLe
-                | OpCode::IfLeZ
-                | OpCode::IfGt
-                | OpCode::IfGtZ
-                | OpCode::IfGe
-                | OpCode::IfGeZ
-                | OpCode::IfTruthy
-                | OpCode::IfFalsy
-                | OpCode::Jmp => {
-                    let idx = ((insns[index].as_u16() << 8) | insns[index + 1].as_u16()) as i16;
-                    index += 2;
-                    self.write(&format!(
-                        "{}",
-                        index
-                            .checked_add_signed(idx as isize)
-                            .expect(&format!("Invalid jump address: too big: {}", idx))
-                    ));
-                }
-            }
+    index += 1;
+
+    match opcode {
+        OpCode::Pop
+        | OpCode::Nop
+        | OpCode::Halt
+        | OpCode::Add
+        | OpCode::Sub
+        | OpCode::Mult
+        | OpCode::Div
+        | OpCode::Print
+        | OpCode::BPush0
+        | OpCode::BPush1
+        | OpCode::Neg
+        | OpCode::Dup
+        | OpCode::ArrNew
+        | OpCode::ArrLd
+        | OpCode::ArrPut
+        | OpCode::Inherit
+        | OpCode::Method
+        | OpCode::NPush
+        | OpCode::Ret
+        | OpCode::Not => {}
+        OpCode::Load0 | OpCode::Load1 | OpCode::Load2 | OpCode::Load3 => {}
+        OpCode::Store0 | OpCode::Store1 | OpCode::Store2 | OpCode::Store3 => {}
+
+        OpCode::Load
+        | OpCode::Store
+        | OpCode::GLoad
+        | OpCode::GStore
+        | OpCode::FnLoad
+        | OpCode::Closure
+        | OpCode::UpvLoad
+        | OpCode::UpvStore
+        | OpCode::CellNew
+        | OpCode::CellLoad
+        | OpCode::CellStore
+        | OpCode::ClsNew => {
+            out.push_str(&format!("{}", read_u16(insns, index)));
+            index += 2;
+        }
+        OpCode::Ldc | OpCode::GetField | OpCode::PutField | OpCode::GetSuper | OpCode::NatLoad => {
+            let const_index = read_u16(insns, index);
+            let constant = constant_pool.get(const_index).unwrap();
+            out.push_str(&format!("#{:<10} // {}", const_index, constant));
+            index += 2
+        }
+        OpCode::Call => {
+            out.push_str(&format!("{}", insns[index]));
+            index += 1;
+        }
+        OpCode::IfEq
+        | OpCode::IfEqZ
+        | OpCode::IfNe
+        | OpCode::IfNeZ
+        | OpCode::IfLt
+        | OpCode::IfLtZ
+        | OpCode::IfLe
+        | OpCode::IfLeZ
+        | OpCode::IfGt
+        | OpCode::IfGtZ
+        | OpCode::IfGe
+        | OpCode::IfGeZ
+        | OpCode::IfTruthy
+        | OpCode::IfFalsy
+        | OpCode::Jmp => {
+            let idx = read_u16(insns, index) as i16;
+            index += 2;
+            out.push_str(&format!(
+                "{}",
+                index
+                    .checked_add_signed(idx as isize)
+                    .expect(&format!("Invalid jump address: too big: {}", idx))
+            ));
         }
     }
 
-    fn write_16(&mut self, insns: &Vec<u8>, index: usize) {
-        let idx = (insns[index].as_u16() << 8) | insns[index + 1].as_u16();
-        self.write(&format!("{}", idx))
-    }
+    return (out, index);
+}
+
+fn read_u16(insns: &[u8], index: usize) -> u16 {
+    return (insns[index].as_u16() << 8) | insns[index + 1].as_u16();
 }
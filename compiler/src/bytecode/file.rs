@@ -18,11 +18,14 @@ use std::io::{Error, Write};
 
 use crate::bytecode;
 use crate::bytecode::attrs::Attr;
+use crate::bytecode::attrs::Code;
+use crate::bytecode::attrs::LineNumberTable;
 use crate::bytecode::bytes::AssertingByteConversions;
 use crate::bytecode::bytes::ByteOutput;
 use crate::bytecode::cp::ConstantPool;
 use crate::bytecode::cp_info::{CpInfoTag, Utf8Info};
 use crate::bytecode::decls::YKBDecl;
+use crate::bytecode::decls::YKBDeclType;
 use crate::bytecode::version::YKBVersion;
 use crate::bytecode::ConstantEntry;
 
@@ -96,6 +99,7 @@ impl YKBFile {
         size += writer.write_u16(self.version.major_version())?;
         size += writer.write_u16(self.version.minor_version())?;
         size += self.write_constant_pool(writer)?;
+        size += self.write_decls(writer)?;
         size += self.write_attrs(writer)?;
         Ok(size)
     }
@@ -136,6 +140,45 @@ impl YKBFile {
         Ok(size)
     }
 
+    fn write_decls<W: Write>(&mut self, writer: &mut ByteOutput<W>) -> Result<usize, Error> {
+        let decls = self.declarations();
+        let mut size = writer.write_u16(decls.len().as_u16())?;
+        for decl in decls.iter() {
+            size += writer.write_u8(decl.dtype().tag())?;
+            size += writer.write_u16(*decl.name_index())?;
+
+            match decl.dtype() {
+                YKBDeclType::ClassDecl => {
+                    let class = decl.as_class().unwrap();
+                    size += writer.write_u16(class.super_name_index)?;
+                    size += writer.write_u16(class.methods.len().as_u16())?;
+                    for method in &class.methods {
+                        size += writer.write_u16(*method)?;
+                    }
+                }
+                YKBDeclType::FuncDecl => {
+                    let func = decl.as_func().unwrap();
+                    size += writer.write_u8(func.arity())?;
+                    for param_name in &func.param_names {
+                        size += writer.write_u16(*param_name)?;
+                    }
+
+                    size += writer.write_u16(func.upvalues.len().as_u16())?;
+                    for upvalue in &func.upvalues {
+                        size += writer.write_u8(upvalue.is_local as u8)?;
+                        size += writer.write_u16(upvalue.index)?;
+                    }
+
+                    size += writer.write_u16(func.attributes.len().as_u16())?;
+                    for attr in &func.attributes {
+                        size += self.write_attr(attr, writer)?;
+                    }
+                }
+            }
+        }
+        Ok(size)
+    }
+
     fn write_attrs<W: Write>(&mut self, writer: &mut ByteOutput<W>) -> Result<usize, Error> {
         let attrs = self.attributes();
         let mut size = writer.write_u16(attrs.len().as_u16())?;
@@ -151,25 +194,55 @@ impl YKBFile {
         attr: &Attr,
         writer: &mut ByteOutput<W>,
     ) -> Result<usize, Error> {
-        let name_index = self
-            .constant_pool()
-            .lookup(&ConstantEntry::Utf8(Utf8Info::from(attr.name())))
-            .expect(format!("Could not find {} in constant pool", attr.name()).as_str());
-
-        let mut size = writer.write_u16(name_index)?;
+        let mut size = self.write_attr_name(attr.name(), writer)?;
 
         match attr {
             Attr::Code(code) => {
-                size += writer.write_u16(code.max_stack())?;
-                size += writer.write_u16(code.max_locals())?;
-                size += writer.write_u32(code.instructions().len().as_code_size())?;
-                size += writer.write_bytes(code.instructions())?;
+                size += self.write_code(code, writer)?;
             }
             Attr::SourceFile(source_file) => {
                 size += writer.write_u16(source_file.name_index)?;
             }
+            Attr::LineNumberTable(line_numbers) => {
+                size += self.write_line_numbers(line_numbers, writer)?;
+            }
         };
 
         Ok(size)
     }
+
+    fn write_attr_name<W: Write>(
+        &self,
+        name: &str,
+        writer: &mut ByteOutput<W>,
+    ) -> Result<usize, Error> {
+        let name_index = self
+            .constant_pool()
+            .lookup(&ConstantEntry::Utf8(Utf8Info::from(name)))
+            .expect(format!("Could not find {} in constant pool", name).as_str());
+
+        writer.write_u16(name_index)
+    }
+
+    fn write_line_numbers<W: Write>(
+        &self,
+        line_numbers: &LineNumberTable,
+        writer: &mut ByteOutput<W>,
+    ) -> Result<usize, Error> {
+        let entries = line_numbers.entries();
+        let mut size = writer.write_u16(entries.len().as_u16())?;
+        for entry in entries {
+            size += writer.write_u32(entry.start_pc)?;
+            size += writer.write_u32(entry.line)?;
+        }
+        Ok(size)
+    }
+
+    fn write_code<W: Write>(&self, code: &Code, writer: &mut ByteOutput<W>) -> Result<usize, Error> {
+        let mut size = writer.write_u16(code.max_stack())?;
+        size += writer.write_u16(code.max_locals())?;
+        size += writer.write_u32(code.instructions().len().as_code_size())?;
+        size += writer.write_bytes(code.instructions())?;
+        Ok(size)
+    }
 }
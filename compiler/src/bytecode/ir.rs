@@ -0,0 +1,552 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+use std::collections::HashSet;
+
+use crate::bytecode::attrs::CodeSize;
+use crate::bytecode::attrs::LineNumberTable;
+use crate::bytecode::bytes::AssertingByteConversions;
+use crate::bytecode::cp::ConstantPool;
+use crate::bytecode::opcode::OpCode;
+use crate::bytecode::opcode::OpCodeExt;
+
+/// A label which names a [BasicBlock]. Labels can be created before the block they name is
+/// placed in the code, so that forward jumps can refer to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Label(usize);
+
+/// The operand of an [IrInsn].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operand {
+    /// The operand of a non-jump instruction, `0` if the instruction has no operand.
+    Value(u16),
+
+    /// The block to which a jump instruction jumps.
+    Target(Label),
+}
+
+/// An instruction in the IR.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IrInsn {
+    pub opcode: OpCode,
+    pub operand: Operand,
+
+    /// The source line for which the instruction was written, if any.
+    pub line: Option<u32>,
+}
+
+/// A reference to an instruction in the IR, which stays valid until the IR is optimized.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InsnRef {
+    block: Label,
+    index: usize,
+}
+
+/// The error returned when the code is lowered, if a jump cannot reach its target as the offset
+/// does not fit in the operand of the jump.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JumpOutOfRange {
+    /// The source line of the jump, if any.
+    pub line: Option<u32>,
+}
+
+/// A sequence of instructions which is only entered at its first instruction. A block ends with
+/// at most one jump, `ret` or `halt`, and falls through to the next block in the layout unless
+/// it ends with an unconditional jump, `ret` or `halt`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BasicBlock {
+    pub insns: Vec<IrInsn>,
+}
+
+impl IrInsn {
+    /// Get the block this jump instruction jumps to, or [None] if this is not a jump.
+    pub fn target(&self) -> Option<Label> {
+        return match self.operand {
+            Operand::Target(label) => Some(label),
+            Operand::Value(_) => None,
+        };
+    }
+
+    /// Get the number of bytes taken by this instruction once it is lowered.
+    pub fn size(&self) -> CodeSize {
+        return 1 + self.opcode.operand_size() as CodeSize;
+    }
+}
+
+impl BasicBlock {
+    /// Whether this block ends with an instruction after which no other instruction can be
+    /// written to the block.
+    fn is_terminated(&self) -> bool {
+        return self.insns.last().is_some_and(|insn| {
+            insn.opcode.is_jmp() || insn.opcode == OpCode::Ret || insn.opcode == OpCode::Halt
+        });
+    }
+}
+
+/// The code of a function (or of the top-level statements) as a control flow graph of
+/// [BasicBlock]s with symbolic jump targets. The instructions are written to the current block,
+/// and the blocks are laid out in the order in which they are placed with [IrCode::bind].
+#[derive(Debug, Clone, PartialEq)]
+pub struct IrCode {
+    /// The blocks, indexed by their labels.
+    blocks: Vec<BasicBlock>,
+
+    /// The labels of the blocks which are placed in the code, in the order of their layout.
+    layout: Vec<Label>,
+
+    /// The block to which the instructions are written.
+    current: Label,
+
+    /// The source line of the instructions written from now on.
+    line: Option<u32>,
+}
+
+impl IrCode {
+    /// Create new, empty code with an entry block.
+    pub fn new() -> Self {
+        return IrCode {
+            blocks: vec![BasicBlock::default()],
+            layout: vec![Label(0)],
+            current: Label(0),
+            line: None,
+        };
+    }
+
+    /// Get the blocks of this code, in the order of their layout.
+    pub fn blocks(&self) -> impl Iterator<Item = (Label, &BasicBlock)> {
+        return self
+            .layout
+            .iter()
+            .map(|label| (*label, &self.blocks[label.0]));
+    }
+
+    /// Get the labels of the blocks of this code, in the order of their layout.
+    pub fn labels(&self) -> Vec<Label> {
+        return self.layout.clone();
+    }
+
+    /// Get the block named by the given label.
+    pub fn block(&self, label: Label) -> &BasicBlock {
+        return &self.blocks[label.0];
+    }
+
+    /// Get the block named by the given label.
+    pub fn block_mut(&mut self, label: Label) -> &mut BasicBlock {
+        return &mut self.blocks[label.0];
+    }
+
+    /// Get the instruction which is executed first when the code jumps to the given label, or
+    /// [None] if the code ends there.
+    pub fn insn_at(&self, label: Label) -> Option<&IrInsn> {
+        return self
+            .resolve_label(label)
+            .map(|label| &self.blocks[label.0].insns[0]);
+    }
+
+    /// Get the instruction which is executed after the last instruction of the block named by the
+    /// given label, if that instruction does not jump. Returns [None] if the code ends there.
+    pub fn insn_after(&self, label: Label) -> Option<&IrInsn> {
+        return self
+            .resolve(self.position(label) + 1)
+            .map(|label| &self.blocks[label.0].insns[0]);
+    }
+
+    /// Whether no instructions have been written to this code.
+    pub fn is_empty(&self) -> bool {
+        return self.blocks.iter().all(|block| block.insns.is_empty());
+    }
+
+    /// Create a label for a new block. The block must be placed with [IrCode::bind] before the
+    /// code is lowered.
+    pub fn new_label(&mut self) -> Label {
+        self.blocks.push(BasicBlock::default());
+        return Label(self.blocks.len() - 1);
+    }
+
+    /// Place the block named by the given label after the blocks placed so far, and write the
+    /// following instructions to it.
+    pub fn bind(&mut self, label: Label) {
+        if self.layout.contains(&label) {
+            panic!("Label {:?} is already bound", label);
+        }
+
+        self.layout.push(label);
+        self.current = label;
+    }
+
+    /// Set the source line of the instructions written from now on.
+    pub fn mark_line(&mut self, line: u32) {
+        self.line = Some(line);
+    }
+
+    /// Get a reference to the instruction which will be written next.
+    pub fn pos(&mut self) -> InsnRef {
+        self.start_block_if_terminated();
+        return InsnRef {
+            block: self.current,
+            index: self.blocks[self.current.0].insns.len(),
+        };
+    }
+
+    /// Get the instruction at the given reference.
+    pub fn insn_mut(&mut self, insn: InsnRef) -> &mut IrInsn {
+        return &mut self.blocks[insn.block.0].insns[insn.index];
+    }
+
+    /// Write a non-jump instruction.
+    pub fn emit(&mut self, opcode: OpCode, operand: u16) {
+        debug_assert!(
+            !opcode.is_jmp(),
+            "Jumps must be written with IrCode::emit_jmp"
+        );
+        self.push(opcode, Operand::Value(operand));
+    }
+
+    /// Write a jump instruction which jumps to the block named by the given label.
+    pub fn emit_jmp(&mut self, opcode: OpCode, target: Label) {
+        debug_assert!(opcode.is_jmp(), "{} is not a jump", opcode);
+        self.push(opcode, Operand::Target(target));
+    }
+
+    fn push(&mut self, opcode: OpCode, operand: Operand) {
+        self.start_block_if_terminated();
+        let line = self.line;
+        self.blocks[self.current.0].insns.push(IrInsn {
+            opcode,
+            operand,
+            line,
+        });
+    }
+
+    /// The instructions after a jump are written to a new block, which is only reachable through
+    /// a jump if the jump is unconditional.
+    fn start_block_if_terminated(&mut self) {
+        if self.blocks[self.current.0].is_terminated() {
+            let label = self.new_label();
+            self.bind(label);
+        }
+    }
+
+    /// Get the position of the block named by the given label in the layout.
+    fn position(&self, label: Label) -> usize {
+        return match self.layout.iter().position(|l| *l == label) {
+            Some(position) => position,
+            None => panic!("Label {:?} is not bound", label),
+        };
+    }
+
+    /// Get the first non-empty block at or after the given position in the layout, which is the
+    /// block that is executed when the code reaches that position. Returns [None] if the code
+    /// ends at that position.
+    fn resolve(&self, position: usize) -> Option<Label> {
+        return self.layout[position..]
+            .iter()
+            .find(|label| !self.blocks[label.0].insns.is_empty())
+            .copied();
+    }
+
+    /// Get the block which is executed when the code jumps to the given label.
+    fn resolve_label(&self, label: Label) -> Option<Label> {
+        return self.resolve(self.position(label));
+    }
+
+    /// Get the blocks which may be executed after the block named by the given label.
+    fn successors(&self, label: Label) -> Vec<Label> {
+        let last = self.blocks[label.0].insns.last();
+        let mut successors = Vec::new();
+        if let Some(target) = last.and_then(IrInsn::target) {
+            successors.extend(self.resolve_label(target));
+        }
+
+        let falls_through = !last
+            .is_some_and(|insn| matches!(insn.opcode, OpCode::Jmp | OpCode::Ret | OpCode::Halt));
+        if falls_through {
+            successors.extend(self.resolve(self.position(label) + 1));
+        }
+
+        return successors;
+    }
+
+    /// Optimize the jumps in this code. Jumps to jumps are threaded to the final target, jumps
+    /// which only continue with the next block are removed and the blocks which are no longer
+    /// the target of any jump are merged into the block before them. The [InsnRef]s to this
+    /// code are no longer valid once it is optimized.
+    pub fn optimize(&mut self) {
+        self.thread_jmps();
+        self.remove_redundant_jmps();
+        self.merge_blocks();
+    }
+
+    fn thread_jmps(&mut self) {
+        for label in self.layout.clone() {
+            let Some(insn) = self.blocks[label.0].insns.last().copied() else {
+                continue;
+            };
+            let Some(mut target) = insn.target() else {
+                continue;
+            };
+
+            // the number of blocks bounds the length of the chains, so that jumps which form a
+            // cycle are left as they are
+            for _ in 0..self.blocks.len() {
+                let Some(t_block) = self.resolve_label(target) else {
+                    break;
+                };
+
+                let t_insn = &self.blocks[t_block.0].insns[0];
+                let Some(t_target) = t_insn.target() else {
+                    break;
+                };
+
+                // `iftruthy` and `iffalsy` only peek the operand, so a jump to the same
+                // conditional jump is taken as well. the comparison jumps pop their operands.
+                let same_peek = t_insn.opcode == insn.opcode
+                    && matches!(insn.opcode, OpCode::IfTruthy | OpCode::IfFalsy);
+                if t_insn.opcode != OpCode::Jmp && !same_peek {
+                    break;
+                }
+
+                target = t_target;
+            }
+
+            // jumps to empty blocks jump to the block which follows them instead, so that the
+            // empty blocks can be removed
+            let target = self.resolve_label(target).unwrap_or(target);
+            self.blocks[label.0].insns.last_mut().unwrap().operand = Operand::Target(target);
+        }
+    }
+
+    fn remove_redundant_jmps(&mut self) {
+        // removing a jump may make the jumps before it redundant
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for position in 0..self.layout.len() {
+                changed |= self.remove_redundant_jmp(position);
+            }
+        }
+    }
+
+    /// Remove the jump at the end of the block at the given position in the layout if it jumps to
+    /// the block which is executed next anyway. Returns whether the jump was removed.
+    fn remove_redundant_jmp(&mut self, position: usize) -> bool {
+        let label = self.layout[position];
+        let Some(insn) = self.blocks[label.0].insns.last().copied() else {
+            return false;
+        };
+        let Some(target) = insn.target() else {
+            return false;
+        };
+
+        // the comparison jumps pop their operands, so they cannot be removed
+        if !matches!(
+            insn.opcode,
+            OpCode::Jmp | OpCode::IfTruthy | OpCode::IfFalsy
+        ) {
+            return false;
+        }
+
+        if self.resolve_label(target) != self.resolve(position + 1) {
+            return false;
+        }
+
+        self.blocks[label.0].insns.pop();
+        return true;
+    }
+
+    /// Merge each block which is not the target of any jump into the block before it, if that
+    /// block falls through to it. The empty blocks which are not the target of any jump are
+    /// removed.
+    fn merge_blocks(&mut self) {
+        let targets: HashSet<Label> = self
+            .blocks()
+            .flat_map(|(_, block)| block.insns.iter().filter_map(IrInsn::target))
+            .collect();
+
+        let mut layout = vec![self.layout[0]];
+        for position in 1..self.layout.len() {
+            let label = self.layout[position];
+            if targets.contains(&label) {
+                layout.push(label);
+                continue;
+            }
+
+            let prev = *layout.last().unwrap();
+            if !self.blocks[prev.0].is_terminated() {
+                let insns = std::mem::take(&mut self.blocks[label.0].insns);
+                self.blocks[prev.0].insns.extend(insns);
+            } else if !self.blocks[label.0].insns.is_empty() {
+                layout.push(label);
+            }
+        }
+
+        self.layout = layout;
+        self.current = *self.layout.last().unwrap();
+    }
+
+    /// Remove the blocks which cannot be reached from the start of the code, such as the jumps
+    /// left after a branch which always exits. The empty blocks are kept, as they may still be the
+    /// target of jumps. Returns whether any block was removed.
+    pub fn eliminate_dead_code(&mut self) -> bool {
+        let mut reachable = vec![false; self.blocks.len()];
+        let mut pending: Vec<Label> = self.resolve(0).into_iter().collect();
+        while let Some(label) = pending.pop() {
+            if !reachable[label.0] {
+                reachable[label.0] = true;
+                pending.extend(self.successors(label));
+            }
+        }
+
+        let (layout, removed): (Vec<Label>, Vec<Label>) = self
+            .layout
+            .iter()
+            .partition(|label| reachable[label.0] || self.blocks[label.0].insns.is_empty());
+        for label in &removed {
+            self.blocks[label.0].insns.clear();
+        }
+
+        self.layout = layout;
+        self.current = *self.layout.last().unwrap();
+        return !removed.is_empty();
+    }
+
+    /// Compute the maximum depth of the operand stack along every path through this code.
+    pub fn max_stack(&self) -> usize {
+        let mut depths: Vec<Option<usize>> = vec![None; self.blocks.len()];
+        let mut pending = Vec::new();
+        if let Some(entry) = self.resolve(0) {
+            depths[entry.0] = Some(0);
+            pending.push(entry);
+        }
+
+        let mut max = 0;
+        while let Some(label) = pending.pop() {
+            let mut depth = depths[label.0].unwrap();
+            for insn in &self.blocks[label.0].insns {
+                let operand = match insn.operand {
+                    Operand::Value(value) => value,
+                    Operand::Target(_) => 0,
+                };
+                let (pops, pushes) = insn.opcode.stack_usage(operand);
+                depth = depth.saturating_sub(pops) + pushes;
+                max = max.max(depth);
+            }
+
+            // the depth is the same along every path to a block, so each block is visited once
+            for successor in self.successors(label) {
+                if depths[successor.0].is_none() {
+                    depths[successor.0] = Some(depth);
+                    pending.push(successor);
+                }
+            }
+        }
+
+        return max;
+    }
+
+    /// Get a human-readable dump of this code. The blocks are numbered in the order of their
+    /// layout, and the constant pool is used to show the constants the instructions refer to.
+    pub fn dump(&self, constant_pool: &ConstantPool) -> String {
+        let name = |label: Label| match self.layout.iter().position(|l| *l == label) {
+            Some(position) => format!("B{}", position),
+            None => format!("<unbound {}>", label.0),
+        };
+
+        let mut out = String::new();
+        for (label, block) in self.blocks() {
+            out.push_str(&format!("{}:\n", name(label)));
+            for insn in &block.insns {
+                let line = insn
+                    .line
+                    .map(|line| format!("L{}", line))
+                    .unwrap_or_default();
+                out.push_str(&format!("    {:<6}{}", line, insn.opcode));
+                match (insn.opcode, insn.operand) {
+                    (_, Operand::Target(target)) => out.push_str(&format!(" {}", name(target))),
+                    (
+                        OpCode::Ldc
+                        | OpCode::GetField
+                        | OpCode::PutField
+                        | OpCode::GetSuper
+                        | OpCode::NatLoad,
+                        Operand::Value(index),
+                    ) => {
+                        let constant = constant_pool
+                            .get(index)
+                            .map(|constant| constant.to_string())
+                            .unwrap_or_default();
+                        out.push_str(&format!(" #{:<10} // {}", index, constant));
+                    }
+                    (opcode, Operand::Value(value)) if opcode.operand_size() > 0 => {
+                        out.push_str(&format!(" {}", value))
+                    }
+                    (_, Operand::Value(_)) => {}
+                }
+                out.push('\n');
+            }
+        }
+
+        return out;
+    }
+
+    /// Lower this code to bytecode, and map the instructions to their source lines in the given
+    /// table. Returns the instructions, or an error if the offset of a jump does not fit in 16
+    /// bits.
+    pub fn lower(&self, line_numbers: &mut LineNumberTable) -> Result<Vec<u8>, JumpOutOfRange> {
+        // the address of each block
+        let mut addresses = vec![None; self.blocks.len()];
+        let mut pc = 0;
+        for (label, block) in self.blocks() {
+            addresses[label.0] = Some(pc);
+            pc += block.insns.iter().map(IrInsn::size).sum::<CodeSize>();
+        }
+
+        let mut bytes = Vec::with_capacity(pc as usize);
+        for (_, block) in self.blocks() {
+            for insn in &block.insns {
+                let pc = bytes.len().as_code_size();
+                if let Some(line) = insn.line {
+                    line_numbers.push(pc, line);
+                }
+
+                let operand = match insn.operand {
+                    Operand::Value(value) => value,
+                    Operand::Target(label) => {
+                        let target = match addresses[label.0] {
+                            Some(target) => target,
+                            None => panic!("Label {:?} is not bound", label),
+                        };
+                        let offset = target as i64 - pc as i64 - 3;
+                        match i16::try_from(offset) {
+                            Ok(offset) => offset as u16,
+                            Err(_) => return Err(JumpOutOfRange { line: insn.line }),
+                        }
+                    }
+                };
+
+                bytes.push(insn.opcode.as_op_size());
+                match insn.opcode.operand_size() {
+                    0 => {}
+                    1 => bytes.push(operand.as_u8()),
+                    _ => {
+                        bytes.push((operand >> 8).as_u8());
+                        bytes.push(operand as u8);
+                    }
+                }
+            }
+        }
+
+        return Ok(bytes);
+    }
+}
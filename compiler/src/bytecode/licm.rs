@@ -0,0 +1,518 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::ast::ASTVisitor;
+use crate::ast::AssignExpr;
+use crate::ast::BinaryExpr;
+use crate::ast::BinaryOp;
+use crate::ast::BlockStmt;
+use crate::ast::ClassDecl;
+use crate::ast::CompoundAssignExpr;
+use crate::ast::Decl;
+use crate::ast::Expr;
+use crate::ast::FuncCallExpr;
+use crate::ast::FuncDecl;
+use crate::ast::IdentifierExpr;
+use crate::ast::LambdaExpr;
+use crate::ast::LiteralExpr;
+use crate::ast::Program;
+use crate::ast::Stmt;
+use crate::ast::UnaryExpr;
+use crate::ast::UnaryOp;
+use crate::ast::VarStmt;
+use crate::ast::Visitable;
+
+/// Collects the names of the variables which are written (assigned or declared) in a part of the
+/// program, and whether that part calls any function.
+struct Writes {
+    names: HashSet<String>,
+    calls: bool,
+
+    /// The number of function bodies enclosing the node being visited.
+    func_depth: usize,
+
+    /// Whether only the writes in the bodies of the functions, lambdas and methods are collected.
+    only_in_funcs: bool,
+}
+
+impl Writes {
+    fn new(only_in_funcs: bool) -> Self {
+        return Writes {
+            names: HashSet::new(),
+            calls: false,
+            func_depth: 0,
+            only_in_funcs,
+        };
+    }
+
+    fn write(&mut self, name: &str) {
+        if !self.only_in_funcs || self.func_depth > 0 {
+            self.names.insert(name.to_string());
+        }
+    }
+
+    fn write_target(&mut self, target: &Expr) {
+        if let Expr::Identifier(identifier) = target {
+            self.write(&identifier.name);
+        }
+    }
+}
+
+impl ASTVisitor<(), ()> for Writes {
+    fn visit_class_decl(&mut self, class_decl: &mut ClassDecl, p: &mut ()) -> Option<()> {
+        self.write(&class_decl.name.name);
+        self.default_visit_class_decl(class_decl, p)
+    }
+
+    fn visit_func_decl(&mut self, func_decl: &mut FuncDecl, p: &mut ()) -> Option<()> {
+        self.write(&func_decl.name.name);
+        self.func_depth += 1;
+        self.default_visit_func_decl(func_decl, p);
+        self.func_depth -= 1;
+        None
+    }
+
+    fn visit_var_stmt(&mut self, var_decl: &mut VarStmt, p: &mut ()) -> Option<()> {
+        self.write(&var_decl.name.name);
+        self.default_visit_var_stmt(var_decl, p)
+    }
+
+    fn visit_assign_expr(&mut self, assign_expr: &mut AssignExpr, p: &mut ()) -> Option<()> {
+        self.write_target(&assign_expr.target);
+        self.default_visit_assign_expr(assign_expr, p)
+    }
+
+    fn visit_compound_assign_expr(
+        &mut self,
+        compound_assign_expr: &mut CompoundAssignExpr,
+        p: &mut (),
+    ) -> Option<()> {
+        self.write_target(&compound_assign_expr.target);
+        self.default_visit_compound_assign_expr(compound_assign_expr, p)
+    }
+
+    fn visit_func_call_expr(
+        &mut self,
+        func_call_expr: &mut FuncCallExpr,
+        p: &mut (),
+    ) -> Option<()> {
+        self.calls = true;
+        self.default_visit_func_call_expr(func_call_expr, p)
+    }
+
+    fn visit_lambda_expr(&mut self, lambda_expr: &mut LambdaExpr, p: &mut ()) -> Option<()> {
+        self.func_depth += 1;
+        self.default_visit_lambda_expr(lambda_expr, p);
+        self.func_depth -= 1;
+        None
+    }
+}
+
+/// Collects the values assigned to the variables of the program, to find the variables which
+/// always hold a number.
+struct Values {
+    /// The values assigned to each variable. `None` is a value which may not be a number.
+    values: HashMap<String, Vec<Option<Expr>>>,
+
+    /// The names of the parameters, functions and classes of the program.
+    others: HashSet<String>,
+}
+
+impl Values {
+    fn assign(&mut self, name: &str, value: Option<Expr>) {
+        self.values.entry(name.to_string()).or_default().push(value);
+    }
+
+    fn declare_params(&mut self, params: &[IdentifierExpr]) {
+        for param in params {
+            self.others.insert(param.name.clone());
+        }
+    }
+}
+
+impl ASTVisitor<(), ()> for Values {
+    fn visit_class_decl(&mut self, class_decl: &mut ClassDecl, p: &mut ()) -> Option<()> {
+        self.others.insert(class_decl.name.name.clone());
+        self.default_visit_class_decl(class_decl, p)
+    }
+
+    fn visit_func_decl(&mut self, func_decl: &mut FuncDecl, p: &mut ()) -> Option<()> {
+        self.others.insert(func_decl.name.name.clone());
+        self.declare_params(&func_decl.params);
+        self.default_visit_func_decl(func_decl, p)
+    }
+
+    fn visit_var_stmt(&mut self, var_decl: &mut VarStmt, p: &mut ()) -> Option<()> {
+        self.assign(&var_decl.name.name, var_decl.initializer.clone());
+        self.default_visit_var_stmt(var_decl, p)
+    }
+
+    fn visit_assign_expr(&mut self, assign_expr: &mut AssignExpr, p: &mut ()) -> Option<()> {
+        if let Expr::Identifier(identifier) = &assign_expr.target {
+            self.assign(&identifier.name, Some(assign_expr.value.clone()));
+        }
+        self.default_visit_assign_expr(assign_expr, p)
+    }
+
+    fn visit_compound_assign_expr(
+        &mut self,
+        compound_assign_expr: &mut CompoundAssignExpr,
+        p: &mut (),
+    ) -> Option<()> {
+        if let Expr::Identifier(identifier) = &compound_assign_expr.target {
+            // the variable keeps holding a number if a number is added to it
+            let value = if is_arith_op(&compound_assign_expr.op) {
+                Some(compound_assign_expr.value.clone())
+            } else {
+                None
+            };
+            self.assign(&identifier.name, value);
+        }
+        self.default_visit_compound_assign_expr(compound_assign_expr, p)
+    }
+
+    fn visit_lambda_expr(&mut self, lambda_expr: &mut LambdaExpr, p: &mut ()) -> Option<()> {
+        self.declare_params(&lambda_expr.params);
+        self.default_visit_lambda_expr(lambda_expr, p)
+    }
+}
+
+/// Get the names of the variables which are written in the bodies of the functions, lambdas and
+/// methods of the given program. Calling a function may change these variables.
+pub fn written_in_funcs(program: &mut Program) -> HashSet<String> {
+    let mut writes = Writes::new(true);
+    program.accept(&mut writes, &mut ());
+    return writes.names;
+}
+
+/// Get the names of the variables of the given program which always hold a number : the variables
+/// which are declared with a number, and which are only assigned numbers. Parameters are never
+/// known to hold a number.
+pub fn number_vars(program: &mut Program) -> HashSet<String> {
+    let mut values = Values {
+        values: HashMap::new(),
+        others: HashSet::new(),
+    };
+    program.accept(&mut values, &mut ());
+
+    let mut numbers: HashSet<String> = values
+        .values
+        .keys()
+        .filter(|name| !values.others.contains(*name))
+        .cloned()
+        .collect();
+
+    // a variable assigned the value of another variable holds a number if the other one does, so
+    // the variables are removed until all the remaining ones are only assigned numbers
+    loop {
+        let not_numbers: Vec<String> = numbers
+            .iter()
+            .filter(|name| {
+                values.values[*name].iter().any(|value| match value {
+                    Some(value) => !is_number(value, &|name| numbers.contains(name)),
+                    None => true,
+                })
+            })
+            .cloned()
+            .collect();
+
+        if not_numbers.is_empty() {
+            return numbers;
+        }
+
+        for name in not_numbers {
+            numbers.remove(&name);
+        }
+    }
+}
+
+/// Find the loop-invariant expressions of a loop with the given condition, step and body, which
+/// can be evaluated once before the loop instead of in every iteration. `written_in_funcs` are
+/// the names returned by [written_in_funcs] for the program.
+///
+/// An expression is invariant if it has no side effects and none of the variables it reads are
+/// written in the loop, or in any function if the loop calls a function. As the hoisted
+/// expressions are evaluated before the statements of the loop, only the expressions which can
+/// never raise an error are returned : these only read the local variables of the function, for
+/// which `is_local` returns `true`, and only apply the arithmetic and comparison operators to
+/// numbers. `number_vars` are the names returned by [number_vars] for the program. Returns the
+/// [key] and the expression for each distinct expression.
+pub fn invariant_exprs(
+    mut condition: Option<&mut Expr>,
+    step: Option<&mut Expr>,
+    body: &mut BlockStmt,
+    written_in_funcs: &HashSet<String>,
+    number_vars: &HashSet<String>,
+    is_local: &dyn Fn(&str) -> bool,
+) -> Vec<(String, Expr)> {
+    let mut writes = Writes::new(false);
+    if let Some(condition) = condition.as_mut() {
+        writes.visit_expr(condition, &mut ());
+    }
+    if let Some(step) = step {
+        writes.visit_expr(step, &mut ());
+    }
+    writes.visit_block_stmt(body, &mut ());
+
+    let is_invariant = |name: &str| {
+        if writes.names.contains(name) {
+            return false;
+        }
+
+        // any function may be called by the calls in the loop
+        return !writes.calls || !written_in_funcs.contains(name);
+    };
+    let is_number = |name: &str| is_local(name) && number_vars.contains(name);
+    let hoistable = Hoistable {
+        is_invariant: &is_invariant,
+        is_local,
+        is_number: &is_number,
+    };
+
+    let mut exprs = Vec::new();
+    if let Some(condition) = condition {
+        collect_expr(condition, &hoistable, &mut exprs);
+    }
+    collect_block(body, &hoistable, &mut exprs);
+    return exprs;
+}
+
+/// Get a key which identifies the value of the given expression, if the expression has no side
+/// effects and only reads the variables for which `is_invariant` returns `true`. Expressions with
+/// the same key evaluate to the same value, as long as these variables are not written.
+pub fn key(expr: &Expr, is_invariant: &dyn Fn(&str) -> bool) -> Option<String> {
+    return match expr {
+        Expr::Binary(binary) => binary_key(binary, is_invariant),
+        Expr::Unary(unary) => unary_key(unary, is_invariant),
+        Expr::Identifier(identifier) if is_invariant(&identifier.name) => {
+            Some(identifier.name.clone())
+        }
+        Expr::Literal(literal) => Some(match literal {
+            LiteralExpr::Null(_) => String::from("null"),
+            LiteralExpr::Bool((value, _)) => value.to_string(),
+            LiteralExpr::Number((value, _)) => format!("{:?}", value),
+            LiteralExpr::String((value, _)) => value.clone(),
+        }),
+        _ => None,
+    };
+}
+
+/// Same as [key], for a binary expression. The logical operators are not supported, as their
+/// right operand is not always evaluated.
+pub fn binary_key(binary: &BinaryExpr, is_invariant: &dyn Fn(&str) -> bool) -> Option<String> {
+    if binary.op == BinaryOp::And || binary.op == BinaryOp::Or {
+        return None;
+    }
+
+    let left = key(&binary.left, is_invariant)?;
+    let right = key(&binary.right, is_invariant)?;
+    return Some(format!("({} {} {})", binary.op.sym(), left, right));
+}
+
+/// Same as [key], for a unary expression.
+pub fn unary_key(unary: &UnaryExpr, is_invariant: &dyn Fn(&str) -> bool) -> Option<String> {
+    let expr = key(&unary.expr, is_invariant)?;
+    return Some(format!("({} {})", unary.op.sym(), expr));
+}
+
+/// Whether the given operator is an arithmetic operator.
+fn is_arith_op(op: &BinaryOp) -> bool {
+    return matches!(
+        op,
+        BinaryOp::Plus | BinaryOp::Minus | BinaryOp::Mult | BinaryOp::Div
+    );
+}
+
+/// Whether the given expression always evaluates to a number, if the variables for which
+/// `is_number` returns `true` hold numbers. Negating a value which is not a number results in `0`.
+fn is_number(expr: &Expr, is_number_var: &dyn Fn(&str) -> bool) -> bool {
+    return match expr {
+        Expr::Literal(LiteralExpr::Number(_)) => true,
+        Expr::Identifier(identifier) => is_number_var(&identifier.name),
+        Expr::Unary(unary) => unary.op == UnaryOp::Negate,
+        Expr::Binary(binary) => {
+            is_arith_op(&binary.op)
+                && is_number(&binary.left, is_number_var)
+                && is_number(&binary.right, is_number_var)
+        }
+        _ => false,
+    };
+}
+
+/// The conditions for hoisting an expression out of a loop.
+struct Hoistable<'a> {
+    /// Whether a variable is not written in the loop.
+    is_invariant: &'a dyn Fn(&str) -> bool,
+
+    /// Whether a variable is a local variable of the function, which can always be loaded.
+    is_local: &'a dyn Fn(&str) -> bool,
+
+    /// Whether a variable is a local variable which always holds a number.
+    is_number: &'a dyn Fn(&str) -> bool,
+}
+
+impl Hoistable<'_> {
+    /// Get the [key] of the given expression if it can be hoisted out of the loop.
+    fn key(&self, expr: &Expr) -> Option<String> {
+        let key = match expr {
+            Expr::Binary(binary) => binary_key(binary, self.is_invariant),
+            Expr::Unary(unary) => unary_key(unary, self.is_invariant),
+            _ => None,
+        };
+
+        return key.filter(|_| self.never_fails(expr));
+    }
+
+    /// Whether evaluating the given expression can never raise an error. Any value can be
+    /// compared for equality and negated, but the other operators fail for some operands.
+    fn never_fails(&self, expr: &Expr) -> bool {
+        return match expr {
+            Expr::Literal(_) => true,
+            Expr::Identifier(identifier) => (self.is_local)(&identifier.name),
+            Expr::Unary(unary) => self.never_fails(&unary.expr),
+            Expr::Binary(binary) if binary.op == BinaryOp::EqEq || binary.op == BinaryOp::NotEq => {
+                self.never_fails(&binary.left) && self.never_fails(&binary.right)
+            }
+            Expr::Binary(binary) => {
+                is_number(&binary.left, self.is_number)
+                    && is_number(&binary.right, self.is_number)
+                    && self.never_fails(&binary.left)
+                    && self.never_fails(&binary.right)
+            }
+            _ => false,
+        };
+    }
+}
+
+/// Collect the invariant expressions in the given expression, which is evaluated in every
+/// iteration. The operands of an invariant expression are not collected separately.
+fn collect_expr(expr: &Expr, hoistable: &Hoistable, exprs: &mut Vec<(String, Expr)>) {
+    if let Some(key) = hoistable.key(expr) {
+        if !exprs.iter().any(|(k, _)| *k == key) {
+            exprs.push((key, expr.clone()));
+        }
+        return;
+    }
+
+    match expr {
+        // the right operand of a logical expression is not always evaluated
+        Expr::Binary(binary) if binary.op == BinaryOp::And || binary.op == BinaryOp::Or => {
+            collect_expr(&binary.left, hoistable, exprs);
+        }
+        Expr::Binary(binary) => {
+            collect_expr(&binary.left, hoistable, exprs);
+            collect_expr(&binary.right, hoistable, exprs);
+        }
+        Expr::Unary(unary) => collect_expr(&unary.expr, hoistable, exprs),
+        Expr::Assign(assign) => collect_expr(&assign.value, hoistable, exprs),
+        Expr::CompoundAssign(assign) => collect_expr(&assign.value, hoistable, exprs),
+        Expr::FuncCall(call) => {
+            collect_expr(&call.callee, hoistable, exprs);
+            for arg in &call.args {
+                collect_expr(arg, hoistable, exprs);
+            }
+        }
+        Expr::MemberAccess(access) => collect_expr(&access.receiver, hoistable, exprs),
+        Expr::Array(array) => {
+            for element in &array.elements {
+                collect_expr(element, hoistable, exprs);
+            }
+        }
+        Expr::ArrayAccess(access) => {
+            collect_expr(&access.array, hoistable, exprs);
+            collect_expr(&access.index, hoistable, exprs);
+        }
+        Expr::Lambda(_) | Expr::Identifier(_) | Expr::Literal(_) => {}
+    }
+}
+
+/// Collect the invariant expressions in the statements of the given block, up to the first
+/// statement which may skip the statements after it. Returns whether all the statements of the
+/// block were visited.
+fn collect_block(
+    block: &BlockStmt,
+    hoistable: &Hoistable,
+    exprs: &mut Vec<(String, Expr)>,
+) -> bool {
+    for decl in &block.decls {
+        if let Decl::Stmt(stmt) = decl {
+            if !collect_stmt(stmt, hoistable, exprs) {
+                return false;
+            }
+        }
+    }
+
+    return true;
+}
+
+/// Collect the invariant expressions in the given statement. Returns whether the statement always
+/// continues with the statement after it.
+fn collect_stmt(stmt: &Stmt, hoistable: &Hoistable, exprs: &mut Vec<(String, Expr)>) -> bool {
+    return match stmt {
+        Stmt::Expr(expr_stmt) => {
+            collect_expr(&expr_stmt.expr, hoistable, exprs);
+            true
+        }
+        Stmt::Print(print_stmt) => {
+            collect_expr(&print_stmt.expr, hoistable, exprs);
+            true
+        }
+        Stmt::Var(var_stmt) => {
+            if let Some(initializer) = var_stmt.initializer.as_ref() {
+                collect_expr(initializer, hoistable, exprs);
+            }
+            true
+        }
+        Stmt::Return(return_stmt) => {
+            collect_expr(&return_stmt.expr, hoistable, exprs);
+            false
+        }
+
+        // only the condition of an `if` statement is always evaluated
+        Stmt::If(if_stmt) => {
+            collect_expr(&if_stmt.condition, hoistable, exprs);
+            !may_exit(&if_stmt.then_branch) && !if_stmt.else_branch.as_ref().is_some_and(may_exit)
+        }
+        Stmt::Block(block) => collect_block(block, hoistable, exprs),
+        Stmt::While(while_stmt) => !may_exit(&while_stmt.body),
+        Stmt::For(for_stmt) => !may_exit(&for_stmt.body),
+        Stmt::Break(_) | Stmt::Continue(_) => false,
+        Stmt::Empty(_) | Stmt::Error(_) => true,
+    };
+}
+
+/// Whether the given block contains a `break`, `continue` or `return` statement, outside of the
+/// functions declared in it.
+fn may_exit(block: &BlockStmt) -> bool {
+    return block.decls.iter().any(|decl| match decl {
+        Decl::Stmt(stmt) => stmt_may_exit(stmt),
+        Decl::Func(_) | Decl::Class(_) => false,
+    });
+}
+
+fn stmt_may_exit(stmt: &Stmt) -> bool {
+    return match stmt {
+        Stmt::Break(_) | Stmt::Continue(_) | Stmt::Return(_) => true,
+        Stmt::If(if_stmt) => {
+            may_exit(&if_stmt.then_branch) || if_stmt.else_branch.as_ref().is_some_and(may_exit)
+        }
+        Stmt::Block(block) => may_exit(block),
+        Stmt::While(while_stmt) => may_exit(&while_stmt.body),
+        Stmt::For(for_stmt) => may_exit(&for_stmt.body),
+        Stmt::Expr(_) | Stmt::Print(_) | Stmt::Var(_) | Stmt::Empty(_) | Stmt::Error(_) => false,
+    };
+}
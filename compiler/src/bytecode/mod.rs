@@ -1,6 +1,7 @@
 pub use cp::ConstantEntry;
 pub use cp::ConstantPool;
 pub use cp::CpSize;
+pub use disassembler::disassemble_insn;
 pub use disassembler::YKBDisassembler;
 pub use file::YKBFile;
 pub use reader::YKBFileReader;
@@ -21,10 +22,13 @@ pub mod attrs;
 pub mod bytes;
 mod cp;
 pub mod cp_info;
-mod decls;
+pub mod decls;
 mod disassembler;
 mod file;
+mod ir;
+mod licm;
 pub mod opcode;
+mod peephole;
 mod reader;
 mod version;
 mod writer;
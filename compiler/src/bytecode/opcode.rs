@@ -63,9 +63,18 @@ macro_rules! def_opcodes {
         }
 
         pub fn get_opcode(code: OpSize) -> OpCode {
+            match try_get_opcode(code) {
+                Some(opcode) => opcode,
+                None => unreachable!("Unknown/unsupported opcode: {:?}", code),
+            }
+        }
+
+        /// Get the opcode for the given instruction byte, or [None] if the byte is not a
+        /// valid opcode.
+        pub fn try_get_opcode(code: OpSize) -> Option<OpCode> {
             match code {
-                $($code => OpCode::$name,)+
-                _ => unreachable!("Unknown/unsupported opcode: {:?}", code),
+                $($code => Some(OpCode::$name),)+
+                _ => None,
             }
         }
 
@@ -87,6 +96,67 @@ impl OpCode {
     pub fn as_op_size(self) -> OpSize {
         return self as OpSize;
     }
+
+    /// Get the number of operands popped and pushed by an instruction with this opcode and the
+    /// given operand, as executed by the VM. Unlike [OpCodeExt::stack_effect], this accounts for
+    /// the operands of the conditional jumps and the arguments of the calls.
+    pub fn stack_usage(self, operand: u16) -> (usize, usize) {
+        return match self {
+            OpCode::Nop | OpCode::Halt | OpCode::Jmp => (0, 0),
+            OpCode::Add | OpCode::Sub | OpCode::Mult | OpCode::Div => (2, 1),
+            OpCode::IfEq
+            | OpCode::IfNe
+            | OpCode::IfLt
+            | OpCode::IfLe
+            | OpCode::IfGt
+            | OpCode::IfGe => (2, 0),
+            OpCode::IfEqZ
+            | OpCode::IfNeZ
+            | OpCode::IfLtZ
+            | OpCode::IfLeZ
+            | OpCode::IfGtZ
+            | OpCode::IfGeZ => (1, 0),
+
+            // the conditional jumps only peek the operand
+            OpCode::IfTruthy | OpCode::IfFalsy => (1, 1),
+            OpCode::Ldc
+            | OpCode::BPush0
+            | OpCode::BPush1
+            | OpCode::Load
+            | OpCode::Load0
+            | OpCode::Load1
+            | OpCode::Load2
+            | OpCode::Load3
+            | OpCode::NPush
+            | OpCode::GLoad
+            | OpCode::FnLoad
+            | OpCode::Closure
+            | OpCode::UpvLoad
+            | OpCode::CellLoad
+            | OpCode::ClsNew
+            | OpCode::NatLoad => (0, 1),
+            OpCode::Print
+            | OpCode::Pop
+            | OpCode::Store
+            | OpCode::Store0
+            | OpCode::Store1
+            | OpCode::Store2
+            | OpCode::Store3
+            | OpCode::GStore
+            | OpCode::UpvStore
+            | OpCode::CellNew
+            | OpCode::CellStore
+            | OpCode::Ret => (1, 0),
+            OpCode::Neg | OpCode::Not | OpCode::ArrNew | OpCode::GetField => (1, 1),
+            OpCode::Dup => (1, 2),
+            OpCode::ArrLd | OpCode::Inherit | OpCode::Method | OpCode::GetSuper => (2, 1),
+            OpCode::ArrPut => (3, 0),
+            OpCode::PutField => (2, 0),
+
+            // the callee and the arguments are popped and the result is pushed
+            OpCode::Call => (operand as usize + 1, 1),
+        };
+    }
 }
 
 // format: {name, opcode, stack_effect, mnemonic, operand_size, [, is_jmp]}
@@ -142,6 +212,29 @@ def_opcodes!(
   {ArrNew,      0x27,   0,  "arrnew"    , 0},
   {ArrLd,       0x28,  -1,  "arrld"     , 0},
   {ArrPut,      0x29,  -3,  "arrput"    , 0},
+  {NPush,       0x2A,   1,  "npush"     , 0},
+  {GLoad,       0x2B,   1,  "gload"     , 2},
+  {GStore,      0x2C,  -1,  "gstore"    , 2},
+  {FnLoad,      0x2D,   1,  "fnload"    , 2},
+
+    // the stack effect of `call` depends on the number of arguments, which is specified as the
+    // operand to the instruction. The callee and its arguments are popped and the result of the
+    // call is pushed, so the actual stack effect is `-argc`
+  {Call,        0x2E,   0,  "call"      , 1},
+  {Ret,         0x2F,  -1,  "ret"       , 0},
+  {Closure,     0x30,   1,  "closure"   , 2},
+  {UpvLoad,     0x31,   1,  "upvload"   , 2},
+  {UpvStore,    0x32,  -1,  "upvstore"  , 2},
+  {CellNew,     0x33,  -1,  "cellnew"   , 2},
+  {CellLoad,    0x34,   1,  "cellload"  , 2},
+  {CellStore,   0x35,  -1,  "cellstore" , 2},
+  {ClsNew,      0x36,   1,  "clsnew"    , 2},
+  {Inherit,     0x37,  -1,  "inherit"   , 0},
+  {Method,      0x38,  -1,  "method"    , 0},
+  {GetField,    0x39,   0,  "getfield"  , 2},
+  {PutField,    0x3A,  -2,  "putfield"  , 2},
+  {GetSuper,    0x3B,  -1,  "getsuper"  , 2},
+  {NatLoad,     0x3C,   1,  "natload"   , 2},
 );
 
 impl Display for OpCode {
@@ -151,16 +244,17 @@ impl Display for OpCode {
 }
 
 /// Get the opcode for the given binary comparison operator. This returns the opcode which checks
-/// whether the binary comparison is **FALSE**. So, for example, if the binary expr is `true == false`,
-/// this function will return `IfNe` so the VM will execute the else branch.
+/// whether the binary comparison is **TRUE**. So, for example, if the binary expr is `a < b`,
+/// this function will return `IfLt`. The inverse opcode (`IfGe`) must not be used to check that the
+/// comparison is false, as any ordered comparison with `NaN` is false.
 pub fn opcode_cmp(op: &BinaryOp) -> OpCode {
     match op {
-        BinaryOp::EqEq => OpCode::IfNe,
-        BinaryOp::NotEq => OpCode::IfEq,
-        BinaryOp::Gt => OpCode::IfLe,
-        BinaryOp::GtEq => OpCode::IfLt,
-        BinaryOp::Lt => OpCode::IfGe,
-        BinaryOp::LtEq => OpCode::IfGt,
+        BinaryOp::EqEq => OpCode::IfEq,
+        BinaryOp::NotEq => OpCode::IfNe,
+        BinaryOp::Gt => OpCode::IfGt,
+        BinaryOp::GtEq => OpCode::IfGe,
+        BinaryOp::Lt => OpCode::IfLt,
+        BinaryOp::LtEq => OpCode::IfLe,
         _ => unreachable!("opcode_cmp is not implemented for {:?}", op),
     }
 }
@@ -168,12 +262,12 @@ pub fn opcode_cmp(op: &BinaryOp) -> OpCode {
 /// Same as `opcode_cmp`, but for when one of the operands is **ZERO**.
 pub fn opcode_cmpz(op: &BinaryOp) -> OpCode {
     match op {
-        BinaryOp::EqEq => OpCode::IfNeZ,
-        BinaryOp::NotEq => OpCode::IfEqZ,
-        BinaryOp::Gt => OpCode::IfLeZ,
-        BinaryOp::GtEq => OpCode::IfLtZ,
-        BinaryOp::Lt => OpCode::IfGeZ,
-        BinaryOp::LtEq => OpCode::IfGtZ,
+        BinaryOp::EqEq => OpCode::IfEqZ,
+        BinaryOp::NotEq => OpCode::IfNeZ,
+        BinaryOp::Gt => OpCode::IfGtZ,
+        BinaryOp::GtEq => OpCode::IfGeZ,
+        BinaryOp::Lt => OpCode::IfLtZ,
+        BinaryOp::LtEq => OpCode::IfLeZ,
         _ => unreachable!("opcode_cmpz is not implemented for {:?}", op),
     }
 }
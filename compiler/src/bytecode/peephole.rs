@@ -0,0 +1,230 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use log::trace;
+
+use crate::bytecode::ir::IrCode;
+use crate::bytecode::ir::IrInsn;
+use crate::bytecode::ir::Label;
+use crate::bytecode::ir::Operand;
+use crate::bytecode::opcode::OpCode;
+
+/// A rule of the peephole optimizer, which rewrites a window of consecutive instructions in a
+/// [crate::bytecode::ir::BasicBlock].
+struct Rule {
+    /// The name of the rule.
+    name: &'static str,
+
+    /// The opcodes accepted at each position of the window.
+    pattern: &'static [&'static [OpCode]],
+
+    /// Rewrite the matched window in the block named by the given label, or return [None] if the
+    /// window must be kept as it is.
+    rewrite: fn(&[IrInsn], &IrCode, Label) -> Option<Vec<IrInsn>>,
+}
+
+const PUSHES: &[OpCode] = &[OpCode::Ldc, OpCode::BPush0, OpCode::BPush1, OpCode::NPush];
+const CONST_PUSHES: &[OpCode] = &[OpCode::BPush0, OpCode::BPush1, OpCode::NPush];
+const STORES: &[OpCode] = &[
+    OpCode::Store,
+    OpCode::Store0,
+    OpCode::Store1,
+    OpCode::Store2,
+    OpCode::Store3,
+];
+const LOADS: &[OpCode] = &[
+    OpCode::Load,
+    OpCode::Load0,
+    OpCode::Load1,
+    OpCode::Load2,
+    OpCode::Load3,
+];
+const CONDITIONAL_JMPS: &[OpCode] = &[OpCode::IfTruthy, OpCode::IfFalsy];
+
+static RULES: &[Rule] = &[
+    Rule {
+        name: "store-load",
+        pattern: &[STORES, LOADS],
+        rewrite: store_load,
+    },
+    Rule {
+        name: "dup-pop",
+        pattern: &[&[OpCode::Dup], &[OpCode::Pop]],
+        rewrite: remove,
+    },
+    Rule {
+        name: "push-pop",
+        pattern: &[PUSHES, &[OpCode::Pop]],
+        rewrite: remove,
+    },
+    Rule {
+        name: "not-jmp",
+        pattern: &[&[OpCode::Not], CONDITIONAL_JMPS],
+        rewrite: not_jmp,
+    },
+    Rule {
+        name: "const-jmp",
+        pattern: &[CONST_PUSHES, CONDITIONAL_JMPS],
+        rewrite: const_jmp,
+    },
+];
+
+impl Rule {
+    /// Try to apply this rule to the window starting at the given index in the block named by the
+    /// given label.
+    fn apply(&self, code: &IrCode, label: Label, index: usize) -> Option<Vec<IrInsn>> {
+        let window = code
+            .block(label)
+            .insns
+            .get(index..index + self.pattern.len())?;
+        let matches = window
+            .iter()
+            .zip(self.pattern)
+            .all(|(insn, opcodes)| opcodes.contains(&insn.opcode));
+        if !matches {
+            return None;
+        }
+
+        return (self.rewrite)(window, code, label);
+    }
+}
+
+/// Run the peephole optimizer on the blocks of the given code until none of the rules applies.
+/// Only the first instruction of a block can be the target of a jump, so the windows never span
+/// more than one block. The jumps are optimized with [IrCode::optimize] after each round, as the
+/// rewritten jumps may let blocks merge into new windows. Returns whether any instruction was
+/// rewritten.
+pub fn optimize(code: &mut IrCode) -> bool {
+    let mut changed = false;
+    while optimize_blocks(code) {
+        code.optimize();
+        changed = true;
+    }
+
+    return changed;
+}
+
+/// Apply the rules to each block until none of them applies. Returns whether any rule applied.
+fn optimize_blocks(code: &mut IrCode) -> bool {
+    let mut changed = false;
+    for label in code.labels() {
+        while let Some(insns) = optimize_once(code, label) {
+            code.block_mut(label).insns = insns;
+            changed = true;
+        }
+    }
+
+    return changed;
+}
+
+/// Apply the rules once over the block named by the given label. Returns [None] if no rule
+/// applied.
+fn optimize_once(code: &IrCode, label: Label) -> Option<Vec<IrInsn>> {
+    let insns = &code.block(label).insns;
+    let mut result = Vec::with_capacity(insns.len());
+    let mut changed = false;
+    let mut index = 0;
+
+    'insns: while index < insns.len() {
+        for rule in RULES {
+            if let Some(rewritten) = rule.apply(code, label, index) {
+                trace!("[Peephole] Applied {} in {:?}", rule.name, label);
+                result.extend(rewritten);
+                index += rule.pattern.len();
+                changed = true;
+                continue 'insns;
+            }
+        }
+
+        result.push(insns[index]);
+        index += 1;
+    }
+
+    return if changed { Some(result) } else { None };
+}
+
+/// Get the local variable slot used by the given load or store instruction.
+fn slot(insn: &IrInsn) -> Option<u16> {
+    return match (insn.opcode, insn.operand) {
+        (OpCode::Load0 | OpCode::Store0, _) => Some(0),
+        (OpCode::Load1 | OpCode::Store1, _) => Some(1),
+        (OpCode::Load2 | OpCode::Store2, _) => Some(2),
+        (OpCode::Load3 | OpCode::Store3, _) => Some(3),
+        (_, Operand::Value(slot)) => Some(slot),
+        (_, Operand::Target(_)) => None,
+    };
+}
+
+/// `store_n; load_n` -> `dup; store_n`
+fn store_load(window: &[IrInsn], _: &IrCode, _: Label) -> Option<Vec<IrInsn>> {
+    let (store, load) = (window[0], window[1]);
+    if slot(&store) != slot(&load) {
+        return None;
+    }
+
+    return Some(vec![
+        IrInsn {
+            opcode: OpCode::Dup,
+            operand: Operand::Value(0),
+            line: store.line,
+        },
+        IrInsn {
+            line: load.line,
+            ..store
+        },
+    ]);
+}
+
+/// `dup; pop` or `ldc; pop` -> nothing
+fn remove(_: &[IrInsn], _: &IrCode, _: Label) -> Option<Vec<IrInsn>> {
+    return Some(vec![]);
+}
+
+/// `not; iffalsy` -> `iftruthy` and `not; iftruthy` -> `iffalsy`
+///
+/// The conditional jumps leave the negated value on the operand stack, so the rule only applies
+/// when the value is popped right away on both the branches.
+fn not_jmp(window: &[IrInsn], code: &IrCode, label: Label) -> Option<Vec<IrInsn>> {
+    let jmp = window[1];
+    let is_pop = |insn: Option<&IrInsn>| insn.is_some_and(|insn| insn.opcode == OpCode::Pop);
+    if !is_pop(code.insn_after(label)) || !is_pop(code.insn_at(jmp.target()?)) {
+        return None;
+    }
+
+    let opcode = match jmp.opcode {
+        OpCode::IfTruthy => OpCode::IfFalsy,
+        _ => OpCode::IfTruthy,
+    };
+
+    return Some(vec![IrInsn { opcode, ..jmp }]);
+}
+
+/// `bpush_1; iftruthy` -> `bpush_1; jmp` and `bpush_1; iffalsy` -> `bpush_1`, and the same for
+/// the falsy `bpush_0` and `npush`.
+fn const_jmp(window: &[IrInsn], _: &IrCode, _: Label) -> Option<Vec<IrInsn>> {
+    let (push, jmp) = (window[0], window[1]);
+    let truthy = push.opcode == OpCode::BPush1;
+    if truthy != (jmp.opcode == OpCode::IfTruthy) {
+        return Some(vec![push]);
+    }
+
+    return Some(vec![
+        push,
+        IrInsn {
+            opcode: OpCode::Jmp,
+            ..jmp
+        },
+    ]);
+}
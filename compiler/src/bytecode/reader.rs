@@ -20,6 +20,9 @@ use std::io::Read;
 use util::result::map_err;
 
 use crate::bytecode::cp_info::CpInfoTag;
+use crate::bytecode::decls;
+use crate::bytecode::decls::YKBDecl;
+use crate::bytecode::decls::YKBDeclType;
 use crate::bytecode::cp_info::NumberInfo;
 use crate::bytecode::cp_info::StringInfo;
 use crate::bytecode::cp_info::Utf8Info;
@@ -52,6 +55,9 @@ impl<R: Read> YKBFileReader<R> {
         let mut file = YKBFile::new(version);
 
         self.read_constant_pool(file.constant_pool_mut())?;
+        let decls = self.read_decls(file.constant_pool())?;
+        file.declarations_mut().extend(decls);
+
         let attrs = self.read_attrs(file.constant_pool())?;
         file.attributes_mut().clear();
         file.attributes_mut().extend(attrs);
@@ -123,6 +129,84 @@ impl<R: Read> YKBFileReader<R> {
         Ok(ConstantEntry::String(StringInfo::new(string_index)))
     }
 
+    pub fn read_decls(
+        &mut self,
+        constant_pool: &ConstantPool,
+    ) -> Result<Vec<Box<dyn YKBDecl>>, Error> {
+        let count = map_err(self.buf.read_u16(), "Unable to read declaration count")?;
+        let mut decls: Vec<Box<dyn YKBDecl>> = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let decl = map_err(self.read_decl(constant_pool), "Unable to read declaration")?;
+            decls.push(decl);
+        }
+
+        return Ok(decls);
+    }
+
+    pub fn read_decl(&mut self, constant_pool: &ConstantPool) -> Result<Box<dyn YKBDecl>, Error> {
+        let tag = map_err(self.buf.read_u8(), "Unable to read declaration tag")?;
+        let name_index = map_err(self.buf.read_u16(), "Unable to read declaration name index")?;
+
+        match tag {
+            YKBDeclType::CLASS => {
+                let super_name_index =
+                    map_err(self.buf.read_u16(), "Unable to read superclass name index")?;
+                let method_count = map_err(self.buf.read_u16(), "Unable to read method count")?;
+                let mut methods = Vec::with_capacity(method_count as usize);
+                for _ in 0..method_count {
+                    let method = map_err(self.buf.read_u16(), "Unable to read method index")?;
+                    methods.push(method);
+                }
+
+                Ok(Box::new(decls::ClassDecl::new(
+                    name_index,
+                    super_name_index,
+                    methods,
+                )))
+            }
+            YKBDeclType::FUNC => {
+                let arity = map_err(self.buf.read_u8(), "Unable to read function arity")?;
+                let mut param_names = Vec::with_capacity(arity as usize);
+                for _ in 0..arity {
+                    let param_name =
+                        map_err(self.buf.read_u16(), "Unable to read parameter name index")?;
+                    param_names.push(param_name);
+                }
+
+                let upvalue_count = map_err(self.buf.read_u16(), "Unable to read upvalue count")?;
+                let mut upvalues = Vec::with_capacity(upvalue_count as usize);
+                for _ in 0..upvalue_count {
+                    let is_local = map_err(self.buf.read_u8(), "Unable to read upvalue kind")?;
+                    let index = map_err(self.buf.read_u16(), "Unable to read upvalue index")?;
+                    upvalues.push(decls::UpvalueInfo::new(is_local != 0, index));
+                }
+
+                let attrs = self.read_attrs(constant_pool)?;
+                let code_count = attrs
+                    .iter()
+                    .filter(|attr| attr.name() == attrs::CODE)
+                    .count();
+                if code_count != 1 {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        "Expected exactly one Code attribute for function declaration",
+                    ));
+                }
+
+                Ok(Box::new(decls::FuncDecl::new(
+                    name_index,
+                    param_names,
+                    upvalues,
+                    attrs,
+                )))
+            }
+            _ => Err(Error::new(
+                ErrorKind::InvalidData,
+                "Invalid declaration tag",
+            )),
+        }
+    }
+
     pub fn read_attrs(&mut self, constant_pool: &ConstantPool) -> Result<Vec<attrs::Attr>, Error> {
         let count = map_err(self.buf.read_u16(), "Unable to read attribute count")?;
         let mut attrs = Vec::with_capacity(count as usize);
@@ -137,13 +221,18 @@ impl<R: Read> YKBFileReader<R> {
     pub fn read_attr(&mut self, constant_pool: &ConstantPool) -> Result<attrs::Attr, Error> {
         let name_index: CpSize =
             map_err(self.buf.read_u16(), "Unable to read attribute name index")?;
-        let info = constant_pool
-            .get(name_index)
-            .map(|entry| entry.as_utf8().unwrap())
-            .expect(&format!(
-                "Expected a Utf8Info entry at constant pool index {}",
-                name_index
-            ));
+        let info = match constant_pool.get(name_index).and_then(|entry| entry.as_utf8()) {
+            Some(info) => info,
+            None => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "Expected a Utf8Info entry at constant pool index {}",
+                        name_index
+                    ),
+                ))
+            }
+        };
 
         let name = info.to_string();
 
@@ -160,8 +249,21 @@ impl<R: Read> YKBFileReader<R> {
                     map_err(self.buf.read_u16(), "Unable to read source file name index")?;
                 attrs::Attr::SourceFile(attrs::SourceFile::new(name_index))
             }
+            attrs::LINE_NUMBER_TABLE => {
+                let count = map_err(self.buf.read_u16(), "Unable to read line number count")?;
+                let mut entries = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    let start_pc = map_err(self.buf.read_u32(), "Unable to read start pc")?;
+                    let line = map_err(self.buf.read_u32(), "Unable to read line number")?;
+                    entries.push(attrs::LineNumber { start_pc, line });
+                }
+                attrs::Attr::LineNumberTable(attrs::LineNumberTable::with_entries(entries))
+            }
             _ => {
-                panic!("Unknown attribute: {}", name);
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Unknown attribute: {}", name),
+                ))
             }
         };
 
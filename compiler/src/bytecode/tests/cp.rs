@@ -70,7 +70,7 @@ fn test_cp_push_from_program() {
     let features = CompilerFeatures::default();
     let mut file = YKBFile::new(YKBVersion::LATEST.clone());
     let mut writer = YKBFileWriter::new(&mut file, &features);
-    writer.write(&mut program);
+    writer.write(&mut program).unwrap();
     let constant_pool = file.constant_pool();
 
     assert!(5 <= constant_pool.len());
@@ -0,0 +1,325 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+use std::io::Cursor;
+
+use crate::bytecode::attrs::LineNumber;
+use crate::bytecode::attrs::LineNumberTable;
+use crate::bytecode::ir::IrCode;
+use crate::bytecode::ir::JumpOutOfRange;
+use crate::bytecode::opcode::OpCode;
+use crate::bytecode::opcode::OpSize;
+use crate::bytecode::ConstantPool;
+use crate::comp::YKCompiler;
+use crate::features::CompilerFeatures;
+
+fn optimize_and_lower(code: &mut IrCode) -> Vec<OpSize> {
+    code.optimize();
+    return code.lower(&mut LineNumberTable::new()).unwrap();
+}
+
+fn dump_ir(source: &str) -> String {
+    let features = CompilerFeatures::default();
+    let mut compiler = YKCompiler::new();
+    let (mut program, has_errors) = compiler.parse(Cursor::new(source)).unwrap();
+    assert!(!has_errors);
+
+    compiler.attr(&mut program, &features);
+    return compiler.dump_ir(&mut program, &features).unwrap();
+}
+
+#[test]
+fn test_jmp_offsets() {
+    let mut code = IrCode::new();
+    let else_branch = code.new_label();
+    let end = code.new_label();
+    code.emit(OpCode::Load0, 0);
+    code.emit_jmp(OpCode::IfFalsy, else_branch);
+    code.emit(OpCode::Ldc, 1);
+    code.emit_jmp(OpCode::Jmp, end);
+    code.bind(else_branch);
+    code.emit(OpCode::Ldc, 2);
+    code.bind(end);
+    code.emit(OpCode::Print, 0);
+
+    #[rustfmt::skip]
+    assert_eq!(
+        vec![
+            OpCode::Load0 as OpSize,
+            OpCode::IfFalsy as OpSize, 0x00, 0x06, // 10
+            OpCode::Ldc as OpSize, 0x00, 0x01,
+            OpCode::Jmp as OpSize, 0x00, 0x03, // 13
+            OpCode::Ldc as OpSize, 0x00, 0x02,
+            OpCode::Print as OpSize,
+        ],
+        optimize_and_lower(&mut code)
+    );
+}
+
+#[test]
+fn test_backward_jmp_offset() {
+    let mut code = IrCode::new();
+    let start = code.new_label();
+    code.bind(start);
+    code.emit(OpCode::Load0, 0);
+    code.emit(OpCode::Print, 0);
+    code.emit_jmp(OpCode::Jmp, start);
+
+    #[rustfmt::skip]
+    assert_eq!(
+        vec![
+            OpCode::Load0 as OpSize,
+            OpCode::Print as OpSize,
+            OpCode::Jmp as OpSize, 0xFF, 0xFB, // 0
+        ],
+        optimize_and_lower(&mut code)
+    );
+}
+
+#[test]
+fn test_jmp_to_jmp_threaded() {
+    let mut code = IrCode::new();
+    let middle = code.new_label();
+    let end = code.new_label();
+    code.emit(OpCode::Load0, 0);
+    code.emit_jmp(OpCode::IfTruthy, middle);
+    code.emit(OpCode::Print, 0);
+    code.emit(OpCode::NPush, 0);
+    code.bind(middle);
+    code.emit_jmp(OpCode::Jmp, end);
+    code.emit(OpCode::NPush, 0);
+    code.bind(end);
+    code.emit(OpCode::Print, 0);
+
+    #[rustfmt::skip]
+    assert_eq!(
+        vec![
+            OpCode::Load0 as OpSize,
+            OpCode::IfTruthy as OpSize, 0x00, 0x06, // 10
+            OpCode::Print as OpSize,
+            OpCode::NPush as OpSize,
+            OpCode::Jmp as OpSize, 0x00, 0x01, // 10
+            OpCode::NPush as OpSize,
+            OpCode::Print as OpSize,
+        ],
+        optimize_and_lower(&mut code)
+    );
+}
+
+#[test]
+fn test_jmp_to_next_block_removed() {
+    let mut code = IrCode::new();
+    let next = code.new_label();
+    let empty = code.new_label();
+    code.emit(OpCode::Load0, 0);
+    code.emit_jmp(OpCode::IfFalsy, empty);
+    code.emit_jmp(OpCode::Jmp, next);
+    code.bind(empty);
+    code.bind(next);
+    code.emit(OpCode::Print, 0);
+
+    assert_eq!(
+        vec![OpCode::Load0 as OpSize, OpCode::Print as OpSize],
+        optimize_and_lower(&mut code)
+    );
+}
+
+#[test]
+fn test_cmp_jmp_to_next_block_kept() {
+    let mut code = IrCode::new();
+    let next = code.new_label();
+    code.emit(OpCode::Load0, 0);
+    code.emit(OpCode::Load1, 0);
+    code.emit_jmp(OpCode::IfEq, next);
+    code.bind(next);
+    code.emit(OpCode::NPush, 0);
+
+    // the comparison pops the operands, even though it does not jump anywhere else
+    #[rustfmt::skip]
+    assert_eq!(
+        vec![
+            OpCode::Load0 as OpSize,
+            OpCode::Load1 as OpSize,
+            OpCode::IfEq as OpSize, 0x00, 0x00, // 5
+            OpCode::NPush as OpSize,
+        ],
+        optimize_and_lower(&mut code)
+    );
+}
+
+#[test]
+fn test_line_numbers() {
+    let mut code = IrCode::new();
+    let end = code.new_label();
+    code.mark_line(1);
+    code.emit(OpCode::Load0, 0);
+    code.emit_jmp(OpCode::IfFalsy, end);
+    code.mark_line(2);
+    code.emit(OpCode::Load0, 0);
+    code.emit(OpCode::Print, 0);
+    code.bind(end);
+    code.mark_line(3);
+    code.emit(OpCode::Pop, 0);
+
+    code.optimize();
+    let mut line_numbers = LineNumberTable::new();
+    code.lower(&mut line_numbers).unwrap();
+    assert_eq!(
+        &vec![
+            LineNumber {
+                start_pc: 0,
+                line: 1
+            },
+            LineNumber {
+                start_pc: 4,
+                line: 2
+            },
+            LineNumber {
+                start_pc: 6,
+                line: 3
+            },
+        ],
+        line_numbers.entries()
+    );
+}
+
+#[test]
+fn test_fall_through_blocks_merged() {
+    let mut code = IrCode::new();
+    let unused = code.new_label();
+    let target = code.new_label();
+    code.emit(OpCode::Load0, 0);
+    code.bind(unused);
+    code.emit(OpCode::Print, 0);
+    code.bind(target);
+    code.emit(OpCode::Load0, 0);
+    code.emit_jmp(OpCode::Jmp, target);
+    code.optimize();
+
+    assert_eq!(
+        "B0:
+          load_0
+          print
+B1:
+          load_0
+          jmp B1
+",
+        code.dump(&ConstantPool::new())
+    );
+}
+
+#[test]
+fn test_unreachable_blocks_removed() {
+    let mut code = IrCode::new();
+    let else_branch = code.new_label();
+    let end = code.new_label();
+    code.emit(OpCode::Load0, 0);
+    code.emit_jmp(OpCode::IfFalsy, else_branch);
+    code.emit(OpCode::Ret, 0);
+    code.emit_jmp(OpCode::Jmp, end);
+    code.bind(else_branch);
+    code.emit(OpCode::Ret, 0);
+    code.bind(end);
+    code.emit(OpCode::Print, 0);
+    code.optimize();
+
+    assert!(code.eliminate_dead_code());
+    assert!(!code.eliminate_dead_code());
+    assert_eq!(
+        "B0:
+          load_0
+          iffalsy B2
+B1:
+          ret
+B2:
+          ret
+",
+        code.dump(&ConstantPool::new())
+    );
+}
+
+#[test]
+fn test_max_stack() {
+    let mut code = IrCode::new();
+    let else_branch = code.new_label();
+    code.emit(OpCode::Load0, 0);
+    code.emit_jmp(OpCode::IfTruthy, else_branch);
+    code.emit(OpCode::Load0, 0);
+    code.emit(OpCode::Load0, 0);
+    code.emit(OpCode::Add, 0);
+    code.emit(OpCode::Ret, 0);
+    code.bind(else_branch);
+    code.emit(OpCode::Ret, 0);
+
+    assert_eq!(3, code.max_stack());
+}
+
+#[test]
+fn test_jmp_offset_out_of_range() {
+    let mut code = IrCode::new();
+    let end = code.new_label();
+    code.mark_line(1);
+    code.emit_jmp(OpCode::Jmp, end);
+    code.mark_line(2);
+    for _ in 0..11000 {
+        code.emit(OpCode::Ldc, 1);
+    }
+    code.bind(end);
+    code.emit(OpCode::Halt, 0);
+
+    assert_eq!(
+        Err(JumpOutOfRange { line: Some(1) }),
+        code.lower(&mut LineNumberTable::new())
+    );
+}
+
+#[test]
+#[should_panic(expected = "is not bound")]
+fn test_unbound_label() {
+    let mut code = IrCode::new();
+    let label = code.new_label();
+    code.emit_jmp(OpCode::Jmp, label);
+    code.lower(&mut LineNumberTable::new()).unwrap();
+}
+
+#[test]
+fn test_dump_ir() {
+    let dump = dump_ir("fun f(a) { while (a) { a = false; } } print f;");
+    assert_eq!(
+        "#0: fun f:
+B0:
+B1:
+    L1    load_0
+    L1    iffalsy B3
+B2:
+    L1    pop
+    L1    bpush_0
+    L1    store_0
+    L1    jmp B1
+B3:
+    L1    pop
+    L1    npush
+    L1    ret
+
+<top-level>:
+B0:
+    L1    fnload 0
+    L1    dup
+    L1    store_0
+    L1    print
+
+",
+        dump
+    );
+}
@@ -0,0 +1,141 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+use std::io::Cursor;
+
+use crate::comp::YKCompiler;
+use crate::features::CompilerFeatures;
+
+fn dump_ir(source: &str, licm: bool) -> String {
+    let mut features = CompilerFeatures::default();
+    features.licm = licm;
+
+    let mut compiler = YKCompiler::new();
+    let (mut program, has_errors) = compiler.parse(Cursor::new(source)).unwrap();
+    assert!(!has_errors);
+
+    compiler.attr(&mut program, &features);
+    return compiler.dump_ir(&mut program, &features).unwrap();
+}
+
+/// Assert that no expression of the given source is hoisted out of its loops.
+fn assert_not_hoisted(source: &str) {
+    assert_eq!(dump_ir(source, false), dump_ir(source, true));
+}
+
+#[test]
+fn test_invariant_expr_hoisted() {
+    let dump = dump_ir(
+        "fun f(k) { var n = -k; var i = 0; while (i < 3) { print n * 2; i = i + 1; } }",
+        true,
+    );
+    assert_eq!(
+        "#0: fun f:
+B0:
+    L1    load_0
+    L1    neg
+    L1    store_1
+    L1    ldc #4          // 0
+    L1    dup
+    L1    store_2
+    L1    ldc #5          // 3
+    L1    iflt B2
+B1:
+    L1    bpush_0
+    L1    jmp B3
+B2:
+    L1    bpush_1
+B3:
+    L1    iffalsy B11
+B4:
+    L1    pop
+    L1    load_1
+    L1    ldc #6          // 2
+    L1    mult
+    L1    store_3
+    L1    jmp B10
+B5:
+    L1    load_2
+    L1    ldc #5          // 3
+    L1    iflt B7
+B6:
+    L1    bpush_0
+    L1    jmp B8
+B7:
+    L1    bpush_1
+B8:
+    L1    iffalsy B11
+B9:
+    L1    pop
+B10:
+    L1    load_3
+    L1    print
+    L1    load_2
+    L1    ldc #7          // 1
+    L1    add
+    L1    store_2
+    L1    jmp B5
+B11:
+    L1    pop
+    L1    npush
+    L1    ret
+
+<top-level>:
+B0:
+    L1    fnload 0
+    L1    store_0
+
+",
+        dump
+    );
+}
+
+#[test]
+fn test_assigned_operand_not_hoisted() {
+    assert_not_hoisted("fun f(n) { var i = 0; while (i < 3) { print n * 2; n = i; i = i + 1; } }");
+    assert_not_hoisted("fun f(n) { for (var i = 0; i < 3; n = n + 1) { print -n; } }");
+}
+
+#[test]
+fn test_operand_written_by_call_not_hoisted() {
+    assert_not_hoisted(
+        "var n = 1; fun g() { n = n + 1; } fun f() { var i = 0; while (i < 3) { print n * 2; g(); i = i + 1; } }",
+    );
+}
+
+#[test]
+fn test_conditionally_evaluated_expr_not_hoisted() {
+    assert_not_hoisted(
+        "fun f(n) { var i = 0; while (i < 3) { if (i == 1) { break; } print -n; i = i + 1; } }",
+    );
+    assert_not_hoisted("fun f(n, b) { var i = 0; while (b and n * 2) { i = i + 1; } }");
+    assert_not_hoisted("fun f(n) { var i = 0; while (i < 3) { print fun() { return -n; }; } }");
+}
+
+#[test]
+fn test_expr_which_may_fail_not_hoisted() {
+    // the operands are not known to be numbers
+    assert_not_hoisted("fun f(n) { var i = 0; while (i < 3) { print n * 2; i = i + 1; } }");
+    assert_not_hoisted(
+        "fun f(n) { var m = n; var i = 0; while (i < 3) { print m < 2; i = i + 1; } }",
+    );
+    assert_not_hoisted(
+        "fun f() { var s = \"a\"; var i = 0; while (i < 3) { print s - 1; i = i + 1; } }",
+    );
+
+    // the variable is assigned a value which is not a number elsewhere
+    assert_not_hoisted(
+        "fun f() { var n = 1; var i = 0; while (i < 3) { print n * 2; i = i + 1; } n = null; }",
+    );
+}
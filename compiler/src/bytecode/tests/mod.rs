@@ -16,5 +16,8 @@
 mod cp;
 mod cp_info;
 mod disassembler;
+mod ir;
+mod licm;
+mod peephole;
 mod util;
 mod writer;
@@ -0,0 +1,142 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::bytecode::attrs::LineNumberTable;
+use crate::bytecode::ir::IrCode;
+use crate::bytecode::opcode::OpCode;
+use crate::bytecode::opcode::OpSize;
+use crate::bytecode::peephole;
+
+fn optimize(code: &mut IrCode) -> Vec<OpSize> {
+    code.optimize();
+    peephole::optimize(code);
+    return code.lower(&mut LineNumberTable::new()).unwrap();
+}
+
+#[test]
+fn test_dup_pop_removed() {
+    let mut code = IrCode::new();
+    code.emit(OpCode::Load0, 0);
+    code.emit(OpCode::Dup, 0);
+    code.emit(OpCode::Pop, 0);
+    code.emit(OpCode::Print, 0);
+    assert_eq!(
+        vec![OpCode::Load0 as OpSize, OpCode::Print as OpSize],
+        optimize(&mut code)
+    );
+}
+
+#[test]
+fn test_push_pop_removed() {
+    let mut code = IrCode::new();
+    code.emit(OpCode::Ldc, 1);
+    code.emit(OpCode::Pop, 0);
+    code.emit(OpCode::NPush, 0);
+    code.emit(OpCode::Pop, 0);
+    code.emit(OpCode::BPush0, 0);
+    code.emit(OpCode::Pop, 0);
+    code.emit(OpCode::Load0, 0);
+    code.emit(OpCode::Print, 0);
+    assert_eq!(
+        vec![OpCode::Load0 as OpSize, OpCode::Print as OpSize],
+        optimize(&mut code)
+    );
+}
+
+#[test]
+fn test_jmps_repatched() {
+    let mut code = IrCode::new();
+    let end = code.new_label();
+    code.emit(OpCode::Load0, 0);
+    code.emit_jmp(OpCode::IfFalsy, end);
+    code.emit(OpCode::Dup, 0);
+    code.emit(OpCode::Pop, 0);
+    code.emit(OpCode::Print, 0);
+    code.bind(end);
+    code.emit(OpCode::Load0, 0);
+    code.emit(OpCode::Print, 0);
+
+    #[rustfmt::skip]
+    assert_eq!(
+        vec![
+            OpCode::Load0 as OpSize,
+            OpCode::IfFalsy as OpSize, 0x00, 0x01, // 5
+            OpCode::Print as OpSize,
+            OpCode::Load0 as OpSize,
+            OpCode::Print as OpSize,
+        ],
+        optimize(&mut code)
+    );
+}
+
+#[test]
+fn test_jmp_into_window_not_rewritten() {
+    let mut code = IrCode::new();
+    let pop = code.new_label();
+    code.emit(OpCode::Load0, 0);
+    code.emit(OpCode::Dup, 0);
+    code.bind(pop);
+    code.emit(OpCode::Pop, 0);
+    code.emit_jmp(OpCode::Jmp, pop);
+
+    #[rustfmt::skip]
+    assert_eq!(
+        vec![
+            OpCode::Load0 as OpSize,
+            OpCode::Dup as OpSize,
+            OpCode::Pop as OpSize,
+            OpCode::Jmp as OpSize, 0xFF, 0xFC, // 2
+        ],
+        optimize(&mut code)
+    );
+}
+
+#[test]
+fn test_store_load_of_different_slots_not_rewritten() {
+    let mut code = IrCode::new();
+    code.emit(OpCode::Store0, 0);
+    code.emit(OpCode::Load1, 0);
+    code.emit(OpCode::Store, 4);
+    code.emit(OpCode::Load, 5);
+
+    #[rustfmt::skip]
+    assert_eq!(
+        vec![
+            OpCode::Store0 as OpSize,
+            OpCode::Load1 as OpSize,
+            OpCode::Store as OpSize, 0x00, 0x04,
+            OpCode::Load as OpSize, 0x00, 0x05,
+        ],
+        optimize(&mut code)
+    );
+}
+
+#[test]
+fn test_store_load_with_operand_rewritten() {
+    let mut code = IrCode::new();
+    code.emit(OpCode::Store, 4);
+    code.emit(OpCode::Load, 4);
+    code.emit(OpCode::Print, 0);
+
+    #[rustfmt::skip]
+    assert_eq!(
+        vec![
+            OpCode::Dup as OpSize,
+            OpCode::Store as OpSize, 0x00, 0x04,
+            OpCode::Print as OpSize,
+        ],
+        optimize(&mut code)
+    );
+}
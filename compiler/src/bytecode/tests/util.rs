@@ -36,7 +36,7 @@ pub(crate) fn compile_to_bytecode<'a>(
     assert!(!has_errors);
 
     compiler.attr(&mut program, features);
-    let mut ykbfile = compiler.ir(&mut program, features);
+    let mut ykbfile = compiler.ir(&mut program, features).unwrap();
 
     let display = bytecode_path.display();
     let file = match File::create(&bytecode_path) {
@@ -19,13 +19,21 @@ use std::path::Path;
 use crate::bytecode::attrs;
 use crate::bytecode::bytes::ByteInput;
 use crate::bytecode::cp_info::NumberInfo;
+use crate::bytecode::cp_info::StringInfo;
 use crate::bytecode::cp_info::Utf8Info;
+use crate::bytecode::disassemble_insn;
 use crate::bytecode::opcode::OpCode;
 use crate::bytecode::opcode::OpSize;
 use crate::bytecode::tests::util::compile_to_bytecode;
 use crate::bytecode::ConstantEntry;
+use crate::bytecode::YKBFile;
 use crate::bytecode::YKBFileReader;
+use crate::bytecode::YKBFileWriter;
+use crate::bytecode::YKBVersion;
 use crate::features::CompilerFeatures;
+use crate::location::Position;
+use crate::messages;
+use crate::tests::util::parse_attr;
 
 #[test]
 fn test_program_writer() {
@@ -65,6 +73,7 @@ fn test_arithemetic_constant_folding() {
             ConstantEntry::Number(NumberInfo::from(&12f64)), // 3*4 folded to 12
             ConstantEntry::Number(NumberInfo::from(&0.8f64)), // 4/5 folded to 0.8
             ConstantEntry::Utf8(Utf8Info::from("Code")), // "Code" is the name of the "Code" attribute for the YKBFile's top-level statements
+            ConstantEntry::Utf8(Utf8Info::from("LineNumberTable")),
         ],
         &vec![
             OpCode::Ldc as OpSize, 0x00, 0x01, // 3
@@ -98,6 +107,7 @@ fn test_disabled_arithemetic_constant_folding() {
             ConstantEntry::Number(NumberInfo::from(&4f64)),
             ConstantEntry::Number(NumberInfo::from(&5f64)),
             ConstantEntry::Utf8(Utf8Info::from("Code")),
+            ConstantEntry::Utf8(Utf8Info::from("LineNumberTable")),
         ],
         &vec![
             OpCode::Ldc as OpSize, 0x00, 0x01, // 1
@@ -120,6 +130,29 @@ fn test_disabled_arithemetic_constant_folding() {
     );
 }
 
+#[test]
+fn test_string_concatenation_folding() {
+    let path = Path::new("target/string_concat_folding.ykb");
+
+    #[rustfmt::skip]
+    verify_top_level_insns(
+        "print \"Hello, \" + \"World\" + \"!\";",
+        &path,
+        &CompilerFeatures::default(),
+        &vec![
+            ConstantEntry::None,
+            ConstantEntry::Utf8(Utf8Info::from("Hello, World!")),
+            ConstantEntry::String(StringInfo::new(1)),
+            ConstantEntry::Utf8(Utf8Info::from("Code")),
+            ConstantEntry::Utf8(Utf8Info::from("LineNumberTable")),
+        ],
+        &vec![
+            OpCode::Ldc as OpSize, 0x00, 0x02, // "Hello, World!"
+            OpCode::Print as OpSize,
+        ], 1, 0
+    );
+}
+
 #[test]
 fn test_bpush_ops() {
     let path = Path::new("target/bpush_ops.ykb");
@@ -209,7 +242,9 @@ print false;",
 #[test]
 fn test_variable_decl_and_load() {
     let path = Path::new("target/variable_decl_and_load.ykb");
-    let features = CompilerFeatures::default();
+    let mut features = CompilerFeatures::default();
+    features.const_propagation = false;
+    features.peephole = false;
     verify_top_level_insns(
         "var i = 0; print i;",
         &path,
@@ -231,7 +266,8 @@ fn test_variable_decl_and_load() {
 #[test]
 fn test_variable_reassigning() {
     let path = Path::new("target/variable_reassigning.ykb");
-    let features = CompilerFeatures::default();
+    let mut features = CompilerFeatures::default();
+    features.peephole = false;
     verify_top_level_insns(
         "var i = 0; i = i + 1; print i;",
         &path,
@@ -259,7 +295,8 @@ fn test_variable_reassigning() {
 #[test]
 fn test_multi_variable_decl_and_load() {
     let path = Path::new("target/variable_decl_and_load.ykb");
-    let features = CompilerFeatures::default();
+    let mut features = CompilerFeatures::default();
+    features.const_propagation = false;
     verify_top_level_insns(
         "var a = 0; var b = 1; var c = 2; print a; print b; print c; print a + b + c;",
         &path,
@@ -296,6 +333,218 @@ fn test_multi_variable_decl_and_load() {
     );
 }
 
+#[test]
+fn test_unreachable_insns_eliminated() {
+    let path = Path::new("target/dead_code_elim.ykb");
+    let mut features = CompilerFeatures::default();
+    features.const_propagation = false;
+
+    // the jump over the else branch and the jump back to the start of the loop which follows the
+    // `if` statement can never be executed
+    #[rustfmt::skip]
+    verify_top_level_insns(
+        "var a = true; while a { if a { break; } else { continue; } }",
+        &path,
+        &features,
+        &vec![],
+        &vec![
+            OpCode::BPush1 as OpSize,
+            OpCode::Store0 as OpSize,
+            OpCode::Load0 as OpSize,
+            OpCode::IfFalsy as OpSize, 0x00, 0x0D, // 19
+            OpCode::Pop as OpSize,
+            OpCode::Load0 as OpSize,
+            OpCode::IfFalsy as OpSize, 0x00, 0x04, // 15
+            OpCode::Pop as OpSize,
+            OpCode::Jmp as OpSize, 0x00, 0x05, // 20, break
+            OpCode::Pop as OpSize,
+            OpCode::Jmp as OpSize, 0xFF, 0xEF, // 2, continue
+            OpCode::Pop as OpSize,
+        ], 1, 1
+    );
+}
+
+#[test]
+fn test_unreachable_insns_kept_when_disabled() {
+    let path = Path::new("target/disabled_dead_code_elim.ykb");
+    let mut features = CompilerFeatures::default();
+    features.const_propagation = false;
+    features.dead_code_elim = false;
+
+    #[rustfmt::skip]
+    verify_top_level_insns(
+        "var a = true; while a { if a { break; } else { continue; } }",
+        &path,
+        &features,
+        &vec![],
+        &vec![
+            OpCode::BPush1 as OpSize,
+            OpCode::Store0 as OpSize,
+            OpCode::Load0 as OpSize,
+            OpCode::IfFalsy as OpSize, 0x00, 0x13, // 25
+            OpCode::Pop as OpSize,
+            OpCode::Load0 as OpSize,
+            OpCode::IfFalsy as OpSize, 0x00, 0x07, // 18
+            OpCode::Pop as OpSize,
+            OpCode::Jmp as OpSize, 0x00, 0x0B, // 26, break
+            OpCode::Jmp as OpSize, 0xFF, 0xF0, // 2, the end of the `if` statement
+            OpCode::Pop as OpSize,
+            OpCode::Jmp as OpSize, 0xFF, 0xEC, // 2, continue
+            OpCode::Jmp as OpSize, 0xFF, 0xE9, // 2
+            OpCode::Pop as OpSize,
+        ], 1, 1
+    );
+}
+
+#[test]
+fn test_jmp_out_of_range_reported() {
+    // each statement of the loop body takes 6 bytes
+    let source = format!(
+        "fun f(a) {{\n  while (a) {{\n{}  }}\n}}",
+        "    print a + 1;\n".repeat(6000)
+    );
+    let features = CompilerFeatures::default();
+    let mut program = parse_attr(&source, true, &features);
+    let mut file = YKBFile::new(YKBVersion::LATEST.clone());
+    let errors = YKBFileWriter::new(&mut file, &features)
+        .write(&mut program)
+        .unwrap_err();
+
+    assert_eq!(1, errors.len());
+    assert_eq!(messages::GEN_JUMP_OUT_OF_RANGE.code, errors[0].code);
+    assert_eq!(Position::new(1, 0, -1), errors[0].range.start);
+}
+
+#[test]
+fn test_peephole_store_load() {
+    let path = Path::new("target/peephole_store_load.ykb");
+    let mut features = CompilerFeatures::default();
+    features.const_propagation = false;
+    verify_peephole(
+        "var a = 1; print a;",
+        &path,
+        &features,
+        &["0: ldc #1 // 1", "3: store_0", "4: load_0", "5: print"],
+        &["0: ldc #1 // 1", "3: dup", "4: store_0", "5: print"],
+    );
+}
+
+#[test]
+fn test_peephole_not_jmp() {
+    let path = Path::new("target/peephole_not_jmp.ykb");
+    let mut features = CompilerFeatures::default();
+    features.const_propagation = false;
+    verify_peephole(
+        "var a = true; if !a { print a; }",
+        &path,
+        &features,
+        &[
+            "0: bpush_1",
+            "1: store_0",
+            "2: load_0",
+            "3: not",
+            "4: iffalsy 13",
+            "7: pop",
+            "8: load_0",
+            "9: print",
+            "10: jmp 14",
+            "13: pop",
+        ],
+        &[
+            "0: bpush_1",
+            "1: dup",
+            "2: store_0",
+            "3: iftruthy 12",
+            "6: pop",
+            "7: load_0",
+            "8: print",
+            "9: jmp 13",
+            "12: pop",
+        ],
+    );
+}
+
+#[test]
+fn test_peephole_not_jmp_kept_for_short_circuit() {
+    let path = Path::new("target/peephole_not_jmp_short_circuit.ykb");
+    let mut features = CompilerFeatures::default();
+    features.const_propagation = false;
+
+    // the negated value is the result of the `and` expression when the jump is taken
+    verify_peephole(
+        "var a = true; var b = !a and a; print b;",
+        &path,
+        &features,
+        &[
+            "0: bpush_1",
+            "1: store_0",
+            "2: load_0",
+            "3: not",
+            "4: iffalsy 9",
+            "7: pop",
+            "8: load_0",
+            "9: store_1",
+            "10: load_1",
+            "11: print",
+        ],
+        &[
+            "0: bpush_1",
+            "1: dup",
+            "2: store_0",
+            "3: not",
+            "4: iffalsy 9",
+            "7: pop",
+            "8: load_0",
+            "9: dup",
+            "10: store_1",
+            "11: print",
+        ],
+    );
+}
+
+#[test]
+fn test_peephole_const_jmp() {
+    let path = Path::new("target/peephole_const_jmp.ykb");
+    verify_peephole(
+        "while true { print 1; }",
+        &path,
+        &CompilerFeatures::default(),
+        &[
+            "0: bpush_1",
+            "1: iffalsy 12",
+            "4: pop",
+            "5: ldc #1 // 1",
+            "8: print",
+            "9: jmp 0",
+            "12: pop",
+        ],
+        &["0: ldc #1 // 1", "3: print", "4: jmp 0"],
+    );
+}
+
+#[test]
+fn test_peephole_short_circuit() {
+    let path = Path::new("target/peephole_short_circuit.ykb");
+    let mut features = CompilerFeatures::default();
+    features.const_folding = false;
+    features.const_propagation = false;
+    verify_peephole(
+        "var b = 1; print true and b;",
+        &path,
+        &features,
+        &[
+            "0: ldc #1 // 1",
+            "3: store_0",
+            "4: bpush_1",
+            "5: iffalsy 10",
+            "8: pop",
+            "9: load_0",
+            "10: print",
+        ],
+        &["0: ldc #1 // 1", "3: dup", "4: store_0", "5: print"],
+    );
+}
+
 fn verify_top_level_insns(
     source: &str,
     out_path: &Path,
@@ -324,3 +573,43 @@ fn verify_top_level_insns(
         assert_eq!(exp_insns, insns);
     }
 }
+
+/// Verify the disassembled top-level instructions compiled from the given source, without and with
+/// the peephole optimizer.
+fn verify_peephole(
+    source: &str,
+    out_path: &Path,
+    features: &CompilerFeatures,
+    before: &[&str],
+    after: &[&str],
+) {
+    let mut features = features.clone();
+    features.peephole = false;
+    assert_eq!(before, disassemble_top_level(source, out_path, &features));
+
+    features.peephole = true;
+    assert_eq!(after, disassemble_top_level(source, out_path, &features));
+}
+
+fn disassemble_top_level(
+    source: &str,
+    out_path: &Path,
+    features: &CompilerFeatures,
+) -> Vec<String> {
+    let ykbfile = compile_to_bytecode(features, source, &out_path);
+    let attrs = ykbfile.attributes();
+    let code = match attrs.iter().find(|attr| attr.name() == attrs::CODE) {
+        Some(attrs::Attr::Code(code)) => code,
+        _ => panic!("Expected a Code attribute to be present"),
+    };
+
+    let mut insns = Vec::new();
+    let mut index = 0;
+    while index < code.instructions().len() {
+        let (insn, next) = disassemble_insn(code.instructions(), index, ykbfile.constant_pool());
+        insns.push(insn.split_whitespace().collect::<Vec<_>>().join(" "));
+        index = next;
+    }
+
+    return insns;
+}
@@ -13,7 +13,9 @@
  * program. If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::collections::HashSet;
 use std::ops::Deref;
+use std::rc::Rc;
 
 use crate::ast::{ArrayAccessExpr, ASTVisitor};
 use crate::ast::ArrayExpr;
@@ -25,15 +27,23 @@ use crate::ast::BreakStmt;
 use crate::ast::ClassDecl;
 use crate::ast::CompoundAssignExpr;
 use crate::ast::ContinueStmt;
+use crate::ast::Decl;
 use crate::ast::Expr;
+use crate::ast::ExprStmt;
 use crate::ast::ForStmt;
+use crate::ast::FuncCallExpr;
 use crate::ast::FuncDecl;
 use crate::ast::IdentifierExpr;
 use crate::ast::IdentifierType;
 use crate::ast::IfStmt;
+use crate::ast::LambdaExpr;
 use crate::ast::LiteralExpr;
+use crate::ast::MemberAccessExpr;
 use crate::ast::PrintStmt;
 use crate::ast::Program;
+use crate::ast::ReturnStmt;
+use crate::ast::Spanned;
+use crate::ast::Stmt;
 use crate::ast::UnaryExpr;
 use crate::ast::UnaryOp;
 use crate::ast::VarStmt;
@@ -43,21 +53,33 @@ use crate::bytecode::attrs;
 use crate::bytecode::attrs::Attr;
 use crate::bytecode::attrs::Code;
 use crate::bytecode::attrs::CodeSize;
+use crate::bytecode::attrs::LineNumberTable;
 use crate::bytecode::bytes::AssertingByteConversions;
 use crate::bytecode::cp::ConstantEntry;
 use crate::bytecode::cp_info::NumberInfo;
 use crate::bytecode::cp_info::Utf8Info;
 use crate::bytecode::decls;
 use crate::bytecode::file::YKBFile;
-use crate::bytecode::opcode::get_opcode;
+use crate::bytecode::ir::InsnRef;
+use crate::bytecode::ir::IrCode;
+use crate::bytecode::ir::JumpOutOfRange;
+use crate::bytecode::ir::Label;
+use crate::bytecode::licm;
 use crate::bytecode::opcode::OpCode;
 use crate::bytecode::opcode::opcode_cmp;
 use crate::bytecode::opcode::opcode_cmpz;
-use crate::bytecode::opcode::OpCodeExt;
+use crate::bytecode::peephole;
+use crate::diagnostics::Diagnostic;
+use crate::diagnostics::DiagnosticKind;
 use crate::features::CompilerFeatures;
+use crate::location::Position;
+use crate::location::Range;
 use crate::messages;
 use crate::scope::Scope;
+use crate::scope::VarLocation;
 use crate::symtab::VarSym;
+use crate::symtab::SUPER_NAME;
+use crate::symtab::THIS_NAME;
 
 /// Converts a program into a YKB file.
 pub struct YKBFileWriter<'inst> {
@@ -78,62 +100,83 @@ impl YKBFileWriter<'_> {
         return &mut self.file;
     }
 
-    pub fn write(&mut self, program: &mut Program) {
+    /// Write the given program to the file. Returns the errors reported if the code of the
+    /// program cannot be represented in bytecode.
+    pub fn write(&mut self, program: &mut Program) -> Result<(), Vec<Diagnostic>> {
         let mut codegen = CodeGen::new(&mut self.file, &self.features);
         let mut loops = vec![];
         let mut context = CodeGenContext::new(&mut loops);
         program.accept(&mut codegen, &mut context);
+        return codegen.into_result(());
+    }
+
+    /// Same as [YKBFileWriter::write], but also returns a dump of the IR from which the code of
+    /// the top-level statements and of each function is lowered.
+    pub fn write_with_ir(&mut self, program: &mut Program) -> Result<String, Vec<Diagnostic>> {
+        let mut ir_dump = String::new();
+        let mut codegen = CodeGen::new(&mut self.file, &self.features);
+        codegen.ir_dump = Some(&mut ir_dump);
+
+        let mut loops = vec![];
+        let mut context = CodeGenContext::new(&mut loops);
+        program.accept(&mut codegen, &mut context);
+        let result = codegen.into_result(());
+        return result.map(|_| ir_dump);
     }
 }
 
 struct CodeGen<'a> {
     file: &'a mut YKBFile,
-    #[allow(unused)]
     features: &'a CompilerFeatures,
-    stack_count: i16,
     max_stack: u16,
     local_count: i16,
     max_locals: u16,
-    cp: CodeSize,
+
+    /// The code written so far, which is lowered to [CodeGen::instructions] once it is complete.
+    code: IrCode,
     instructions: Vec<u8>,
-}
 
-#[derive(Debug, PartialEq)]
-struct PendingJump {
-    /// The type of jump.
-    pub typ: JumpType,
+    /// Whether the code being written is the body of a class initializer.
+    is_init: bool,
 
-    /// The index of the instruction where jump occurs.
-    pub from: CodeSize,
-}
+    /// The source lines of the lowered instructions.
+    line_numbers: LineNumberTable,
 
-#[derive(Debug, PartialEq, Clone)]
-enum JumpType {
-    /// Jump instruction for a `continue` statement in a loop.
-    Continue,
+    /// The dump of the IR of each [Code] lowered so far, if it was requested.
+    ir_dump: Option<&'a mut String>,
 
-    /// Jump instruction for a `break` statement in a loop.
-    Break,
-}
+    /// The [licm::key]s of the loop-invariant expressions hoisted out of the loops being written,
+    /// and the variables which hold their values.
+    hoisted: Vec<(String, u16)>,
 
-impl PendingJump {
-    fn new(typ: JumpType, from: CodeSize) -> PendingJump {
-        return PendingJump { typ, from };
-    }
+    /// The names of the variables written in the bodies of the functions of the program. Only
+    /// computed if loop-invariant code motion is enabled.
+    written_in_funcs: Rc<HashSet<String>>,
 
-    pub fn _continue(from: CodeSize) -> PendingJump {
-        return PendingJump::new(JumpType::Continue, from);
-    }
+    /// The names of the variables of the program which always hold a number. Only computed if
+    /// loop-invariant code motion is enabled.
+    number_vars: Rc<HashSet<String>>,
 
-    pub fn _break(from: CodeSize) -> PendingJump {
-        return PendingJump::new(JumpType::Break, from);
-    }
+    /// The errors reported while writing the code, including the code of the nested functions.
+    errors: Vec<Diagnostic>,
+}
+
+/// A class whose creation has been written, but the bodies of the methods have not.
+struct PendingClass {
+    /// The index of the variable which holds the superclass, if the class has a superclass.
+    super_slot: u16,
+
+    /// The declaration indices of the methods, and the instructions which load them.
+    methods: Vec<(u16, InsnRef)>,
 }
 
 #[derive(Debug, PartialEq)]
 struct LoopContext {
-    /// The index of the instruction which begins the loop.
-    pub pc: CodeSize,
+    /// The block at which the program continues when a `continue` statement is encountered.
+    pub continue_to: Label,
+
+    /// The block at which the program continues when a `break` statement is encountered.
+    pub break_to: Label,
 
     /// The type of loop.
     pub typ: LoopType,
@@ -141,10 +184,6 @@ struct LoopContext {
     /// An optional label for the loop.
     pub label: Option<String>,
 
-    /// A list of [PendingJump]s, which contain information about jump instructions which must be
-    /// patched after the bytecode for a loop has been written.
-    pub pending_jumps: Vec<PendingJump>,
-
     /// Whether [CodeGen] is writing bytecode for this loop's condition expression.
     pub is_cond: bool,
 }
@@ -156,12 +195,17 @@ enum LoopType {
 }
 
 impl LoopContext {
-    fn new(pc: CodeSize, typ: LoopType, label: Option<String>) -> LoopContext {
+    fn new(
+        continue_to: Label,
+        break_to: Label,
+        typ: LoopType,
+        label: Option<String>,
+    ) -> LoopContext {
         return LoopContext {
-            pc,
+            continue_to,
+            break_to,
             typ,
             label,
-            pending_jumps: Vec::with_capacity(0),
             is_cond: false,
         };
     }
@@ -205,11 +249,12 @@ impl CodeGenContext<'_> {
 
     fn push_loop(
         &mut self,
-        pc: CodeSize,
+        continue_to: Label,
+        break_to: Label,
         typ: LoopType,
         label: Option<String>,
     ) -> &mut LoopContext {
-        self.loops.push(LoopContext::new(pc, typ, label));
+        self.loops.push(LoopContext::new(continue_to, break_to, typ, label));
         self.loops.last_mut().unwrap()
     }
 
@@ -235,22 +280,35 @@ impl<'a> CodeGen<'a> {
     /// The maximum (overall) depth of the operand stack for [Code] attributes.
     pub const MAX_STACK_SIZE: u16 = 0xFFFF;
 
+    /// The name of the function declarations for lambda expressions.
+    pub const LAMBDA_NAME: &'static str = "<lambda>";
+
     fn new(file: &'a mut YKBFile, features: &'a CompilerFeatures) -> Self {
         return CodeGen {
             file,
             features,
-            stack_count: 0,
             max_stack: 0,
             local_count: 0,
             max_locals: 0,
-            cp: 0,
-            instructions: Vec::with_capacity(65),
+            code: IrCode::new(),
+            instructions: Vec::with_capacity(0),
+            is_init: false,
+            line_numbers: LineNumberTable::new(),
+            ir_dump: None,
+            hoisted: Vec::with_capacity(0),
+            written_in_funcs: Rc::new(HashSet::with_capacity(0)),
+            number_vars: Rc::new(HashSet::with_capacity(0)),
+            errors: Vec::with_capacity(0),
         };
     }
 
-    #[inline(always)]
-    fn cp(&self) -> CodeSize {
-        return self.cp;
+    /// Get the given result, or the errors reported while writing the code, if any.
+    fn into_result<T>(self, result: T) -> Result<T, Vec<Diagnostic>> {
+        return if self.errors.is_empty() {
+            Ok(result)
+        } else {
+            Err(self.errors)
+        };
     }
 
     pub fn update_max_locals(&mut self, locals_effect: i8) {
@@ -261,222 +319,603 @@ impl<'a> CodeGen<'a> {
         }
     }
 
-    fn _update_max_stack(&mut self, op_code: OpCode) {
-        self.update_max_stack(op_code.stack_effect());
+    #[inline(always)]
+    fn emitop(&mut self, opcode: OpCode) {
+        self.emitop0(opcode);
     }
 
-    fn update_max_stack(&mut self, stack_effect: i8) {
-        self.stack_count += stack_effect as i16;
-        if self.stack_count > self.max_stack as i16 {
-            self.max_stack = self.stack_count as u16;
-        }
+    fn emitop0(&mut self, opcode: OpCode) {
+        self.code.emit(opcode, 0);
+    }
 
-        if self.max_stack >= Self::MAX_STACK_SIZE {
-            panic!("Stack size too large!");
-        }
+    fn emit1_8(&mut self, opcode: OpCode, operand: u8) {
+        self.code.emit(opcode, operand as u16);
     }
 
-    fn check_size(&self, additional: CodeSize) {
-        if self.instructions.len().as_code_size() + additional > Self::MAX_INSN_SIZE {
-            panic!("Instruction size too large!");
+    fn emit1_16(&mut self, opcode: OpCode, operand: u16) {
+        self.code.emit(opcode, operand);
+    }
+
+    /// Write a jump instruction which jumps to the block named by the given label.
+    fn emitjmp(&mut self, opcode: OpCode, target: Label) {
+        self.code.emit_jmp(opcode, target);
+    }
+
+    /// Place the block named by the given label at the current position.
+    #[inline(always)]
+    fn bind(&mut self, label: Label) {
+        self.code.bind(label);
+    }
+
+    fn reset(&mut self) {
+        self.max_stack = 0;
+        self.local_count = 0;
+        self.max_locals = 0;
+        self.code = IrCode::new();
+        self.instructions = Vec::with_capacity(0);
+        self.line_numbers = LineNumberTable::new();
+    }
+
+    /// Map the instructions written from now on to the line at which the given range starts.
+    fn mark_line(&mut self, range: &Range) {
+        if range.start.line >= 0 {
+            self.code.mark_line(range.start.line as u32 + 1);
         }
     }
 
-    fn ensure_size_incr(&mut self, additional: CodeSize) {
-        self.check_size(additional);
-        self.instructions
-            .resize(self.cp as usize + additional as usize, 0);
+    fn handle_short_circuit(
+        &mut self,
+        binary: &mut BinaryExpr,
+        op: OpCode,
+        ctx: &mut CodeGenContext,
+    ) {
+        self.visit_expr(&mut binary.left, ctx);
+
+        let end = self.code.new_label();
+        self.emitjmp(op, end);
+        self.emitop0(OpCode::Pop);
+
+        self.visit_expr(&mut binary.right, ctx);
+        self.bind(end);
     }
 
-    fn instructions(&self) -> &Vec<u8> {
-        return &self.instructions;
+    fn load_var(&mut self, idx: &u16) {
+        match idx {
+            0 => self.emitop0(OpCode::Load0),
+            1 => self.emitop0(OpCode::Load1),
+            2 => self.emitop0(OpCode::Load2),
+            3 => self.emitop0(OpCode::Load3),
+            _ => self.emit1_16(OpCode::Load, idx.clone()),
+        };
     }
 
-    fn get_u1(&self, index: CodeSize) -> u8 {
-        return self.instructions[index as usize];
+    fn store_var(&mut self, idx: &u16) {
+        match idx {
+            0 => self.emitop0(OpCode::Store0),
+            1 => self.emitop0(OpCode::Store1),
+            2 => self.emitop0(OpCode::Store2),
+            3 => self.emitop0(OpCode::Store3),
+            _ => self.emit1_16(OpCode::Store, idx.clone()),
+        };
     }
 
-    #[allow(unused)]
-    fn get_u2(&self, index: CodeSize) -> u16 {
-        return (self.instructions[index as usize].as_u16() << 8)
-            | self.instructions[index as usize + 1].as_u16();
+    fn load_named(&mut self, name: &String, scope: &Scope) {
+        match scope.resolve_var(name) {
+            Some(VarLocation::Local(idx)) => self.load_var(&idx),
+            Some(VarLocation::Captured(idx)) => self.emit1_16(OpCode::CellLoad, idx),
+            Some(VarLocation::Upvalue(idx)) => self.emit1_16(OpCode::UpvLoad, idx),
+            Some(VarLocation::Global(idx)) => self.emit1_16(OpCode::GLoad, idx),
+
+            // names which are not declared in the program refer to the native functions
+            // provided by the VM, these have already been validated by the name resolver
+            None => {
+                let name_idx = self
+                    .file
+                    .constant_pool_mut()
+                    .push(ConstantEntry::Utf8(Utf8Info::from(name)));
+                self.emit1_16(OpCode::NatLoad, name_idx);
+            }
+        }
     }
 
-    fn get_i2(&self, index: CodeSize) -> i16 {
-        return ((self.instructions[index as usize] as i16) << 8)
-            | self.instructions[index as usize + 1] as i16;
+    fn store_named(&mut self, name: &String, scope: &Scope) {
+        match scope.resolve_var(name) {
+            Some(VarLocation::Local(idx)) => self.store_var(&idx),
+            Some(VarLocation::Captured(idx)) => self.emit1_16(OpCode::CellStore, idx),
+            Some(VarLocation::Upvalue(idx)) => self.emit1_16(OpCode::UpvStore, idx),
+            Some(VarLocation::Global(idx)) => self.emit1_16(OpCode::GStore, idx),
+            _ => panic!("Variable not found: {}", name),
+        }
     }
 
-    #[inline(always)]
-    fn emitop(&mut self, opcode: OpCode) {
-        self.emitop0(opcode);
+    /// Get the constant pool index of the name of the given member.
+    fn member_name(&mut self, member: &IdentifierExpr) -> u16 {
+        return self
+            .file
+            .constant_pool_mut()
+            .push(ConstantEntry::Utf8(Utf8Info::from(&member.name)));
     }
 
-    fn emitop0(&mut self, opcode: OpCode) {
-        self.ensure_size_incr(1);
+    /// Optimize the instructions written so far, lower them to bytecode and create the [Code]
+    /// attribute for them. `name` names the code in the IR dump.
+    fn build_code(&mut self, name: &str) -> Code {
+        self.code.optimize();
+        if self.features.peephole {
+            peephole::optimize(&mut self.code);
+        }
 
-        self.instructions[self.cp as usize] = opcode.as_op_size();
-        self.cp += 1;
+        if self.features.dead_code_elim && self.code.eliminate_dead_code() {
+            self.code.optimize();
+        }
+
+        if let Some(ir_dump) = self.ir_dump.as_mut() {
+            let code = self.code.dump(self.file.constant_pool());
+            ir_dump.push_str(&format!("{}:\n{}\n", name, code));
+        }
+
+        self.line_numbers = LineNumberTable::new();
+        self.instructions = match self.code.lower(&mut self.line_numbers) {
+            Ok(instructions) => instructions,
+            Err(err) => {
+                self.report_jump_out_of_range(&err);
+                Vec::with_capacity(0)
+            }
+        };
+        if self.instructions.len() as u64 > Self::MAX_INSN_SIZE as u64 {
+            panic!("Instruction size too large!");
+        }
+
+        let max_stack = self.code.max_stack();
+        if max_stack >= Self::MAX_STACK_SIZE as usize {
+            panic!("Stack size too large!");
+        }
 
-        self.update_max_stack(opcode.stack_effect());
+        self.max_stack = max_stack as u16;
+        return Code::with_insns(self.max_stack, self.max_locals, self.instructions.clone());
     }
 
-    fn emit1_16(&mut self, opcode: OpCode, operand: u16) {
-        self.ensure_size_incr(3);
-        self.instructions[self.cp as usize] = opcode.as_op_size();
-        self.instructions[self.cp as usize + 1] = (operand >> 8).as_u8();
-        self.instructions[self.cp as usize + 2] = operand.as_u8();
-        self.cp += 3;
+    /// Report that the code cannot be lowered as a jump cannot reach its target. The error points
+    /// to the line of the jump.
+    fn report_jump_out_of_range(&mut self, err: &JumpOutOfRange) {
+        let range = match err.line {
+            Some(line) => Range {
+                start: Position::new(line as i32 - 1, 0, -1),
+                end: Position::new(line as i32, 0, -1),
+            },
+            None => Range::new(),
+        };
 
-        self._update_max_stack(opcode);
+        let msg = messages::GEN_JUMP_OUT_OF_RANGE;
+        self.errors.push(Diagnostic {
+            kind: DiagnosticKind::Error,
+            range,
+            message: msg.to_string(),
+            code: msg.code,
+            notes: Vec::new(),
+        });
     }
 
-    fn patch2(&mut self, index: CodeSize, d1: u8, d2: u8) {
-        self.instructions[index as usize] = d1;
-        self.instructions[index as usize + 1] = d2;
+    /// Create the [LineNumberTable] attribute for the instructions written so far, if any of the
+    /// instructions have a source line. This must be called after [CodeGen::build_code].
+    fn build_line_numbers(&mut self) -> Option<Attr> {
+        if self.line_numbers.is_empty() {
+            return None;
+        }
+
+        self.file
+            .constant_pool_mut()
+            .push(ConstantEntry::Utf8(Utf8Info::from(attrs::LINE_NUMBER_TABLE)));
+        return Some(Attr::LineNumberTable(self.line_numbers.clone()));
     }
 
-    #[inline(always)]
-    fn jmptocp(&mut self, jmp_from: CodeSize) {
-        self.patch_jmp(jmp_from, self.cp());
+    /// Reserve a declaration for a function with the given name and parameters. The [Code]
+    /// attribute and the upvalues of the function are added to the declaration once the body of
+    /// the function is written. Returns the index of the function declaration.
+    fn reserve_func(&mut self, name: &str, params: &Vec<IdentifierExpr>) -> u16 {
+        let name_index = self
+            .file
+            .constant_pool_mut()
+            .push(ConstantEntry::Utf8(Utf8Info::from(name)));
+
+        let param_names = params
+            .iter()
+            .map(|param| {
+                self.file
+                    .constant_pool_mut()
+                    .push(ConstantEntry::Utf8(Utf8Info::from(&param.name)))
+            })
+            .collect();
+
+        let decl_idx = self.file.declarations().len().as_u16();
+        self.file.declarations_mut().push(Box::new(decls::FuncDecl::new(
+            name_index,
+            param_names,
+            vec![],
+            vec![],
+        )));
+
+        return decl_idx;
     }
 
-    #[inline(always)]
-    fn jmpdelta(from: CodeSize, to: CodeSize) -> i16 {
-        (to as i64 - from as i64 - 3) as i16
-    }
-
-    fn patch_jmp(&mut self, from: CodeSize, to: CodeSize) {
-        let delta = Self::jmpdelta(from, to);
-        if delta == 0 {
-            // jumps to the next instruction
-            self.instructions.remove(from as usize);
-            self.instructions.remove(from as usize);
-            self.instructions.remove(from as usize);
-            self.cp -= 3;
-            return;
-        }
+    /// Define a variable for the function with the given name in the current scope. Returns the
+    /// index of the variable.
+    fn def_func_var(&mut self, name: &IdentifierExpr, ctx: &mut CodeGenContext) -> u16 {
+        let var_idx = match ctx
+            .scope
+            .push_var(VarSym::with_captured(name.name.clone(), name.captured))
+        {
+            // This must have been reported during the attribution phase
+            Err(_) => panic!("{}", &messages::err_dup_func(&name.name)),
+            Ok(index) => index,
+        };
 
-        self.patch2(from + 1, (delta >> 8) as u8, delta as u8);
+        self.update_max_locals(1);
+        return var_idx;
     }
 
-    fn emitjmp(&mut self, opcode: OpCode) -> CodeSize {
-        self.emit1_16(opcode, 0);
-        return self.cp() - 3;
+    /// Define a variable for the class with the given name in the current scope. Returns the
+    /// index of the variable.
+    fn def_class_var(&mut self, name: &IdentifierExpr, ctx: &mut CodeGenContext) -> u16 {
+        let var_idx = match ctx
+            .scope
+            .push_var(VarSym::with_captured(name.name.clone(), name.captured))
+        {
+            // This must have been reported during the attribution phase
+            Err(_) => panic!("{}", &messages::err_dup_class(&name.name)),
+            Ok(index) => index,
+        };
+
+        self.update_max_locals(1);
+        return var_idx;
     }
 
-    fn emitjmp1(&mut self, opcode: OpCode, target: CodeSize) {
-        if Self::jmpdelta(self.cp(), target) == 0 {
-            // jumps to the next instruction
-            return;
+    /// Write the instruction which pushes the function declared at `decl_idx` onto the operand
+    /// stack. Functions which capture variables are pushed as closures.
+    fn load_func(&mut self, decl_idx: u16) {
+        let has_upvalues = !self.file.declarations()[decl_idx as usize]
+            .as_func()
+            .expect("Expected a function declaration")
+            .upvalues
+            .is_empty();
+
+        if has_upvalues {
+            self.emit1_16(OpCode::Closure, decl_idx);
+        } else {
+            self.emit1_16(OpCode::FnLoad, decl_idx);
         }
+    }
 
-        let idx = self.emitjmp(opcode);
-        self.patch_jmp(idx, target);
+    /// Update the instruction `insn`, written by [CodeGen::load_func] before the body of the
+    /// function declared at `decl_idx` was written, to create a closure if required.
+    fn patch_func_load(&mut self, insn: InsnRef, decl_idx: u16) {
+        let has_upvalues = !self.file.declarations()[decl_idx as usize]
+            .as_func()
+            .expect("Expected a function declaration")
+            .upvalues
+            .is_empty();
+
+        if has_upvalues {
+            self.code.insn_mut(insn).opcode = OpCode::Closure;
+        }
     }
 
-    fn reset(&mut self) {
-        self.stack_count = 0;
-        self.max_stack = 0;
-        self.local_count = 0;
-        self.max_locals = 0;
-        self.cp = 0;
-        self.instructions = Vec::with_capacity(0);
+    /// Reserve a declaration for the given class and write the instructions to create the class
+    /// and store it in the variable at `var_idx`. The bodies of the methods are written
+    /// separately with [CodeGen::gen_class].
+    fn declare_class(
+        &mut self,
+        class_decl: &ClassDecl,
+        var_idx: u16,
+        ctx: &mut CodeGenContext,
+    ) -> PendingClass {
+        self.mark_line(class_decl.range());
+        let constant_pool = self.file.constant_pool_mut();
+        let name_index =
+            constant_pool.push(ConstantEntry::Utf8(Utf8Info::from(&class_decl.name.name)));
+        let super_name_index = class_decl
+            .supercls
+            .as_ref()
+            .map(|supercls| constant_pool.push(ConstantEntry::Utf8(Utf8Info::from(&supercls.name))))
+            .unwrap_or(0);
+
+        let decl_idx = self.file.declarations().len().as_u16();
+        self.file.declarations_mut().push(Box::new(decls::ClassDecl::new(
+            name_index,
+            super_name_index,
+            vec![],
+        )));
+
+        let captured = class_decl.name.captured;
+        if captured {
+            // the cell must exist before the methods are created, as they may refer to the class
+            self.emitop0(OpCode::NPush);
+            self.emit1_16(OpCode::CellNew, var_idx);
+        }
+
+        self.emit1_16(OpCode::ClsNew, decl_idx);
+
+        // the superclass is stored in a variable which is captured by the methods
+        let super_slot = ctx.scope.var_count;
+        if let Some(supercls) = class_decl.supercls.as_ref() {
+            self.load_named(&supercls.name, &ctx.scope);
+            self.emitop0(OpCode::Dup);
+            self.emit1_16(OpCode::CellNew, super_slot);
+            self.update_max_locals(1);
+            self.emitop0(OpCode::Inherit);
+        }
+
+        let mut methods = Vec::with_capacity(class_decl.methods.len());
+        for method in &class_decl.methods {
+            let method_idx = self.reserve_func(&method.name.name, &method.params);
+            let insn = self.code.pos();
+            self.emit1_16(OpCode::FnLoad, method_idx);
+            self.emitop0(OpCode::Method);
+            methods.push((method_idx, insn));
+        }
+
+        if captured {
+            self.emit1_16(OpCode::CellStore, var_idx);
+        } else {
+            self.store_var(&var_idx);
+        }
+
+        let mut decl = self.file.declarations()[decl_idx as usize]
+            .as_class()
+            .expect("Expected a class declaration")
+            .clone();
+        decl.methods = methods.iter().map(|(method_idx, _)| *method_idx).collect();
+        self.file.declarations_mut()[decl_idx as usize] = Box::new(decl);
+
+        return PendingClass {
+            super_slot,
+            methods,
+        };
     }
 
-    fn handle_short_circuit(
+    /// Write the bodies of the methods of a class declared with [CodeGen::declare_class].
+    fn gen_class(
         &mut self,
-        binary: &mut BinaryExpr,
-        op: OpCode,
+        class_decl: &mut ClassDecl,
+        pending: PendingClass,
         ctx: &mut CodeGenContext,
     ) {
-        self.visit_expr(&mut binary.left, ctx);
+        let mut loops = vec![];
+        let mut scope = Scope::with_var_count(pending.super_slot);
+        scope.parent = Some(&ctx.scope);
+        if class_decl.supercls.is_some() {
+            let _ = scope.push_var(VarSym::with_captured(String::from(SUPER_NAME), true));
+        }
 
-        let jmpiffalsy = self.emitjmp(op);
-        self.emitop0(OpCode::Pop);
+        let mut class_ctx = CodeGenContext::with_scope(scope, &mut loops);
+        let methods = class_decl.methods.iter_mut().zip(pending.methods);
+        for (method, (method_idx, insn)) in methods {
+            let is_init = method.name.name == decls::ClassDecl::INIT_NAME;
+            self.gen_func(
+                &method.params,
+                &mut method.body,
+                method.this.as_ref(),
+                is_init,
+                method_idx,
+                &mut class_ctx,
+            );
+            self.patch_func_load(insn, method_idx);
+        }
+    }
 
-        self.visit_expr(&mut binary.right, ctx);
-        self.jmptocp(jmpiffalsy);
+    /// Reserve a declaration for the given top-level function, define a variable for it in the
+    /// current scope and write the instructions to store the function in the variable. Returns
+    /// the index of the function declaration.
+    fn declare_func(&mut self, func_decl: &FuncDecl, ctx: &mut CodeGenContext) -> u16 {
+        let decl_idx = self.reserve_func(&func_decl.name.name, &func_decl.params);
+        let var_idx = self.def_func_var(&func_decl.name, ctx);
+
+        // top-level functions cannot capture variables
+        self.mark_line(func_decl.range());
+        self.emit1_16(OpCode::FnLoad, decl_idx);
+        self.store_var(&var_idx);
+
+        return decl_idx;
     }
 
-    fn optimize(&mut self) {
-        let mut idx = 0;
-        while idx < self.cp() {
-            let opcode = get_opcode(self.get_u1(idx));
-            if opcode.is_jmp() {
-                self.resolve_jmp(opcode, idx);
+    /// Write the [Code] for the body of a function to the function declaration at `decl_idx`.
+    /// The function body is written with a fresh [CodeGen] as each function is executed in its
+    /// own call frame.
+    fn gen_func(
+        &mut self,
+        params: &Vec<IdentifierExpr>,
+        body: &mut BlockStmt,
+        this: Option<&IdentifierExpr>,
+        is_init: bool,
+        decl_idx: u16,
+        ctx: &mut CodeGenContext,
+    ) {
+        self.file
+            .constant_pool_mut()
+            .push(ConstantEntry::Utf8(Utf8Info::from(attrs::CODE)));
+
+        let mut loops = vec![];
+        let mut scope = Scope::new_func();
+        scope.parent = Some(&ctx.scope);
+
+        let mut func_ctx = CodeGenContext::with_scope(scope, &mut loops);
+        let mut codegen = CodeGen::new(self.file, self.features);
+        codegen.is_init = is_init;
+        codegen.ir_dump = self.ir_dump.as_deref_mut();
+        codegen.written_in_funcs = Rc::clone(&self.written_in_funcs);
+        codegen.number_vars = Rc::clone(&self.number_vars);
+
+        // the receiver of a method is passed as the first argument
+        let locals: Vec<&IdentifierExpr> = this.into_iter().chain(params.iter()).collect();
+        for local in &locals {
+            let sym = VarSym::with_captured(local.name.clone(), local.captured);
+            if func_ctx.scope.push_var(sym).is_err() {
+                panic!("{}", &messages::err_dup_var(&local.name));
             }
+            codegen.update_max_locals(1);
+        }
 
-            idx += 1 + opcode.operand_size() as CodeSize;
+        // captured parameters are moved to upvalue cells before the body is executed
+        for (idx, local) in locals.iter().enumerate() {
+            if local.captured {
+                codegen.load_var(&idx.as_u16());
+                codegen.emit1_16(OpCode::CellNew, idx.as_u16());
+            }
         }
-    }
 
-    fn resolve_jmp(&mut self, op: OpCode, idx: CodeSize) {
-        let jmp_delta = self.get_i2(idx + 1);
+        codegen.visit_block_stmt(body, &mut func_ctx);
 
-        let target = idx.checked_add_signed(jmp_delta as i32 + 3).unwrap();
+        // functions return null if the end of the body is reached, initializers return
+        // the receiver
+        codegen.emit_return(None, &mut func_ctx);
 
-        // target address jumps out of execution
-        if target >= self.cp() {
-            return;
+        let name = codegen.func_name(decl_idx);
+        let code = codegen.build_code(&name);
+        let line_numbers = codegen.build_line_numbers();
+        self.errors.append(&mut codegen.errors);
+        let upvalues = func_ctx
+            .scope
+            .upvalues
+            .borrow()
+            .iter()
+            .map(|upvalue| decls::UpvalueInfo::new(upvalue.is_local, upvalue.index))
+            .collect();
+
+        let mut decl = self.file.declarations()[decl_idx as usize]
+            .as_func()
+            .expect("Expected a function declaration")
+            .clone();
+        decl.upvalues = upvalues;
+        decl.attributes.push(Attr::Code(code));
+        decl.attributes.extend(line_numbers);
+        self.file.declarations_mut()[decl_idx as usize] = Box::new(decl);
+    }
+
+    /// Get the name of the function declared at `decl_idx`, as shown in the IR dump.
+    fn func_name(&self, decl_idx: u16) -> String {
+        let name_index = self.file.declarations()[decl_idx as usize]
+            .as_func()
+            .expect("Expected a function declaration")
+            .name_index;
+        let name = self
+            .file
+            .constant_pool()
+            .get(name_index)
+            .and_then(|entry| entry.as_utf8())
+            .map(|utf8| utf8.to_string())
+            .unwrap_or_default();
+        return format!("#{}: fun {}", decl_idx, name);
+    }
+
+    /// Write the instructions to return the value of the given expression, or `null` if there is
+    /// no expression. Initializers always return the receiver.
+    fn emit_return(&mut self, expr: Option<&mut Expr>, ctx: &mut CodeGenContext) {
+        if self.is_init {
+            self.load_named(&String::from(THIS_NAME), &ctx.scope);
+        } else if let Some(expr) = expr {
+            self.visit_expr(expr, ctx);
+        } else {
+            self.emitop0(OpCode::NPush);
         }
 
-        let t_op = get_opcode(self.get_u1(target));
-        if !t_op.is_jmp() {
-            return;
+        self.emitop0(OpCode::Ret);
+    }
+
+    /// Find the loop-invariant expressions of the loop with the given condition, step and body, if
+    /// loop-invariant code motion is enabled.
+    fn invariant_exprs(
+        &self,
+        condition: Option<&mut Expr>,
+        step: Option<&mut Expr>,
+        body: &mut BlockStmt,
+        scope: &Scope,
+    ) -> Vec<(String, Expr)> {
+        if !self.features.licm {
+            return Vec::with_capacity(0);
         }
 
-        // if the instruction to which `op` jumps is also a jump instruction
-        // then resolve that target jump first
-        self.resolve_jmp(t_op, target);
+        let is_local = |name: &str| scope.find_func_sym(&name.to_string()).is_some();
 
-        let t_delta = self.get_i2(target + 1);
-        let t_addr = target.checked_add_signed(t_delta as i32 + 3).unwrap();
+        return licm::invariant_exprs(
+            condition,
+            step,
+            body,
+            &self.written_in_funcs,
+            &self.number_vars,
+            &is_local,
+        );
+    }
 
-        // if both opcodes are same, or if the current jmp jumps to an unconditional jmp
-        // then update this jmp to jump directly to where the target jmp jumps
-        if op == t_op || t_op == OpCode::Jmp {
-            self.patch_jmp(idx, t_addr);
+    /// Write the condition check of a loop, which jumps to `exit` if the condition is false.
+    fn emit_loop_cond(
+        &mut self,
+        condition: Option<&mut Expr>,
+        exit: Label,
+        ctx: &mut CodeGenContext,
+    ) {
+        if let Some(condition) = condition {
+            self.visit_expr(condition, ctx);
         }
 
-        return;
+        self.emitjmp(OpCode::IfFalsy, exit);
+        self.emitop0(OpCode::Pop);
     }
 
-    /// Patch pending jumps in loops. `continue_at` is the address of the instruction at which the
-    /// program continues when a [JumpType::Continue] jump is encountered. `break_to` is the address of
-    /// the instruction at which the program continues when a [JumpType::Break] jump is encountered.
-    fn patch_loop_jmps(
+    /// Write the header of a loop, which starts at `start` and jumps to `exit` if the condition is
+    /// false. If there are invariant expressions, the condition is checked once before the loop
+    /// and the expressions are evaluated into hidden variables in between, so that they are only
+    /// evaluated if the loop is entered.
+    fn emit_loop_header(
         &mut self,
-        pending: &mut Vec<PendingJump>,
-        continue_at: CodeSize,
-        break_to: CodeSize,
+        mut condition: Option<&mut Expr>,
+        invariants: Vec<(String, Expr)>,
+        start: Label,
+        exit: Label,
+        ctx: &mut CodeGenContext,
     ) {
-        for pending in pending.drain(0..) {
-            match pending.typ {
-                JumpType::Continue => self.patch_jmp(pending.from, continue_at),
-                JumpType::Break => self.patch_jmp(pending.from, break_to),
-            }
+        if invariants.is_empty() {
+            self.bind(start);
+            self.emit_loop_cond(condition, exit, ctx);
+            return;
         }
-    }
 
-    fn load_var(&mut self, idx: &u16) {
-        match idx {
-            0 => self.emitop0(OpCode::Load0),
-            1 => self.emitop0(OpCode::Load1),
-            2 => self.emitop0(OpCode::Load2),
-            3 => self.emitop0(OpCode::Load3),
-            _ => self.emit1_16(OpCode::Load, idx.clone()),
-        };
+        self.emit_loop_cond(condition.as_deref_mut(), exit, ctx);
+        for (key, mut expr) in invariants {
+            self.visit_expr(&mut expr, ctx);
+
+            let name = format!("<licm {}>", ctx.scope.var_count);
+            let var_idx = ctx
+                .scope
+                .push_var(VarSym::with_captured(name, false))
+                .expect("Hidden variables are never duplicated");
+            self.store_var(&var_idx);
+            self.update_max_locals(1);
+            self.hoisted.push((key, var_idx));
+        }
+
+        let body = self.code.new_label();
+        self.emitjmp(OpCode::Jmp, body);
+
+        self.bind(start);
+        self.emit_loop_cond(condition, exit, ctx);
+        self.bind(body);
     }
 
-    fn store_var(&mut self, idx: &u16) {
-        match idx {
-            0 => self.emitop0(OpCode::Store0),
-            1 => self.emitop0(OpCode::Store1),
-            2 => self.emitop0(OpCode::Store2),
-            3 => self.emitop0(OpCode::Store3),
-            _ => self.emit1_16(OpCode::Store, idx.clone()),
-        };
+    /// Load the hidden variable which holds the value of the expression with the given key, if the
+    /// expression has been hoisted out of an enclosing loop. Returns whether the value was loaded.
+    fn load_hoisted(&mut self, key: Option<String>) -> bool {
+        let var_idx = key.and_then(|key| {
+            self.hoisted
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, var_idx)| *var_idx)
+        });
+
+        if let Some(var_idx) = var_idx {
+            self.load_var(&var_idx);
+            return true;
+        }
+
+        return false;
     }
 
     fn to_arith_opcode(&self, op: &BinaryOp) -> OpCode {
@@ -491,6 +930,16 @@ impl<'a> CodeGen<'a> {
 }
 
 impl ASTVisitor<CodeGenContext<'_>, ()> for CodeGen<'_> {
+    fn visit_decl(&mut self, decl: &mut Decl, ctx: &mut CodeGenContext) -> Option<()> {
+        self.mark_line(decl.range());
+        self.default_visit_decl(decl, ctx)
+    }
+
+    fn visit_stmt(&mut self, stmt: &mut Stmt, ctx: &mut CodeGenContext) -> Option<()> {
+        self.mark_line(stmt.range());
+        self.default_visit_stmt(stmt, ctx)
+    }
+
     fn visit_program(&mut self, program: &mut Program, ctx: &mut CodeGenContext) -> Option<()> {
         if self
             .file
@@ -502,25 +951,74 @@ impl ASTVisitor<CodeGenContext<'_>, ()> for CodeGen<'_> {
             panic!("A YKBFile cannot have multiple Code attributes")
         }
 
-        self.default_visit_program(program, ctx, true, false);
+        if self.features.licm {
+            self.written_in_funcs = Rc::new(licm::written_in_funcs(program));
+            self.number_vars = Rc::new(licm::number_vars(program));
+        }
+
+        // top-level functions are stored in their variables before the top-level statements are
+        // executed, so that they can be called before they are declared
+        let mut funcs = Vec::with_capacity(0);
+        for decl in &program.decls {
+            if let Decl::Func(func_decl) = decl {
+                funcs.push(self.declare_func(func_decl, ctx));
+            }
+        }
+
+        // top-level classes are created after the functions, in the order of their declaration
+        let mut class_vars = Vec::with_capacity(0);
+        for decl in &program.decls {
+            if let Decl::Class(class_decl) = decl {
+                class_vars.push(self.def_class_var(&class_decl.name, ctx));
+            }
+        }
+
+        let mut classes = Vec::with_capacity(0);
+        let mut class_vars = class_vars.into_iter();
+        for decl in &program.decls {
+            if let Decl::Class(class_decl) = decl {
+                let var_idx = class_vars.next().unwrap();
+                classes.push(self.declare_class(class_decl, var_idx, ctx));
+            }
+        }
+
         for i in 0..program.stmts.len() {
             let stmt = program.stmts.get_mut(i).unwrap();
             self.visit_stmt(stmt, ctx);
         }
 
-        if self.instructions().len() > 0 {
+        // the function bodies are written after the top-level statements so that all the
+        // top-level variables are known to the functions
+        let mut funcs = funcs.into_iter();
+        let mut classes = classes.into_iter();
+        for decl in program.decls.iter_mut() {
+            match decl {
+                Decl::Func(func_decl) => self.gen_func(
+                    &func_decl.params,
+                    &mut func_decl.body,
+                    None,
+                    false,
+                    funcs.next().unwrap(),
+                    ctx,
+                ),
+                Decl::Class(class_decl) => {
+                    self.gen_class(class_decl, classes.next().unwrap(), ctx)
+                }
+                _ => {
+                    self.visit_decl(decl, ctx);
+                }
+            }
+        }
+
+        if !self.code.is_empty() {
             self.file
                 .constant_pool_mut()
                 .push(ConstantEntry::Utf8(Utf8Info::from(attrs::CODE)));
 
-            self.optimize();
-
-            let code = Attr::Code(Code::with_insns(
-                self.max_stack,
-                self.max_locals,
-                self.instructions.clone(),
-            ));
+            let code = Attr::Code(self.build_code("<top-level>"));
+            let line_numbers = self.build_line_numbers();
             self.file.attributes_mut().push(code);
+            self.file.attributes_mut().extend(line_numbers);
         }
 
         self.reset();
@@ -530,14 +1028,12 @@ impl ASTVisitor<CodeGenContext<'_>, ()> for CodeGen<'_> {
     fn visit_class_decl(
         &mut self,
         class_decl: &mut ClassDecl,
-        _ctx: &mut CodeGenContext,
+        ctx: &mut CodeGenContext,
     ) -> Option<()> {
-        let constant_pool = self.file.constant_pool_mut();
-        let name_index =
-            constant_pool.push(ConstantEntry::Utf8(Utf8Info::from(&class_decl.name.name)));
-        self.file
-            .declarations_mut()
-            .push(Box::new(decls::ClassDecl::new(name_index)));
+        // top-level classes are handled in visit_program, so this is a nested class
+        let var_idx = self.def_class_var(&class_decl.name, ctx);
+        let pending = self.declare_class(class_decl, var_idx, ctx);
+        self.gen_class(class_decl, pending, ctx);
         None
     }
 
@@ -546,14 +1042,35 @@ impl ASTVisitor<CodeGenContext<'_>, ()> for CodeGen<'_> {
         func_decl: &mut FuncDecl,
         ctx: &mut CodeGenContext,
     ) -> Option<()> {
-        let constant_pool = self.file.constant_pool_mut();
-        let name_index =
-            constant_pool.push(ConstantEntry::Utf8(Utf8Info::from(&func_decl.name.name)));
-        self.file
-            .declarations_mut()
-            .push(Box::new(decls::FuncDecl::new(name_index)));
+        // top-level functions are handled in visit_program, so this is a nested function
+        let decl_idx = self.reserve_func(&func_decl.name.name, &func_decl.params);
+        let var_idx = self.def_func_var(&func_decl.name, ctx);
+        self.gen_func(&func_decl.params, &mut func_decl.body, None, false, decl_idx, ctx);
+
+        if func_decl.name.captured {
+            // the cell must exist before the closure is created, as the function may refer to
+            // itself
+            self.emitop0(OpCode::NPush);
+            self.emit1_16(OpCode::CellNew, var_idx);
+            self.load_func(decl_idx);
+            self.emit1_16(OpCode::CellStore, var_idx);
+        } else {
+            self.load_func(decl_idx);
+            self.store_var(&var_idx);
+        }
 
-        self.default_visit_func_decl(func_decl, ctx)
+        None
+    }
+
+    fn visit_lambda_expr(
+        &mut self,
+        lambda_expr: &mut LambdaExpr,
+        ctx: &mut CodeGenContext,
+    ) -> Option<()> {
+        let decl_idx = self.reserve_func(Self::LAMBDA_NAME, &lambda_expr.params);
+        self.gen_func(&lambda_expr.params, &mut lambda_expr.body, None, false, decl_idx, ctx);
+        self.load_func(decl_idx);
+        None
     }
 
     fn visit_var_stmt(
@@ -566,10 +1083,16 @@ impl ASTVisitor<CodeGenContext<'_>, ()> for CodeGen<'_> {
         // the variable is declared
         if let Some(expr) = var_decl.initializer.as_mut() {
             self.visit_expr(expr, ctx);
+        } else {
+            self.emitop0(OpCode::NPush);
         }
 
         let var_name = &var_decl.name.name.clone();
-        let var_idx = match ctx.scope.push_var(VarSym::new(var_name.clone())) {
+        let captured = var_decl.name.captured;
+        let var_idx = match ctx
+            .scope
+            .push_var(VarSym::with_captured(var_name.clone(), captured))
+        {
             // This duplicate variable error must have been handled during the attribution phase
             // if it wansn't somehow reported at that point, then we panic
             Err(_) => panic!("{}", &messages::err_dup_var(&var_name)),
@@ -577,6 +1100,8 @@ impl ASTVisitor<CodeGenContext<'_>, ()> for CodeGen<'_> {
         };
 
         let opcode = match var_idx {
+            // captured variables are stored in upvalue cells
+            _ if captured => OpCode::CellNew,
             0 => OpCode::Store0,
             1 => OpCode::Store1,
             2 => OpCode::Store2,
@@ -584,7 +1109,7 @@ impl ASTVisitor<CodeGenContext<'_>, ()> for CodeGen<'_> {
             _ => OpCode::Store,
         };
 
-        if opcode != OpCode::Store {
+        if opcode != OpCode::Store && opcode != OpCode::CellNew {
             self.emitop(opcode);
         } else {
             self.emit1_16(opcode, var_idx);
@@ -621,43 +1146,53 @@ impl ASTVisitor<CodeGenContext<'_>, ()> for CodeGen<'_> {
             self.visit_stmt(init, ctx);
         }
 
-        let cp = self.cp();
-        let mut _continue = cp;
+        let invariants = self.invariant_exprs(
+            for_stmt.condition.as_mut(),
+            for_stmt.step.as_mut(),
+            &mut for_stmt.body,
+            &ctx.scope,
+        );
+
+        // the variables of the hoisted expressions are only visible to the loop
+        let mut scope = Scope::with_var_count(ctx.scope.var_count);
+        scope.parent = Some(&ctx.scope);
+        let ctx = &mut CodeGenContext::with_scope(scope, ctx.loops.as_mut());
+        let hoisted = self.hoisted.len();
+
+        let start = self.code.new_label();
+        let _continue = self.code.new_label();
+        let _break = self.code.new_label();
+        let exit = self.code.new_label();
 
         ctx.push_loop(
-            cp,
+            _continue,
+            _break,
             LoopType::For,
             for_stmt.label.as_ref().map(|l| l.name.clone()),
         );
 
         // step2: exec condition, if any
-        if let Some(condition) = for_stmt.condition.as_mut() {
-            self.visit_expr(condition, ctx);
-        }
-
         // if condition is false, jump to the end
-        let branch = self.emitjmp(OpCode::IfFalsy);
-        self.emitop0(OpCode::Pop);
+        self.emit_loop_header(for_stmt.condition.as_mut(), invariants, start, exit, ctx);
 
         // if condition is true, exec body
         self.visit_block_stmt(&mut for_stmt.body, ctx);
 
         // exec step expr, if any
+        self.bind(_continue);
         if let Some(step) = for_stmt.step.as_mut() {
-            _continue = self.cp();
             self.visit_expr(step, ctx);
         }
 
         // step3: jmp to start of loop (condition check)
-        self.emitjmp1(OpCode::Jmp, cp);
+        self.emitjmp(OpCode::Jmp, start);
 
-        self.jmptocp(branch);
+        self.bind(exit);
         self.emitop0(OpCode::Pop);
 
-        let _break = self.cp();
-
-        let mut _loop = ctx.pop_loop().unwrap();
-        self.patch_loop_jmps(_loop.pending_jumps.as_mut(), _continue, _break);
+        self.bind(_break);
+        ctx.pop_loop();
+        self.hoisted.truncate(hoisted);
 
         None
     }
@@ -667,12 +1202,12 @@ impl ASTVisitor<CodeGenContext<'_>, ()> for CodeGen<'_> {
         break_stmt: &mut BreakStmt,
         ctx: &mut CodeGenContext<'_>,
     ) -> Option<()> {
-        let _loop = ctx
+        let break_to = ctx
             .find_loop(break_stmt.label.as_ref())
-            .expect("Expected a loop context");
+            .expect("Expected a loop context")
+            .break_to;
 
-        let pc = self.emitjmp(OpCode::Jmp);
-        _loop.pending_jumps.push(PendingJump::_break(pc));
+        self.emitjmp(OpCode::Jmp, break_to);
 
         None
     }
@@ -682,12 +1217,12 @@ impl ASTVisitor<CodeGenContext<'_>, ()> for CodeGen<'_> {
         continue_stmt: &mut ContinueStmt,
         ctx: &mut CodeGenContext<'_>,
     ) -> Option<()> {
-        let _loop = ctx
+        let continue_to = ctx
             .find_loop(continue_stmt.label.as_ref())
-            .expect("Expected a loop context");
+            .expect("Expected a loop context")
+            .continue_to;
 
-        let pc = self.emitjmp(OpCode::Jmp);
-        _loop.pending_jumps.push(PendingJump::_continue(pc));
+        self.emitjmp(OpCode::Jmp, continue_to);
 
         None
     }
@@ -695,26 +1230,50 @@ impl ASTVisitor<CodeGenContext<'_>, ()> for CodeGen<'_> {
     fn visit_if_stmt(&mut self, if_stmt: &mut IfStmt, ctx: &mut CodeGenContext) -> Option<()> {
         self.visit_expr(&mut if_stmt.condition, ctx);
 
-        let jmp = self.emitjmp(OpCode::IfFalsy);
+        let else_branch = self.code.new_label();
+        let end = self.code.new_label();
+
+        self.emitjmp(OpCode::IfFalsy, else_branch);
         self.emitop0(OpCode::Pop);
         self.visit_block_stmt(&mut if_stmt.then_branch, ctx);
 
-        let mut jmpto = self.cp();
-        if let Some(else_branch) = if_stmt.else_branch.as_mut() {
-            let elsejmp = self.emitjmp(OpCode::Jmp);
-            jmpto = self.cp();
+        // iffalsy (or iftruthy) does not pop the operands.
+        // so we pop the condition operand manually
+        // this must be done in any case, whether we have the else branch or not
+        self.emitjmp(OpCode::Jmp, end);
+        self.bind(else_branch);
 
-            self.emitop0(OpCode::Pop);
+        self.emitop0(OpCode::Pop);
+        if let Some(else_branch) = if_stmt.else_branch.as_mut() {
             self.visit_block_stmt(else_branch, ctx);
-            self.jmptocp(elsejmp);
-        } else {
-            // iffalsy (or iftruthy) does not pop the operands.
-            // so we pop the condition operand manually
-            // this must be done in any case, whether we have the else branch or not
-            self.emitop0(OpCode::Pop);
         }
 
-        self.patch_jmp(jmp, jmpto);
+        self.bind(end);
+
+        None
+    }
+
+    fn visit_return_stmt(
+        &mut self,
+        return_stmt: &mut ReturnStmt,
+        ctx: &mut CodeGenContext<'_>,
+    ) -> Option<()> {
+        self.emit_return(Some(&mut return_stmt.expr), ctx);
+        None
+    }
+
+    fn visit_expr_stmt(
+        &mut self,
+        expr_stmt: &mut ExprStmt,
+        ctx: &mut CodeGenContext<'_>,
+    ) -> Option<()> {
+        self.visit_expr(&mut expr_stmt.expr, ctx);
+
+        // the result of a function call is discarded when the call is used as a statement,
+        // otherwise calls in loops would keep growing the operand stack
+        if let Expr::FuncCall(_) = &expr_stmt.expr {
+            self.emitop0(OpCode::Pop);
+        }
 
         None
     }
@@ -734,30 +1293,40 @@ impl ASTVisitor<CodeGenContext<'_>, ()> for CodeGen<'_> {
         while_stmt: &mut WhileStmt,
         ctx: &mut CodeGenContext<'_>,
     ) -> Option<()> {
-        let cp = self.cp();
+        let invariants = self.invariant_exprs(
+            Some(&mut while_stmt.condition),
+            None,
+            &mut while_stmt.body,
+            &ctx.scope,
+        );
+
+        // the variables of the hoisted expressions are only visible to the loop
+        let mut scope = Scope::with_var_count(ctx.scope.var_count);
+        scope.parent = Some(&ctx.scope);
+        let ctx = &mut CodeGenContext::with_scope(scope, ctx.loops.as_mut());
+        let hoisted = self.hoisted.len();
+
+        let start = self.code.new_label();
+        let _break = self.code.new_label();
+        let exit = self.code.new_label();
         ctx.push_loop(
-            cp,
+            start,
+            _break,
             LoopType::While,
             while_stmt.label.as_ref().map(|l| l.name.clone()),
         );
 
-        let _continue = cp;
-
-        self.visit_expr(&mut while_stmt.condition, ctx);
-        let jmp = self.emitjmp(OpCode::IfFalsy);
-        self.emitop0(OpCode::Pop);
+        self.emit_loop_header(Some(&mut while_stmt.condition), invariants, start, exit, ctx);
 
         self.visit_block_stmt(&mut while_stmt.body, ctx);
-        self.emitjmp1(OpCode::Jmp, cp);
+        self.emitjmp(OpCode::Jmp, start);
 
-        self.jmptocp(jmp);
+        self.bind(exit);
         self.emitop0(OpCode::Pop);
 
-        let _break = self.cp();
-
-        if let Some(mut _loop) = ctx.pop_loop() {
-            self.patch_loop_jmps(_loop.pending_jumps.as_mut(), _continue, _break);
-        }
+        self.bind(_break);
+        ctx.pop_loop();
+        self.hoisted.truncate(hoisted);
 
         None
     }
@@ -770,7 +1339,14 @@ impl ASTVisitor<CodeGenContext<'_>, ()> for CodeGen<'_> {
         match &mut assign.target {
             Expr::Identifier(identifier) => {
                 self.visit_expr(&mut assign.value, ctx);
-                self.store_var(ctx.scope.req_var_idx(&identifier.name));
+                self.store_named(&identifier.name, &ctx.scope);
+            }
+
+            Expr::MemberAccess(acc) => {
+                self.visit_expr(&mut acc.receiver, ctx);
+                self.visit_expr(&mut assign.value, ctx);
+                let name_idx = self.member_name(&acc.member);
+                self.emit1_16(OpCode::PutField, name_idx);
             }
 
             Expr::ArrayAccess(acc) => {
@@ -794,11 +1370,19 @@ impl ASTVisitor<CodeGenContext<'_>, ()> for CodeGen<'_> {
     ) -> Option<()> {
         match &mut assign.target {
             Expr::Identifier(identifier) => {
-                let idx = ctx.scope.req_var_idx(&identifier.name).clone();
-                self.load_var(&idx);
+                self.load_named(&identifier.name, &ctx.scope);
                 self.visit_expr(&mut assign.value, ctx);
                 self.emitop0(self.to_arith_opcode(&assign.op));
-                self.store_var(&idx);
+                self.store_named(&identifier.name, &ctx.scope);
+            }
+            Expr::MemberAccess(acc) => {
+                let name_idx = self.member_name(&acc.member);
+                self.visit_expr(&mut acc.receiver, ctx);
+                self.emitop0(OpCode::Dup);
+                self.emit1_16(OpCode::GetField, name_idx);
+                self.visit_expr(&mut assign.value, ctx);
+                self.emitop0(self.to_arith_opcode(&assign.op));
+                self.emit1_16(OpCode::PutField, name_idx);
             }
             _ => panic!("Unsupported assign expr: {:?}", assign),
         };
@@ -811,6 +1395,10 @@ impl ASTVisitor<CodeGenContext<'_>, ()> for CodeGen<'_> {
         binary: &mut BinaryExpr,
         ctx: &mut CodeGenContext,
     ) -> Option<()> {
+        if !self.hoisted.is_empty() && self.load_hoisted(licm::binary_key(binary, &|_| true)) {
+            return None;
+        }
+
         match &binary.op {
             BinaryOp::Plus | BinaryOp::Minus | BinaryOp::Mult | BinaryOp::Div => {
                 self.visit_expr(&mut binary.left, ctx);
@@ -836,38 +1424,29 @@ impl ASTVisitor<CodeGenContext<'_>, ()> for CodeGen<'_> {
 
         // 0(is_zero): whether any of the operands are 0
         // 1(on_left): whether the left operand is 0
-        let (is_z, z_on_left) = binary
-            .left
-            .Literal()
-            .and_then(|l| l.Number().map(|l| (&l.0 == &0f64, true)))
-            .or_else(|| {
-                binary
-                    .right
-                    .Literal()
-                    .and_then(|l| l.Number().map(|l| (&l.0 == &0f64, false)))
-            })
-            .unwrap_or((false, false));
-
-        // Determine the opcode which checks if the condition is **FALSE**
-        // For example, the binary operator is EqEq in `if true == false`,
-        // therefore, the else condition must be executed if true != false
-        // as a result, the actual opcode should be `IfNe`
-        let opcode = match (is_z, z_on_left) {
-            (false, false) => opcode_cmp(&binary.op),
-            (false, true) => opcode_cmp(&binary.op.inv_cmp().unwrap()),
-            (true, false) => opcode_cmpz(&binary.op),
-
-            (true, true) => {
-                if binary.op != BinaryOp::EqEq && binary.op != BinaryOp::NotEq {
-                    opcode_cmpz(&binary.op.inv_cmp().unwrap())
-                } else {
-                    // if 0 is the left operand, and the operator is EqEq or NotEq,
-                    // we don't need to invert the binary operator
-                    // this is becuase a == 0 and 0 == a have the same result
-                    // same goes for a != 0 and 0 != a
-                    opcode_cmpz(&binary.op)
-                }
-            }
+        // a zero left operand can only be swapped with the right operand in an equality check, as
+        // a == 0 and 0 == a have the same result, same goes for a != 0 and 0 != a
+        // the ordered comparisons keep the order of their operands, so that a comparison of
+        // incompatible operands reports them in the order in which they were written
+        let is_eq = binary.op == BinaryOp::EqEq || binary.op == BinaryOp::NotEq;
+        let is_zero =
+            |e: &Expr| matches!(e.Literal().and_then(|l| l.Number()), Some(n) if n.0 == 0f64);
+        let (is_z, z_on_left) = if is_zero(&binary.right) {
+            (true, false)
+        } else if is_eq && is_zero(&binary.left) {
+            (true, true)
+        } else {
+            (false, false)
+        };
+
+        // Determine the opcode which checks if the condition is **TRUE**
+        // For example, the binary operator is Lt in `a < b`, so the opcode is `IfLt`
+        // the inverse opcode (`IfGe`) cannot be used to push false, as every ordered comparison
+        // with NaN is false
+        let opcode = if is_z {
+            opcode_cmpz(&binary.op)
+        } else {
+            opcode_cmp(&binary.op)
         };
 
         match &binary.op {
@@ -895,18 +1474,19 @@ impl ASTVisitor<CodeGenContext<'_>, ()> for CodeGen<'_> {
                 }
 
                 // compare operands
-                let ifcmps = self.emitjmp(opcode);
-
-                // 1... if comparison succeeds, push true, and jmp to next insn
-                self.emitop0(OpCode::BPush1);
-                let cmpsj = self.emitjmp(OpCode::Jmp);
+                let push1 = self.code.new_label();
+                let end = self.code.new_label();
+                self.emitjmp(opcode, push1); // push true if comparison succeeds
 
-                // 2... if comparison fails, push false
-                let push0 = self.cp();
+                // 1... if comparison fails, push false, and jmp to next insn
                 self.emitop0(OpCode::BPush0);
+                self.emitjmp(OpCode::Jmp, end);
+
+                // 2... if comparison succeeds, push true
+                self.bind(push1);
+                self.emitop0(OpCode::BPush1);
 
-                self.jmptocp(cmpsj); // jump to next insn if comparison succeeds
-                self.patch_jmp(ifcmps, push0); // push false if comparison succeeds
+                self.bind(end);
             }
             _ => unreachable!(),
         }
@@ -919,6 +1499,10 @@ impl ASTVisitor<CodeGenContext<'_>, ()> for CodeGen<'_> {
         unary_expr: &mut UnaryExpr,
         ctx: &mut CodeGenContext<'_>,
     ) -> Option<()> {
+        if !self.hoisted.is_empty() && self.load_hoisted(licm::unary_key(unary_expr, &|_| true)) {
+            return None;
+        }
+
         self.visit_expr(&mut unary_expr.expr, ctx);
         self.emitop0(match &unary_expr.op {
             UnaryOp::Negate => OpCode::Neg,
@@ -939,12 +1523,8 @@ impl ASTVisitor<CodeGenContext<'_>, ()> for CodeGen<'_> {
             constant_pool.push(ConstantEntry::Utf8(Utf8Info::from(&identifier.name)));
         }
 
-        if !typ.is_decl_name() && typ != &IdentifierType::Keyword {
-            if let Some(idx) = ctx.scope.get_var_idx(&identifier.name) {
-                self.load_var(idx);
-            } else {
-                panic!("Variable not found: {}", &identifier.name);
-            }
+        if !typ.is_decl_name() {
+            self.load_named(&identifier.name, &ctx.scope);
         }
 
         None
@@ -957,7 +1537,9 @@ impl ASTVisitor<CodeGenContext<'_>, ()> for CodeGen<'_> {
     ) -> Option<()> {
         let constant_pool = self.file.constant_pool_mut();
         match literal {
-            LiteralExpr::Null(_) => {}
+            LiteralExpr::Null(_) => {
+                self.emitop(OpCode::NPush);
+            }
             LiteralExpr::Bool((boo, _)) => {
                 self.emitop(if *boo { OpCode::BPush1 } else { OpCode::BPush0 });
             }
@@ -1000,6 +1582,47 @@ impl ASTVisitor<CodeGenContext<'_>, ()> for CodeGen<'_> {
         None
     }
 
+    fn visit_func_call_expr(
+        &mut self,
+        call: &mut FuncCallExpr,
+        ctx: &mut CodeGenContext<'_>,
+    ) -> Option<()> {
+        self.visit_expr(&mut call.callee, ctx);
+        for arg in call.args.iter_mut() {
+            self.visit_expr(arg, ctx);
+        }
+
+        // the callee and the arguments are popped, and the result is pushed
+        let argc = call.args.len().as_u8();
+        self.emit1_8(OpCode::Call, argc);
+        None
+    }
+
+    fn visit_member_access_expr(
+        &mut self,
+        member_access_expr: &mut MemberAccessExpr,
+        ctx: &mut CodeGenContext<'_>,
+    ) -> Option<()> {
+        let name_idx = self.member_name(&member_access_expr.member);
+        let is_super = member_access_expr
+            .receiver
+            .Identifier()
+            .map(|receiver| receiver.typ == IdentifierType::Keyword && receiver.name == SUPER_NAME)
+            .unwrap_or(false);
+
+        if is_super {
+            // methods of the superclass are bound to the receiver of the current method
+            self.load_named(&String::from(THIS_NAME), &ctx.scope);
+            self.load_named(&String::from(SUPER_NAME), &ctx.scope);
+            self.emit1_16(OpCode::GetSuper, name_idx);
+        } else {
+            self.visit_expr(&mut member_access_expr.receiver, ctx);
+            self.emit1_16(OpCode::GetField, name_idx);
+        }
+
+        None
+    }
+
     fn visit_array_access_expr(
         &mut self,
         array_expr: &mut ArrayAccessExpr,
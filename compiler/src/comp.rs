@@ -13,15 +13,21 @@
  * program. If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::fs;
 use std::fs::File;
 use std::io::Read;
+use std::path::Path;
 use std::path::PathBuf;
 
 use log::debug;
 use log::error;
 use log::info;
+use log::log_enabled;
+use log::Level;
 
 use crate::args::CompileArgs;
+use crate::args::Emit;
+use crate::args::MessageFormat;
 use crate::ast::Program;
 use crate::bytecode::EXT_YK;
 use crate::bytecode::EXT_YKB;
@@ -30,19 +36,30 @@ use crate::bytecode::YKBFileWriter;
 use crate::bytecode::YKBVersion;
 pub use crate::comp::attr::Attr;
 pub use crate::comp::constfold::ConstFold;
+pub use crate::comp::constprop::ConstProp;
+pub use crate::comp::dce::DeadCodeElim;
+pub use crate::comp::lint::Lint;
 pub use crate::comp::resolve::Resolve;
 use crate::diagnostics::CollectingDiagnosticHandler;
+use crate::diagnostics::Diagnostic;
+use crate::diagnostics::DiagnosticRenderer;
 use crate::features::CompilerFeatures;
 use crate::lexer::YKLexer;
 use crate::parser::YKParser;
 
 mod attr;
 mod constfold;
+mod constprop;
+mod dce;
+mod lint;
 mod resolve;
 
 // Compiles source files into bytecode.
 pub struct YKCompiler {
     diagnostics: CollectingDiagnosticHandler,
+
+    /// The names of the native functions which are provided by the VM.
+    natives: Vec<String>,
 }
 
 impl YKCompiler {
@@ -50,10 +67,26 @@ impl YKCompiler {
     pub fn new() -> YKCompiler {
         YKCompiler {
             diagnostics: CollectingDiagnosticHandler::new(),
+            natives: Vec::with_capacity(0),
+        }
+    }
+
+    /// Declare a native function with the given name, so that the programs compiled by this
+    /// compiler can call it.
+    pub fn declare_native(&mut self, name: &str) {
+        if !self.natives.iter().any(|native| native == name) {
+            self.natives.push(name.to_string());
         }
     }
 
+    /// Get the names of the native functions declared in this compiler.
+    pub fn natives(&self) -> &Vec<String> {
+        return &self.natives;
+    }
+
     pub fn compile(&mut self, args: &CompileArgs, features: &CompilerFeatures) -> Result<(), ()> {
+        args.color.apply();
+
         for path in &args.files {
             if !path.exists() {
                 error!("File not found: {}", path.display());
@@ -74,26 +107,51 @@ impl YKCompiler {
     fn perform_compilation(
         &mut self,
         path: &PathBuf,
-        _args: &CompileArgs,
+        args: &CompileArgs,
         features: &CompilerFeatures,
     ) -> Result<(), ()> {
         let display = path.file_name().unwrap();
         debug!("[{:?}] Compiling", display);
 
-        let file = File::open(path).unwrap();
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(err) => {
+                error!("Unable to read {}: {}", path.display(), err);
+                return Err(());
+            }
+        };
 
         info!("[{:?}] Parsing file", display);
 
-        let (mut program, mut has_errors) = self.parse(file)?;
-        has_errors |= self.attr(&mut program, features);
+        let (mut program, mut has_errors) = self.parse(source.as_bytes())?;
+
+        // the program contains error nodes if it could not be parsed, which would only cause
+        // bogus errors in the later phases
+        if !has_errors {
+            has_errors = self.attr(&mut program, features);
+        }
+        self.report_diagnostics(path, &source, args.message_format);
 
         if has_errors {
             info!("[{:?}] Compilation failed", display);
             return Err(());
         }
 
+        if args.emit == Emit::Ir {
+            info!("[{:?}] Generating IR", display);
+            let dump = self.dump_ir(&mut program, features);
+            self.report_diagnostics(path, &source, args.message_format);
+            print!("{}", dump?);
+            return Ok(());
+        }
+
         info!("[{:?}] Generating bytecode", display);
-        let mut ykbfile = self.ir(&mut program, features);
+        let ykbfile = self.ir(&mut program, features);
+        self.report_diagnostics(path, &source, args.message_format);
+        let Ok(mut ykbfile) = ykbfile else {
+            info!("[{:?}] Compilation failed", display);
+            return Err(());
+        };
 
         info!("[{:?}] Writing bytecode", display);
 
@@ -107,6 +165,30 @@ impl YKCompiler {
         Ok(())
     }
 
+    /// Print the diagnostics reported while compiling the file at the given path, and clear them.
+    /// Human-readable diagnostics are printed to the standard error, along with the logs, while
+    /// JSON diagnostics are printed to the standard output so that they can be parsed by tools.
+    fn report_diagnostics(&mut self, path: &Path, source: &str, format: MessageFormat) {
+        let path = path.display().to_string();
+        let renderer = DiagnosticRenderer::new(&path, source);
+
+        // the phases report the diagnostics in the order in which they visit the program
+        self.diagnostics
+            .diagnostics
+            .sort_by_key(|diagnostic| diagnostic.range.start.index);
+        for diagnostic in self.diagnostics.diagnostics.drain(..) {
+            match format {
+                MessageFormat::Json => println!("{}", diagnostic.to_json(&path)),
+
+                // diagnostics are silenced along with the rest of the output
+                MessageFormat::Human if log_enabled!(Level::Error) => {
+                    eprint!("{}", renderer.render(&diagnostic))
+                }
+                MessageFormat::Human => {}
+            }
+        }
+    }
+
     /// Parse source code and return the resulting AST.
     pub fn parse<R: Read>(&mut self, source: R) -> Result<(Program, bool), ()> {
         let lexer = YKLexer::new(source, &mut self.diagnostics);
@@ -118,15 +200,40 @@ impl YKCompiler {
     /// Run the attribution phase on the given program and return whether any errors were found.
     pub fn attr(&mut self, program: &mut Program, features: &CompilerFeatures) -> bool {
         let mut attr = Attr::new(features, &mut self.diagnostics);
+        attr.declare_natives(&self.natives);
         attr.analyze(program);
         attr.has_errors()
     }
 
-    /// Generate the intermediate [YKBFile] representation for the given program.
-    pub fn ir(&mut self, program: &mut Program, features: &CompilerFeatures) -> YKBFile {
+    /// Generate the intermediate [YKBFile] representation for the given program. Returns an
+    /// error if the code of the program cannot be represented in bytecode, the errors are
+    /// collected with the other diagnostics.
+    pub fn ir(
+        &mut self,
+        program: &mut Program,
+        features: &CompilerFeatures,
+    ) -> Result<YKBFile, ()> {
+        let mut ykbfile = YKBFile::new(YKBVersion::LATEST.clone());
+        let mut ykbwriter = YKBFileWriter::new(&mut ykbfile, features);
+        let result = ykbwriter.write(program);
+        self.collect_errors(result)?;
+        Ok(ykbfile)
+    }
+
+    /// Generate the bytecode for the given program and return a dump of the IR from which the
+    /// code of the top-level statements and of each function is lowered.
+    pub fn dump_ir(
+        &mut self,
+        program: &mut Program,
+        features: &CompilerFeatures,
+    ) -> Result<String, ()> {
         let mut ykbfile = YKBFile::new(YKBVersion::LATEST.clone());
         let mut ykbwriter = YKBFileWriter::new(&mut ykbfile, features);
-        ykbwriter.write(program);
-        ykbfile
+        let result = ykbwriter.write_with_ir(program);
+        self.collect_errors(result)
+    }
+
+    fn collect_errors<T>(&mut self, result: Result<T, Vec<Diagnostic>>) -> Result<T, ()> {
+        result.map_err(|errors| self.diagnostics.diagnostics.extend(errors))
     }
 }
@@ -16,6 +16,9 @@
 use crate::ast::Program;
 use crate::ast::Visitable;
 use crate::comp::ConstFold;
+use crate::comp::ConstProp;
+use crate::comp::DeadCodeElim;
+use crate::comp::Lint;
 use crate::comp::Resolve;
 use crate::diagnostics::DiagnosticHandler;
 use crate::features::CompilerFeatures;
@@ -48,6 +51,13 @@ impl<'inst> Attr<'inst> {
         return self.has_errors;
     }
 
+    /// Declare the native functions with the given names.
+    pub fn declare_natives(&mut self, natives: &[String]) {
+        for native in natives {
+            self.resolve.declare_native(native);
+        }
+    }
+
     /// Reset the state.
     pub fn reset(&mut self) {
         self.resolve.reset();
@@ -59,10 +69,29 @@ impl<'inst> Attr<'inst> {
         self.reset();
         self.resolve(program);
 
+        // the lints are run before the constant folding, so that the conditions are still in the
+        // form in which they were written
+        if !self.has_errors {
+            let mut lint = Lint::new(self.features, self.resolve.diagnostics());
+            lint.analyze(program);
+        }
+
+        // Substitute the constant variables, if enabled, so that the expressions reading them can
+        // be folded
+        if !self.has_errors && self.features.const_propagation {
+            ConstProp::new(self.features).analyze(program);
+        }
+
         // Fold constant expressions, if enabled
         if self.features.const_folding {
             program.accept(&mut self.constfold, &mut ());
         }
+
+        // Remove the code which is never executed, if enabled. This runs after the constant
+        // folding so that the conditions which fold to a literal are eliminated as well
+        if self.features.dead_code_elim {
+            program.accept(&mut DeadCodeElim::new(), &mut ());
+        }
     }
 
     /// Perform the name resolution.
@@ -13,6 +13,8 @@
  * program. If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::cmp::Ordering;
+
 use log::trace;
 
 use crate::ast::ASTVisitor;
@@ -22,12 +24,15 @@ use crate::ast::BlockStmt;
 use crate::ast::Decl;
 use crate::ast::EmptyStmt;
 use crate::ast::Expr;
+use crate::ast::ForStmt;
 use crate::ast::IfStmt;
 use crate::ast::LiteralExpr;
 use crate::ast::Spanned;
+use crate::ast::SpannedMut;
 use crate::ast::Stmt;
 use crate::ast::UnaryExpr;
 use crate::ast::UnaryOp;
+use crate::ast::WhileStmt;
 
 /// Helper for constant folding in the compiler.
 pub struct ConstFold;
@@ -142,8 +147,29 @@ impl ConstFold {
             right = exp;
         }
 
-        match (left, right) {
-            (Expr::Literal(l), Expr::Literal(r)) => match (l, r) {
+        if let (Expr::Literal(l), Expr::Literal(r)) = (left, right) {
+            if binary.op.is_cmp() {
+                let result = self.apply_comparison(&binary.op, l, r)?;
+                trace!(
+                    "[ConstFold] Folding {:?} {} {:?} => {}",
+                    l,
+                    binary.op.sym(),
+                    r,
+                    &result
+                );
+                return Some(Expr::Literal(LiteralExpr::Bool((
+                    result,
+                    binary.range().clone(),
+                ))));
+            }
+        }
+
+        if let (BinaryOp::And | BinaryOp::Or, Expr::Literal(l)) = (&binary.op, left) {
+            return Some(self.fold_logical(l, binary, right));
+        }
+
+        if let (Expr::Literal(l), Expr::Literal(r)) = (left, right) {
+            match (l, r) {
                 (LiteralExpr::Number((l, _)), LiteralExpr::Number((r, _))) => match &binary.op {
                     BinaryOp::Plus | BinaryOp::Minus | BinaryOp::Mult | BinaryOp::Div => {
                         let result = self.apply_arithmetic(&binary.op, l, r);
@@ -159,145 +185,129 @@ impl ConstFold {
                             binary.range().clone(),
                         ))));
                     }
-                    BinaryOp::EqEq
-                    | BinaryOp::NotEq
-                    | BinaryOp::Gt
-                    | BinaryOp::GtEq
-                    | BinaryOp::Lt
-                    | BinaryOp::LtEq => {
-                        let result = self.apply_arithmetical_logic(&binary.op, l, r);
-                        trace!(
-                            "[ConstFold] Folding {} {} {} => {}",
-                            l,
-                            binary.op.sym(),
-                            r,
-                            &result
-                        );
-                        return Some(Expr::Literal(LiteralExpr::Bool((
-                            result,
-                            binary.range().clone(),
-                        ))));
-                    }
-                    _ => {}
-                },
-
-                (LiteralExpr::Bool((l, _)), LiteralExpr::Bool((r, _))) => match &binary.op {
-                    BinaryOp::And
-                    | BinaryOp::Or
-                    | BinaryOp::EqEq
-                    | BinaryOp::NotEq
-                    | BinaryOp::Gt
-                    | BinaryOp::GtEq
-                    | BinaryOp::Lt
-                    | BinaryOp::LtEq => {
-                        let result = self.apply_boolean_logic(&binary.op, l, r);
-                        trace!(
-                            "[ConstFold] Folding {} {} {} => {}",
-                            l,
-                            binary.op.sym(),
-                            r,
-                            &result
-                        );
-                        return Some(Expr::Literal(LiteralExpr::Bool((
-                            result,
-                            binary.range().clone(),
-                        ))));
-                    }
                     _ => {}
                 },
 
-                (LiteralExpr::Bool((b, _)), expr) | (expr, LiteralExpr::Bool((b, _))) => {
-                    return self.fold_bool_binary_expr(b, binary, &|| Expr::Literal(expr.clone()));
+                (LiteralExpr::String(_), LiteralExpr::String(_) | LiteralExpr::Number(_))
+                | (LiteralExpr::Number(_), LiteralExpr::String(_))
+                    if binary.op == BinaryOp::Plus =>
+                {
+                    let result = format!("\"{}{}\"", Self::to_str(l), Self::to_str(r));
+                    trace!("[ConstFold] Folding {:?} + {:?} => {}", l, r, &result);
+                    return Some(Expr::Literal(LiteralExpr::String((
+                        result,
+                        binary.range().clone(),
+                    ))));
                 }
 
                 _ => {}
-            },
-
-            (expr, Expr::Literal(l)) | (Expr::Literal(l), expr) => {
-                if let Some((b, _)) = l.Bool() {
-                    return self.fold_bool_binary_expr(b, binary, &|| expr.clone());
-                }
             }
-            _ => {}
+
+            return None;
         }
 
-        None
+        return self.simplify_identity(binary, left, right);
     }
 
-    fn fold_bool_binary_expr(
-        &self,
-        b: &bool,
-        binary: &BinaryExpr,
-        expr: &dyn Fn() -> Expr,
-    ) -> Option<Expr> {
-        match &binary.op {
-            BinaryOp::And => {
-                // b and <expr>
-                // <expr> and b
-                // ===>
-                // false  -- if b == false
-                // <expr> -- otherwise
-                if !b {
-                    return Some(Expr::Literal(LiteralExpr::Bool((
-                        false,
-                        binary.range().clone(),
-                    ))));
-                }
-                return Some(expr());
-            }
-            BinaryOp::Or => {
-                // b or <expr>
-                // <expr> or b
-                // ===>
-                // true  -- if b == true
-                // <expr> -- otherwise
-                if *b {
-                    return Some(Expr::Literal(LiteralExpr::Bool((
-                        true,
-                        binary.range().clone(),
-                    ))));
-                }
-                return Some(expr());
-            }
+    /// Fold the `and` or `or` expression whose left operand is the given literal. The right
+    /// operand is only evaluated if the truthiness of the literal does not decide the result.
+    fn fold_logical(&self, l: &LiteralExpr, binary: &BinaryExpr, right: &Expr) -> Expr {
+        // <literal> and <expr>  ===>  <expr> if the literal is truthy, <literal> otherwise
+        // <literal> or <expr>   ===>  <literal> if the literal is truthy, <expr> otherwise
+        let short_circuit = Self::is_truthy(l) == (binary.op == BinaryOp::Or);
+        trace!(
+            "[ConstFold] Folding {:?} {} <expr> => {}",
+            l,
+            binary.op.sym(),
+            if short_circuit { "<literal>" } else { "<expr>" }
+        );
+
+        if !short_circuit {
+            return right.clone();
+        }
 
-            BinaryOp::EqEq => {
-                // b == <expr>
-                // <expr> == b
-                // ===>
-                // <expr> -- if b == true
-                // !<expr> -- if b == false
-                if *b {
-                    return Some(expr());
-                }
+        let mut literal = l.clone();
+        *literal.range_mut() = binary.range().clone();
+        return Expr::Literal(literal);
+    }
 
-                return Some(Expr::Unary(Box::from(UnaryExpr::new(
-                    UnaryOp::Not,
-                    expr(),
-                    binary.range().clone(),
-                ))));
-            }
-            BinaryOp::NotEq => {
-                // b != <expr>
-                // <expr> != b
-                // ===>
-                // !<expr> -- if b == true
-                // <expr> -- if b == false
-
-                if *b {
-                    return Some(Expr::Unary(Box::from(UnaryExpr::new(
-                        UnaryOp::Not,
-                        expr(),
-                        binary.range().clone(),
-                    ))));
+    /// Simplify the arithmetic operations with an identity element, `x * 1`, `x - 0` and `x / 1`,
+    /// to their non-constant operand. The operand must be known to be a number, as strings are
+    /// concatenated and repeated, and the other values fail at runtime.
+    ///
+    /// `x + 0` is not simplified, as it evaluates to `0` when `x` is `-0`. `x * 0` and `x - x`
+    /// are not simplified to `0`, as they evaluate to NaN when `x` is NaN or infinite.
+    fn simplify_identity(&self, binary: &BinaryExpr, left: &Expr, right: &Expr) -> Option<Expr> {
+        let Expr::Literal(LiteralExpr::Number((num, _))) = right else {
+            return None;
+        };
+
+        // `x - -0` is `0` when `x` is `-0`
+        match binary.op {
+            BinaryOp::Minus if *num == 0.0 && num.is_sign_positive() => {}
+            BinaryOp::Mult | BinaryOp::Div if *num == 1.0 => {}
+            _ => return None,
+        }
+
+        if !Self::is_number(left) {
+            return None;
+        }
+
+        trace!(
+            "[ConstFold] Simplifying {} with identity operand to <expr>",
+            binary.op.sym()
+        );
+        return Some(left.clone());
+    }
+
+    /// Simplify the given expression whose value is only checked for its truthiness, like the
+    /// condition of an `if` statement. Double negations are removed, in the expression itself and
+    /// in the operands of the `!`, `and` and `or` operators, which are also only checked for
+    /// their truthiness.
+    pub fn simplify_condition(&self, expr: &mut Expr) {
+        match expr {
+            Expr::Unary(unary) if unary.op == UnaryOp::Not => {
+                if let Expr::Unary(inner) = &unary.expr {
+                    if inner.op == UnaryOp::Not {
+                        trace!("[ConstFold] Removing double negation in condition");
+                        *expr = inner.expr.clone();
+                        return self.simplify_condition(expr);
+                    }
                 }
 
-                return Some(expr());
+                self.simplify_condition(&mut unary.expr);
+            }
+            Expr::Binary(binary) if matches!(binary.op, BinaryOp::And | BinaryOp::Or) => {
+                self.simplify_condition(&mut binary.left);
+                self.simplify_condition(&mut binary.right);
             }
-
             _ => {}
         }
+    }
 
-        None
+    /// Get whether the given expression evaluates to a number, or fails at runtime.
+    fn is_number(expr: &Expr) -> bool {
+        return match expr {
+            Expr::Literal(LiteralExpr::Number(_)) => true,
+            Expr::Unary(unary) => unary.op == UnaryOp::Negate,
+            Expr::Binary(binary) => match &binary.op {
+                BinaryOp::Minus | BinaryOp::Div => true,
+                BinaryOp::Plus | BinaryOp::Mult => {
+                    Self::is_number(&binary.left) && Self::is_number(&binary.right)
+                }
+                _ => false,
+            },
+            _ => false,
+        };
+    }
+
+    /// Get whether the given literal is truthy, with the same semantics as the VM.
+    fn is_truthy(literal: &LiteralExpr) -> bool {
+        return match literal {
+            LiteralExpr::Bool((b, _)) => *b,
+            LiteralExpr::Null(_) => false,
+            _ => true,
+        };
     }
 
     fn apply_arithmetic(&self, op: &BinaryOp, l: &f64, r: &f64) -> f64 {
@@ -310,34 +320,72 @@ impl ConstFold {
         }
     }
 
-    fn apply_arithmetical_logic(&self, op: &BinaryOp, l: &f64, r: &f64) -> bool {
-        match op {
-            BinaryOp::EqEq => l == r,
-            BinaryOp::NotEq => l != r,
-            BinaryOp::Gt => l > r,
-            BinaryOp::GtEq => l >= r,
-            BinaryOp::Lt => l < r,
-            BinaryOp::LtEq => l <= r,
-            _ => panic!("Unsupported logical operator: {}", op.sym()),
-        }
+    /// Compare the given literals, with the same semantics as the VM. Values of different types
+    /// are never equal and only numbers and strings can be ordered. Returns [None] if the
+    /// literals cannot be ordered, in which case the comparison fails at runtime.
+    fn apply_comparison(&self, op: &BinaryOp, l: &LiteralExpr, r: &LiteralExpr) -> Option<bool> {
+        let ordering = match (l, r) {
+            (LiteralExpr::Number((l, _)), LiteralExpr::Number((r, _))) => l.partial_cmp(r),
+            (LiteralExpr::String((l, _)), LiteralExpr::String((r, _))) => {
+                // compare the strings without the double quotes
+                Some(l[1..l.len() - 1].cmp(&r[1..r.len() - 1]))
+            }
+            _ => {
+                let eq = match (l, r) {
+                    (LiteralExpr::Null(_), LiteralExpr::Null(_)) => true,
+                    (LiteralExpr::Bool((l, _)), LiteralExpr::Bool((r, _))) => l == r,
+                    _ => false,
+                };
+
+                return match op {
+                    BinaryOp::EqEq => Some(eq),
+                    BinaryOp::NotEq => Some(!eq),
+                    _ => None,
+                };
+            }
+        };
+
+        return Some(match op {
+            BinaryOp::EqEq => ordering == Some(Ordering::Equal),
+            BinaryOp::NotEq => ordering != Some(Ordering::Equal),
+            BinaryOp::Gt => ordering == Some(Ordering::Greater),
+            BinaryOp::GtEq => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+            BinaryOp::Lt => ordering == Some(Ordering::Less),
+            BinaryOp::LtEq => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            _ => panic!("Unsupported comparison operator: {}", op.sym()),
+        });
     }
 
-    fn apply_boolean_logic(&self, op: &BinaryOp, l: &bool, r: &bool) -> bool {
-        match op {
-            BinaryOp::EqEq => l == r,
-            BinaryOp::NotEq => l != r,
-            BinaryOp::Gt => l > r,
-            BinaryOp::GtEq => l >= r,
-            BinaryOp::Lt => l < r,
-            BinaryOp::LtEq => l <= r,
-            BinaryOp::And => *l && *r,
-            BinaryOp::Or => *l || *r,
-            _ => panic!("Unsupported logical operator: {}", op.sym()),
+    /// Get the string representation of the given string or number literal, as computed by the
+    /// VM when concatenating strings.
+    fn to_str(literal: &LiteralExpr) -> String {
+        match literal {
+            // remove the double quotes
+            LiteralExpr::String((str, _)) => str[1..str.len() - 1].to_string(),
+            LiteralExpr::Number((num, _)) => num.to_string(),
+            _ => panic!("Expected a string or a number literal"),
         }
     }
 }
 
 impl ASTVisitor<(), ()> for ConstFold {
+    fn visit_if_stmt(&mut self, if_stmt: &mut IfStmt, p: &mut ()) -> Option<()> {
+        self.simplify_condition(&mut if_stmt.condition);
+        self.default_visit_if_stmt(if_stmt, p)
+    }
+
+    fn visit_while_stmt(&mut self, while_stmt: &mut WhileStmt, p: &mut ()) -> Option<()> {
+        self.simplify_condition(&mut while_stmt.condition);
+        self.default_visit_while_stmt(while_stmt, p)
+    }
+
+    fn visit_for_stmt(&mut self, for_stmt: &mut ForStmt, p: &mut ()) -> Option<()> {
+        if let Some(condition) = for_stmt.condition.as_mut() {
+            self.simplify_condition(condition);
+        }
+        self.default_visit_for_stmt(for_stmt, p)
+    }
+
     fn visit_stmt(&mut self, stmt: &mut Stmt, p: &mut ()) -> Option<()> {
         if let Some(folded) = self.try_fold_stmt(stmt) {
             *stmt = folded;
@@ -345,9 +393,11 @@ impl ASTVisitor<(), ()> for ConstFold {
         self.default_visit_stmt(stmt, p)
     }
     fn visit_expr(&mut self, expr: &mut Expr, p: &mut ()) -> Option<()> {
+        // the operands are folded first, so that the expression is folded with their values
+        self.default_visit_expr(expr, p);
         if let Some(folded) = self.try_fold_expr(expr) {
             *expr = folded;
         }
-        self.default_visit_expr(expr, p)
+        None
     }
 }
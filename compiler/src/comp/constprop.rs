@@ -0,0 +1,401 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use log::debug;
+use log::trace;
+
+use crate::ast::ASTVisitor;
+use crate::ast::AssignExpr;
+use crate::ast::BlockStmt;
+use crate::ast::ClassDecl;
+use crate::ast::CompoundAssignExpr;
+use crate::ast::Decl;
+use crate::ast::EmptyStmt;
+use crate::ast::Expr;
+use crate::ast::FuncDecl;
+use crate::ast::IdentifierExpr;
+use crate::ast::LambdaExpr;
+use crate::ast::LiteralExpr;
+use crate::ast::MemberAccessExpr;
+use crate::ast::Program;
+use crate::ast::Spanned;
+use crate::ast::SpannedMut;
+use crate::ast::Stmt;
+use crate::ast::VarStmt;
+use crate::ast::Visitable;
+use crate::comp::ConstFold;
+use crate::features::CompilerFeatures;
+
+/// Substitutes the value of the variables which are initialized with a constant and never
+/// reassigned into the expressions which read them, so that these expressions can be folded by
+/// [ConstFold]. The names are resolved with the same scoping rules as [crate::comp::Resolve].
+///
+/// The declarations of the `const` variables whose uses are all substituted are removed, so
+/// that they do not use a local variable.
+pub struct ConstProp<'inst> {
+    features: &'inst CompilerFeatures,
+    constfold: ConstFold,
+
+    /// The variables declared in the program, in the order in which they are declared.
+    vars: Vec<PropVar>,
+
+    /// The names declared in each of the enclosing scopes, along with the index of the variable
+    /// they refer to, if they refer to a variable.
+    scopes: Vec<Vec<(String, Option<usize>)>>,
+
+    /// The index of the next variable to be declared.
+    next_var: usize,
+
+    /// Whether the uses of the variables are being substituted. If `false`, the assignments to the
+    /// variables are being collected.
+    substitute: bool,
+
+    /// Whether the top-level functions and classes are being visited. These can be called before
+    /// the global variables are initialized, so the global variables are not substituted in them.
+    in_decls: bool,
+
+    /// The number of uses of the variables which have been substituted.
+    substitutions: usize,
+}
+
+/// A variable declared in the program.
+struct PropVar {
+    /// Whether the variable is assigned after its declaration.
+    reassigned: bool,
+
+    /// Whether the variable is read where it is not substituted, i.e. from a top-level function
+    /// or class.
+    read_in_decls: bool,
+
+    /// The constant the variable is initialized with, if any.
+    value: Option<LiteralExpr>,
+}
+
+impl ConstProp<'_> {
+    /// Create a new instance of the constant propagation pass.
+    pub fn new(features: &CompilerFeatures) -> ConstProp<'_> {
+        return ConstProp {
+            features,
+            constfold: ConstFold::new(),
+            vars: Vec::new(),
+            scopes: Vec::new(),
+            next_var: 0,
+            substitute: false,
+            in_decls: false,
+            substitutions: 0,
+        };
+    }
+
+    /// Returns the number of uses of the variables which were substituted by the last analysis.
+    pub fn substitutions(&self) -> usize {
+        return self.substitutions;
+    }
+
+    /// Analyze the given program and substitute the constant variables in it. The assignments to
+    /// the variables are collected first, then the program is visited again to substitute the
+    /// variables which are never reassigned.
+    pub fn analyze(&mut self, program: &mut Program) {
+        self.vars.clear();
+        self.substitutions = 0;
+
+        self.substitute = false;
+        program.accept(self, &mut ());
+
+        self.substitute = true;
+        program.accept(self, &mut ());
+
+        debug!(
+            "[ConstProp] Substituted {} use(s) of constant variables",
+            self.substitutions
+        );
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+
+    fn pop_scope(&mut self) {
+        self.scopes.pop().expect("Expected a scope");
+    }
+
+    /// Declare a name which does not refer to a variable, e.g. a function or a parameter.
+    fn declare(&mut self, name: &IdentifierExpr) {
+        let scope = self.scopes.last_mut().expect("Expected a scope");
+        scope.push((name.name.clone(), None));
+    }
+
+    /// Declare the variable with the given name, initialized with the given value. The variables
+    /// are numbered in the order of their declaration, which is the same in both the visits of
+    /// the program.
+    fn declare_var(&mut self, name: &IdentifierExpr, value: Option<LiteralExpr>) {
+        let index = self.next_var;
+        self.next_var += 1;
+        if self.substitute {
+            self.vars[index].value = value;
+        } else {
+            self.vars.push(PropVar {
+                reassigned: false,
+                read_in_decls: false,
+                value: None,
+            });
+        }
+
+        let scope = self.scopes.last_mut().expect("Expected a scope");
+        scope.push((name.name.clone(), Some(index)));
+    }
+
+    /// Find the variable with the given name. Returns the index of the variable and whether it
+    /// is a global variable, or [None] if the name does not refer to a variable.
+    fn find_var(&self, name: &str) -> Option<(usize, bool)> {
+        for (depth, scope) in self.scopes.iter().enumerate().rev() {
+            if let Some((_, var)) = scope.iter().rev().find(|(n, _)| n == name) {
+                return var.map(|index| (index, depth == 0));
+            }
+        }
+
+        None
+    }
+
+    /// Get the constant value of the variable with the given name, if it has one.
+    fn constant(&self, name: &str) -> Option<LiteralExpr> {
+        let (index, is_global) = self.find_var(name)?;
+        if is_global && self.in_decls {
+            return None;
+        }
+
+        let var = &self.vars[index];
+        if var.reassigned {
+            return None;
+        }
+
+        return var.value.clone();
+    }
+
+    /// Record a use of the variable with the given name.
+    fn read(&mut self, name: &str) {
+        if let Some((index, is_global)) = self.find_var(name) {
+            if is_global && self.in_decls {
+                self.vars[index].read_in_decls = true;
+            }
+        }
+    }
+
+    /// Returns whether the given declaration is a constant whose uses are all substituted.
+    fn is_inlined(&self, var_decl: &VarStmt) -> bool {
+        if !var_decl.is_const {
+            return false;
+        }
+
+        return match self.find_var(&var_decl.name.name) {
+            Some((index, _)) => {
+                let var = &self.vars[index];
+                var.value.is_some() && !var.reassigned && !var.read_in_decls
+            }
+            None => false,
+        };
+    }
+
+    /// Record an assignment to the given target.
+    fn assign(&mut self, target: &Expr) {
+        if self.substitute {
+            return;
+        }
+
+        if let Expr::Identifier(identifier) = target {
+            if let Some((index, _)) = self.find_var(&identifier.name) {
+                self.vars[index].reassigned = true;
+            }
+        }
+    }
+
+    /// Get the constant the given initializer evaluates to, if any.
+    fn initial_value(&self, initializer: Option<&Expr>) -> Option<LiteralExpr> {
+        let initializer = initializer?;
+        if let Expr::Literal(literal) = initializer {
+            return Some(literal.clone());
+        }
+
+        if !self.features.const_folding {
+            return None;
+        }
+
+        return match self.constfold.try_fold_expr(initializer) {
+            Some(Expr::Literal(literal)) => Some(literal),
+            _ => None,
+        };
+    }
+
+    /// Visit the parameters and the body of a function.
+    fn visit_func(
+        &mut self,
+        this: Option<&IdentifierExpr>,
+        params: &Vec<IdentifierExpr>,
+        body: &mut BlockStmt,
+    ) {
+        self.push_scope();
+        if let Some(this) = this {
+            self.declare(this);
+        }
+        for param in params {
+            self.declare(param);
+        }
+
+        self.visit_block_stmt(body, &mut ());
+        self.pop_scope();
+    }
+}
+
+impl ASTVisitor<(), ()> for ConstProp<'_> {
+    fn visit_program(&mut self, program: &mut Program, p: &mut ()) -> Option<()> {
+        self.scopes.clear();
+        self.next_var = 0;
+        self.push_scope();
+
+        // top-level functions and classes can be used before they are declared
+        for decl in &program.decls {
+            match decl {
+                Decl::Func(func_decl) => self.declare(&func_decl.name),
+                Decl::Class(class_decl) => self.declare(&class_decl.name),
+                _ => {}
+            }
+        }
+
+        // visit the top-level statements before the function bodies, just like the resolver
+        self.in_decls = false;
+        self.default_visit_program(program, p, false, true);
+        self.in_decls = true;
+        self.default_visit_program(program, p, true, false);
+        self.in_decls = false;
+
+        self.pop_scope();
+        None
+    }
+
+    fn visit_func_decl(&mut self, func_decl: &mut FuncDecl, _p: &mut ()) -> Option<()> {
+        // top-level functions are declared before visiting the program
+        if self.scopes.len() > 1 {
+            self.declare(&func_decl.name);
+        }
+
+        self.visit_func(None, &func_decl.params, &mut func_decl.body);
+        None
+    }
+
+    fn visit_lambda_expr(&mut self, lambda_expr: &mut LambdaExpr, _p: &mut ()) -> Option<()> {
+        self.visit_func(None, &lambda_expr.params, &mut lambda_expr.body);
+        None
+    }
+
+    fn visit_class_decl(&mut self, class_decl: &mut ClassDecl, _p: &mut ()) -> Option<()> {
+        // top-level classes are declared before visiting the program
+        if self.scopes.len() > 1 {
+            self.declare(&class_decl.name);
+        }
+
+        for method in class_decl.methods.iter_mut() {
+            self.visit_func(method.this.as_ref(), &method.params, &mut method.body);
+        }
+
+        None
+    }
+
+    fn visit_var_stmt(&mut self, var_decl: &mut VarStmt, p: &mut ()) -> Option<()> {
+        // the initializer is visited first, as it cannot refer to the variable being declared
+        if let Some(initializer) = var_decl.initializer.as_mut() {
+            self.visit_expr(initializer, p);
+        }
+
+        let value = self.initial_value(var_decl.initializer.as_ref());
+        self.declare_var(&var_decl.name, value);
+        None
+    }
+
+    fn visit_block_stmt(&mut self, block_stmt: &mut BlockStmt, p: &mut ()) -> Option<()> {
+        self.push_scope();
+        self.default_visit_block_stmt(block_stmt, p);
+        self.pop_scope();
+        None
+    }
+
+    fn visit_stmt(&mut self, stmt: &mut Stmt, p: &mut ()) -> Option<()> {
+        self.default_visit_stmt(stmt, p);
+
+        // the value of an inlined constant is never read from its variable
+        if let Stmt::Var(var_decl) = stmt {
+            if self.substitute && self.is_inlined(var_decl) {
+                trace!("[ConstProp] Inlining constant '{}'", var_decl.name.name);
+                *stmt = Stmt::Empty(EmptyStmt::new(var_decl.range().clone()));
+            }
+        }
+
+        None
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expr, p: &mut ()) -> Option<()> {
+        if let Expr::Identifier(identifier) = expr {
+            if !self.substitute {
+                self.read(&identifier.name);
+                return None;
+            }
+
+            if let Some(mut value) = self.constant(&identifier.name) {
+                trace!(
+                    "[ConstProp] Substituting '{}' with {}",
+                    identifier.name,
+                    value
+                );
+                *value.range_mut() = identifier.range().clone();
+                *expr = Expr::Literal(value);
+                self.substitutions += 1;
+            }
+
+            return None;
+        }
+
+        self.default_visit_expr(expr, p)
+    }
+
+    fn visit_assign_expr(&mut self, assign_expr: &mut AssignExpr, p: &mut ()) -> Option<()> {
+        // the variable being assigned to is not read
+        self.assign(&assign_expr.target);
+        if !matches!(assign_expr.target, Expr::Identifier(_)) {
+            self.visit_expr(&mut assign_expr.target, p);
+        }
+
+        self.visit_expr(&mut assign_expr.value, p)
+    }
+
+    fn visit_compound_assign_expr(
+        &mut self,
+        compound_assign_expr: &mut CompoundAssignExpr,
+        p: &mut (),
+    ) -> Option<()> {
+        self.assign(&compound_assign_expr.target);
+        if !matches!(compound_assign_expr.target, Expr::Identifier(_)) {
+            self.visit_expr(&mut compound_assign_expr.target, p);
+        }
+
+        self.visit_expr(&mut compound_assign_expr.value, p)
+    }
+
+    fn visit_member_access_expr(
+        &mut self,
+        member_access_expr: &mut MemberAccessExpr,
+        p: &mut (),
+    ) -> Option<()> {
+        // members are not variables
+        self.visit_expr(&mut member_access_expr.receiver, p)
+    }
+}
@@ -0,0 +1,128 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use log::trace;
+
+use crate::ast::ASTVisitor;
+use crate::ast::BlockStmt;
+use crate::ast::Decl;
+use crate::ast::EmptyStmt;
+use crate::ast::Expr;
+use crate::ast::LiteralExpr;
+use crate::ast::Program;
+use crate::ast::Spanned;
+use crate::ast::Stmt;
+
+/// Removes the statements which can never be executed. These are the statements which follow a
+/// statement that always exits its block and the loops whose condition is always falsy.
+#[derive(Default)]
+pub struct DeadCodeElim;
+
+impl DeadCodeElim {
+    /// Create a new instance of [DeadCodeElim].
+    pub fn new() -> DeadCodeElim {
+        DeadCodeElim {}
+    }
+
+    /// Returns whether the given statement always exits the block containing it, i.e. the
+    /// statements that follow it are never executed.
+    pub fn always_exits(stmt: &Stmt) -> bool {
+        return match stmt {
+            Stmt::Break(_) | Stmt::Continue(_) | Stmt::Return(_) => true,
+            Stmt::Block(block) => Self::block_exits(block),
+            Stmt::If(_if) => {
+                Self::block_exits(&_if.then_branch)
+                    && _if.else_branch.as_ref().is_some_and(Self::block_exits)
+            }
+            _ => false,
+        };
+    }
+
+    fn block_exits(block: &BlockStmt) -> bool {
+        return block.decls.iter().any(|decl| match decl {
+            Decl::Stmt(stmt) => Self::always_exits(stmt),
+            _ => false,
+        });
+    }
+
+    /// Returns whether the given condition is a literal which is always falsy.
+    fn is_falsy(condition: &Expr) -> bool {
+        return match condition {
+            Expr::Literal(LiteralExpr::Bool((value, _))) => !value,
+            Expr::Literal(LiteralExpr::Null(_)) => true,
+            _ => false,
+        };
+    }
+
+    /// Try to eliminate the given statement. Returns the statement which replaces it if the
+    /// statement can never be executed, or [None] otherwise.
+    fn eliminate(&self, stmt: &mut Stmt) -> Option<Stmt> {
+        match stmt {
+            Stmt::While(_while) if Self::is_falsy(&_while.condition) => {
+                trace!("[DeadCodeElim] Removing while loop with falsy condition");
+                return Some(Stmt::Empty(EmptyStmt::new(_while.range().clone())));
+            }
+
+            // the initializer of the loop is executed once, even if the body never is
+            Stmt::For(_for) if _for.condition.as_ref().is_some_and(Self::is_falsy) => {
+                trace!("[DeadCodeElim] Removing for loop with falsy condition");
+                let range = _for.range().clone();
+                return Some(
+                    _for.init
+                        .take()
+                        .unwrap_or_else(|| Stmt::Empty(EmptyStmt::new(range))),
+                );
+            }
+            _ => None,
+        }
+    }
+}
+
+impl ASTVisitor<(), ()> for DeadCodeElim {
+    fn visit_program(&mut self, program: &mut Program, p: &mut ()) -> Option<()> {
+        self.default_visit_program(program, p, true, true);
+
+        let exit = program.stmts.iter().position(Self::always_exits);
+        if let Some(index) = exit.filter(|index| index + 1 < program.stmts.len()) {
+            trace!("[DeadCodeElim] Removing unreachable top-level statements");
+            program.stmts.truncate(index + 1);
+        }
+
+        None
+    }
+
+    fn visit_stmt(&mut self, stmt: &mut Stmt, p: &mut ()) -> Option<()> {
+        if let Some(replacement) = self.eliminate(stmt) {
+            *stmt = replacement;
+        }
+        self.default_visit_stmt(stmt, p)
+    }
+
+    fn visit_block_stmt(&mut self, block_stmt: &mut BlockStmt, p: &mut ()) -> Option<()> {
+        self.default_visit_block_stmt(block_stmt, p);
+
+        let exit = block_stmt.decls.iter().position(|decl| match decl {
+            Decl::Stmt(stmt) => Self::always_exits(stmt),
+            _ => false,
+        });
+
+        if let Some(index) = exit.filter(|index| index + 1 < block_stmt.decls.len()) {
+            trace!("[DeadCodeElim] Removing unreachable statements in block");
+            block_stmt.decls.truncate(index + 1);
+        }
+
+        None
+    }
+}
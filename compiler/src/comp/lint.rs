@@ -0,0 +1,418 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::ast::ASTVisitor;
+use crate::ast::AssignExpr;
+use crate::ast::BlockStmt;
+use crate::ast::BreakStmt;
+use crate::ast::ClassDecl;
+use crate::ast::ContinueStmt;
+use crate::ast::Decl;
+use crate::ast::Expr;
+use crate::ast::ForStmt;
+use crate::ast::FuncDecl;
+use crate::ast::IdentifierExpr;
+use crate::ast::IfStmt;
+use crate::ast::LambdaExpr;
+use crate::ast::LiteralExpr;
+use crate::ast::MemberAccessExpr;
+use crate::ast::Program;
+use crate::ast::Spanned;
+use crate::ast::Stmt;
+use crate::ast::VarStmt;
+use crate::ast::Visitable;
+use crate::ast::WhileStmt;
+use crate::comp::ConstFold;
+use crate::diagnostics::Diagnostic;
+use crate::diagnostics::DiagnosticHandler;
+use crate::diagnostics::DiagnosticKind;
+use crate::features::CompilerFeatures;
+use crate::location::Range;
+use crate::messages;
+use crate::messages::Message;
+
+/// Reports warnings about code which is valid, but most likely not what the programmer intended.
+/// The lints are only run on programs which have been resolved without errors.
+pub struct Lint<'inst> {
+    features: &'inst CompilerFeatures,
+    diagnostics: &'inst mut (dyn DiagnosticHandler + 'inst),
+    constfold: ConstFold,
+    scopes: Vec<LintScope>,
+}
+
+/// The names declared in a scope.
+struct LintScope {
+    bindings: Vec<Binding>,
+
+    /// Whether this scope is the outermost scope of a function.
+    is_func: bool,
+}
+
+/// A name declared in a scope.
+struct Binding {
+    name: String,
+    range: Range,
+    kind: BindingKind,
+
+    /// Whether the value of the binding is read anywhere.
+    read: bool,
+}
+
+#[derive(PartialEq)]
+enum BindingKind {
+    Var,
+    Param,
+
+    /// Functions, classes and the receivers of methods.
+    Other,
+}
+
+impl Lint<'_> {
+    /// Create a new instance of the lint pass.
+    pub fn new<'a>(
+        features: &'a CompilerFeatures,
+        diagnostics: &'a mut (dyn DiagnosticHandler + 'a),
+    ) -> Lint<'a> {
+        return Lint {
+            features,
+            diagnostics,
+            constfold: ConstFold::new(),
+            scopes: Vec::new(),
+        };
+    }
+
+    /// Analyze the given program.
+    pub fn analyze(&mut self, program: &mut Program) {
+        self.scopes.clear();
+        program.accept(self, &mut ());
+    }
+
+    fn warn(&mut self, range: &Range, msg: &Message, notes: Vec<String>) {
+        self.diagnostics.handle(Diagnostic {
+            kind: DiagnosticKind::Warning,
+            range: range.clone(),
+            message: msg.to_string(),
+            code: msg.code,
+            notes,
+        });
+    }
+
+    fn push_scope(&mut self, is_func: bool) {
+        self.scopes.push(LintScope {
+            bindings: Vec::new(),
+            is_func,
+        });
+    }
+
+    /// Pop the innermost scope and report the variables in it which were never read.
+    fn pop_scope(&mut self) {
+        let scope = self.scopes.pop().expect("Expected a scope");
+        if !self.features.unused_variables {
+            return;
+        }
+
+        // variables whose name starts with an underscore are intentionally unused
+        for binding in scope.bindings {
+            if binding.kind == BindingKind::Var && !binding.read && !binding.name.starts_with('_') {
+                self.warn(
+                    &binding.range,
+                    &messages::warn_unused_var(&binding.name),
+                    Vec::new(),
+                );
+            }
+        }
+    }
+
+    /// Declare the given name in the innermost scope.
+    fn declare(&mut self, name: &IdentifierExpr, kind: BindingKind) {
+        if kind != BindingKind::Other && self.features.shadowing {
+            self.check_shadowing(name);
+        }
+
+        self.scopes
+            .last_mut()
+            .expect("Expected a scope")
+            .bindings
+            .push(Binding {
+                name: name.name.clone(),
+                range: name.range().clone(),
+                kind,
+                read: false,
+            });
+    }
+
+    /// Report a warning if the given variable shadows a variable of an enclosing function, or a
+    /// variable of the top-level code. Redeclaring a variable within the same function is an
+    /// error, which is reported by [crate::comp::Resolve].
+    fn check_shadowing(&mut self, name: &IdentifierExpr) {
+        let mut outer = false;
+        let mut shadowed: Option<Range> = None;
+        for scope in self.scopes.iter().rev() {
+            if outer {
+                shadowed = scope
+                    .bindings
+                    .iter()
+                    .rev()
+                    .find(|binding| binding.name == name.name && binding.kind != BindingKind::Other)
+                    .map(|binding| binding.range.clone());
+                if shadowed.is_some() {
+                    break;
+                }
+            }
+
+            outer |= scope.is_func;
+        }
+
+        if let Some(shadowed) = shadowed {
+            let note = format!(
+                "the shadowed variable is declared on line {}",
+                shadowed.start.line + 1
+            );
+            self.warn(
+                name.range(),
+                &messages::warn_shadowed_var(&name.name),
+                vec![note],
+            );
+        }
+    }
+
+    /// Mark the innermost binding with the given name as read.
+    fn read(&mut self, name: &str) {
+        for scope in self.scopes.iter_mut().rev() {
+            if let Some(binding) = scope
+                .bindings
+                .iter_mut()
+                .rev()
+                .find(|binding| binding.name == name)
+            {
+                binding.read = true;
+                return;
+            }
+        }
+    }
+
+    /// Report a warning if the given condition of an `if` or `while` statement always evaluates to
+    /// the same value. `while true` is not reported, as it is the way to write infinite loops.
+    fn check_condition(&mut self, condition: &Expr, is_loop: bool) {
+        if !self.features.constant_conditions {
+            return;
+        }
+
+        if is_loop && matches!(condition, Expr::Literal(LiteralExpr::Bool((true, _)))) {
+            return;
+        }
+
+        let folded = match condition {
+            Expr::Literal(literal) => Some(literal.clone()),
+            _ => match self.constfold.try_fold_expr(condition) {
+                Some(Expr::Literal(literal)) => Some(literal),
+                _ => None,
+            },
+        };
+
+        let value = match folded {
+            Some(LiteralExpr::Null(_)) => "null".to_string(),
+            Some(LiteralExpr::Bool((value, _))) => value.to_string(),
+            Some(LiteralExpr::Number((value, _))) => value.to_string(),
+            Some(LiteralExpr::String((value, _))) => value,
+            None => return,
+        };
+
+        self.warn(
+            condition.range(),
+            &messages::warn_const_condition(&value),
+            Vec::new(),
+        );
+    }
+
+    /// Visit the parameters and the body of a function.
+    fn visit_func(
+        &mut self,
+        this: Option<&IdentifierExpr>,
+        params: &Vec<IdentifierExpr>,
+        body: &mut BlockStmt,
+    ) {
+        self.push_scope(true);
+        if let Some(this) = this {
+            self.declare(this, BindingKind::Other);
+        }
+        for param in params {
+            self.declare(param, BindingKind::Param);
+        }
+
+        self.visit_block_stmt(body, &mut ());
+        self.pop_scope();
+    }
+}
+
+impl ASTVisitor<(), ()> for Lint<'_> {
+    fn visit_program(&mut self, program: &mut Program, p: &mut ()) -> Option<()> {
+        self.push_scope(false);
+
+        // top-level functions and classes can be used before they are declared
+        for decl in &program.decls {
+            match decl {
+                Decl::Func(func_decl) => self.declare(&func_decl.name, BindingKind::Other),
+                Decl::Class(class_decl) => self.declare(&class_decl.name, BindingKind::Other),
+                _ => {}
+            }
+        }
+
+        // visit the top-level statements before the function bodies, just like the resolver
+        self.default_visit_program(program, p, false, true);
+        self.default_visit_program(program, p, true, false);
+
+        self.pop_scope();
+        None
+    }
+
+    fn visit_func_decl(&mut self, func_decl: &mut FuncDecl, _p: &mut ()) -> Option<()> {
+        // top-level functions are declared before visiting the program
+        if self.scopes.len() > 1 {
+            self.declare(&func_decl.name, BindingKind::Other);
+        }
+
+        self.visit_func(None, &func_decl.params, &mut func_decl.body);
+        None
+    }
+
+    fn visit_lambda_expr(&mut self, lambda_expr: &mut LambdaExpr, _p: &mut ()) -> Option<()> {
+        self.visit_func(None, &lambda_expr.params, &mut lambda_expr.body);
+        None
+    }
+
+    fn visit_class_decl(&mut self, class_decl: &mut ClassDecl, _p: &mut ()) -> Option<()> {
+        // top-level classes are declared before visiting the program
+        if self.scopes.len() > 1 {
+            self.declare(&class_decl.name, BindingKind::Other);
+        }
+
+        if let Some(supercls) = class_decl.supercls.as_ref() {
+            self.read(&supercls.name);
+        }
+
+        for method in class_decl.methods.iter_mut() {
+            self.visit_func(method.this.as_ref(), &method.params, &mut method.body);
+        }
+
+        None
+    }
+
+    fn visit_var_stmt(&mut self, var_decl: &mut VarStmt, p: &mut ()) -> Option<()> {
+        // the initializer is visited first, as it cannot refer to the variable being declared
+        if let Some(initializer) = var_decl.initializer.as_mut() {
+            self.visit_expr(initializer, p);
+        }
+
+        self.declare(&var_decl.name, BindingKind::Var);
+        None
+    }
+
+    fn visit_block_stmt(&mut self, block_stmt: &mut BlockStmt, p: &mut ()) -> Option<()> {
+        self.push_scope(false);
+
+        let mut exit: Option<&'static str> = None;
+        for decl in block_stmt.decls.iter_mut() {
+            if let Some(stmt) = exit.take() {
+                if self.features.unreachable_code {
+                    self.warn(
+                        decl.range(),
+                        &messages::warn_unreachable_code(stmt),
+                        Vec::new(),
+                    );
+                }
+            }
+
+            self.visit_decl(decl, p);
+
+            exit = match decl {
+                Decl::Stmt(Stmt::Break(_)) => Some("break"),
+                Decl::Stmt(Stmt::Continue(_)) => Some("continue"),
+                Decl::Stmt(Stmt::Return(_)) => Some("return"),
+                _ => exit,
+            };
+        }
+
+        self.pop_scope();
+        None
+    }
+
+    fn visit_if_stmt(&mut self, if_stmt: &mut IfStmt, p: &mut ()) -> Option<()> {
+        self.check_condition(&if_stmt.condition, false);
+        self.default_visit_if_stmt(if_stmt, p)
+    }
+
+    fn visit_while_stmt(&mut self, while_stmt: &mut WhileStmt, p: &mut ()) -> Option<()> {
+        self.check_condition(&while_stmt.condition, true);
+
+        // labels are not variables
+        self.visit_expr(&mut while_stmt.condition, p);
+        self.visit_block_stmt(&mut while_stmt.body, p)
+    }
+
+    fn visit_for_stmt(&mut self, for_stmt: &mut ForStmt, p: &mut ()) -> Option<()> {
+        // the variable in the initializer is declared in the enclosing scope
+        if let Some(init) = for_stmt.init.as_mut() {
+            self.visit_stmt(init, p);
+        }
+        if let Some(condition) = for_stmt.condition.as_mut() {
+            self.visit_expr(condition, p);
+        }
+        if let Some(step) = for_stmt.step.as_mut() {
+            self.visit_expr(step, p);
+        }
+
+        self.visit_block_stmt(&mut for_stmt.body, p)
+    }
+
+    fn visit_break_stmt(&mut self, _break_stmt: &mut BreakStmt, _p: &mut ()) -> Option<()> {
+        None
+    }
+
+    fn visit_continue_stmt(
+        &mut self,
+        _continue_stmt: &mut ContinueStmt,
+        _p: &mut (),
+    ) -> Option<()> {
+        None
+    }
+
+    fn visit_assign_expr(&mut self, assign_expr: &mut AssignExpr, p: &mut ()) -> Option<()> {
+        // assigning to a variable does not read it
+        if !matches!(assign_expr.target, Expr::Identifier(_)) {
+            self.visit_expr(&mut assign_expr.target, p);
+        }
+
+        self.visit_expr(&mut assign_expr.value, p)
+    }
+
+    fn visit_member_access_expr(
+        &mut self,
+        member_access_expr: &mut MemberAccessExpr,
+        p: &mut (),
+    ) -> Option<()> {
+        // members are not variables
+        self.visit_expr(&mut member_access_expr.receiver, p)
+    }
+
+    fn visit_identifier_expr(
+        &mut self,
+        identifier: &mut IdentifierExpr,
+        _p: &mut (),
+    ) -> Option<()> {
+        self.read(&identifier.name);
+        None
+    }
+}
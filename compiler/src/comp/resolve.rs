@@ -16,21 +16,36 @@
 use crate::ast::ASTVisitor;
 use crate::ast::BlockStmt;
 use crate::ast::BreakStmt;
+use crate::ast::AssignExpr;
+use crate::ast::ClassDecl;
+use crate::ast::CompoundAssignExpr;
 use crate::ast::ContinueStmt;
+use crate::ast::Decl;
+use crate::ast::Expr;
 use crate::ast::ForStmt;
+use crate::ast::FuncDecl;
 use crate::ast::IdentifierExpr;
+use crate::ast::LambdaExpr;
+use crate::ast::LiteralExpr;
+use crate::ast::MemberAccessExpr;
 use crate::ast::Program;
+use crate::ast::ReturnStmt;
 use crate::ast::Spanned;
+use crate::ast::Stmt;
 use crate::ast::VarStmt;
 use crate::ast::Visitable;
 use crate::ast::WhileStmt;
+use crate::bytecode::decls;
 use crate::diagnostics::Diagnostic;
 use crate::diagnostics::DiagnosticHandler;
 use crate::diagnostics::DiagnosticKind;
 use crate::location::Range;
 use crate::messages;
+use crate::messages::Message;
 use crate::scope::Scope;
 use crate::symtab::LoopSym;
+use crate::symtab::SUPER_NAME;
+use crate::symtab::THIS_NAME;
 use crate::symtab::Symbol;
 use crate::symtab::VarSym;
 
@@ -39,6 +54,20 @@ pub struct Resolve<'inst> {
     scope: Option<Scope<'inst>>,
     diagnostics: &'inst mut (dyn DiagnosticHandler + 'inst),
     has_errors: bool,
+    func_depth: u16,
+    class_depth: u16,
+
+    /// Whether the resolver is visiting the body of a class initializer.
+    in_init: bool,
+
+    /// The top-level classes which have been visited so far.
+    declared_classes: Vec<String>,
+
+    /// All the top-level classes of the program.
+    classes: Vec<String>,
+
+    /// The names of the native functions provided by the VM.
+    natives: Vec<String>,
 }
 
 impl Resolve<'_> {
@@ -48,13 +77,32 @@ impl Resolve<'_> {
             diagnostics,
             scope: None,
             has_errors: false,
+            func_depth: 0,
+            class_depth: 0,
+            in_init: false,
+            declared_classes: Vec::with_capacity(0),
+            classes: Vec::with_capacity(0),
+            natives: Vec::with_capacity(0),
         };
     }
 
+    /// Declare a native function with the given name. Native functions are accessible from
+    /// anywhere in the program, unless shadowed by a variable with the same name.
+    pub fn declare_native(&mut self, name: &str) {
+        if !self.natives.iter().any(|native| native == name) {
+            self.natives.push(name.to_string());
+        }
+    }
+
     /// Reset the state of the name resolver.
     pub fn reset(&mut self) {
         self.scope = None;
         self.has_errors = false;
+        self.func_depth = 0;
+        self.class_depth = 0;
+        self.in_init = false;
+        self.declared_classes.clear();
+        self.classes.clear();
     }
 
     /// Returns whether there were any errors during name resolution.
@@ -62,20 +110,135 @@ impl Resolve<'_> {
         return self.has_errors;
     }
 
+    /// Get the handler to which the diagnostics are reported.
+    pub(crate) fn diagnostics(&mut self) -> &mut dyn DiagnosticHandler {
+        return self.diagnostics;
+    }
+
     pub fn analyze(&mut self, program: &mut Program) {
         let mut scope = Scope::new();
         program.accept(self, &mut scope);
     }
 
-    fn report_err(&mut self, range: &Range, msg: &str) {
+    fn report_err(&mut self, range: &Range, msg: &Message) {
         self.has_errors = true;
         self.diagnostics.handle(Diagnostic {
             kind: DiagnosticKind::Error,
             range: range.clone(),
             message: msg.to_string(),
+            code: msg.code,
+            notes: Vec::new(),
         });
     }
 
+    fn def_func(&mut self, name: &IdentifierExpr, scope: &mut Scope) {
+        match scope.push_var(VarSym::new(name.name.clone())) {
+            Ok(_) => {}
+            Err(_) => self.report_err(&name.range(), &messages::err_dup_func(&name.name)),
+        }
+    }
+
+    fn def_class(&mut self, name: &IdentifierExpr, scope: &mut Scope) {
+        match scope.push_var(VarSym::new(name.name.clone())) {
+            Ok(_) => {}
+            Err(_) => self.report_err(&name.range(), &messages::err_dup_class(&name.name)),
+        }
+    }
+
+    fn def_params(&mut self, params: &Vec<IdentifierExpr>, scope: &mut Scope) {
+        for param in params {
+            match scope.push_var(VarSym::new(param.name.clone())) {
+                Err(_) => self.report_err(&param.range(), &messages::err_dup_var(&param.name)),
+                Ok(_) => {}
+            };
+        }
+    }
+
+    /// Returns whether the given name refers to a native function in the given scope.
+    fn is_native(&self, name: &String, scope: &Scope) -> bool {
+        return scope.resolve_var(name).is_none()
+            && scope.find_sym(name).is_none()
+            && self.natives.contains(name);
+    }
+
+    /// Report an error if the target of an assignment is a native function or a constant.
+    fn check_assign_target(&mut self, target: &Expr, scope: &Scope) {
+        if let Expr::Identifier(identifier) = target {
+            if self.is_native(&identifier.name, scope) {
+                self.report_err(
+                    identifier.range(),
+                    &messages::err_assign_native(&identifier.name),
+                );
+            } else if scope.is_const(&identifier.name) {
+                self.report_err(
+                    identifier.range(),
+                    &messages::err_assign_const(&identifier.name),
+                );
+            }
+        }
+    }
+
+    /// Mark the given variable declaration as captured if a closure captures the variable
+    /// declared in the given scope.
+    fn mark_captured(&self, name: &mut IdentifierExpr, scope: &Scope) {
+        if let Some(var) = scope.symbols.get_var(&name.name) {
+            name.captured = var.captured.get();
+        }
+    }
+
+    /// Resolve the superclass of the given class. Top-level classes are created before the
+    /// top-level statements are executed, in the order in which they are declared, so the
+    /// superclass of a top-level class must be declared before it. Any other top-level variable
+    /// is not a class when the class is created.
+    fn resolve_supercls(&mut self, class_decl: &mut ClassDecl, scope: &mut Scope) {
+        let name = &class_decl.name.name;
+        let supercls = match class_decl.supercls.as_mut() {
+            Some(supercls) => supercls,
+            None => return,
+        };
+
+        if &supercls.name == name {
+            self.report_err(&supercls.range(), &messages::err_inherit_self(name));
+            return;
+        }
+
+        if scope.is_root()
+            && !self.declared_classes.contains(&supercls.name)
+            && scope.symbols.get_var(&supercls.name).is_some()
+        {
+            let msg = if self.classes.contains(&supercls.name) {
+                messages::err_supercls_after_cls(&supercls.name, name)
+            } else {
+                messages::err_not_a_class(&supercls.name)
+            };
+            self.report_err(&supercls.range(), &msg);
+            return;
+        }
+
+        self.visit_identifier_expr(supercls, scope);
+    }
+
+    fn visit_method(&mut self, method: &mut FuncDecl, scope: &Scope) {
+        let mut method_scope = Scope::new_func();
+        method_scope.parent = Some(scope);
+
+        // the receiver is always the first local variable of a method
+        let this = method.this.as_mut().expect("Expected a method");
+        let _ = method_scope.push_var(VarSym::new(this.name.clone()));
+        self.def_params(&method.params, &mut method_scope);
+
+        let in_init = std::mem::replace(&mut self.in_init, method.name.name == decls::ClassDecl::INIT_NAME);
+        self.func_depth += 1;
+        self.visit_block_stmt(&mut method.body, &mut method_scope);
+        self.func_depth -= 1;
+        self.in_init = in_init;
+
+        self.mark_captured(method.this.as_mut().unwrap(), &method_scope);
+        for param in method.params.iter_mut() {
+            self.mark_captured(param, &method_scope);
+        }
+    }
+
     fn def_loop_label(&mut self, label: Option<&IdentifierExpr>, scope: &mut Scope) {
         if let Some(label) = label {
             match scope.push_sym(Symbol::LabeledLoop(LoopSym::new(label.name.clone()))) {
@@ -98,12 +261,112 @@ impl Resolve<'_> {
 impl<'inst> ASTVisitor<Scope<'inst>, ()> for Resolve<'_> {
     fn visit_program(&mut self, program: &mut Program, p: &mut Scope) -> Option<()> {
         self.scope = Some(Scope::new());
-        self.default_visit_program(program, p, true, true);
+
+        // top-level functions and classes can be used before they are declared
+        for decl in &program.decls {
+            match decl {
+                Decl::Func(func_decl) => self.def_func(&func_decl.name, p),
+                Decl::Class(class_decl) => {
+                    self.def_class(&class_decl.name, p);
+                    self.classes.push(class_decl.name.name.clone());
+                }
+                _ => {}
+            }
+        }
+
+        // visit the top-level statements before the function bodies so that the functions
+        // can access all the top-level variables
+        self.default_visit_program(program, p, false, true);
+        self.default_visit_program(program, p, true, false);
         self.scope = None;
+        self.declared_classes.clear();
+        self.classes.clear();
+
+        None
+    }
+
+    fn visit_func_decl(&mut self, func_decl: &mut FuncDecl, p: &mut Scope) -> Option<()> {
+        // top-level functions are defined before visiting the program
+        if !p.is_root() {
+            self.def_func(&func_decl.name, p);
+        }
+
+        let mut scope = Scope::new_func();
+        scope.parent = Some(&p);
+        self.def_params(&func_decl.params, &mut scope);
+
+        let in_init = std::mem::replace(&mut self.in_init, false);
+        self.func_depth += 1;
+        self.visit_block_stmt(&mut func_decl.body, &mut scope);
+        self.func_depth -= 1;
+        self.in_init = in_init;
+
+        for param in func_decl.params.iter_mut() {
+            self.mark_captured(param, &scope);
+        }
+
+        None
+    }
+
+    fn visit_lambda_expr(&mut self, lambda_expr: &mut LambdaExpr, p: &mut Scope) -> Option<()> {
+        let mut scope = Scope::new_func();
+        scope.parent = Some(&p);
+        self.def_params(&lambda_expr.params, &mut scope);
+
+        let in_init = std::mem::replace(&mut self.in_init, false);
+        self.func_depth += 1;
+        self.visit_block_stmt(&mut lambda_expr.body, &mut scope);
+        self.func_depth -= 1;
+        self.in_init = in_init;
+
+        for param in lambda_expr.params.iter_mut() {
+            self.mark_captured(param, &scope);
+        }
+
+        None
+    }
+
+    fn visit_class_decl(&mut self, class_decl: &mut ClassDecl, p: &mut Scope) -> Option<()> {
+        // top-level classes are defined before visiting the program
+        if !p.is_root() {
+            self.def_class(&class_decl.name, p);
+        }
+
+        self.resolve_supercls(class_decl, p);
+        if p.is_root() {
+            self.declared_classes.push(class_decl.name.name.clone());
+        }
+
+        // the methods of a subclass access the superclass through the 'super' variable
+        let mut scope = Scope::new();
+        scope.parent = Some(&p);
+        if class_decl.supercls.is_some() {
+            let _ = scope.push_var(VarSym::new(String::from(SUPER_NAME)));
+        }
+
+        self.class_depth += 1;
+        for method in class_decl.methods.iter_mut() {
+            self.visit_method(method, &scope);
+        }
+        self.class_depth -= 1;
 
         None
     }
 
+    fn visit_return_stmt(&mut self, return_stmt: &mut ReturnStmt, p: &mut Scope) -> Option<()> {
+        if self.func_depth == 0 {
+            self.report_err(return_stmt.range(), &messages::RES_RETURN_OUTSIDE_FUNC);
+        }
+
+        // 'return;' is parsed as a return statement with a null literal
+        let returns_value = !matches!(return_stmt.expr, Expr::Literal(LiteralExpr::Null(_)));
+        if self.in_init && returns_value {
+            self.report_err(return_stmt.range(), &messages::RES_RETURN_VALUE_IN_INIT);
+        }
+
+        self.default_visit_return_stmt(return_stmt, p)
+    }
+
     fn visit_var_stmt(&mut self, var_decl: &mut VarStmt, scope: &mut Scope) -> Option<()> {
         let var_name = &var_decl.name.name.clone();
 
@@ -113,8 +376,17 @@ impl<'inst> ASTVisitor<Scope<'inst>, ()> for Resolve<'_> {
             self.visit_expr(expr, scope);
         }
 
-        match scope.push_var(VarSym::new(var_name.clone())) {
-            Err(_) => self.report_err(&var_decl.name.range(), &messages::err_dup_var(&var_name)),
+        let sym = if var_decl.is_const {
+            VarSym::new_const(var_name.clone())
+        } else {
+            VarSym::new(var_name.clone())
+        };
+
+        match scope.push_var(sym) {
+            Err(_) => {
+                self.report_err(&var_decl.name.range(), &messages::err_dup_var(&var_name));
+                scope.rejected_vars.push(var_name.clone());
+            }
             Ok(_) => {}
         };
 
@@ -126,12 +398,30 @@ impl<'inst> ASTVisitor<Scope<'inst>, ()> for Resolve<'_> {
         new.parent = Some(&p);
         self.default_visit_block_stmt(block_stmt, &mut new);
 
+        // the variables and functions declared in this block are now known to be captured
+        // or not, which is required for the code generation
+        for decl in block_stmt.decls.iter_mut() {
+            match decl {
+                Decl::Func(func_decl) => self.mark_captured(&mut func_decl.name, &new),
+                Decl::Class(class_decl) => self.mark_captured(&mut class_decl.name, &new),
+                Decl::Stmt(Stmt::Var(var_stmt)) => self.mark_captured(&mut var_stmt.name, &new),
+                _ => {}
+            }
+        }
+
         None
     }
 
     fn visit_for_stmt(&mut self, for_stmt: &mut ForStmt, scope: &mut Scope<'inst>) -> Option<()> {
         self.def_loop_label(for_stmt.label.as_ref(), scope);
-        self.default_visit_for_stmt(for_stmt, scope)
+        self.default_visit_for_stmt(for_stmt, scope);
+
+        // the variable in the initializer is declared in the enclosing scope
+        if let Some(Stmt::Var(var_stmt)) = for_stmt.init.as_mut() {
+            self.mark_captured(&mut var_stmt.name, scope);
+        }
+
+        None
     }
 
     fn visit_break_stmt(
@@ -167,10 +457,44 @@ impl<'inst> ASTVisitor<Scope<'inst>, ()> for Resolve<'_> {
         _p: &mut Scope<'inst>,
     ) -> Option<()> {
         let name = &identifier.name;
-        if _p.find_sym(name).is_none() {
-            self.report_err(identifier.range(), &messages::err_undef_var(name));
+        if _p.resolve_var(name).is_some()
+            || _p.find_sym(name).is_some()
+            || self.natives.contains(name)
+        {
+            return None;
         }
 
+        let msg = match name.as_str() {
+            THIS_NAME => messages::RES_THIS_OUTSIDE_CLASS,
+            SUPER_NAME if self.class_depth > 0 => messages::RES_SUPER_WITHOUT_SUPERCLASS,
+            SUPER_NAME => messages::RES_SUPER_OUTSIDE_CLASS,
+            _ => messages::err_undef_var(name),
+        };
+
+        self.report_err(identifier.range(), &msg);
         None
     }
+
+    fn visit_assign_expr(&mut self, assign_expr: &mut AssignExpr, p: &mut Scope<'inst>) -> Option<()> {
+        self.check_assign_target(&assign_expr.target, p);
+        self.default_visit_assign_expr(assign_expr, p)
+    }
+
+    fn visit_compound_assign_expr(
+        &mut self,
+        compound_assign_expr: &mut CompoundAssignExpr,
+        p: &mut Scope<'inst>,
+    ) -> Option<()> {
+        self.check_assign_target(&compound_assign_expr.target, p);
+        self.default_visit_compound_assign_expr(compound_assign_expr, p)
+    }
+
+    fn visit_member_access_expr(
+        &mut self,
+        member_access_expr: &mut MemberAccessExpr,
+        p: &mut Scope<'inst>,
+    ) -> Option<()> {
+        // members are resolved at runtime
+        self.visit_expr(&mut member_access_expr.receiver, p)
+    }
 }
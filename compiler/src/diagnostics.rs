@@ -15,8 +15,15 @@
 
 use std::sync::OnceLock;
 
+use util::json;
+
+use crate::location::Position;
 use crate::location::Range;
 
+pub use render::DiagnosticRenderer;
+
+mod render;
+
 pub trait DiagnosticHandler {
     fn handle(&mut self, diagnostic: Diagnostic);
 }
@@ -59,6 +66,50 @@ pub struct Diagnostic {
     pub range: Range,
     pub message: String,
     pub kind: DiagnosticKind,
+
+    /// The stable code of the message, see [crate::messages::Message].
+    pub code: &'static str,
+
+    /// Additional notes which are shown along with the message.
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    /// Attach a note to this diagnostic.
+    pub fn with_note(mut self, note: &str) -> Diagnostic {
+        self.notes.push(note.to_string());
+        return self;
+    }
+
+    /// Format this diagnostic, reported in the given file, as a JSON object. The lines and
+    /// columns in the range are 1-based, and the range is `null` if the diagnostic does not
+    /// point to any location in the file.
+    pub fn to_json(&self, file: &str) -> String {
+        let range = if self.range.start.line < 0 {
+            "null".to_string()
+        } else {
+            format!(
+                "{{\"start\":{},\"end\":{}}}",
+                position_json(&self.range.start),
+                position_json(&self.range.end)
+            )
+        };
+        let notes: Vec<String> = self
+            .notes
+            .iter()
+            .map(|note| format!("\"{}\"", json::escape(note)))
+            .collect();
+
+        return format!(
+            "{{\"file\":\"{}\",\"range\":{},\"severity\":\"{}\",\"code\":\"{}\",\"message\":\"{}\",\"notes\":[{}]}}",
+            json::escape(file),
+            range,
+            self.kind.label(),
+            self.code,
+            json::escape(&self.message),
+            notes.join(",")
+        );
+    }
 }
 
 #[derive(PartialEq, Eq, Debug, Clone)]
@@ -66,4 +117,23 @@ pub enum DiagnosticKind {
     Error,
     Warning,
     Note,
-}
\ No newline at end of file
+}
+
+impl DiagnosticKind {
+    /// The label which is shown before the messages of this kind.
+    pub fn label(&self) -> &'static str {
+        return match self {
+            DiagnosticKind::Error => "error",
+            DiagnosticKind::Warning => "warning",
+            DiagnosticKind::Note => "note",
+        };
+    }
+}
+
+fn position_json(position: &Position) -> String {
+    return format!(
+        "{{\"line\":{},\"column\":{}}}",
+        position.line + 1,
+        position.column + 1
+    );
+}
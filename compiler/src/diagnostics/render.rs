@@ -0,0 +1,152 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::fmt::Write;
+
+use colored::Color;
+use colored::Colorize;
+
+use crate::diagnostics::Diagnostic;
+use crate::diagnostics::DiagnosticKind;
+
+/// Renders diagnostics for the terminal, along with the line of source code they point to.
+pub struct DiagnosticRenderer<'a> {
+    path: &'a str,
+    lines: Vec<&'a str>,
+}
+
+impl<'a> DiagnosticRenderer<'a> {
+    /// Create a new renderer for the diagnostics in the given source file.
+    pub fn new(path: &'a str, source: &'a str) -> DiagnosticRenderer<'a> {
+        return DiagnosticRenderer {
+            path,
+            lines: source.lines().collect(),
+        };
+    }
+
+    /// Render the given diagnostic in the following format :
+    ///
+    /// ```text
+    /// file.yk:1:9: error: Expected an expression
+    ///   |
+    /// 1 | var a = ;
+    ///   |         ^
+    ///   = note: ...
+    /// ```
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let mut out = String::new();
+        let start = &diagnostic.range.start;
+        let color = kind_color(&diagnostic.kind);
+        let label = diagnostic.kind.label().color(color).bold();
+
+        if start.line < 0 {
+            writeln!(
+                out,
+                "{}: {}: {}",
+                self.path,
+                label,
+                diagnostic.message.bold()
+            )
+            .unwrap();
+        } else {
+            writeln!(
+                out,
+                "{}:{}:{}: {}: {}",
+                self.path,
+                start.line + 1,
+                start.column + 1,
+                label,
+                diagnostic.message.bold()
+            )
+            .unwrap();
+        }
+
+        let line = usize::try_from(start.line)
+            .ok()
+            .and_then(|line| self.lines.get(line));
+
+        let number = (start.line + 1).to_string();
+        let gutter = match line {
+            Some(_) => " ".repeat(number.len()),
+            None => String::new(),
+        };
+
+        if let Some(line) = line {
+            let bar = "|".blue().bold();
+            let (padding, width) = underline(line, diagnostic);
+            writeln!(out, "{} {}", gutter, bar).unwrap();
+            writeln!(out, "{} {} {}", number.blue().bold(), bar, line).unwrap();
+            writeln!(
+                out,
+                "{} {} {}{}",
+                gutter,
+                bar,
+                padding,
+                "^".repeat(width).color(color).bold()
+            )
+            .unwrap();
+        }
+
+        for note in &diagnostic.notes {
+            writeln!(
+                out,
+                "{} {} {}: {}",
+                gutter,
+                "=".blue().bold(),
+                DiagnosticKind::Note.label().bold(),
+                note
+            )
+            .unwrap();
+        }
+
+        return out;
+    }
+}
+
+/// Get the color used to highlight diagnostics of the given kind.
+fn kind_color(kind: &DiagnosticKind) -> Color {
+    return match kind {
+        DiagnosticKind::Error => Color::Red,
+        DiagnosticKind::Warning => Color::Yellow,
+        DiagnosticKind::Note => Color::Cyan,
+    };
+}
+
+/// Compute the padding before the underline for the range of the diagnostic in the given line,
+/// and the width of the underline. Tabs in the line are preserved in the padding so that the
+/// underline stays aligned with the source.
+fn underline(line: &str, diagnostic: &Diagnostic) -> (String, usize) {
+    let start = &diagnostic.range.start;
+    let end = &diagnostic.range.end;
+    let length = line.chars().count();
+    let column = usize::try_from(start.column).unwrap_or(0).min(length);
+
+    let padding = line
+        .chars()
+        .take(column)
+        .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+        .collect();
+
+    // ranges spanning over multiple lines are underlined till the end of the first line
+    let end_column = if end.line == start.line {
+        usize::try_from(end.column).unwrap_or(0).min(length)
+    } else if end.line > start.line {
+        length
+    } else {
+        column
+    };
+
+    return (padding, end_column.saturating_sub(column).max(1));
+}
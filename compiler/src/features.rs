@@ -21,12 +21,44 @@ use util::define_str_consts;
 #[derive(Debug, Clone, PartialEq)]
 pub struct CompilerFeatures {
     pub const_folding: bool,
+
+    /// Substitute the variables which are initialized with a constant and never reassigned.
+    pub const_propagation: bool,
+
+    /// Warn about variables which are declared but never read.
+    pub unused_variables: bool,
+
+    /// Warn about statements which follow a `break`, `continue` or `return` statement.
+    pub unreachable_code: bool,
+
+    /// Warn about variables which shadow a variable of an enclosing function or the top-level code.
+    pub shadowing: bool,
+
+    /// Warn about `if` and `while` conditions which always evaluate to the same value.
+    pub constant_conditions: bool,
+
+    /// Remove the statements and the instructions which can never be executed.
+    pub dead_code_elim: bool,
+
+    /// Rewrite short sequences of the emitted instructions into cheaper equivalent sequences.
+    pub peephole: bool,
+
+    /// Evaluate the loop-invariant expressions once before the loop instead of in every iteration.
+    pub licm: bool,
 }
 
 impl Default for CompilerFeatures {
     fn default() -> Self {
         CompilerFeatures {
             const_folding: true,
+            const_propagation: true,
+            unused_variables: true,
+            unreachable_code: true,
+            shadowing: true,
+            constant_conditions: true,
+            dead_code_elim: true,
+            peephole: true,
+            licm: false,
         }
     }
 }
@@ -36,6 +68,14 @@ impl CompilerFeatures {
     pub fn set(&mut self, feature: &str, enabled: bool) {
         match feature {
             CompilerFeatures::CONST_FOLDING => self.const_folding = enabled,
+            CompilerFeatures::CONST_PROPAGATION => self.const_propagation = enabled,
+            CompilerFeatures::UNUSED_VARIABLES => self.unused_variables = enabled,
+            CompilerFeatures::UNREACHABLE_CODE => self.unreachable_code = enabled,
+            CompilerFeatures::SHADOWING => self.shadowing = enabled,
+            CompilerFeatures::CONSTANT_CONDITIONS => self.constant_conditions = enabled,
+            CompilerFeatures::DEAD_CODE_ELIM => self.dead_code_elim = enabled,
+            CompilerFeatures::PEEPHOLE => self.peephole = enabled,
+            CompilerFeatures::LICM => self.licm = enabled,
             _ => warn!("Unknown compiler feature: {}", feature),
         }
     }
@@ -44,6 +84,14 @@ impl CompilerFeatures {
     pub fn is_enabled(&self, feature: &str) -> bool {
         match feature {
             CompilerFeatures::CONST_FOLDING => self.const_folding,
+            CompilerFeatures::CONST_PROPAGATION => self.const_propagation,
+            CompilerFeatures::UNUSED_VARIABLES => self.unused_variables,
+            CompilerFeatures::UNREACHABLE_CODE => self.unreachable_code,
+            CompilerFeatures::SHADOWING => self.shadowing,
+            CompilerFeatures::CONSTANT_CONDITIONS => self.constant_conditions,
+            CompilerFeatures::DEAD_CODE_ELIM => self.dead_code_elim,
+            CompilerFeatures::PEEPHOLE => self.peephole,
+            CompilerFeatures::LICM => self.licm,
             _ => false,
         }
     }
@@ -66,5 +114,13 @@ impl CompilerFeatures {
 define_str_consts!(
     impl CompilerFeatures {
         CONST_FOLDING = "const-folding",
+        CONST_PROPAGATION = "const-propagation",
+        UNUSED_VARIABLES = "unused-variables",
+        UNREACHABLE_CODE = "unreachable-code",
+        SHADOWING = "shadowing",
+        CONSTANT_CONDITIONS = "constant-conditions",
+        DEAD_CODE_ELIM = "dead-code-elim",
+        PEEPHOLE = "peephole",
+        LICM = "licm",
     }
 );
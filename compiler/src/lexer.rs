@@ -27,6 +27,7 @@ use crate::diagnostics::DiagnosticKind;
 use crate::location::Position;
 use crate::location::Range;
 use crate::messages;
+use crate::messages::Message;
 use crate::tokens::Token;
 use crate::tokens::TokenType;
 
@@ -43,21 +44,25 @@ pub struct YKLexer<'a, R: Read> {
 }
 
 impl<R: Read> YKLexer<'_, R> {
-    fn report(&mut self, diagnostic_kind: DiagnosticKind, message: &str) {
+    fn report(&mut self, diagnostic_kind: DiagnosticKind, message: &Message) {
         self.diagnostics
             .handle(self.create_diagnostic(diagnostic_kind, message));
     }
 
-    fn create_diagnostic(&self, diagnostic_kind: DiagnosticKind, message: &str) -> Diagnostic {
+    fn create_diagnostic(&self, diagnostic_kind: DiagnosticKind, message: &Message) -> Diagnostic {
         Diagnostic {
             range: Range {
                 start: self.token_start,
                 end: self.position,
             },
 
-            message: String::from(message),
+            message: message.to_string(),
 
             kind: diagnostic_kind,
+
+            code: message.code,
+
+            notes: Vec::new(),
         }
     }
 }
@@ -101,14 +106,20 @@ impl<R: Read> YKLexer<'_, R> {
                     continue;
                 }
 
+                if token.token_type == TokenType::Error {
+                    // the error has already been reported
+                    continue;
+                }
+
                 tokens.push(token)
             }
         }
         return tokens;
     }
 
-    /// Advance to the next token in the input source. This returns [Some] if a valid token
-    /// is recognized, otherwise return [None].
+    /// Advance to the next token in the input source. This returns [None] at the end of the input
+    /// source. Characters which cannot be tokenized are reported, and returned as a
+    /// [TokenType::Error] token.
     pub fn next(&mut self) -> Option<Token> {
         // Skip all whitespaces
         self.skip_whitespaces();
@@ -193,8 +204,8 @@ impl<R: Read> YKLexer<'_, R> {
                     },
 
                     _ => {
-                        self.report(DiagnosticKind::Error, messages::LEX_UNKNOWN_TOKEN);
-                        return None;
+                        self.report(DiagnosticKind::Error, &messages::LEX_UNKNOWN_TOKEN);
+                        return Some(self.token(TokenType::Error));
                     }
                 };
             }
@@ -218,7 +229,18 @@ impl<R: Read> YKLexer<'_, R> {
             Some(c1) => match c1 {
                 'a' => self.match_word_rest(1, "nd", TokenType::And),
                 'b' => self.match_word_rest(1, "reak", TokenType::Break),
-                'c' => self.match_word_rest(1, "ontinue", TokenType::Continue),
+                'c' => match self.token_text.get(1) {
+                    None => None,
+                    Some(c2) => match c2 {
+                        'l' => self.match_word_rest(2, "ass", TokenType::Class),
+                        'o' => match self.token_text.get(3) {
+                            Some('t') => self.match_word_rest(2, "ntinue", TokenType::Continue),
+                            Some('s') => self.match_word_rest(2, "nst", TokenType::Const),
+                            _ => None,
+                        },
+                        _ => None,
+                    },
+                },
                 'e' => self.match_word_rest(1, "lse", TokenType::Else),
                 'f' => match self.token_text.get(1) {
                     None => None,
@@ -319,15 +341,15 @@ impl<R: Read> YKLexer<'_, R> {
         loop {
             let peek = self.peek().unwrap_or(NULL_CHAR);
             if self.is_at_eof() || peek == NULL_CHAR {
-                self.report(DiagnosticKind::Error, messages::LEX_UNEXPECTED_EOF);
-                return None;
+                self.report(DiagnosticKind::Error, &messages::LEX_UNEXPECTED_EOF);
+                return Some(self.token(TokenType::Error));
             }
 
             match peek {
                 '"' => break, // reached end of string
                 '\n' => {
-                    self.report(DiagnosticKind::Error, messages::LEX_STRING_MULTILINE_ERROR);
-                    return None;
+                    self.report(DiagnosticKind::Error, &messages::LEX_STRING_MULTILINE_ERROR);
+                    return Some(self.token(TokenType::Error));
                 }
                 '\\' => {
                     // this consumes the whole escape sequence,
@@ -353,7 +375,7 @@ impl<R: Read> YKLexer<'_, R> {
         let mut char = self.advance().unwrap_or(NULL_CHAR);
 
         if self.is_at_eof() || char == NULL_CHAR || char != '\\' {
-            self.report(DiagnosticKind::Error, messages::LEX_STRING_EXPECTED_ESC_SEQ);
+            self.report(DiagnosticKind::Error, &messages::LEX_STRING_EXPECTED_ESC_SEQ);
             return Err(());
         }
 
@@ -368,7 +390,7 @@ impl<R: Read> YKLexer<'_, R> {
                     if !is_hex_digit(self.advance().unwrap_or(NULL_CHAR)) {
                         self.report(
                             DiagnosticKind::Error,
-                            messages::LEX_STRING_ILLEGAL_UNICODE_ESC,
+                            &messages::LEX_STRING_ILLEGAL_UNICODE_ESC,
                         );
                         return Err(());
                     }
@@ -377,7 +399,7 @@ impl<R: Read> YKLexer<'_, R> {
             _ => {
                 self.report(
                     DiagnosticKind::Error,
-                    messages::LEX_STRING_UNRECOGNIZED_ESC_SEQ,
+                    &messages::LEX_STRING_UNRECOGNIZED_ESC_SEQ,
                 );
                 return Err(());
             }
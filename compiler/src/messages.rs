@@ -13,47 +13,176 @@
  * program. If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+/// A message reported by the compiler, along with its code. The code of a message is stable, it
+/// does not change even if the text of the message does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub code: &'static str,
+    pub text: Cow<'static, str>,
+}
+
+impl Message {
+    const fn new(code: &'static str, text: &'static str) -> Message {
+        return Message {
+            code,
+            text: Cow::Borrowed(text),
+        };
+    }
+
+    fn format(code: &'static str, text: String) -> Message {
+        return Message {
+            code,
+            text: Cow::Owned(text),
+        };
+    }
+}
+
+impl Display for Message {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return f.write_str(&self.text);
+    }
+}
+
+impl PartialEq<String> for Message {
+    fn eq(&self, other: &String) -> bool {
+        return self.text == other.as_str();
+    }
+}
+
+impl PartialEq<Message> for String {
+    fn eq(&self, other: &Message) -> bool {
+        return self.as_str() == other.text;
+    }
+}
+
 // ------------------------ lexer --------------------------
-pub const LEX_UNKNOWN_TOKEN: &str = "unknown token";
-pub const LEX_UNEXPECTED_EOF: &str = "unexpected EOF";
-pub const LEX_STRING_MULTILINE_ERROR: &str = "multiline strings are not supported";
-pub const LEX_STRING_EXPECTED_ESC_SEQ: &str = "expected an escape sequence";
-pub const LEX_STRING_UNRECOGNIZED_ESC_SEQ: &str = "unrecognized escape sequence";
-pub const LEX_STRING_ILLEGAL_UNICODE_ESC: &str = "illegal unicode escape";
+pub const LEX_UNKNOWN_TOKEN: Message = Message::new("E0100", "unknown token");
+pub const LEX_UNEXPECTED_EOF: Message = Message::new("E0101", "unexpected EOF");
+pub const LEX_STRING_MULTILINE_ERROR: Message =
+    Message::new("E0102", "multiline strings are not supported");
+pub const LEX_STRING_EXPECTED_ESC_SEQ: Message =
+    Message::new("E0103", "expected an escape sequence");
+pub const LEX_STRING_UNRECOGNIZED_ESC_SEQ: Message =
+    Message::new("E0104", "unrecognized escape sequence");
+pub const LEX_STRING_ILLEGAL_UNICODE_ESC: Message = Message::new("E0105", "illegal unicode escape");
 
 // ------------------------ parser --------------------------
-pub const PARS_DECL_OR_STMT_EXPECTED: &str = "expected a declaration or statement";
-pub const PARS_UNEXPECTED_EOF: &str = "unexpected EOF";
-pub const PARS_EXPECTED_VAR_NAME: &str = "expected a variable name";
-pub const PARS_EXPECTED_UNARY_OP: &str = "expected a unary operator";
-pub const PARS_EXPECTED_EXPR: &str = "expected an expression";
-pub const PARS_EXPECTED_FUN_NAME: &str = "expected a function name";
-pub const PARS_EXPECTED_PARAM_NAME: &str = "expected a parameter name";
-pub const PARS_EXPECTED_STMT: &str = "expected a statement";
-pub const PARS_EXPECTED_BODY: &str = "expected body";
-pub const PARS_INVALID_ASSIGN_TARGET: &str = "invalid assignment target";
-pub const PARS_EXPECTED_LABEL: &str = "expected a label";
+pub const PARS_DECL_OR_STMT_EXPECTED: Message =
+    Message::new("E0200", "expected a declaration or statement");
+pub const PARS_UNEXPECTED_EOF: Message = Message::new("E0201", "unexpected EOF");
+pub const PARS_EXPECTED_VAR_NAME: Message = Message::new("E0202", "expected a variable name");
+pub const PARS_EXPECTED_UNARY_OP: Message = Message::new("E0203", "expected a unary operator");
+pub const PARS_EXPECTED_EXPR: Message = Message::new("E0204", "expected an expression");
+pub const PARS_EXPECTED_FUN_NAME: Message = Message::new("E0205", "expected a function name");
+pub const PARS_EXPECTED_PARAM_NAME: Message = Message::new("E0206", "expected a parameter name");
+pub const PARS_EXPECTED_CLASS_NAME: Message = Message::new("E0207", "expected a class name");
+pub const PARS_EXPECTED_SUPERCLASS_NAME: Message =
+    Message::new("E0208", "expected a superclass name");
+pub const PARS_EXPECTED_METHOD_NAME: Message = Message::new("E0209", "expected a method name");
+pub const PARS_EXPECTED_MEMBER_NAME: Message = Message::new("E0210", "expected a member name");
+pub const PARS_EXPECTED_STMT: Message = Message::new("E0211", "expected a statement");
+pub const PARS_EXPECTED_BODY: Message = Message::new("E0212", "expected body");
+pub const PARS_INVALID_ASSIGN_TARGET: Message = Message::new("E0213", "invalid assignment target");
+pub const PARS_EXPECTED_LABEL: Message = Message::new("E0214", "expected a label");
+pub const PARS_TOO_MANY_PARAMS: Message =
+    Message::new("E0215", "a function cannot have more than 255 parameters");
+pub const PARS_TOO_MANY_ARGS: Message =
+    Message::new("E0216", "a function cannot be called with more than 255 arguments");
 
-pub fn err_exp_kywrd(keyword: &str) -> String {
-    return format!("expected '{}' keyword", keyword);
+pub fn err_exp_kywrd(keyword: &str) -> Message {
+    return Message::format("E0217", format!("expected '{}' keyword", keyword));
 }
 
-pub fn err_exp_sym(sym: &str) -> String {
-    return format!("expected a '{}'", sym);
+pub fn err_exp_sym(sym: &str) -> Message {
+    return Message::format("E0218", format!("expected a '{}'", sym));
 }
 
 // ------------------------ analyzer --------------------------
-pub fn err_dup_var(sym: &str) -> String {
-    return format!("Variable '{}' is already defined", sym);
+pub const RES_RETURN_OUTSIDE_FUNC: Message =
+    Message::new("E0010", "Cannot return from top-level code");
+pub const RES_RETURN_VALUE_IN_INIT: Message =
+    Message::new("E0011", "Cannot return a value from an initializer");
+pub const RES_THIS_OUTSIDE_CLASS: Message =
+    Message::new("E0012", "Cannot use 'this' outside of a class");
+pub const RES_SUPER_OUTSIDE_CLASS: Message =
+    Message::new("E0013", "Cannot use 'super' outside of a class");
+pub const RES_SUPER_WITHOUT_SUPERCLASS: Message =
+    Message::new("E0014", "Cannot use 'super' in a class with no superclass");
+
+pub fn err_undef_var(sym: &str) -> Message {
+    return Message::format("E0001", format!("Variable '{}' is not defined", sym));
+}
+
+pub fn err_dup_var(sym: &str) -> Message {
+    return Message::format("E0002", format!("Variable '{}' is already defined", sym));
+}
+
+pub fn err_dup_func(sym: &str) -> Message {
+    return Message::format("E0003", format!("Function '{}' is already defined", sym));
+}
+
+pub fn err_dup_class(sym: &str) -> Message {
+    return Message::format("E0004", format!("Class '{}' is already defined", sym));
 }
-pub fn err_undef_var(sym: &str) -> String {
-    return format!("Variable '{}' is not defined", sym);
+
+pub fn err_assign_native(sym: &str) -> Message {
+    return Message::format("E0005", format!("Cannot assign to native function '{}'", sym));
+}
+
+pub fn err_assign_const(sym: &str) -> Message {
+    return Message::format("E0015", format!("Cannot assign to constant '{}'", sym));
+}
+
+pub fn err_inherit_self(sym: &str) -> Message {
+    return Message::format("E0006", format!("Class '{}' cannot inherit from itself", sym));
+}
+
+pub fn err_supercls_after_cls(supercls: &str, cls: &str) -> Message {
+    return Message::format(
+        "E0007",
+        format!(
+            "Superclass '{}' must be declared before class '{}'",
+            supercls, cls
+        ),
+    );
+}
+
+pub fn err_not_a_class(sym: &str) -> Message {
+    return Message::format("E0016", format!("'{}' is not a class", sym));
+}
+
+pub(crate) fn err_dup_label(label: &str) -> Message {
+    return Message::format("E0008", format!("Loop '{}' is already defined", label));
+}
+
+pub(crate) fn err_undef_label(label: &str) -> Message {
+    return Message::format("E0009", format!("Loop '{}' is not defined", label));
+}
+
+// ------------------------ code generation --------------------------
+pub const GEN_JUMP_OUT_OF_RANGE: Message =
+    Message::new("E0300", "Cannot jump over more than 32767 bytes of code");
+
+// ------------------------ lints --------------------------
+pub fn warn_unused_var(sym: &str) -> Message {
+    return Message::format("W0001", format!("Variable '{}' is never read", sym));
+}
+
+pub fn warn_unreachable_code(stmt: &str) -> Message {
+    return Message::format("W0002", format!("Unreachable code after '{}'", stmt));
 }
 
-pub(crate) fn err_dup_label(label: &str) -> String {
-    return format!("Loop '{}' is already defined", label);
+pub fn warn_shadowed_var(sym: &str) -> Message {
+    return Message::format(
+        "W0003",
+        format!("Variable '{}' shadows a variable of an outer scope", sym),
+    );
 }
 
-pub(crate) fn err_undef_label(label: &str) -> String {
-    return format!("Loop '{}' is not defined", label);
+pub fn warn_const_condition(value: &str) -> Message {
+    return Message::format("W0004", format!("Condition always evaluates to {}", value));
 }
@@ -22,17 +22,23 @@ use crate::ast::BinaryExpr;
 use crate::ast::BinaryOp;
 use crate::ast::BlockStmt;
 use crate::ast::BreakStmt;
+use crate::ast::ClassDecl;
 use crate::ast::CompoundAssignExpr;
 use crate::ast::ContinueStmt;
 use crate::ast::Decl;
+use crate::ast::DeclType;
+use crate::ast::ErrorStmt;
 use crate::ast::Expr;
 use crate::ast::ExprStmt;
 use crate::ast::ForStmt;
+use crate::ast::FuncCallExpr;
 use crate::ast::FuncDecl;
 use crate::ast::IdentifierExpr;
 use crate::ast::IdentifierType;
 use crate::ast::IfStmt;
+use crate::ast::LambdaExpr;
 use crate::ast::LiteralExpr;
+use crate::ast::MemberAccessExpr;
 use crate::ast::PrintStmt;
 use crate::ast::Program;
 use crate::ast::ReturnStmt;
@@ -49,6 +55,7 @@ use crate::lexer::YKLexer;
 use crate::location::Position;
 use crate::location::Range;
 use crate::messages;
+use crate::messages::Message;
 use crate::messages::err_exp_kywrd;
 use crate::messages::err_exp_sym;
 use crate::tokens::Token;
@@ -58,6 +65,10 @@ pub struct YKParser<'a, R: Read> {
     lexer: YKLexer<'a, R>,
     has_error: bool,
 
+    // whether the parser is recovering from a syntax error
+    // further errors are not reported until the parser synchronizes at a statement boundary
+    panic_mode: bool,
+
     // parser state
     position: Position,
     current: Option<Token>,
@@ -71,6 +82,7 @@ impl<R: Read> YKParser<'_, R> {
         let mut parser = YKParser {
             lexer,
             has_error: false,
+            panic_mode: false,
             position: Position::NO_POS,
             current: None,
             next: None,
@@ -84,15 +96,26 @@ impl<R: Read> YKParser<'_, R> {
         return parser;
     }
 
-    fn report(&mut self, diagnostic_kind: DiagnosticKind, message: &str) {
+    fn report(&mut self, diagnostic_kind: DiagnosticKind, message: &Message) {
         let is_error = diagnostic_kind == DiagnosticKind::Error;
-        self.lexer.diagnostics
-            .handle(self.create_diagnostic(diagnostic_kind, message));
+        if is_error && self.panic_mode {
+            // errors caused by the previous error would only be noise
+            return;
+        }
 
         self.has_error = self.has_error || is_error;
+        self.panic_mode = self.panic_mode || is_error;
+
+        if is_error && self.peek().is_some_and(|t| t.token_type == TokenType::Error) {
+            // the lexer has already reported an error at this location
+            return;
+        }
+
+        self.lexer.diagnostics
+            .handle(self.create_diagnostic(diagnostic_kind, message));
     }
 
-    fn create_diagnostic(&self, diagnostic_kind: DiagnosticKind, message: &str) -> Diagnostic {
+    fn create_diagnostic(&self, diagnostic_kind: DiagnosticKind, message: &Message) -> Diagnostic {
         let range = self
             .peek()
             .map(|tkn| tkn.range)
@@ -107,8 +130,10 @@ impl<R: Read> YKParser<'_, R> {
 
         Diagnostic {
             range: range.clone(),
-            message: String::from(message),
+            message: message.to_string(),
             kind: diagnostic_kind,
+            code: message.code,
+            notes: Vec::new(),
         }
     }
 
@@ -140,8 +165,36 @@ impl<R: Read> YKParser<'_, R> {
         return Program::new(decls, stmts, Range::NO_RANGE);
     }
 
-    /// Returns the next declaration in the input source.
+    /// Returns the next declaration in the input source. If the declaration cannot be parsed, the
+    /// parser synchronizes at the next statement boundary and returns an [ErrorStmt] spanning the
+    /// skipped source, so that the following declarations are parsed as usual.
     fn decl(&mut self) -> Option<Decl> {
+        let start = self.peek().map(|token| token.range);
+        let decl = self.decl0();
+        if !self.panic_mode {
+            return decl;
+        }
+
+        if decl.is_some() {
+            // the declaration was parsed completely, despite the error
+            self.panic_mode = false;
+            return decl;
+        }
+
+        // always skip the token which caused the error, if nothing was consumed
+        if self.peek().map(|token| token.range) == start {
+            self.advance();
+        }
+
+        self.synchronize();
+
+        let mut range = start.unwrap_or(Range::new().set_start_pos(&self.position));
+        return Some(Decl::Stmt(Stmt::Error(ErrorStmt::new(
+            range.set_end_pos(&self.position),
+        ))));
+    }
+
+    fn decl0(&mut self) -> Option<Decl> {
         let token = self.peek();
 
         if token.is_some_and(|t| &t.token_type == &TokenType::Semicolon) {
@@ -151,59 +204,158 @@ impl<R: Read> YKParser<'_, R> {
 
         return match token {
             Some(token) => match token.token_type {
-                TokenType::Fun => self.fun_decl(),
+                // 'fun' followed by '(' is a lambda expression
+                TokenType::Fun
+                    if self
+                        .peek_next()
+                        .map(|t| t.token_type != TokenType::LParen)
+                        .unwrap_or(true) =>
+                {
+                    self.fun_decl()
+                }
+                TokenType::Class => self.class_decl(),
                 _ => {
                     let stmt = self.try_stmt_decl();
                     if stmt.is_none() {
-                        self.report(DiagnosticKind::Error, messages::PARS_DECL_OR_STMT_EXPECTED);
+                        self.report(DiagnosticKind::Error, &messages::PARS_DECL_OR_STMT_EXPECTED);
                     }
                     stmt
                 }
             },
             None => {
-                self.report(DiagnosticKind::Error, messages::PARS_UNEXPECTED_EOF);
+                self.report(DiagnosticKind::Error, &messages::PARS_UNEXPECTED_EOF);
                 None
             }
         };
     }
 
-    /// Returns the next variable declaration in the input source.
+    /// Returns the next variable declaration in the input source. Constants are declared with
+    /// `const` instead of `var`, and must have an initializer.
     fn var_stmt(&mut self) -> Option<VarStmt> {
-        let var = self.accept(TokenType::Var, &err_exp_kywrd("var"))?;
+        let is_const = self.peek()?.token_type == TokenType::Const;
+        let var = if is_const {
+            self.accept(TokenType::Const, &err_exp_kywrd("const"))?
+        } else {
+            self.accept(TokenType::Var, &err_exp_kywrd("var"))?
+        };
+
         let mut range = var.range;
-        let var_name = self.accept(TokenType::Identifier, messages::PARS_EXPECTED_VAR_NAME)?;
+        let var_name = self.accept(TokenType::Identifier, &messages::PARS_EXPECTED_VAR_NAME)?;
         range.set_end(&var_name.range);
 
+        // constants must be initialized
+        let has_init = if is_const {
+            self.accept(TokenType::Eq, &err_exp_sym("="))?;
+            true
+        } else {
+            self.tmatch(&TokenType::Eq) != None
+        };
+
         let mut init: Option<Expr> = None;
-        if self.tmatch(&TokenType::Eq) != None {
-            init = self.expr();
-            if let Some(expr) = &init {
-                range.set_end(&expr.range());
-            }
+        if has_init {
+            let expr = self.expr()?;
+            range.set_end(&expr.range());
+            init = Some(expr);
         }
 
         Some(VarStmt::new(
-            IdentifierExpr::new(var_name.text, IdentifierType::VarName, var_name.range),
+            IdentifierExpr::new(var_name.text, IdentifierType::VarName, false, var_name.range),
             init,
+            is_const,
             range,
         ))
     }
 
     fn fun_decl(&mut self) -> Option<Decl> {
         let mut fun = self.accept(TokenType::Fun, &err_exp_kywrd("fun"))?;
-        let fun_name = self.accept(TokenType::Identifier, messages::PARS_EXPECTED_FUN_NAME)?;
+        let fun_name = self.accept(TokenType::Identifier, &messages::PARS_EXPECTED_FUN_NAME)?;
         let params = self.fun_params()?;
         let body = self.block()?;
         let end = body.range().end;
 
         Some(Decl::Func(FuncDecl::new(
-            IdentifierExpr::new(fun_name.text, IdentifierType::FuncName, fun_name.range),
+            IdentifierExpr::new(fun_name.text, IdentifierType::FuncName, false, fun_name.range),
             params,
             body,
+            None,
             fun.range.set_end_pos(&end),
         )))
     }
 
+    fn class_decl(&mut self) -> Option<Decl> {
+        let mut class = self.accept(TokenType::Class, &err_exp_kywrd("class"))?;
+        let class_name = self.accept(TokenType::Identifier, &messages::PARS_EXPECTED_CLASS_NAME)?;
+
+        let mut supercls = None;
+        if self.tmatch(&TokenType::Colon).is_some() {
+            let name =
+                self.accept(TokenType::Identifier, &messages::PARS_EXPECTED_SUPERCLASS_NAME)?;
+            supercls = Some(IdentifierExpr::new(
+                name.text,
+                IdentifierType::Other,
+                false,
+                name.range,
+            ));
+        }
+
+        self.accept(TokenType::LBrace, &err_exp_sym("{"))?;
+
+        let mut methods = Vec::new();
+        while self
+            .peek()
+            .map(|t| t.token_type != TokenType::RBrace)
+            .unwrap_or(false)
+        {
+            match self.method() {
+                Some(method) => methods.push(method),
+                None => {
+                    // skip the invalid method and continue with the next one
+                    self.synchronize();
+                    if self.peek().is_some_and(|t| t.token_type != TokenType::RBrace) {
+                        return None;
+                    }
+                }
+            }
+        }
+
+        let rbrace = self.accept(TokenType::RBrace, &err_exp_sym("}"))?;
+
+        Some(Decl::Class(ClassDecl::new(
+            IdentifierExpr::new(
+                class_name.text,
+                IdentifierType::ClassName,
+                false,
+                class_name.range,
+            ),
+            supercls,
+            methods,
+            DeclType::TopLevel,
+            class.range.set_end(&rbrace.range),
+        )))
+    }
+
+    fn method(&mut self) -> Option<FuncDecl> {
+        let mut name = self.accept(TokenType::Identifier, &messages::PARS_EXPECTED_METHOD_NAME)?;
+        let params = self.fun_params()?;
+        let body = self.block()?;
+        let end = body.range().end;
+
+        let this = IdentifierExpr::new(
+            String::from("this"),
+            IdentifierType::Keyword,
+            false,
+            name.range.clone(),
+        );
+
+        Some(FuncDecl::new(
+            IdentifierExpr::new(name.text, IdentifierType::FuncName, false, name.range),
+            params,
+            body,
+            Some(this),
+            name.range.set_end_pos(&end),
+        ))
+    }
+
     fn fun_params(&mut self) -> Option<Vec<IdentifierExpr>> {
         self.accept(TokenType::LParen, &err_exp_sym("("))?;
         let mut params = Vec::new();
@@ -214,12 +366,11 @@ impl<R: Read> YKParser<'_, R> {
         }
 
         loop {
-            let param = self
-                .accept(TokenType::Identifier, messages::PARS_EXPECTED_PARAM_NAME)
-                .unwrap();
+            let param = self.accept(TokenType::Identifier, &messages::PARS_EXPECTED_PARAM_NAME)?;
             params.push(IdentifierExpr::new(
                 param.text,
                 IdentifierType::ParamName,
+                false,
                 param.range,
             ));
             if self.tmatch(&TokenType::Comma).is_none() {
@@ -227,6 +378,10 @@ impl<R: Read> YKParser<'_, R> {
             }
         }
 
+        if params.len() > u8::MAX as usize {
+            self.report(DiagnosticKind::Error, &messages::PARS_TOO_MANY_PARAMS);
+        }
+
         self.accept(TokenType::RParen, &err_exp_sym(")"))?;
 
         return Some(params);
@@ -274,7 +429,9 @@ impl<R: Read> YKParser<'_, R> {
                 match token_type {
                     TokenType::Print => self.print_stmt().map(|stmt| Stmt::Print(stmt)),
                     TokenType::Return => self.return_stmt().map(|stmt| Stmt::Return(stmt)),
-                    TokenType::Var => self.var_stmt().map(|stmt| Stmt::Var(stmt)),
+                    TokenType::Var | TokenType::Const => {
+                        self.var_stmt().map(|stmt| Stmt::Var(stmt))
+                    }
                     TokenType::Break => self._break().map(|stmt| Stmt::Break(stmt)),
                     TokenType::Continue => self._continue().map(|stmt| Stmt::Continue(stmt)),
                     _ => self.expr().map(|expr| Stmt::Expr(ExprStmt::from(expr))),
@@ -346,7 +503,7 @@ impl<R: Read> YKParser<'_, R> {
             label = match self.primary().take() {
                 Some(Expr::Identifier(ident)) => Some(ident),
                 _ => {
-                    self.report(DiagnosticKind::Error, messages::PARS_EXPECTED_LABEL);
+                    self.report(DiagnosticKind::Error, &messages::PARS_EXPECTED_LABEL);
                     None
                 }
             }
@@ -364,7 +521,7 @@ impl<R: Read> YKParser<'_, R> {
         let token = self.accept(TokenType::Print, &err_exp_kywrd("print"))?;
         let expro = self.expr();
         if expro.is_none() {
-            self.report(DiagnosticKind::Error, messages::PARS_EXPECTED_EXPR);
+            self.report(DiagnosticKind::Error, &messages::PARS_EXPECTED_EXPR);
             return None;
         }
 
@@ -380,7 +537,7 @@ impl<R: Read> YKParser<'_, R> {
 
         let token = self.peek()?;
 
-        let init = if token.token_type == TokenType::Var {
+        let init = if matches!(token.token_type, TokenType::Var | TokenType::Const) {
             self.var_stmt().map(|var| Stmt::Var(var))
         } else {
             self.expr().map(|mut expr| {
@@ -399,7 +556,7 @@ impl<R: Read> YKParser<'_, R> {
 
         let body = self.block();
         if body.is_none() {
-            self.report(DiagnosticKind::Error, messages::PARS_EXPECTED_BODY);
+            self.report(DiagnosticKind::Error, &messages::PARS_EXPECTED_BODY);
             return None;
         }
 
@@ -412,12 +569,12 @@ impl<R: Read> YKParser<'_, R> {
     fn if_stmt(&mut self) -> Option<IfStmt> {
         let token = self.accept(TokenType::If, &err_exp_kywrd("if"))?;
         self.tmatch(&TokenType::LParen);
-        let condition = self.expr();
+        let condition = self.expr()?;
         self.tmatch(&TokenType::RParen);
 
         let body = self.block();
         if body.is_none() {
-            self.report(DiagnosticKind::Error, messages::PARS_EXPECTED_BODY);
+            self.report(DiagnosticKind::Error, &messages::PARS_EXPECTED_BODY);
             return None;
         }
 
@@ -425,7 +582,7 @@ impl<R: Read> YKParser<'_, R> {
         if self.tmatch(&TokenType::Else).is_some() {
             else_body = self.block();
             if else_body.is_none() {
-                self.report(DiagnosticKind::Error, messages::PARS_EXPECTED_BODY);
+                self.report(DiagnosticKind::Error, &messages::PARS_EXPECTED_BODY);
                 return None;
             }
         }
@@ -440,18 +597,18 @@ impl<R: Read> YKParser<'_, R> {
 
         range.set_start(&token.range);
 
-        return Some(IfStmt::new(condition.unwrap(), body, else_body, range));
+        return Some(IfStmt::new(condition, body, else_body, range));
     }
 
     fn while_stmt(&mut self) -> Option<WhileStmt> {
         let token = self.accept(TokenType::While, &err_exp_kywrd("while"))?;
         self.tmatch(&TokenType::LParen);
-        let condition = self.expr();
+        let condition = self.expr()?;
         self.tmatch(&TokenType::RParen);
 
         let body = self.block();
         if body.is_none() {
-            self.report(DiagnosticKind::Error, messages::PARS_EXPECTED_BODY);
+            self.report(DiagnosticKind::Error, &messages::PARS_EXPECTED_BODY);
             return None;
         }
 
@@ -459,14 +616,25 @@ impl<R: Read> YKParser<'_, R> {
         let mut range = Range::from(body.range());
         range.set_start(&token.range);
 
-        return Some(WhileStmt::new(None, condition.unwrap(), body, range));
+        return Some(WhileStmt::new(None, condition, body, range));
     }
 
     fn return_stmt(&mut self) -> Option<ReturnStmt> {
         let token = self.accept(TokenType::Return, &err_exp_kywrd("return"))?;
+
+        // 'return;' returns null
+        if self
+            .peek()
+            .map(|t| t.token_type == TokenType::Semicolon)
+            .unwrap_or(false)
+        {
+            let expr = Expr::Literal(LiteralExpr::Null(((), token.range.clone())));
+            return Some(ReturnStmt::new(expr, token.range));
+        }
+
         let expr = self.expr();
         if expr.is_none() {
-            self.report(DiagnosticKind::Error, messages::PARS_EXPECTED_EXPR);
+            self.report(DiagnosticKind::Error, &messages::PARS_EXPECTED_EXPR);
             return None;
         }
 
@@ -580,7 +748,7 @@ impl<R: Read> YKParser<'_, R> {
             return self.unary_op(&op);
         }
 
-        self.array_access()
+        self.call()
     }
 
     fn unary_op(&mut self, op: &Token) -> Option<Expr> {
@@ -594,37 +762,101 @@ impl<R: Read> YKParser<'_, R> {
             return Some(Expr::Unary(Box::from(unary)));
         }
 
-        self.report(DiagnosticKind::Error, messages::PARS_EXPECTED_EXPR);
+        self.report(DiagnosticKind::Error, &messages::PARS_EXPECTED_EXPR);
         return None;
     }
 
-    fn array_access(&mut self) -> Option<Expr> {
-        let expr = self.primary()?;
+    fn call(&mut self) -> Option<Expr> {
+        let mut expr = self.primary()?;
 
-        if let Some(token) = self.tmatch(&TokenType::LBrack) {
-            let mut range = token.range.clone();
-            let idx = self.expr();
-            if idx.is_none() {
-                self.report(DiagnosticKind::Error, messages::PARS_EXPECTED_EXPR);
-                return None;
+        loop {
+            if self.tmatch(&TokenType::LParen).is_some() {
+                expr = self.call_args(expr)?;
+            } else if let Some(token) = self.tmatch(&TokenType::LBrack) {
+                expr = self.array_access(expr, token)?;
+            } else if self.tmatch(&TokenType::Dot).is_some() {
+                expr = self.member_access(expr)?;
+            } else {
+                break;
+            }
+        }
+
+        return Some(expr);
+    }
+
+    fn call_args(&mut self, callee: Expr) -> Option<Expr> {
+        let mut args = Vec::new();
+        if self
+            .peek()
+            .map(|t| t.token_type != TokenType::RParen)
+            .unwrap_or(false)
+        {
+            loop {
+                args.push(self.expr()?);
+                if self.tmatch(&TokenType::Comma).is_none() {
+                    break;
+                }
             }
+        }
+
+        if args.len() > u8::MAX as usize {
+            self.report(DiagnosticKind::Error, &messages::PARS_TOO_MANY_ARGS);
+        }
 
-            self.consume(TokenType::RBrack, &err_exp_sym("]"));
+        let rparen = self.accept(TokenType::RParen, &err_exp_sym(")"))?;
+        let mut range = Range::from(callee.range());
+        range.set_end(&rparen.range);
 
-            let idx = idx.unwrap();
-            range.set_end(idx.range());
+        return Some(Expr::FuncCall(Box::from(FuncCallExpr::new(
+            callee, args, range,
+        ))));
+    }
 
-            return Some(Expr::ArrayAccess(ArrayAccessExpr::new(
-                Box::from(expr),
-                Box::from(idx),
-                range,
-            )));
+    fn array_access(&mut self, expr: Expr, token: Token) -> Option<Expr> {
+        let mut range = token.range.clone();
+        let idx = self.expr();
+        if idx.is_none() {
+            self.report(DiagnosticKind::Error, &messages::PARS_EXPECTED_EXPR);
+            return None;
         }
 
-        return Some(expr);
+        self.consume(TokenType::RBrack, &err_exp_sym("]"));
+
+        let idx = idx.unwrap();
+        range.set_end(idx.range());
+
+        return Some(Expr::ArrayAccess(ArrayAccessExpr::new(
+            Box::from(expr),
+            Box::from(idx),
+            range,
+        )));
+    }
+
+    fn member_access(&mut self, receiver: Expr) -> Option<Expr> {
+        let member = self.accept(TokenType::Identifier, &messages::PARS_EXPECTED_MEMBER_NAME)?;
+
+        let mut range = Range::from(receiver.range());
+        range.set_end(&member.range);
+
+        Some(Expr::MemberAccess(Box::from(MemberAccessExpr::new(
+            receiver,
+            IdentifierExpr::new(member.text, IdentifierType::Other, false, member.range),
+            range,
+        ))))
     }
 
     fn primary(&mut self) -> Option<Expr> {
+        // the delimiters are not consumed, so that the parser can synchronize at them
+        if self.peek().is_some_and(|t| {
+            matches!(
+                t.token_type,
+                TokenType::LBrace | TokenType::RBrace | TokenType::Semicolon | TokenType::Error
+            )
+        }) {
+            self.report(DiagnosticKind::Error, &messages::PARS_EXPECTED_EXPR);
+            return None;
+        }
+
         if let Some(token) = self.advance() {
             match token.token_type {
                 TokenType::True => Some(Expr::Literal(LiteralExpr::Bool((true, token.range)))),
@@ -641,26 +873,52 @@ impl<R: Read> YKParser<'_, R> {
                 TokenType::This => Some(Expr::Identifier(IdentifierExpr::new(
                     String::from("this"),
                     IdentifierType::Keyword,
+                    false,
                     token.range,
                 ))),
+                TokenType::Super => {
+                    // 'super' can only be used to access the members of the superclass
+                    let receiver = Expr::Identifier(IdentifierExpr::new(
+                        String::from("super"),
+                        IdentifierType::Keyword,
+                        false,
+                        token.range,
+                    ));
+                    self.accept(TokenType::Dot, &err_exp_sym("."))?;
+                    self.member_access(receiver)
+                }
                 TokenType::Identifier => Some(Expr::Identifier(IdentifierExpr::new(
                     token.text,
                     IdentifierType::Other,
+                    false,
                     token.range,
                 ))),
                 TokenType::LParen => self.grouping(),
                 TokenType::LBrack => self.array(token),
+                TokenType::Fun => self.lambda(token),
                 _ => {
-                    self.report(DiagnosticKind::Error, messages::PARS_EXPECTED_EXPR);
+                    self.report(DiagnosticKind::Error, &messages::PARS_EXPECTED_EXPR);
                     None
                 }
             }
         } else {
-            self.report(DiagnosticKind::Error, messages::PARS_UNEXPECTED_EOF);
+            self.report(DiagnosticKind::Error, &messages::PARS_UNEXPECTED_EOF);
             None
         }
     }
 
+    fn lambda(&mut self, mut fun: Token) -> Option<Expr> {
+        let params = self.fun_params()?;
+        let body = self.block()?;
+        let end = body.range().end;
+
+        Some(Expr::Lambda(Box::from(LambdaExpr::new(
+            params,
+            body,
+            fun.range.set_end_pos(&end),
+        ))))
+    }
+
     fn array(&mut self, token: Token) -> Option<Expr> {
         // empty array
         if self.tmatch(&TokenType::RBrack).is_some() {
@@ -669,7 +927,7 @@ impl<R: Read> YKParser<'_, R> {
 
         let expr = self.expr();
         if expr.is_none() {
-            self.report(DiagnosticKind::Error, messages::PARS_EXPECTED_EXPR);
+            self.report(DiagnosticKind::Error, &messages::PARS_EXPECTED_EXPR);
             return None;
         }
 
@@ -686,7 +944,7 @@ impl<R: Read> YKParser<'_, R> {
                 exprs.push(expr.unwrap());
                 continue;
             } else if comma_without_expr {
-                self.report(DiagnosticKind::Error, messages::PARS_EXPECTED_EXPR);
+                self.report(DiagnosticKind::Error, &messages::PARS_EXPECTED_EXPR);
             }
 
             comma_without_expr = true;
@@ -768,13 +1026,51 @@ impl<R: Read> YKParser<'_, R> {
             return None;
         }
 
-        self.report(DiagnosticKind::Error, messages::PARS_EXPECTED_EXPR);
+        self.report(DiagnosticKind::Error, &messages::PARS_EXPECTED_EXPR);
         return None;
     }
 
+    /// Skips the tokens until a statement or a declaration boundary is reached, i.e. until a `;` is
+    /// consumed, or a `}` or a keyword which starts a statement is found. Blocks nested in the
+    /// skipped source are skipped entirely.
+    fn synchronize(&mut self) {
+        self.panic_mode = false;
+
+        let mut depth = 0;
+        while let Some(token) = self.peek() {
+            match token.token_type {
+                TokenType::LBrace => depth += 1,
+                TokenType::RBrace if depth > 0 => depth -= 1,
+                TokenType::Semicolon if depth == 0 => {
+                    self.advance();
+                    return;
+                }
+                TokenType::RBrace
+                | TokenType::Class
+                | TokenType::Fun
+                | TokenType::Var
+                | TokenType::Const
+                | TokenType::For
+                | TokenType::If
+                | TokenType::While
+                | TokenType::Print
+                | TokenType::Return
+                | TokenType::Break
+                | TokenType::Continue
+                    if depth == 0 =>
+                {
+                    return
+                }
+                _ => {}
+            }
+
+            self.advance();
+        }
+    }
+
     /// Consumes and returns the next token in the input source if it matches the given token type.
     /// Reports an error with the given error message otherwise.
-    fn accept(&mut self, expected: TokenType, err_msg: &str) -> Option<Token> {
+    fn accept(&mut self, expected: TokenType, err_msg: &Message) -> Option<Token> {
         if let Some(token) = self.peek() {
             if token.token_type == expected {
                 return self.advance();
@@ -787,15 +1083,10 @@ impl<R: Read> YKParser<'_, R> {
 
     /// Similar to [YKParser::accept], but does not return the token.
     #[allow(unused)]
-    fn consume(&mut self, token: TokenType, err_msg: &str) {
+    fn consume(&mut self, token: TokenType, err_msg: &Message) {
         self.accept(token, err_msg);
     }
 
-    #[allow(unused)]
-    fn consume0(&mut self, token: TokenType, err_msg: &String) {
-        self.consume(token, err_msg.as_str());
-    }
-
     /// Similar to [YKParser::accept], but does not report any error if the token does not match.
     fn tmatch(&mut self, token: &TokenType) -> Option<Token> {
         if &self.peek()?.token_type == token {
@@ -13,6 +13,8 @@
  * program. If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::cell::RefCell;
+
 use crate::symtab::Symtab;
 use crate::symtab::VarSym;
 use crate::symtab::{Sym, Symbol};
@@ -22,6 +24,47 @@ pub struct Scope<'inst> {
     pub parent: Option<&'inst Scope<'inst>>,
     pub symbols: Symtab,
     pub var_count: u16,
+
+    /// Whether this scope is the outermost scope of a function body. Variables defined in a
+    /// function's scope live in the function's own call frame.
+    pub is_func: bool,
+
+    /// The variables of enclosing functions captured by the function of this scope. Only
+    /// function scopes have upvalues.
+    pub upvalues: RefCell<Vec<Upvalue>>,
+
+    /// The names of the variables whose declaration in this scope was rejected, as they were
+    /// already defined.
+    pub rejected_vars: Vec<String>,
+}
+
+/// A variable captured by a function from the function enclosing it.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Upvalue {
+    /// Whether the captured variable is a local variable of the enclosing function. If `false`,
+    /// the variable is one of the upvalues of the enclosing function.
+    pub is_local: bool,
+
+    /// The index of the local variable or the upvalue in the enclosing function.
+    pub index: u16,
+}
+
+/// The location of a variable, relative to the code in which the variable is accessed.
+#[derive(Debug, PartialEq, Clone)]
+pub enum VarLocation {
+    /// The variable is a local variable of the current function (or the top-level code).
+    Local(u16),
+
+    /// The variable is a local variable of the current function (or the top-level code) which is
+    /// captured by a closure. The variable is stored in an upvalue cell.
+    Captured(u16),
+
+    /// The variable is defined in the root scope and is accessed from within a function.
+    Global(u16),
+
+    /// The variable is a local variable of an enclosing function, accessed with the upvalue at the
+    /// given index.
+    Upvalue(u16),
 }
 
 impl Scope<'_> {
@@ -36,9 +79,20 @@ impl Scope<'_> {
             parent: None,
             symbols: Symtab::new(),
             var_count: count,
+            is_func: false,
+            upvalues: RefCell::new(Vec::with_capacity(0)),
+            rejected_vars: Vec::with_capacity(0),
         }
     }
 
+    /// Create a new scope for the body of a function. Variables in the function scope are indexed
+    /// from 0, as the function gets its own call frame.
+    pub fn new_func<'a>() -> Scope<'a> {
+        let mut scope = Self::new();
+        scope.is_func = true;
+        return scope;
+    }
+
     /// Returns whether this scope is the root scope.
     pub fn is_root(&self) -> bool {
         return self.parent.is_none();
@@ -81,9 +135,38 @@ impl Scope<'_> {
         }
     }
 
+    /// Find the symbol with the given name in this scope and its parents, without looking past the
+    /// scope of the function in which this scope is defined.
+    pub fn find_func_sym(&self, name: &String) -> Option<&Symbol> {
+        if let Some(sym) = self.symbols.get_sym(name) {
+            return Some(sym);
+        }
+
+        if self.is_func {
+            return None;
+        }
+
+        return self.parent.and_then(|p| p.find_func_sym(name));
+    }
+
+    /// Returns whether the variable with the given name, as seen from this scope, is a constant.
+    /// A name whose declaration was rejected in this scope is not a constant, as it was meant to
+    /// refer to the rejected variable.
+    pub fn is_const(&self, name: &String) -> bool {
+        if self.rejected_vars.contains(name) {
+            return false;
+        }
+
+        if self.symbols.has_sym(name) {
+            return self.symbols.is_const(name);
+        }
+
+        return self.parent.map(|p| p.is_const(name)).unwrap_or(false);
+    }
+
     /// Push a new symbol to this scope. See [Symtab::push_sym] for more details.
     pub fn push_sym(&mut self, sym: Symbol) -> Result<(), ()> {
-        match self.find_sym(&String::from(sym.name())) {
+        match self.find_func_sym(&String::from(sym.name())) {
             None => self.symbols.push_sym(sym),
             Some(_) => Err(()),
         }
@@ -91,7 +174,7 @@ impl Scope<'_> {
 
     /// Push a new variable symbol to this scope. See [Symtab::push_var] for more details.
     pub fn push_var(&mut self, sym: VarSym) -> Result<u16, ()> {
-        match self.find_sym(&sym.name) {
+        match self.find_func_sym(&sym.name) {
             None => {
                 let r = self.symbols.push_var(sym, self.var_count);
                 self.var_count += 1;
@@ -110,6 +193,67 @@ impl Scope<'_> {
             .or_else(|| self.parent.and_then(|p| p.get_var_idx(name)));
     }
 
+    /// Resolve the location of the variable with the given name, taking function boundaries
+    /// into account. If the variable belongs to an enclosing function, it is marked as captured
+    /// and the upvalues for the variable are added to the functions in between.
+    pub fn resolve_var(&self, name: &String) -> Option<VarLocation> {
+        let mut scope = self;
+        let mut funcs: Vec<&Scope> = Vec::with_capacity(0);
+        loop {
+            if let Some(idx) = scope.symbols.get_var_idx(name) {
+                if funcs.is_empty() {
+                    let captured = scope
+                        .symbols
+                        .get_var(name)
+                        .map(|var| var.captured.get())
+                        .unwrap_or(false);
+                    return Some(if captured {
+                        VarLocation::Captured(*idx)
+                    } else {
+                        VarLocation::Local(*idx)
+                    });
+                }
+
+                if scope.is_root() {
+                    return Some(VarLocation::Global(*idx));
+                }
+
+                if let Some(var) = scope.symbols.get_var(name) {
+                    var.captured.set(true);
+                }
+
+                // the outermost function captures the local variable, and each of the
+                // inner functions captures the upvalue of the function enclosing it
+                let mut index = *idx;
+                let mut is_local = true;
+                for func in funcs.iter().rev() {
+                    index = func.add_upvalue(Upvalue { is_local, index });
+                    is_local = false;
+                }
+
+                return Some(VarLocation::Upvalue(index));
+            }
+
+            if scope.is_func {
+                funcs.push(scope);
+            }
+
+            scope = scope.parent?;
+        }
+    }
+
+    /// Add the given upvalue to this (function) scope, if it does not already exist. Returns the
+    /// index of the upvalue.
+    pub fn add_upvalue(&self, upvalue: Upvalue) -> u16 {
+        let mut upvalues = self.upvalues.borrow_mut();
+        if let Some(index) = upvalues.iter().position(|u| u == &upvalue) {
+            return index as u16;
+        }
+
+        upvalues.push(upvalue);
+        return (upvalues.len() - 1) as u16;
+    }
+
     pub fn req_var_idx(&self, name: &String) -> &u16 {
         self.get_var_idx(name)
             .expect(&format!("Expected a variable with name: {}", name))
@@ -13,9 +13,16 @@
  * program. If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::cell::Cell;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 
+/// The name of the receiver variable in methods.
+pub const THIS_NAME: &str = "this";
+
+/// The name of the variable which holds the superclass of a class.
+pub const SUPER_NAME: &str = "super";
+
 pub trait Sym {
     fn name(&self) -> &str;
 }
@@ -38,12 +45,34 @@ impl Sym for Symbol {
 #[derive(Debug, PartialEq, Clone)]
 pub struct VarSym {
     pub name: String,
+
+    /// Whether the variable is captured by a closure.
+    pub captured: Cell<bool>,
+
+    /// Whether the variable is declared with `const`, and cannot be reassigned.
+    pub is_const: bool,
 }
 
 impl VarSym {
     /// Create a new [VarSym] with the given name.
     pub fn new(name: String) -> Self {
-        VarSym { name }
+        Self::with_captured(name, false)
+    }
+
+    /// Create a new [VarSym] with the given name, which may be captured by a closure.
+    pub fn with_captured(name: String, captured: bool) -> Self {
+        VarSym {
+            name,
+            captured: Cell::new(captured),
+            is_const: false,
+        }
+    }
+
+    /// Create a new [VarSym] for a constant with the given name.
+    pub fn new_const(name: String) -> Self {
+        let mut sym = Self::new(name);
+        sym.is_const = true;
+        return sym;
     }
 }
 
@@ -114,4 +143,17 @@ impl Symtab {
     pub fn get_var_idx(&self, name: &String) -> Option<&u16> {
         self.var_indices.get(name)
     }
+
+    /// Returns whether the symbol with the given name is a constant.
+    pub fn is_const(&self, name: &String) -> bool {
+        return self.get_var(name).map(|var| var.is_const).unwrap_or(false);
+    }
+
+    /// Get the variable symbol with the given name.
+    pub fn get_var(&self, name: &String) -> Option<&VarSym> {
+        match self.symbols.get(name) {
+            Some(Symbol::Variable(var)) => Some(var),
+            _ => None,
+        }
+    }
 }
@@ -13,9 +13,19 @@
  * program. If not, see <https://www.gnu.org/licenses/>.
  */
 
+use crate::ast::ASTVisitor;
+use crate::ast::AstNode;
+use crate::ast::BinaryOp;
 use crate::ast::NodeType;
+use crate::ast::Stmt;
+use crate::ast::UnaryOp;
 use crate::boxed_vec;
 use crate::features::CompilerFeatures;
+use crate::tests::matcher::Any;
+use crate::tests::matcher::Binary;
+use crate::tests::matcher::Identifier;
+use crate::tests::matcher::Null;
+use crate::tests::matcher::Unary;
 use crate::tests::matcher::{Bool, String};
 use crate::tests::matcher::{Empty, Program};
 use crate::tests::matcher::{Node, Number};
@@ -137,3 +147,322 @@ fn test_if_stmt_folding() {
         ),
     );
 }
+
+#[test]
+fn test_mixed_type_comparison_fold() {
+    let mut features = CompilerFeatures::default();
+    features.const_folding = true;
+
+    let cases = [
+        ("\"a\" == \"a\"", Bool(true)),
+        ("\"a\" != \"a\"", Bool(false)),
+        ("\"a\" < \"b\"", Bool(true)),
+        ("\"a!\" < \"a\"", Bool(false)),
+        ("null == null", Bool(true)),
+        ("null == false", Bool(false)),
+        ("1 == \"1\"", Bool(false)),
+        ("1 != true", Bool(true)),
+        ("true == false", Bool(false)),
+    ];
+
+    for (src, expected) in cases {
+        println!("[ConstantFolding] Check case: {}", src);
+        match_node(
+            &mut parse_attr(&format!("{};", src), true, &features),
+            &mut Program(vec![], boxed_vec![expected]),
+        );
+    }
+}
+
+#[test]
+fn test_incomparable_types_not_folded() {
+    let mut features = CompilerFeatures::default();
+    features.const_folding = true;
+
+    let cases = [
+        ("true < false", Binary(BinaryOp::Lt, boxed_vec![Bool(true), Bool(false)])),
+        ("1 <= \"1\"", Binary(BinaryOp::LtEq, boxed_vec![Number(1f64), String("\"1\"")])),
+        ("null > 0", Binary(BinaryOp::Gt, boxed_vec![Null(), Number(0f64)])),
+    ];
+
+    for (src, expected) in cases {
+        println!("[ConstantFolding] Check case: {}", src);
+        match_node(
+            &mut parse_attr(&format!("{};", src), true, &features),
+            &mut Program(vec![], boxed_vec![expected]),
+        );
+    }
+}
+
+#[test]
+fn test_string_concatenation_fold() {
+    let mut features = CompilerFeatures::default();
+    features.const_folding = true;
+
+    let cases = [
+        ("\"a\" + \"b\"", String("\"ab\"")),
+        ("\"a\" + \"b\" + \"c\"", String("\"abc\"")),
+        ("\"n=\" + 1", String("\"n=1\"")),
+        ("\"n=\" + 1.5", String("\"n=1.5\"")),
+        ("1 + 2 + \"a\"", String("\"3a\"")),
+    ];
+
+    for (src, expected) in cases {
+        println!("[ConstantFolding] Check case: {}", src);
+        match_node(
+            &mut parse_attr(&format!("{};", src), true, &features),
+            &mut Program(vec![], boxed_vec![expected]),
+        );
+    }
+}
+
+#[test]
+fn test_short_circuit_fold() {
+    verify_fold("true or a", NodeType::LiteralExpr, Bool(true));
+    verify_fold("1 or a", NodeType::LiteralExpr, Number(1f64));
+    verify_fold("\"s\" or a", NodeType::LiteralExpr, String("\"s\""));
+    verify_fold("null or a", NodeType::IdentifierExpr, Identifier("a"));
+    verify_fold("false and a", NodeType::LiteralExpr, Bool(false));
+    verify_fold("null and a", NodeType::LiteralExpr, Null());
+    verify_fold("0 and a", NodeType::IdentifierExpr, Identifier("a"));
+    verify_fold("1 and true", NodeType::LiteralExpr, Bool(true));
+    verify_fold(
+        "a or true",
+        NodeType::BinaryExpr,
+        Binary(BinaryOp::Or, boxed_vec![Identifier("a"), Bool(true)]),
+    );
+    verify_fold(
+        "a and false",
+        NodeType::BinaryExpr,
+        Binary(BinaryOp::And, boxed_vec![Identifier("a"), Bool(false)]),
+    );
+}
+
+#[test]
+fn test_identity_simplification() {
+    verify_fold(
+        "-a - 0",
+        NodeType::UnaryExpr,
+        Unary(UnaryOp::Negate, Box::from(Identifier("a"))),
+    );
+    verify_fold(
+        "-a * 1",
+        NodeType::UnaryExpr,
+        Unary(UnaryOp::Negate, Box::from(Identifier("a"))),
+    );
+    verify_fold(
+        "-a / 1",
+        NodeType::UnaryExpr,
+        Unary(UnaryOp::Negate, Box::from(Identifier("a"))),
+    );
+    verify_fold(
+        "(a - 1) * 1",
+        NodeType::BinaryExpr,
+        Binary(BinaryOp::Minus, boxed_vec![Identifier("a"), Number(1f64)]),
+    );
+}
+
+#[test]
+fn test_identity_not_simplified() {
+    // `a` may be a string, and `x * 0` and `x - x` are NaN for NaN and infinite values
+    verify_fold(
+        "a - 0",
+        NodeType::BinaryExpr,
+        Binary(BinaryOp::Minus, boxed_vec![Identifier("a"), Number(0f64)]),
+    );
+    verify_fold(
+        "a * 1",
+        NodeType::BinaryExpr,
+        Binary(BinaryOp::Mult, boxed_vec![Identifier("a"), Number(1f64)]),
+    );
+    verify_fold(
+        "-a * 0",
+        NodeType::BinaryExpr,
+        Binary(
+            BinaryOp::Mult,
+            boxed_vec![
+                Unary(UnaryOp::Negate, Box::from(Identifier("a"))),
+                Number(0f64)
+            ],
+        ),
+    );
+    verify_fold(
+        "a - a",
+        NodeType::BinaryExpr,
+        Binary(
+            BinaryOp::Minus,
+            boxed_vec![Identifier("a"), Identifier("a")],
+        ),
+    );
+
+    // `x + 0` and `x - -0` are `0` when `x` is `-0`
+    verify_fold(
+        "-a + 0",
+        NodeType::BinaryExpr,
+        Binary(
+            BinaryOp::Plus,
+            boxed_vec![
+                Unary(UnaryOp::Negate, Box::from(Identifier("a"))),
+                Number(0f64)
+            ],
+        ),
+    );
+    verify_fold(
+        "0 + -a",
+        NodeType::BinaryExpr,
+        Binary(
+            BinaryOp::Plus,
+            boxed_vec![
+                Number(0f64),
+                Unary(UnaryOp::Negate, Box::from(Identifier("a")))
+            ],
+        ),
+    );
+    verify_fold(
+        "-a - -0",
+        NodeType::BinaryExpr,
+        Binary(
+            BinaryOp::Minus,
+            boxed_vec![
+                Unary(UnaryOp::Negate, Box::from(Identifier("a"))),
+                Number(-0f64)
+            ],
+        ),
+    );
+}
+
+#[test]
+fn test_constant_chain_not_reassociated() {
+    // the intermediate results are rounded, and `a - 1` fails with an error which mentions the
+    // constant if `a` is not a number
+    verify_fold(
+        "-a + 1 + 2",
+        NodeType::BinaryExpr,
+        Binary(
+            BinaryOp::Plus,
+            boxed_vec![
+                Binary(
+                    BinaryOp::Plus,
+                    boxed_vec![
+                        Unary(UnaryOp::Negate, Box::from(Identifier("a"))),
+                        Number(1f64)
+                    ],
+                ),
+                Number(2f64)
+            ],
+        ),
+    );
+    verify_fold(
+        "a - 1 - 2",
+        NodeType::BinaryExpr,
+        Binary(
+            BinaryOp::Minus,
+            boxed_vec![
+                Binary(BinaryOp::Minus, boxed_vec![Identifier("a"), Number(1f64)]),
+                Number(2f64)
+            ],
+        ),
+    );
+    verify_fold(
+        "a / 2 * 3 * 4",
+        NodeType::BinaryExpr,
+        Binary(
+            BinaryOp::Mult,
+            boxed_vec![
+                Binary(
+                    BinaryOp::Mult,
+                    boxed_vec![
+                        Binary(BinaryOp::Div, boxed_vec![Identifier("a"), Number(2f64)]),
+                        Number(3f64)
+                    ],
+                ),
+                Number(4f64)
+            ],
+        ),
+    );
+}
+
+#[test]
+fn test_double_negation_in_conditions() {
+    let mut features = CompilerFeatures::default();
+    features.const_propagation = false;
+
+    let mut program = parse_attr(
+        "var a = 1; if !!a { print a; } while !!!a { print a; } for (a = 0; !!a and !(!!a); a = a + 1) { print a; }",
+        true,
+        &features,
+    );
+
+    if let Stmt::If(if_stmt) = &mut program.stmts[1] {
+        assert_eq!(NodeType::IdentifierExpr, if_stmt.condition.typ());
+        match_node(&mut if_stmt.condition, &mut Identifier("a"));
+    } else {
+        panic!("Expected an if statement");
+    }
+
+    if let Stmt::While(while_stmt) = &mut program.stmts[2] {
+        assert_eq!(NodeType::UnaryExpr, while_stmt.condition.typ());
+        match_node(
+            &mut while_stmt.condition,
+            &mut Unary(UnaryOp::Not, Box::from(Identifier("a"))),
+        );
+    } else {
+        panic!("Expected a while statement");
+    }
+
+    if let Stmt::For(for_stmt) = &mut program.stmts[3] {
+        match_node(
+            for_stmt.condition.as_mut().unwrap(),
+            &mut Binary(
+                BinaryOp::And,
+                boxed_vec![
+                    Identifier("a"),
+                    Unary(UnaryOp::Not, Box::from(Identifier("a")))
+                ],
+            ),
+        );
+    } else {
+        panic!("Expected a for statement");
+    }
+}
+
+#[test]
+fn test_double_negation_kept_outside_conditions() {
+    let mut features = CompilerFeatures::default();
+    features.const_propagation = false;
+
+    // `!!a` converts `a` to a boolean
+    match_node(
+        &mut parse_attr("var a = 1; !!a;", true, &features),
+        &mut Program(
+            vec![],
+            boxed_vec![
+                Any(),
+                Unary(
+                    UnaryOp::Not,
+                    Box::from(Unary(UnaryOp::Not, Box::from(Identifier("a"))))
+                )
+            ],
+        ),
+    );
+}
+
+/// Verify that the expression which follows the declaration of the variable `a` is folded to a
+/// node of the given type, which matches the expected node. The constant propagation is disabled, so `a` is not substituted.
+fn verify_fold(src: &str, typ: NodeType, expected: impl ASTVisitor<(), bool> + 'static) {
+    let mut features = CompilerFeatures::default();
+    features.const_propagation = false;
+
+    println!("[ConstantFolding] Check case: {}", src);
+    let mut program = parse_attr(&format!("var a = 1; {};", src), true, &features);
+    if let Stmt::Expr(expr_stmt) = &program.stmts[1] {
+        assert_eq!(typ, expr_stmt.expr.typ());
+    } else {
+        panic!("Expected an expression statement");
+    }
+
+    match_node(
+        &mut program,
+        &mut Program(vec![], boxed_vec![Any(), expected]),
+    );
+}
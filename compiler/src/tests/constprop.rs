@@ -0,0 +1,241 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::ast::BinaryOp;
+use crate::ast::Expr;
+use crate::ast::NodeType;
+use crate::ast::Program;
+use crate::boxed_vec;
+use crate::comp::ConstProp;
+use crate::features::CompilerFeatures;
+use crate::tests::matcher;
+use crate::tests::matcher::Any;
+use crate::tests::matcher::Binary;
+use crate::tests::matcher::Empty;
+use crate::tests::matcher::Identifier;
+use crate::tests::matcher::Node;
+use crate::tests::matcher::Number;
+use crate::tests::util::match_node;
+use crate::tests::util::parse_attr;
+
+/// Get the expression returned by the first statement of the first function in the program.
+fn returned_expr(program: &Program) -> &Expr {
+    let body = &program.decls[0].Func().unwrap().body;
+    return &body.decls[0].Stmt().unwrap().Return().unwrap().expr;
+}
+
+#[test]
+fn test_constant_substituted_and_folded() {
+    match_node(
+        &mut parse_attr(
+            "var n = 10; print n * 2;",
+            true,
+            &CompilerFeatures::default(),
+        ),
+        &mut matcher::Program(
+            vec![],
+            boxed_vec![
+                Node(
+                    NodeType::VarStmt,
+                    boxed_vec![Identifier("n"), Number(10f64)]
+                ),
+                Node(NodeType::PrintStmt, boxed_vec![Number(20f64)])
+            ],
+        ),
+    );
+}
+
+#[test]
+fn test_constants_propagated_through_initializers() {
+    match_node(
+        &mut parse_attr(
+            "var a = 2; var b = a * 3; print b + a;",
+            true,
+            &CompilerFeatures::default(),
+        ),
+        &mut matcher::Program(
+            vec![],
+            boxed_vec![
+                Node(NodeType::VarStmt, boxed_vec![Identifier("a"), Number(2f64)]),
+                Node(NodeType::VarStmt, boxed_vec![Identifier("b"), Number(6f64)]),
+                Node(NodeType::PrintStmt, boxed_vec![Number(8f64)])
+            ],
+        ),
+    );
+}
+
+#[test]
+fn test_reassigned_variable_not_substituted() {
+    for src in [
+        "var n = 10; n = 5; print n * 2;",
+        "var n = 10; n += 5; print n * 2;",
+        "var n = 10; fun f() { n = 5; } print n * 2;",
+    ] {
+        println!("[ConstProp] Check case: {}", src);
+        let mut program = parse_attr(src, true, &CompilerFeatures::default());
+        let print = program.stmts.pop().unwrap();
+        match_node(
+            &mut print.Print().unwrap().clone(),
+            &mut Node(
+                NodeType::PrintStmt,
+                boxed_vec![Binary(
+                    BinaryOp::Mult,
+                    boxed_vec![Identifier("n"), Number(2f64)]
+                )],
+            ),
+        );
+    }
+}
+
+#[test]
+fn test_shadowed_variable_not_substituted() {
+    let program = parse_attr(
+        "var n = 1; fun f() { return n; } print f(); var g = fun(n) { return n; }; print g(2);",
+        true,
+        &CompilerFeatures::default(),
+    );
+
+    // the function may be called before the global variable is initialized
+    assert!(returned_expr(&program).Identifier().is_some());
+
+    let g = program.stmts[2]
+        .Var()
+        .unwrap()
+        .initializer
+        .as_ref()
+        .unwrap();
+    let body = &g.Lambda().unwrap().body;
+    let returned = &body.decls[0].Stmt().unwrap().Return().unwrap().expr;
+    assert!(returned.Identifier().is_some());
+}
+
+#[test]
+fn test_local_variable_substituted() {
+    let program = parse_attr(
+        "fun f() { var n = 3; return n + 1; }",
+        true,
+        &CompilerFeatures::default(),
+    );
+    let body = &program.decls[0].Func().unwrap().body;
+    let returned = &body.decls[1].Stmt().unwrap().Return().unwrap().expr;
+    assert_eq!(
+        Some(4f64),
+        returned.Literal().and_then(|l| l.Number()).map(|n| n.0)
+    );
+}
+
+#[test]
+fn test_substitutions_counted() {
+    let mut features = CompilerFeatures::default();
+    features.const_propagation = false;
+
+    let mut program = parse_attr(
+        "var a = 1; var b = a; print a + b; fun f() { var a = 2; return a; }",
+        true,
+        &features,
+    );
+
+    let mut constprop = ConstProp::new(&features);
+    constprop.analyze(&mut program);
+    assert_eq!(4, constprop.substitutions());
+}
+
+#[test]
+fn test_const_propagation_disabled() {
+    let mut features = CompilerFeatures::default();
+    features.const_propagation = false;
+
+    match_node(
+        &mut parse_attr("var n = 10; print n * 2;", true, &features),
+        &mut matcher::Program(
+            vec![],
+            boxed_vec![
+                Node(
+                    NodeType::VarStmt,
+                    boxed_vec![Identifier("n"), Number(10f64)]
+                ),
+                Node(
+                    NodeType::PrintStmt,
+                    boxed_vec![Binary(
+                        BinaryOp::Mult,
+                        boxed_vec![Identifier("n"), Number(2f64)]
+                    )]
+                )
+            ],
+        ),
+    );
+}
+
+#[test]
+fn test_const_inlined() {
+    match_node(
+        &mut parse_attr(
+            "const n = 5 * 2; print n * 2;",
+            true,
+            &CompilerFeatures::default(),
+        ),
+        &mut matcher::Program(
+            vec![],
+            boxed_vec![
+                Empty(),
+                Node(NodeType::PrintStmt, boxed_vec![Number(20f64)])
+            ],
+        ),
+    );
+}
+
+#[test]
+fn test_const_not_inlined() {
+    let mut features = CompilerFeatures::default();
+
+    // the value of `n` is only known at runtime
+    match_node(
+        &mut parse_attr("var a = 1; a = 2; const n = a; print n;", true, &features),
+        &mut matcher::Program(
+            vec![],
+            boxed_vec![
+                Any(),
+                Any(),
+                Node(
+                    NodeType::VarStmt,
+                    boxed_vec![Identifier("n"), Identifier("a")]
+                ),
+                Node(NodeType::PrintStmt, boxed_vec![Identifier("n")])
+            ],
+        ),
+    );
+
+    // `f` may be called before `n` is initialized
+    let program = parse_attr(
+        "fun f() { return n; } const n = 1; print n;",
+        true,
+        &features,
+    );
+    assert!(returned_expr(&program).Identifier().is_some());
+    assert!(program.stmts[0].Var().is_some());
+
+    // constants are only inlined if the constant propagation is enabled
+    features.const_propagation = false;
+    match_node(
+        &mut parse_attr("const n = 1; print n;", true, &features),
+        &mut matcher::Program(
+            vec![],
+            boxed_vec![
+                Node(NodeType::VarStmt, boxed_vec![Identifier("n"), Number(1f64)]),
+                Node(NodeType::PrintStmt, boxed_vec![Identifier("n")])
+            ],
+        ),
+    );
+}
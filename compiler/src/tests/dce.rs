@@ -0,0 +1,123 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::ast::NodeType;
+use crate::boxed_vec;
+use crate::features::CompilerFeatures;
+use crate::tests::matcher::Empty;
+use crate::tests::matcher::Identifier;
+use crate::tests::matcher::Node;
+use crate::tests::matcher::Number;
+use crate::tests::matcher::Program;
+use crate::tests::util::match_node;
+use crate::tests::util::parse_attr;
+
+#[test]
+fn test_while_false_eliminated() {
+    match_node(
+        &mut parse_attr(
+            "while false { print 1; } print 2;",
+            true,
+            &CompilerFeatures::default(),
+        ),
+        &mut Program(
+            vec![],
+            boxed_vec![Empty(), Node(NodeType::PrintStmt, boxed_vec![Number(2f64)])],
+        ),
+    );
+}
+
+#[test]
+fn test_while_false_kept_when_disabled() {
+    let mut features = CompilerFeatures::default();
+    features.dead_code_elim = false;
+
+    let program = parse_attr("while false { print 1; }", true, &features);
+    assert_eq!(1, program.stmts.len());
+    assert_eq!(1, program.stmts[0].While().unwrap().body.decls.len());
+}
+
+#[test]
+fn test_for_false_keeps_initializer() {
+    let mut program = parse_attr(
+        "for (var i = 0; false; i = i + 1) { print i; }",
+        true,
+        &CompilerFeatures::default(),
+    );
+    assert_eq!(1, program.stmts.len());
+    match_node(
+        &mut program,
+        &mut Program(
+            vec![],
+            boxed_vec![Node(
+                NodeType::VarStmt,
+                boxed_vec![Identifier("i"), Number(0f64)]
+            )],
+        ),
+    );
+}
+
+#[test]
+fn test_stmts_after_break_eliminated() {
+    let program = parse_attr(
+        "var a = 1; while a { print a; break; print 2; a = 3; }",
+        true,
+        &CompilerFeatures::default(),
+    );
+    let body = &program.stmts[1].While().unwrap().body;
+    assert_eq!(2, body.decls.len());
+    assert!(body.decls[1].Stmt().unwrap().Break().is_some());
+}
+
+#[test]
+fn test_stmts_after_nested_exit_eliminated() {
+    let program = parse_attr(
+        "var a = 1; while a { { continue; } print a; }",
+        true,
+        &CompilerFeatures::default(),
+    );
+    assert_eq!(1, program.stmts[1].While().unwrap().body.decls.len());
+}
+
+#[test]
+fn test_stmts_after_exiting_if_eliminated() {
+    let program = parse_attr(
+        "fun f(a) { if a { return 1; } else { return 2; } print a; }",
+        true,
+        &CompilerFeatures::default(),
+    );
+    let body = &program.decls[0].Func().unwrap().body;
+    assert_eq!(1, body.decls.len());
+    assert!(body.decls[0].Stmt().unwrap().If().is_some());
+}
+
+#[test]
+fn test_stmts_after_partially_exiting_if_kept() {
+    let program = parse_attr(
+        "fun f(a) { if a { return 1; } print a; }",
+        true,
+        &CompilerFeatures::default(),
+    );
+    assert_eq!(2, program.decls[0].Func().unwrap().body.decls.len());
+}
+
+#[test]
+fn test_stmts_after_exit_kept_when_disabled() {
+    let mut features = CompilerFeatures::default();
+    features.dead_code_elim = false;
+
+    let program = parse_attr("fun f() { return 1; print 2; }", true, &features);
+    assert_eq!(2, program.decls[0].Func().unwrap().body.decls.len());
+}
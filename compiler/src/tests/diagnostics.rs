@@ -0,0 +1,203 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::io::Cursor;
+
+use crate::comp::Resolve;
+use crate::diagnostics::CollectingDiagnosticHandler;
+use crate::diagnostics::Diagnostic;
+use crate::diagnostics::DiagnosticKind;
+use crate::diagnostics::DiagnosticRenderer;
+use crate::lexer::YKLexer;
+use crate::location::Position;
+use crate::location::Range;
+use crate::messages;
+use crate::parser::YKParser;
+use crate::tests::util::parse_1;
+
+fn diagnostic(
+    kind: DiagnosticKind,
+    start: (i32, i32),
+    end: (i32, i32),
+    message: &str,
+) -> Diagnostic {
+    return Diagnostic {
+        range: Range {
+            start: Position::new(start.0, start.1, -1),
+            end: Position::new(end.0, end.1, -1),
+        },
+        message: message.to_string(),
+        kind,
+        code: "E9999",
+        notes: Vec::new(),
+    };
+}
+
+fn render(source: &str, diagnostic: &Diagnostic) -> String {
+    colored::control::set_override(false);
+    let renderer = DiagnosticRenderer::new("test.yk", source);
+    return renderer.render(diagnostic);
+}
+
+#[test]
+fn test_render_syntax_error() {
+    let source = "print 1;\nvar a = ;\n";
+    let mut diags = CollectingDiagnosticHandler::new();
+    let lexer = YKLexer::new(Cursor::new(source), &mut diags);
+    let mut parser = YKParser::new(lexer);
+    parser.parse();
+    assert!(parser.has_errors());
+
+    assert_eq!(1, diags.diagnostics.len());
+    assert_eq!(
+        "test.yk:2:9: error: expected an expression
+  |
+2 | var a = ;
+  |         ^
+",
+        render(source, &diags.diagnostics[0])
+    );
+}
+
+#[test]
+fn test_render_underlines_range() {
+    let source = "var a = 1;\nprint abc + a;";
+    let mut diags = CollectingDiagnosticHandler::new();
+    let mut program = parse_1(source, &mut diags);
+    let mut resolve = Resolve::new(&mut diags);
+    resolve.analyze(&mut program);
+
+    assert_eq!(1, diags.diagnostics.len());
+    assert_eq!(
+        "test.yk:2:7: error: Variable 'abc' is not defined
+  |
+2 | print abc + a;
+  |       ^^^
+",
+        render(source, &diags.diagnostics[0])
+    );
+}
+
+#[test]
+fn test_render_kinds() {
+    let source = "var a = 1;";
+    let warning = diagnostic(DiagnosticKind::Warning, (0, 4), (0, 5), "unused variable");
+    let note = diagnostic(DiagnosticKind::Note, (0, 0), (0, 3), "declared here");
+
+    assert!(render(source, &warning).starts_with("test.yk:1:5: warning: unused variable\n"));
+    assert!(render(source, &note).starts_with("test.yk:1:1: note: declared here\n"));
+}
+
+#[test]
+fn test_render_notes() {
+    let source = "var a = 1;\nvar a = 2;";
+    let diagnostic = diagnostic(DiagnosticKind::Error, (1, 4), (1, 5), "duplicate variable")
+        .with_note("the variable was first declared on line 1");
+
+    assert_eq!(
+        "test.yk:2:5: error: duplicate variable
+  |
+2 | var a = 2;
+  |     ^
+  = note: the variable was first declared on line 1
+",
+        render(source, &diagnostic)
+    );
+}
+
+#[test]
+fn test_render_multiline_range() {
+    let source = "print 1 +\n  2;";
+    let diagnostic = diagnostic(DiagnosticKind::Error, (0, 6), (1, 3), "bad expression");
+
+    assert_eq!(
+        "test.yk:1:7: error: bad expression
+  |
+1 | print 1 +
+  |       ^^^
+",
+        render(source, &diagnostic)
+    );
+}
+
+#[test]
+fn test_render_preserves_tabs() {
+    let source = "\tprint x;";
+    let diagnostic = diagnostic(DiagnosticKind::Error, (0, 7), (0, 8), "undefined");
+
+    assert!(render(source, &diagnostic).ends_with("  | \t      ^\n"));
+}
+
+#[test]
+fn test_render_without_position() {
+    let source = "print 1;";
+    let diagnostic = diagnostic(
+        DiagnosticKind::Error,
+        (-1, -1),
+        (-1, -1),
+        "something failed",
+    )
+    .with_note("a note");
+
+    assert_eq!(
+        "test.yk: error: something failed\n = note: a note\n",
+        render(source, &diagnostic)
+    );
+}
+
+#[test]
+fn test_diagnostic_codes() {
+    let mut diags = CollectingDiagnosticHandler::new();
+    let mut program = parse_1("print a;", &mut diags);
+    let mut resolve = Resolve::new(&mut diags);
+    resolve.analyze(&mut program);
+
+    assert_eq!(1, diags.diagnostics.len());
+    assert_eq!("E0001", diags.diagnostics[0].code);
+    assert_eq!(messages::err_undef_var("a").code, diags.diagnostics[0].code);
+
+    let mut diags = CollectingDiagnosticHandler::new();
+    let lexer = YKLexer::new(Cursor::new("var a = ;"), &mut diags);
+    YKParser::new(lexer).parse();
+
+    assert_eq!(1, diags.diagnostics.len());
+    assert_eq!(messages::PARS_EXPECTED_EXPR.code, diags.diagnostics[0].code);
+}
+
+#[test]
+fn test_diagnostic_to_json() {
+    let diagnostic = diagnostic(
+        DiagnosticKind::Warning,
+        (1, 4),
+        (1, 7),
+        "variable \"abc\" is unused",
+    )
+    .with_note("prefix it with '_'");
+
+    assert_eq!(
+        "{\"file\":\"dir\\\\test.yk\",\"range\":{\"start\":{\"line\":2,\"column\":5},\"end\":{\"line\":2,\"column\":8}},\"severity\":\"warning\",\"code\":\"E9999\",\"message\":\"variable \\\"abc\\\" is unused\",\"notes\":[\"prefix it with '_'\"]}",
+        diagnostic.to_json("dir\\test.yk")
+    );
+}
+
+#[test]
+fn test_diagnostic_to_json_without_position() {
+    let diagnostic = diagnostic(DiagnosticKind::Error, (-1, -1), (-1, -1), "failed");
+
+    assert_eq!(
+        "{\"file\":\"test.yk\",\"range\":null,\"severity\":\"error\",\"code\":\"E9999\",\"message\":\"failed\",\"notes\":[]}",
+        diagnostic.to_json("test.yk")
+    );
+}
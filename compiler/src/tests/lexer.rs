@@ -19,6 +19,7 @@ use std::io::Read;
 use crate::diagnostics::CollectingDiagnosticHandler;
 use crate::lexer::YKLexer;
 use crate::messages;
+use crate::messages::Message;
 use crate::tokens::TokenType;
 
 fn check_token_types<R: Read>(lexer: &mut YKLexer<R>, expected_tokens: &Vec<TokenType>) {
@@ -40,7 +41,7 @@ fn match_token_types(src: &str, expected_tokens: &Vec<TokenType>) {
 
 fn check_diagnostic_messages(
     diag_handler: &CollectingDiagnosticHandler,
-    expected_messages: &Vec<&str>,
+    expected_messages: &Vec<Message>,
 ) {
     let messages: Vec<String> = diag_handler
         .diagnostics
@@ -226,6 +227,32 @@ fn test_simple_keyword_lexing() {
     );
 }
 
+#[test]
+fn test_class_keyword_lexing() {
+    match_token_types(
+        "class classes continue cl",
+        &vec![
+            TokenType::Class,
+            TokenType::Identifier,
+            TokenType::Continue,
+            TokenType::Identifier,
+        ],
+    );
+}
+
+#[test]
+fn test_const_keyword_lexing() {
+    match_token_types(
+        "const constant continue con",
+        &vec![
+            TokenType::Const,
+            TokenType::Identifier,
+            TokenType::Continue,
+            TokenType::Identifier,
+        ],
+    );
+}
+
 #[test]
 fn test_mixed_identifier_and_keyword_lexing() {
     match_token_types(
@@ -0,0 +1,229 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::comp::Attr;
+use crate::diagnostics::CollectingDiagnosticHandler;
+use crate::diagnostics::Diagnostic;
+use crate::diagnostics::DiagnosticKind;
+use crate::features::CompilerFeatures;
+use crate::messages::warn_const_condition;
+use crate::messages::warn_shadowed_var;
+use crate::messages::warn_unreachable_code;
+use crate::messages::warn_unused_var;
+use crate::messages::Message;
+use crate::tests::util::parse_1;
+
+fn lint_with_features(src: &str, features: &CompilerFeatures) -> Vec<Diagnostic> {
+    let mut diags = CollectingDiagnosticHandler::new();
+    let mut program = parse_1(src, &mut diags);
+    let mut attr = Attr::new(features, &mut diags);
+    attr.analyze(&mut program);
+    assert!(!attr.has_errors());
+
+    for diagnostic in &diags.diagnostics {
+        assert_eq!(DiagnosticKind::Warning, diagnostic.kind);
+    }
+
+    let mut diagnostics = diags.diagnostics;
+    diagnostics.sort_by_key(|diagnostic| diagnostic.range.start.index);
+    return diagnostics;
+}
+
+fn lint(src: &str) -> Vec<Diagnostic> {
+    return lint_with_features(src, &CompilerFeatures::default());
+}
+
+fn assert_warnings(src: &str, expected: Vec<(Message, i32)>) {
+    let diagnostics = lint(src);
+    let actual: Vec<(String, &str, i32)> = diagnostics
+        .iter()
+        .map(|diag| (diag.message.clone(), diag.code, diag.range.start.line + 1))
+        .collect();
+    let expected: Vec<(String, &str, i32)> = expected
+        .iter()
+        .map(|(msg, line)| (msg.to_string(), msg.code, *line))
+        .collect();
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn test_no_warnings() {
+    assert_warnings(
+        "var a = 1;
+        fun f(x) { return x + a; }
+        print f(2);",
+        vec![],
+    );
+}
+
+#[test]
+fn test_unused_variable() {
+    assert_warnings(
+        "var a = 1;
+        var b = 2;
+        print b;
+        { var c = 3; c = 4; }",
+        vec![(warn_unused_var("a"), 1), (warn_unused_var("c"), 4)],
+    );
+}
+
+#[test]
+fn test_unused_variable_ignores_underscore() {
+    assert_warnings("var _a = 1;", vec![]);
+}
+
+#[test]
+fn test_variable_read_by_closure_or_function() {
+    assert_warnings(
+        "fun f() { return a; }
+        var a = 1;
+        fun g() {
+            var b = 2;
+            return fun() { return b; };
+        }
+        print f();
+        print g();",
+        vec![],
+    );
+}
+
+#[test]
+fn test_compound_assignment_reads_variable() {
+    assert_warnings("var a = 1; a += 1;", vec![]);
+}
+
+#[test]
+fn test_indexed_read_reads_variable() {
+    assert_warnings(
+        "var arr = [1, 2, 3];
+        var i = 0;
+        print arr[i];",
+        vec![],
+    );
+}
+
+#[test]
+fn test_unused_params_are_not_reported() {
+    assert_warnings("fun f(a, b) { return a; } print f(1, 2);", vec![]);
+}
+
+#[test]
+fn test_unreachable_code() {
+    assert_warnings(
+        "fun f() {
+            return 1;
+            print 2;
+            print 3;
+        }
+        while true {
+            break;
+            print 4;
+        }
+        for (var i = 0; i < 2; i = i + 1) {
+            continue;
+            print i;
+        }
+        print f();",
+        vec![
+            (warn_unreachable_code("return"), 3),
+            (warn_unreachable_code("break"), 8),
+            (warn_unreachable_code("continue"), 12),
+        ],
+    );
+}
+
+#[test]
+fn test_shadowing() {
+    let diagnostics = lint(
+        "var a = 1;
+        fun f(a) { return a; }
+        fun g() {
+            var b = 2;
+            return fun() { var b = 3; return b; }();
+        }
+        print a;
+        print f(1) + g();",
+    );
+
+    let messages: Vec<String> = diagnostics
+        .iter()
+        .map(|diag| diag.message.clone())
+        .collect();
+    assert_eq!(
+        vec![
+            warn_shadowed_var("a").to_string(),
+            warn_unused_var("b").to_string(),
+            warn_shadowed_var("b").to_string(),
+        ],
+        messages
+    );
+    assert_eq!(
+        vec!["the shadowed variable is declared on line 1".to_string()],
+        diagnostics[0].notes
+    );
+    assert_eq!(
+        vec!["the shadowed variable is declared on line 4".to_string()],
+        diagnostics[2].notes
+    );
+}
+
+#[test]
+fn test_shadowing_in_method() {
+    assert_warnings(
+        "var x = 1;
+        class A {
+            f(x) { return x; }
+        }
+        print x;
+        print A().f(2);",
+        vec![(warn_shadowed_var("x"), 3)],
+    );
+}
+
+#[test]
+fn test_constant_conditions() {
+    assert_warnings(
+        "var a = 1;
+        if (1 < 2) { print a; }
+        if (false) { print a; }
+        while (!true) { print a; }
+        if (a < 2) { print a; }
+        while true { break; }",
+        vec![
+            (warn_const_condition("true"), 2),
+            (warn_const_condition("false"), 3),
+            (warn_const_condition("false"), 4),
+        ],
+    );
+}
+
+#[test]
+fn test_lints_can_be_disabled() {
+    let src = "var a = 1;
+        fun f(a) { return 1; print 2; }
+        if (true) { print f(1); }";
+    assert_eq!(4, lint(src).len());
+
+    for feature in [
+        CompilerFeatures::UNUSED_VARIABLES,
+        CompilerFeatures::UNREACHABLE_CODE,
+        CompilerFeatures::SHADOWING,
+        CompilerFeatures::CONSTANT_CONDITIONS,
+    ] {
+        let mut features = CompilerFeatures::default();
+        features.set(feature, false);
+        assert_eq!(3, lint_with_features(src, &features).len(), "{}", feature);
+    }
+}
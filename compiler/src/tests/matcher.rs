@@ -24,6 +24,7 @@ use crate::ast::FuncDecl;
 use crate::ast::GroupingExpr;
 use crate::ast::IdentifierExpr;
 use crate::ast::IfStmt;
+use crate::ast::LambdaExpr;
 use crate::ast::LiteralExpr;
 use crate::ast::MemberAccessExpr;
 use crate::ast::NodeType;
@@ -225,6 +226,10 @@ impl ASTVisitor<(), bool> for NoOpMatcher {
         Some(true)
     }
     #[allow(unused_variables)]
+    fn visit_lambda_expr(&mut self, lambda_expr: &mut LambdaExpr, p: &mut ()) -> Option<bool> {
+        Some(true)
+    }
+    #[allow(unused_variables)]
     fn visit_member_access_expr(
         &mut self,
         member_access_expr: &mut MemberAccessExpr,
@@ -457,6 +462,35 @@ impl ASTVisitor<(), bool> for AssertingAstMatcher {
         Some(true)
     }
 
+    fn visit_class_decl(&mut self, class_decl: &mut ClassDecl, _p: &mut ()) -> Option<bool> {
+        assert_eq!(&self.typ, &class_decl.typ());
+
+        let mut idx = 0;
+        if let Some(matcher) = self.nested.get_mut(idx) {
+            mtch!(class_decl.name, matcher.as_mut(), "Failed to match name");
+        }
+
+        if let Some(supercls) = class_decl.supercls.as_mut() {
+            idx += 1;
+            if let Some(matcher) = self.nested.get_mut(idx) {
+                mtch!(supercls, matcher.as_mut(), "Failed to match superclass");
+            }
+        }
+
+        for i in 0..class_decl.methods.len() {
+            idx += 1;
+            if let Some(matcher) = self.nested.get_mut(idx) {
+                mtch!(
+                    class_decl.methods[i],
+                    matcher.as_mut(),
+                    "Failed to match method"
+                );
+            }
+        }
+
+        Some(true)
+    }
+
     fn visit_func_decl(&mut self, func_decl: &mut FuncDecl, _p: &mut ()) -> Option<bool> {
         assert_eq!(&self.typ, &func_decl.typ());
 
@@ -703,4 +737,77 @@ impl ASTVisitor<(), bool> for AssertingAstMatcher {
         }
         Some(true)
     }
+
+    fn visit_func_call_expr(
+        &mut self,
+        func_call_expr: &mut FuncCallExpr,
+        _p: &mut (),
+    ) -> Option<bool> {
+        assert_eq!(&self.typ, &func_call_expr.typ());
+        if let Some(matcher) = self.nested.get_mut(0) {
+            mtch!(
+                &mut func_call_expr.callee,
+                matcher.as_mut(),
+                "Failed to match callee"
+            );
+        }
+
+        assert_eq!(self.nested.len() - 1, func_call_expr.args.len());
+        for i in 0..func_call_expr.args.len() {
+            let matcher = &mut self.nested[i + 1];
+            mtch!(
+                &mut func_call_expr.args[i],
+                matcher.as_mut(),
+                "Failed to match argument"
+            );
+        }
+        Some(true)
+    }
+
+    fn visit_member_access_expr(
+        &mut self,
+        member_access_expr: &mut MemberAccessExpr,
+        _p: &mut (),
+    ) -> Option<bool> {
+        assert_eq!(&self.typ, &member_access_expr.typ());
+        if let Some(matcher) = self.nested.get_mut(0) {
+            mtch!(
+                &mut member_access_expr.receiver,
+                matcher.as_mut(),
+                "Failed to match receiver"
+            );
+        }
+
+        if let Some(matcher) = self.nested.get_mut(1) {
+            mtch!(
+                member_access_expr.member,
+                matcher.as_mut(),
+                "Failed to match member"
+            );
+        }
+
+        Some(true)
+    }
+
+    fn visit_lambda_expr(&mut self, lambda_expr: &mut LambdaExpr, _p: &mut ()) -> Option<bool> {
+        assert_eq!(&self.typ, &lambda_expr.typ());
+
+        let mut idx = 0;
+        for i in 0..lambda_expr.params.len() {
+            if let Some(matcher) = self.nested.get_mut(idx) {
+                mtch!(
+                    lambda_expr.params[i],
+                    matcher.as_mut(),
+                    "Failed to match param"
+                );
+            }
+            idx += 1;
+        }
+
+        if let Some(matcher) = self.nested.get_mut(idx) {
+            mtch!(lambda_expr.body, matcher.as_mut(), "Failed to match body");
+        }
+
+        Some(true)
+    }
 }
@@ -14,8 +14,13 @@
  */
 
 mod constfold;
+mod constprop;
+mod dce;
+mod diagnostics;
 mod lexer;
+mod lint;
 mod matcher;
 mod parser;
 mod resolve;
+mod ykb;
 pub(crate) mod util;
@@ -27,7 +27,8 @@ use crate::ast::UnaryOp;
 use crate::ast::Visitable;
 use crate::boxed_vec;
 use crate::comp::YKCompiler;
-use crate::diagnostics::{CollectingDiagnosticHandler, DiagnosticKind};
+use crate::ast::AstNode;
+use crate::diagnostics::{CollectingDiagnosticHandler, Diagnostic, DiagnosticKind};
 use crate::features::CompilerFeatures;
 use crate::lexer::YKLexer;
 use crate::messages;
@@ -354,16 +355,14 @@ fn test_parser_diagnostic_at_end() {
     program.accept(&mut printer, &mut ());
 
     assert!(program.decls.is_empty());
+    assert_eq!(1, program.stmts.len());
+    assert!(program.stmts[0].Error().is_some());
 
     let diags = &diag_handler.diagnostics;
-    assert!(!diags.is_empty());
-    assert_eq!(2, diags.len());
+    assert_eq!(1, diags.len());
 
     let semi_exp = diags.get(0).expect("Diagnostic expected");
-    let stmt_exp = diags.get(1).expect("Diagnostic expected");
-
     assert_eq!(messages::err_exp_sym(";"), semi_exp.message);
-    assert_eq!(messages::PARS_DECL_OR_STMT_EXPECTED, stmt_exp.message);
 }
 
 #[test]
@@ -922,3 +921,304 @@ fn test_array_access_expr() {
         ),
     );
 }
+
+#[test]
+fn test_func_call_expr() {
+    match_ast(
+        "add(1, 2); noop();",
+        &mut Program(
+            vec![],
+            boxed_vec![
+                Node(
+                    NodeType::FuncCallExpr,
+                    boxed_vec![Identifier("add"), Number(1f64), Number(2f64)]
+                ),
+                Node(NodeType::FuncCallExpr, boxed_vec![Identifier("noop")]),
+            ],
+        ),
+    );
+}
+
+#[test]
+fn test_chained_func_call_expr() {
+    match_ast(
+        "f(1)(2);",
+        &mut Program(
+            vec![],
+            boxed_vec![Node(
+                NodeType::FuncCallExpr,
+                boxed_vec![
+                    Node(
+                        NodeType::FuncCallExpr,
+                        boxed_vec![Identifier("f"), Number(1f64)]
+                    ),
+                    Number(2f64)
+                ]
+            )],
+        ),
+    );
+}
+
+#[test]
+fn test_lambda_expr() {
+    match_ast(
+        "var add = fun(a, b) { print a + b; };",
+        &mut Program(
+            vec![],
+            boxed_vec![Node(
+                NodeType::VarStmt,
+                boxed_vec![
+                    Identifier("add"),
+                    Node(
+                        NodeType::LambdaExpr,
+                        boxed_vec![
+                            Identifier("a"),
+                            Identifier("b"),
+                            Node(
+                                NodeType::BlockStmt,
+                                boxed_vec![Node(
+                                    NodeType::PrintStmt,
+                                    boxed_vec![Binary(
+                                        BinaryOp::Plus,
+                                        boxed_vec![Identifier("a"), Identifier("b")]
+                                    )]
+                                )]
+                            )
+                        ]
+                    )
+                ]
+            )],
+        ),
+    );
+}
+
+#[test]
+fn test_lambda_as_call_arg() {
+    match_ast(
+        "apply(fun(x) { return x; }, 1);",
+        &mut Program(
+            vec![],
+            boxed_vec![Node(
+                NodeType::FuncCallExpr,
+                boxed_vec![
+                    Identifier("apply"),
+                    Node(NodeType::LambdaExpr, boxed_vec![Identifier("x"), Any()]),
+                    Number(1f64)
+                ]
+            )],
+        ),
+    );
+}
+
+#[test]
+fn test_class_decl() {
+    match_ast(
+        "class B : A { init(x) { print x; } get() { print 1; } }",
+        &mut Program(
+            boxed_vec![Node(
+                NodeType::ClassDecl,
+                boxed_vec![
+                    Identifier("B"),
+                    Identifier("A"),
+                    Node(
+                        NodeType::FuncDecl,
+                        boxed_vec![Identifier("init"), Identifier("x"), Any()]
+                    ),
+                    Node(NodeType::FuncDecl, boxed_vec![Identifier("get"), Any()])
+                ]
+            )],
+            vec![],
+        ),
+    );
+}
+
+#[test]
+fn test_member_access_expr() {
+    match_ast(
+        "a.b.c(1); this.x; super.get();",
+        &mut Program(
+            vec![],
+            boxed_vec![
+                Node(
+                    NodeType::FuncCallExpr,
+                    boxed_vec![
+                        Node(
+                            NodeType::MemberAccessExpr,
+                            boxed_vec![
+                                Node(
+                                    NodeType::MemberAccessExpr,
+                                    boxed_vec![Identifier("a"), Identifier("b")]
+                                ),
+                                Identifier("c")
+                            ]
+                        ),
+                        Number(1f64)
+                    ]
+                ),
+                Node(
+                    NodeType::MemberAccessExpr,
+                    boxed_vec![Identifier("this"), Identifier("x")]
+                ),
+                Node(
+                    NodeType::FuncCallExpr,
+                    boxed_vec![Node(
+                        NodeType::MemberAccessExpr,
+                        boxed_vec![Identifier("super"), Identifier("get")]
+                    )]
+                ),
+            ],
+        ),
+    );
+}
+
+fn parse_with_errors(source: &str) -> (crate::ast::Program, Vec<Diagnostic>) {
+    let mut diag_handler = CollectingDiagnosticHandler::new();
+    let lexer = YKLexer::new(Cursor::new(source), &mut diag_handler);
+    let mut parser = YKParser::new(lexer);
+    let program = parser.parse();
+    assert!(parser.has_errors());
+    return (program, diag_handler.diagnostics);
+}
+
+#[test]
+fn test_error_recovery_reports_all_errors() {
+    let (program, diags) = parse_with_errors(
+        "var a = ;
+        print 1;
+        var b = 2 +;
+        print b
+        print 3;",
+    );
+
+    let lines: Vec<i32> = diags.iter().map(|diag| diag.range.start.line).collect();
+    assert_eq!(vec![0, 2, 4], lines);
+    assert_eq!(messages::PARS_EXPECTED_EXPR, diags[0].message);
+    assert_eq!(messages::PARS_EXPECTED_EXPR, diags[1].message);
+    assert_eq!(messages::err_exp_sym(";"), diags[2].message);
+
+    let types: Vec<NodeType> = program.stmts.iter().map(|stmt| stmt.typ()).collect();
+    assert_eq!(
+        vec![
+            NodeType::ErrorStmt,
+            NodeType::PrintStmt,
+            NodeType::ErrorStmt,
+            NodeType::ErrorStmt,
+            NodeType::PrintStmt,
+        ],
+        types
+    );
+
+    // the error node spans the skipped source
+    let error = program.stmts[0].range();
+    assert_eq!(0, error.start.column);
+    assert_eq!(0, error.end.line);
+    assert_eq!(9, error.end.column);
+}
+
+#[test]
+fn test_error_recovery_in_blocks() {
+    let (program, diags) = parse_with_errors(
+        "fun f() {
+            var a = 1 +
+        }
+        while (true) {
+            print ;
+            print 2;
+        }
+        print 3;",
+    );
+
+    // the missing expression is reported at the '}' which closes the body
+    assert_eq!(2, diags.len());
+    assert_eq!(2, diags[0].range.start.line);
+    assert_eq!(4, diags[1].range.start.line);
+
+    let func = program.decls[0].Func().expect("Expected a function");
+    assert_eq!(NodeType::ErrorStmt, func.body.decls[0].typ());
+
+    assert_eq!(2, program.stmts.len());
+    let body = &program.stmts[0].While().expect("Expected a while loop").body;
+    assert_eq!(2, body.decls.len());
+    assert_eq!(NodeType::ErrorStmt, body.decls[0].typ());
+    assert_eq!(NodeType::PrintStmt, program.stmts[1].typ());
+}
+
+#[test]
+fn test_error_recovery_missing_parts() {
+    // missing condition
+    let (program, diags) = parse_with_errors("if { print 1; } print 2;");
+    assert_eq!(1, diags.len());
+    assert_eq!(NodeType::PrintStmt, program.stmts[1].typ());
+
+    // missing parameter name
+    let (program, diags) = parse_with_errors("fun f(a, ) { return a; } print 2;");
+    assert_eq!(1, diags.len());
+    assert_eq!(messages::PARS_EXPECTED_PARAM_NAME, diags[0].message);
+    assert_eq!(NodeType::PrintStmt, program.stmts[1].typ());
+
+    // missing ';' in a for loop
+    let (program, diags) =
+        parse_with_errors("for (var i = 0; i < 2 i = i + 1) { print i; } print 2;");
+    assert_eq!(1, diags.len());
+    assert_eq!(2, program.stmts.len());
+}
+
+#[test]
+fn test_error_recovery_in_class_body() {
+    let (program, diags) = parse_with_errors(
+        "class A {
+            f( {}
+            g() { return 1; }
+        }
+        print 1;",
+    );
+
+    assert_eq!(1, diags.len());
+    assert!(program.decls[0].Class().is_some());
+    assert_eq!(NodeType::PrintStmt, program.stmts[0].typ());
+}
+
+#[test]
+fn test_error_recovery_unknown_token() {
+    // the unknown token is only reported by the lexer
+    for src in [
+        "print 1 % 2; print 3;",
+        "print %; print 3;",
+        "var a = [%]; print 3;",
+        "f(%); print 3;",
+        "a.%; print 3;",
+        "if % { print 1; } print 3;",
+        "%\nprint 3;",
+    ] {
+        let (program, diags) = parse_with_errors(src);
+        assert_eq!(1, diags.len(), "{}", src);
+        assert_eq!(messages::LEX_UNKNOWN_TOKEN, diags[0].message, "{}", src);
+        assert_eq!(
+            NodeType::PrintStmt,
+            program.stmts.last().unwrap().typ(),
+            "{}",
+            src
+        );
+    }
+
+    let (_, diags) = parse_with_errors("print 1 % 2;");
+    assert_eq!(8, diags[0].range.start.column);
+}
+
+#[test]
+fn test_const_decl() {
+    let program = parse("const a = 1; var b = 2; for (const i = 0; i < 1; i) {}");
+    assert!(program.stmts[0].Var().unwrap().is_const);
+    assert!(!program.stmts[1].Var().unwrap().is_const);
+
+    let for_stmt = program.stmts[2].For().unwrap();
+    assert!(for_stmt.init.as_ref().unwrap().Var().unwrap().is_const);
+}
+
+#[test]
+fn test_const_without_initializer() {
+    let (program, diags) = parse_with_errors("const a; print 1;");
+    assert_eq!(1, diags.len());
+    assert_eq!(messages::err_exp_sym("="), diags[0].message);
+    assert_eq!(NodeType::PrintStmt, program.stmts[1].typ());
+}
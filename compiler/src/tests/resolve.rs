@@ -16,12 +16,24 @@
 use crate::comp::Resolve;
 use crate::diagnostics::{CollectingDiagnosticHandler, DiagnosticKind};
 use crate::location::Position;
+use crate::messages::err_assign_const;
+use crate::messages::err_assign_native;
+use crate::messages::err_dup_func;
 use crate::messages::err_dup_var;
+use crate::messages::err_inherit_self;
+use crate::messages::err_not_a_class;
+use crate::messages::err_supercls_after_cls;
 use crate::messages::err_undef_label;
 use crate::messages::err_undef_var;
+use crate::messages::Message;
+use crate::messages::RES_RETURN_OUTSIDE_FUNC;
+use crate::messages::RES_RETURN_VALUE_IN_INIT;
+use crate::messages::RES_SUPER_OUTSIDE_CLASS;
+use crate::messages::RES_SUPER_WITHOUT_SUPERCLASS;
+use crate::messages::RES_THIS_OUTSIDE_CLASS;
 use crate::tests::util::parse_1;
 
-fn match_single_diagnostic(src: &str, msg: String) {
+fn match_single_diagnostic(src: &str, msg: Message) {
     let mut diags = CollectingDiagnosticHandler::new();
     let mut program = parse_1(src, &mut diags);
     let mut analyzer = Resolve::new(&mut diags);
@@ -32,6 +44,7 @@ fn match_single_diagnostic(src: &str, msg: String) {
     assert!(!diagnostics.is_empty());
     assert_eq!(1, diagnostics.len());
     assert_eq!(msg, diagnostics[0].message);
+    assert_eq!(msg.code, diagnostics[0].code);
 }
 
 #[test]
@@ -72,6 +85,12 @@ fn test_undeclared_var() {
     assert_eq!(diagnostic.message, err_undef_var("a"));
 }
 
+#[test]
+fn test_undeclared_indexed_var() {
+    match_single_diagnostic("print zz[0];", err_undef_var("zz"));
+    match_single_diagnostic("var a = [1]; a[zz] = 2;", err_undef_var("zz"));
+}
+
 #[test]
 fn test_var_decl_in_sep_scope() {
     let mut diags = CollectingDiagnosticHandler::new();
@@ -165,3 +184,218 @@ fn test_dup_var_in_nested_loop() {
 fn test_dup_var_in_nested_scope() {
     match_single_diagnostic("var i = 0; { var i = 1; }", err_dup_var("i"));
 }
+
+#[test]
+fn test_return_outside_func() {
+    match_single_diagnostic("return 1;", RES_RETURN_OUTSIDE_FUNC);
+}
+
+#[test]
+fn test_dup_func_decl() {
+    match_single_diagnostic("fun f() {} fun f() {}", err_dup_func("f"));
+}
+
+#[test]
+fn test_dup_func_param() {
+    match_single_diagnostic("fun f(a, a) {}", err_dup_var("a"));
+}
+
+#[test]
+fn test_func_accessing_enclosing_func_var() {
+    let mut diags = CollectingDiagnosticHandler::new();
+    let mut program = parse_1(
+        "fun outer() { var a = 1; var b = 2; fun inner() { return a; } }",
+        &mut diags,
+    );
+    let mut analyzer = Resolve::new(&mut diags);
+    analyzer.analyze(&mut program);
+
+    assert!(diags.diagnostics.is_empty());
+
+    let outer = program.decls[0].Func().unwrap();
+    let a = outer.body.decls[0].Stmt().unwrap().Var().unwrap();
+    let b = outer.body.decls[1].Stmt().unwrap().Var().unwrap();
+    assert!(a.name.captured);
+    assert!(!b.name.captured);
+}
+
+#[test]
+fn test_lambda_captures_param() {
+    let mut diags = CollectingDiagnosticHandler::new();
+    let mut program = parse_1(
+        "fun adder(n) { return fun(x) { return x + n; }; }",
+        &mut diags,
+    );
+    let mut analyzer = Resolve::new(&mut diags);
+    analyzer.analyze(&mut program);
+
+    assert!(diags.diagnostics.is_empty());
+
+    let adder = program.decls[0].Func().unwrap();
+    assert!(adder.params[0].captured);
+}
+
+#[test]
+fn test_func_call_before_decl() {
+    let mut diags = CollectingDiagnosticHandler::new();
+    let mut program = parse_1("var r = f(); fun f() { return r; }", &mut diags);
+    let mut analyzer = Resolve::new(&mut diags);
+    analyzer.analyze(&mut program);
+
+    assert!(diags.diagnostics.is_empty());
+}
+
+#[test]
+fn test_class_methods() {
+    let mut diags = CollectingDiagnosticHandler::new();
+    let mut program = parse_1(
+        "var p = Point(1, 2);
+        class Point {
+            init(x, y) { this.x = x; this.y = y; }
+            sum() { return this.x + this.y; }
+        }
+        class Point3 : Point {
+            init(x, y, z) { super.init(x, y); this.z = z; }
+        }",
+        &mut diags,
+    );
+    let mut analyzer = Resolve::new(&mut diags);
+    analyzer.analyze(&mut program);
+
+    assert!(diags.diagnostics.is_empty());
+}
+
+#[test]
+fn test_this_outside_class() {
+    match_single_diagnostic("fun f() { return this; }", RES_THIS_OUTSIDE_CLASS);
+}
+
+#[test]
+fn test_super_outside_class() {
+    match_single_diagnostic("fun f() { return super.f(); }", RES_SUPER_OUTSIDE_CLASS);
+}
+
+#[test]
+fn test_super_without_superclass() {
+    match_single_diagnostic(
+        "class A { f() { return super.f(); } }",
+        RES_SUPER_WITHOUT_SUPERCLASS,
+    );
+}
+
+#[test]
+fn test_class_inheriting_itself() {
+    match_single_diagnostic("class A : A {}", err_inherit_self("A"));
+}
+
+#[test]
+fn test_superclass_declared_after_class() {
+    match_single_diagnostic("class B : A {} class A {}", err_supercls_after_cls("A", "B"));
+}
+
+#[test]
+fn test_superclass_not_a_class() {
+    match_single_diagnostic("var A = 1; class B : A {}", err_not_a_class("A"));
+    match_single_diagnostic("class B : A {} const A = 1;", err_not_a_class("A"));
+    match_single_diagnostic("fun A() {} class B : A {}", err_not_a_class("A"));
+}
+
+#[test]
+fn test_return_value_from_init() {
+    match_single_diagnostic(
+        "class A { init() { return 1; } }",
+        RES_RETURN_VALUE_IN_INIT,
+    );
+}
+
+#[test]
+fn test_this_captured_in_method() {
+    let mut diags = CollectingDiagnosticHandler::new();
+    let mut program = parse_1(
+        "class A { f() { return fun() { return this; }; } g() { return this; } }",
+        &mut diags,
+    );
+    let mut analyzer = Resolve::new(&mut diags);
+    analyzer.analyze(&mut program);
+
+    assert!(diags.diagnostics.is_empty());
+
+    let class = program.decls[0].Class().unwrap();
+    assert!(class.methods[0].this.as_ref().unwrap().captured);
+    assert!(!class.methods[1].this.as_ref().unwrap().captured);
+}
+
+fn resolve_with_natives(src: &str, natives: &[&str]) -> CollectingDiagnosticHandler {
+    let mut diags = CollectingDiagnosticHandler::new();
+    let mut program = parse_1(src, &mut diags);
+    let mut analyzer = Resolve::new(&mut diags);
+    for native in natives {
+        analyzer.declare_native(native);
+    }
+    analyzer.analyze(&mut program);
+    diags
+}
+
+#[test]
+fn test_native_call() {
+    let diags = resolve_with_natives(
+        "print clock(); fun f() { return clock; } var c = fun() { return clock(); };",
+        &["clock"],
+    );
+    assert!(diags.diagnostics.is_empty());
+}
+
+#[test]
+fn test_undeclared_native() {
+    let diags = resolve_with_natives("print clock();", &["time"]);
+    assert_eq!(1, diags.diagnostics.len());
+    assert_eq!(err_undef_var("clock"), diags.diagnostics[0].message);
+}
+
+#[test]
+fn test_assign_to_native() {
+    let diags = resolve_with_natives("clock = 1; clock += 1;", &["clock"]);
+    assert_eq!(2, diags.diagnostics.len());
+    assert_eq!(err_assign_native("clock"), diags.diagnostics[0].message);
+    assert_eq!(err_assign_native("clock"), diags.diagnostics[1].message);
+}
+
+#[test]
+fn test_assign_to_var_shadowing_native() {
+    let diags = resolve_with_natives("var clock = 1; clock = 2;", &["clock"]);
+    assert!(diags.diagnostics.is_empty());
+}
+
+#[test]
+fn test_assign_to_const() {
+    let diags = resolve_with_natives("const a = 1; a = 2; a += 1;", &[]);
+    assert_eq!(2, diags.diagnostics.len());
+    assert_eq!(err_assign_const("a"), diags.diagnostics[0].message);
+    assert_eq!(err_assign_const("a"), diags.diagnostics[1].message);
+}
+
+#[test]
+fn test_assign_to_const_in_func() {
+    match_single_diagnostic("const a = 1; fun f() { a = 2; }", err_assign_const("a"));
+    match_single_diagnostic(
+        "for (const i = 0; i < 10; i = i + 1) {}",
+        err_assign_const("i"),
+    );
+}
+
+#[test]
+fn test_assign_to_rejected_redeclaration_of_const() {
+    match_single_diagnostic("const a = 1; var a = 2; a = 3;", err_dup_var("a"));
+    match_single_diagnostic("const a = 1; { var a = 2; a += 3; }", err_dup_var("a"));
+
+    let diags = resolve_with_natives("const a = 1; { var a = 2; } a = 3;", &[]);
+    assert_eq!(2, diags.diagnostics.len());
+    assert_eq!(err_dup_var("a"), diags.diagnostics[0].message);
+    assert_eq!(err_assign_const("a"), diags.diagnostics[1].message);
+}
+
+#[test]
+fn test_assign_to_param_shadowing_const() {
+    let diags = resolve_with_natives("const a = 1; fun f(a) { a = 2; }", &[]);
+    assert!(diags.diagnostics.is_empty());
+}
@@ -0,0 +1,259 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::io::Cursor;
+
+use crate::bytecode::bytes::ByteInput;
+use crate::bytecode::YKBDisassembler;
+use crate::bytecode::YKBFile;
+use crate::bytecode::YKBFileReader;
+use crate::comp::YKCompiler;
+use crate::features::CompilerFeatures;
+
+fn compile(source: &str) -> YKBFile {
+    let mut compiler = YKCompiler::new();
+    let features = CompilerFeatures::default();
+    let (mut program, has_errors) = compiler
+        .parse(Cursor::new(source))
+        .expect("Failed to parse source");
+    assert!(!has_errors);
+    assert!(!compiler.attr(&mut program, &features));
+    compiler.ir(&mut program, &features).unwrap()
+}
+
+fn write(file: &mut YKBFile) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    file.write_to(&mut bytes).expect("Failed to write YKB file");
+    bytes
+}
+
+fn read(bytes: Vec<u8>) -> YKBFile {
+    let mut reader = YKBFileReader::new(ByteInput::new(Cursor::new(bytes)));
+    reader.read_file().expect("Failed to read YKB file")
+}
+
+fn disassemble(bytes: Vec<u8>) -> String {
+    let mut out = String::new();
+    let mut disassembler = YKBDisassembler::new(ByteInput::new(Cursor::new(bytes)), &mut out);
+    disassembler.disassemble().expect("Failed to disassemble");
+    out
+}
+
+#[test]
+fn test_func_decls_round_trip() {
+    let mut file = compile(
+        "fun add(a, b) { return a + b; }
+        fun noop() {}
+        print add(1, 2);",
+    );
+
+    let read = read(write(&mut file));
+    assert_eq!(file.version(), read.version());
+    assert_eq!(file.constant_pool(), read.constant_pool());
+    assert_eq!(*file.attributes(), *read.attributes());
+
+    let decls = file.declarations();
+    let read_decls = read.declarations();
+    assert_eq!(2, read_decls.len());
+    for i in 0..decls.len() {
+        let func = decls[i].as_func().expect("Expected a function declaration");
+        let read_func = read_decls[i].as_func().expect("Expected a function declaration");
+        assert_eq!(func, read_func);
+    }
+
+    let add = read_decls[0].as_func().unwrap();
+    assert_eq!(2, add.arity());
+    assert_eq!(
+        vec!["a".to_string(), "b".to_string()],
+        add.param_names
+            .iter()
+            .map(|name| read.constant_pool().get(*name).unwrap().to_string())
+            .collect::<Vec<String>>()
+    );
+    assert_eq!(2, add.code().max_locals());
+
+    let noop = read_decls[1].as_func().unwrap();
+    assert_eq!(0, noop.arity());
+    assert_eq!(0, noop.code().max_locals());
+}
+
+#[test]
+fn test_disassemble_func_sections() {
+    let mut file = compile(
+        "fun add(a, b) { return a + b; }
+        fun noop() {}",
+    );
+
+    let out = disassemble(write(&mut file));
+    assert!(out.contains("#0: fun add(a, b):"));
+    assert!(out.contains("#1: fun noop():"));
+    assert_eq!(3, out.matches("Code: ").count());
+}
+
+#[test]
+fn test_closure_upvalues_round_trip() {
+    let mut file = compile(
+        "fun counter() {
+            var count = 0;
+            return fun() { count = count + 1; return count; };
+        }",
+    );
+
+    let read = read(write(&mut file));
+    let read_decls = read.declarations();
+    assert_eq!(file.declarations().len(), read_decls.len());
+
+    let counter = read_decls[0].as_func().unwrap();
+    assert!(counter.upvalues.is_empty());
+
+    let lambda = read_decls[1].as_func().unwrap();
+    assert_eq!(file.declarations()[1].as_func().unwrap(), lambda);
+    assert_eq!(1, lambda.upvalues.len());
+    assert!(lambda.upvalues[0].is_local);
+    assert_eq!(0, lambda.upvalues[0].index);
+
+    let out = disassemble(write(&mut file));
+    assert!(out.contains("#1: fun <lambda>():"));
+    assert!(out.contains("upvalues:"));
+}
+
+#[test]
+fn test_class_decls_round_trip() {
+    let mut file = compile(
+        "class A { get() { return 1; } }
+        class B : A { init(x) { this.x = x; } get() { return super.get() + this.x; } }
+        print B(1).get();",
+    );
+
+    let read = read(write(&mut file));
+    let read_decls = read.declarations();
+    assert_eq!(file.declarations().len(), read_decls.len());
+
+    let a = read_decls[0].as_class().expect("Expected a class declaration");
+    assert!(!a.has_super());
+    assert_eq!(file.declarations()[0].as_class().unwrap(), a);
+    assert_eq!(1, a.methods.len());
+
+    let b = read_decls[2].as_class().expect("Expected a class declaration");
+    assert!(b.has_super());
+    assert_eq!(file.declarations()[2].as_class().unwrap(), b);
+    assert_eq!(
+        "A",
+        read.constant_pool().get(b.super_name_index).unwrap().to_string()
+    );
+    assert_eq!(2, b.methods.len());
+    for method in &b.methods {
+        let func = read_decls[*method as usize]
+            .as_func()
+            .expect("Expected a method declaration");
+        assert_eq!(file.declarations()[*method as usize].as_func().unwrap(), func);
+    }
+
+    let out = disassemble(write(&mut file));
+    assert!(out.contains("#0: class A:"));
+    assert!(out.contains("#2: class B : A:"));
+}
+
+#[test]
+fn test_native_call_disassembly() {
+    let mut compiler = YKCompiler::new();
+    compiler.declare_native("clock");
+    let features = CompilerFeatures::default();
+    let (mut program, has_errors) = compiler
+        .parse(Cursor::new("print clock();"))
+        .expect("Failed to parse source");
+    assert!(!has_errors);
+    assert!(!compiler.attr(&mut program, &features));
+    let mut file = compiler.ir(&mut program, &features).unwrap();
+
+    let out = disassemble(write(&mut file));
+    assert!(out.contains("natload"));
+    assert!(out.contains("// clock"));
+}
+
+#[test]
+fn test_line_numbers_round_trip() {
+    let mut file = compile(
+        "fun f(a) {
+            var b = a * 2;
+            return b;
+        }
+        print f(1);",
+    );
+
+    let read = read(write(&mut file));
+    let attrs = file.attributes();
+    let read_attrs = read.attributes();
+    let lines = attrs
+        .iter()
+        .find_map(|attr| attr.LineNumberTable())
+        .expect("Expected a LineNumberTable attribute");
+    assert_eq!(
+        lines,
+        read_attrs
+            .iter()
+            .find_map(|attr| attr.LineNumberTable())
+            .unwrap()
+    );
+    assert_eq!(Some(5), lines.line_at(lines.entries().last().unwrap().start_pc));
+
+    let read_decls = read.declarations();
+    let func = read_decls[0].as_func().unwrap();
+    let func_lines = func
+        .line_numbers()
+        .expect("Expected a LineNumberTable for the function");
+    let lines: Vec<u32> = func_lines.entries().iter().map(|l| l.line).collect();
+    assert_eq!(vec![2, 3], lines);
+
+    drop(attrs);
+    let out = disassemble(write(&mut file));
+    assert!(out.contains("LineNumberTable"));
+    assert!(out.contains("L2"));
+}
+
+#[test]
+fn test_malformed_files_rejected() {
+    let mut file = compile(
+        "class A { f() { return \"a\"; } }
+        fun g(a) { return a * 2; }
+        print A().f();
+        print g(1);",
+    );
+    let bytes = write(&mut file);
+
+    // every truncated file is reported as an error
+    for len in 0..bytes.len() {
+        let mut reader = YKBFileReader::new(ByteInput::new(Cursor::new(&bytes[..len])));
+        assert!(
+            reader.read_file().is_err(),
+            "Expected an error at length {}",
+            len
+        );
+    }
+
+    // corrupted bytes are either reported as an error or read as a different file
+    for index in 0..bytes.len() {
+        for value in [0x00, 0x80, 0xFF] {
+            let mut corrupted = bytes.clone();
+            corrupted[index] = value;
+            let mut reader = YKBFileReader::new(ByteInput::new(Cursor::new(corrupted)));
+            if let Ok(file) = reader.read_file() {
+                for entry in file.constant_pool().entries() {
+                    let _ = entry.to_string();
+                }
+            }
+        }
+    }
+}
@@ -78,11 +78,13 @@ pub enum TokenType {
     Fun,      // fun
     For,      // for
     While,    // while
+    Class,    // class
     Break,    // break,
     Continue, // continue
     Null,     // null
     Return,   // return
     Var,      // var
+    Const,    // const
     Super,    // super
     This,     // this
     Print,    // print
@@ -95,6 +97,8 @@ pub enum TokenType {
     False, // false
 
     Comment, // Anything after a '//' (inclusive)
+
+    Error, // Characters which could not be tokenized, reported by the lexer
 }
 
 impl PartialEq<Self> for TokenType {
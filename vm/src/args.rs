@@ -14,6 +14,7 @@
  */
 
 use clap::Args;
+use clap::ValueEnum;
 use std::path::PathBuf;
 
 #[derive(Args, Debug)]
@@ -21,4 +22,29 @@ use std::path::PathBuf;
 pub struct RunArgs {
     #[arg(help = "Input bytecode file(s)")]
     pub path: PathBuf,
+
+    #[arg(long, help = "Profile the execution and print a report when the program exits")]
+    pub profile: bool,
+
+    #[arg(long, help = "Format of the profile report", value_enum, default_value_t = ProfileFormat::Table)]
+    pub profile_format: ProfileFormat,
+
+    #[arg(long, help = "Number of most executed instructions listed in the profile report", default_value_t = 10)]
+    pub profile_hot_spots: usize,
+}
+
+/// The format of the profile report.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ProfileFormat {
+    /// Human-readable tables.
+    Table,
+
+    /// A JSON object.
+    Json,
+}
+
+#[derive(Args, Debug)]
+pub struct DebugArgs {
+    #[arg(help = "Input bytecode file")]
+    pub path: PathBuf,
 }
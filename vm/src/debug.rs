@@ -0,0 +1,90 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use compiler::bytecode::attrs::Code;
+use compiler::bytecode::ConstantPool;
+
+use crate::error::StackTraceElement;
+use crate::value::Value;
+
+/// A location at which the execution of a program is paused.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Breakpoint {
+    /// Pause before executing the instruction at the given address in the function with the
+    /// given name. The top-level code is named [YKVM::TOP_LEVEL_NAME](crate::YKVM::TOP_LEVEL_NAME).
+    Pc { function: String, pc: usize },
+
+    /// Pause before executing the first instruction generated for the given source line.
+    Line(u32),
+}
+
+/// The action to perform when a paused program is resumed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DebugAction {
+    /// Resume the execution until the next breakpoint is hit.
+    Continue,
+
+    /// Execute the next instruction and pause again.
+    Step,
+
+    /// Execute the next instruction and pause again in the same call frame, stepping over the
+    /// function calls.
+    StepOver,
+
+    /// Abort the execution.
+    Quit,
+}
+
+/// The state of a paused program.
+pub struct PausedState<'a> {
+    /// The name of the function being executed.
+    pub function: &'a str,
+
+    /// The address of the instruction which is executed next.
+    pub pc: usize,
+
+    /// The source line of the instruction which is executed next, if known.
+    pub line: Option<u32>,
+
+    /// The number of active call frames.
+    pub depth: usize,
+
+    /// The operands of the current call frame, the top of the stack being the last element.
+    pub operands: &'a [Value],
+
+    /// The local variables of the current call frame.
+    pub locals: &'a [Value],
+
+    /// The code being executed in the current call frame.
+    pub code: &'a Code,
+
+    /// The constant pool of the program.
+    pub constant_pool: &'a ConstantPool,
+
+    /// The call stack, starting with the current call frame.
+    pub stack_trace: Vec<StackTraceElement>,
+}
+
+/// A debugger which controls the execution of a program run with
+/// [YKVM::debug](crate::YKVM::debug).
+pub trait DebugHook {
+    /// Get the breakpoints at which the execution must be paused.
+    fn breakpoints(&self) -> &[Breakpoint];
+
+    /// Called when the execution is paused, before the first instruction of the program is
+    /// executed and whenever a breakpoint is hit or a step is complete. Returns how the
+    /// execution must be resumed.
+    fn on_pause(&mut self, state: &PausedState) -> DebugAction;
+}
@@ -0,0 +1,191 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::error::Error;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+/// The kind of a [RuntimeError].
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeErrorKind {
+    /// An operation was performed on a value of an unsupported type.
+    TypeError,
+
+    /// A function was called with the wrong number of arguments.
+    ArityMismatch,
+
+    /// An array was accessed with an index which is out of its bounds.
+    IndexOutOfBounds,
+
+    /// An array was created with an invalid size.
+    InvalidArraySize,
+
+    /// The maximum call depth or the maximum operand stack size was exceeded.
+    StackOverflow,
+
+    /// An undefined property of an instance or a class was accessed.
+    UndefinedProperty,
+
+    /// A native function which is not registered with the VM was accessed.
+    UndefinedNative,
+
+    /// The bytecode being executed is invalid.
+    InvalidBytecode,
+
+    /// An error reported by a native function.
+    Native,
+
+    /// The maximum number of instructions to execute was exceeded.
+    InstructionLimitExceeded,
+
+    /// A string longer than [Value::MAX_STRING_LEN](crate::value::Value::MAX_STRING_LEN) bytes
+    /// would have been created.
+    StringLengthExceeded,
+
+    /// An allocation would have exceeded the maximum heap size.
+    HeapLimitExceeded,
+
+    /// The maximum total size of the operand stack was exceeded.
+    OperandLimitExceeded,
+
+    /// The execution was interrupted by the embedder.
+    Interrupted,
+}
+
+/// An entry in the stack trace of a [RuntimeError].
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackTraceElement {
+    /// The name of the function being executed.
+    pub function: String,
+
+    /// The address of the instruction being executed in the function.
+    pub pc: usize,
+
+    /// The source line of the instruction being executed, if known.
+    pub line: Option<u32>,
+}
+
+/// An error which occurs while executing a program.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub kind: RuntimeErrorKind,
+    pub message: String,
+
+    /// The address of the instruction which caused the error.
+    pub pc: usize,
+
+    /// The call stack at the time of the error, starting with the innermost call.
+    pub stack_trace: Vec<StackTraceElement>,
+}
+
+impl RuntimeError {
+    /// Create a new runtime error with the given kind and message. The address and the stack
+    /// trace are filled in by the VM.
+    pub fn new(kind: RuntimeErrorKind, message: impl Into<String>) -> RuntimeError {
+        return RuntimeError {
+            kind,
+            message: message.into(),
+            pc: 0,
+            stack_trace: Vec::with_capacity(0),
+        };
+    }
+}
+
+impl Display for RuntimeErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(
+            match self {
+                RuntimeErrorKind::TypeError => "TypeError",
+                RuntimeErrorKind::ArityMismatch => "ArityMismatch",
+                RuntimeErrorKind::IndexOutOfBounds => "IndexOutOfBounds",
+                RuntimeErrorKind::InvalidArraySize => "InvalidArraySize",
+                RuntimeErrorKind::StackOverflow => "StackOverflow",
+                RuntimeErrorKind::UndefinedProperty => "UndefinedProperty",
+                RuntimeErrorKind::UndefinedNative => "UndefinedNative",
+                RuntimeErrorKind::InvalidBytecode => "InvalidBytecode",
+                RuntimeErrorKind::Native => "NativeError",
+                RuntimeErrorKind::InstructionLimitExceeded => "InstructionLimitExceeded",
+                RuntimeErrorKind::StringLengthExceeded => "StringLengthExceeded",
+                RuntimeErrorKind::HeapLimitExceeded => "HeapLimitExceeded",
+                RuntimeErrorKind::OperandLimitExceeded => "OperandLimitExceeded",
+                RuntimeErrorKind::Interrupted => "Interrupted",
+            },
+            f,
+        )
+    }
+}
+
+impl Display for StackTraceElement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "at {} (line: {}, pc: {})", self.function, line, self.pc),
+            None => write!(f, "at {} (pc: {})", self.function, self.pc),
+        }
+    }
+}
+
+/// The number of frames shown at each end of a long stack trace.
+const STACK_TRACE_EDGE_FRAMES: usize = 10;
+
+impl RuntimeError {
+    /// Groups the consecutive identical elements of the stack trace, which are the frames of a
+    /// function calling itself from the same place.
+    fn collapsed_stack_trace(&self) -> Vec<(&StackTraceElement, usize)> {
+        let mut frames: Vec<(&StackTraceElement, usize)> = Vec::new();
+        for element in &self.stack_trace {
+            match frames.last_mut() {
+                Some((last, count)) if *last == element => *count += 1,
+                _ => frames.push((element, 1)),
+            }
+        }
+
+        return frames;
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.kind, self.message)?;
+
+        let frames = self.collapsed_stack_trace();
+        if frames.len() <= 2 * STACK_TRACE_EDGE_FRAMES {
+            return write_frames(f, &frames);
+        }
+
+        let (head, rest) = frames.split_at(STACK_TRACE_EDGE_FRAMES);
+        let (skipped, tail) = rest.split_at(rest.len() - STACK_TRACE_EDGE_FRAMES);
+        write_frames(f, head)?;
+        let count: usize = skipped.iter().map(|(_, count)| count).sum();
+        write!(f, "\n    ... {} more frames", count)?;
+        write_frames(f, tail)
+    }
+}
+
+fn write_frames(f: &mut Formatter<'_>, frames: &[(&StackTraceElement, usize)]) -> std::fmt::Result {
+    for (element, count) in frames {
+        write!(f, "\n    {}", element)?;
+        if *count > 1 {
+            write!(
+                f,
+                "\n    ... previous frame repeated {} more times",
+                count - 1
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+impl Error for RuntimeError {}
@@ -1,7 +1,29 @@
+pub use debug::Breakpoint;
+pub use debug::DebugAction;
+pub use debug::DebugHook;
+pub use debug::PausedState;
+pub use error::RuntimeError;
+pub use error::RuntimeErrorKind;
+pub use error::StackTraceElement;
+pub use limits::VmLimits;
+pub use memory::HeapStats;
+pub use native::NativeContext;
+pub use native::NativeFn;
+pub use profile::OpcodeStats;
+pub use profile::PcStats;
+pub use profile::Profile;
+pub use value::Value;
+pub use verifier::verify;
 pub use vm::YKVM;
 
 pub mod args;
+mod debug;
+mod error;
+mod limits;
+mod native;
+mod profile;
 mod value;
+mod verifier;
 mod vm;
 
 #[cfg(test)]
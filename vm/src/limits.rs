@@ -0,0 +1,50 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+/// The limits on the resources used by the VM while executing a program. The limits are useful
+/// when running untrusted scripts. Exceeding any of the limits aborts the execution with a
+/// [RuntimeError](crate::RuntimeError).
+#[derive(Debug, Clone, PartialEq)]
+pub struct VmLimits {
+    /// The maximum number of instructions to execute in a single run, if any.
+    pub max_instructions: Option<u64>,
+
+    /// The maximum number of bytes which can be allocated on the heap at a time, if any. The
+    /// strings are not allocated on the heap, but creating a string longer than the remaining
+    /// bytes fails as well.
+    pub max_heap_bytes: Option<usize>,
+
+    /// The maximum number of call frames which can be active at a time.
+    pub max_call_depth: usize,
+
+    /// The maximum number of operands on the operand stack, across all the call frames, if any.
+    pub max_operands: Option<usize>,
+}
+
+impl VmLimits {
+    /// The default maximum call depth.
+    pub const DEFAULT_MAX_CALL_DEPTH: usize = 1024;
+}
+
+impl Default for VmLimits {
+    fn default() -> Self {
+        return VmLimits {
+            max_instructions: None,
+            max_heap_bytes: None,
+            max_call_depth: Self::DEFAULT_MAX_CALL_DEPTH,
+            max_operands: None,
+        };
+    }
+}
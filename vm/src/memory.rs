@@ -13,56 +13,307 @@
  * program. If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::cmp::max;
+use std::cmp::min;
 use std::ptr::NonNull;
 
 use log::Level::Trace;
+use log::debug;
 use log::log_enabled;
 use log::trace;
 
+use crate::error::RuntimeError;
+use crate::error::RuntimeErrorKind;
 use crate::object::IObj;
 use crate::object::Obj;
+use crate::object::ObjArray;
+use crate::object::ObjBoundMethod;
+use crate::object::ObjClass;
+use crate::object::ObjClosure;
+use crate::object::ObjFunction;
+use crate::object::ObjInstance;
+use crate::object::ObjNative;
+use crate::object::ObjString;
+use crate::object::ObjType;
+use crate::object::ObjUpvalue;
+use crate::value::Value;
 
+/// Statistics about the memory usage of a [Heap].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct HeapStats {
+    /// The number of bytes occupied by the objects which are currently allocated.
+    pub bytes_allocated: usize,
+
+    /// The maximum number of bytes that were allocated at any point of time.
+    pub max_bytes_allocated: usize,
+
+    /// The number of objects which are currently allocated.
+    pub objects: usize,
+
+    /// The number of garbage collections performed so far.
+    pub collections: usize,
+}
+
+/// The heap of the VM. The objects are allocated on the heap and are freed by a mark-and-sweep
+/// garbage collector once the number of allocated bytes exceeds a threshold.
 pub struct Heap {
     objects: Option<NonNull<Obj>>,
+    stats: HeapStats,
+
+    /// The minimum number of allocated bytes after which a garbage collection is performed.
+    threshold: usize,
+
+    /// The number of allocated bytes after which the next garbage collection is performed.
+    next_gc: usize,
+
+    /// The objects which are marked, but whose references are yet to be marked.
+    gray: Vec<NonNull<Obj>>,
+
+    /// The maximum number of bytes which can be allocated at a time, if any.
+    max_bytes: Option<usize>,
 }
 
 impl Heap {
+    /// The default garbage collection threshold, in bytes.
+    pub const DEFAULT_GC_THRESHOLD: usize = 1024 * 1024;
+
+    /// The factor by which the collection threshold grows relative to the number of bytes which
+    /// survive a garbage collection.
+    const GC_GROW_FACTOR: usize = 2;
+
     /// Create a new heap.
     pub fn new() -> Heap {
         return Heap {
             objects: None,
+            stats: HeapStats::default(),
+            threshold: Self::DEFAULT_GC_THRESHOLD,
+            next_gc: Self::DEFAULT_GC_THRESHOLD,
+            gray: Vec::with_capacity(0),
+            max_bytes: None,
+        };
+    }
+
+    /// Get the statistics of this heap.
+    pub fn stats(&self) -> HeapStats {
+        return self.stats;
+    }
+
+    /// Set the minimum number of allocated bytes after which a garbage collection is performed.
+    pub fn set_gc_threshold(&mut self, threshold: usize) {
+        self.threshold = threshold;
+        self.update_next_gc(max(self.stats.bytes_allocated, threshold));
+    }
+
+    /// Set the maximum number of bytes which can be allocated at a time. Allocations exceeding
+    /// the limit fail with a [RuntimeErrorKind::HeapLimitExceeded] error.
+    ///
+    /// The strings held in [Value::String] are not allocated on the heap, and are not counted.
+    /// However, the VM checks with [Heap::check_alloc] that each string it creates would fit in
+    /// the remaining bytes.
+    pub fn set_max_bytes(&mut self, max_bytes: Option<usize>) {
+        self.max_bytes = max_bytes;
+        self.update_next_gc(self.next_gc);
+    }
+
+    /// Set the number of allocated bytes after which the next garbage collection is performed.
+    /// If there is a heap limit, the garbage is collected well before the limit is reached, as
+    /// the collector only runs between instructions.
+    fn update_next_gc(&mut self, next_gc: usize) {
+        self.next_gc = match self.max_bytes {
+            Some(max_bytes) => min(next_gc, max_bytes - max_bytes / 4),
+            None => next_gc,
         };
     }
 
     /// Allocate a new object on the heap and return a pointer to the same.
-    pub fn allocate_obj<T: IObj>(&mut self, o: T) -> NonNull<Obj> {
+    pub fn allocate_obj<T: IObj>(&mut self, o: T) -> Result<NonNull<Obj>, RuntimeError> {
         if log_enabled!(Trace) {
             trace!("Heap::allocate_obj({:?})", o);
         }
 
+        // the object is measured before it is moved to the heap, the objects start with their
+        // header, like the objects on the heap
+        let size = Obj::size_of(&NonNull::from(&o).cast());
+        self.check_alloc(size)?;
+
         let new: NonNull<Obj> = NonNull::new(Box::into_raw(Box::new(o)).cast()).unwrap();
-        
         unsafe {
             (*new.as_ptr()).next = self.objects;
             self.objects = Some(new);
         }
+
+        self.stats.bytes_allocated += size;
+        self.stats.max_bytes_allocated =
+            max(self.stats.max_bytes_allocated, self.stats.bytes_allocated);
+        self.stats.objects += 1;
         
-        new
+        Ok(new)
+    }
+
+    /// Check that the given number of bytes can be allocated without exceeding the maximum heap
+    /// size, if any.
+    pub fn check_alloc(&self, size: usize) -> Result<(), RuntimeError> {
+        if let Some(max_bytes) = self.max_bytes {
+            if self.stats.bytes_allocated.saturating_add(size) > max_bytes {
+                return Err(RuntimeError::new(
+                    RuntimeErrorKind::HeapLimitExceeded,
+                    format!("Maximum heap size of {} bytes exceeded", max_bytes),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether the number of allocated bytes has exceeded the collection threshold.
+    pub fn should_collect(&self) -> bool {
+        return self.stats.bytes_allocated > self.next_gc;
+    }
+
+    /// Perform a garbage collection. The objects which are not reachable from the given roots are
+    /// freed.
+    pub fn collect<'a>(&mut self, roots: impl Iterator<Item = &'a NonNull<Obj>>) {
+        let before = self.stats.bytes_allocated;
+        if log_enabled!(Trace) {
+            trace!("Heap::collect(bytes_allocated={})", before);
+        }
+
+        for root in roots {
+            self.mark_obj(*root);
+        }
+
+        while let Some(obj) = self.gray.pop() {
+            self.blacken(obj);
+        }
+
+        self.sweep();
+
+        self.stats.collections += 1;
+        self.update_next_gc(max(
+            self.stats.bytes_allocated * Self::GC_GROW_FACTOR,
+            self.threshold,
+        ));
+
+        debug!(
+            "GC: collected {} bytes ({} -> {}), next at {}",
+            before - self.stats.bytes_allocated,
+            before,
+            self.stats.bytes_allocated,
+            self.next_gc
+        );
+    }
+
+    fn mark_value(&mut self, value: &Value) {
+        if let Value::Ref(obj) = value {
+            self.mark_obj(*obj);
+        }
+    }
+
+    fn mark_obj(&mut self, obj: NonNull<Obj>) {
+        unsafe {
+            if (*obj.as_ptr()).is_marked {
+                return;
+            }
+
+            (*obj.as_ptr()).is_marked = true;
+        }
+
+        self.gray.push(obj);
+    }
+
+    /// Mark the objects referenced by the given object.
+    fn blacken(&mut self, obj: NonNull<Obj>) {
+        match unsafe { &obj.as_ref().typ } {
+            ObjType::String | ObjType::Function | ObjType::Native => {}
+            ObjType::Array => {
+                for element in &Obj::AsArrayRef(&obj).unwrap().elements {
+                    self.mark_value(element);
+                }
+            }
+            ObjType::Closure => {
+                let closure = Obj::AsClosureRef(&obj).unwrap();
+                self.mark_obj(closure.function);
+                for upvalue in &closure.upvalues {
+                    self.mark_obj(*upvalue);
+                }
+            }
+            ObjType::Upvalue => {
+                self.mark_value(&Obj::AsUpvalueRef(&obj).unwrap().value);
+            }
+            ObjType::Class => {
+                for method in Obj::AsClassRef(&obj).unwrap().methods.values() {
+                    self.mark_obj(*method);
+                }
+            }
+            ObjType::Instance => {
+                let instance = Obj::AsInstanceRef(&obj).unwrap();
+                self.mark_obj(instance.class);
+                for field in instance.fields.values() {
+                    self.mark_value(field);
+                }
+            }
+            ObjType::BoundMethod => {
+                let bound = Obj::AsBoundMethodRef(&obj).unwrap();
+                self.mark_value(&bound.receiver);
+                self.mark_obj(bound.method);
+            }
+        }
+    }
+
+    /// Free the objects which were not marked and clear the marks of the remaining objects.
+    fn sweep(&mut self) {
+        let mut previous: Option<NonNull<Obj>> = None;
+        let mut current = self.objects;
+        while let Some(obj) = current {
+            unsafe {
+                current = (*obj.as_ptr()).next;
+                if (*obj.as_ptr()).is_marked {
+                    (*obj.as_ptr()).is_marked = false;
+                    previous = Some(obj);
+                    continue;
+                }
+
+                match previous {
+                    Some(previous) => (*previous.as_ptr()).next = current,
+                    None => self.objects = current,
+                }
+
+                self.free(obj);
+            }
+        }
+    }
+
+    unsafe fn free(&mut self, obj: NonNull<Obj>) {
+        self.stats.bytes_allocated -= Obj::size_of(&obj);
+        self.stats.objects -= 1;
+        Self::free_object(obj);
     }
 
     /// Free the given object from the heap.
     pub unsafe fn free_object(obj: NonNull<Obj>) {
-        drop(Box::from_raw(obj.as_ptr()));
+        match obj.as_ref().typ {
+            ObjType::String => drop(Box::from_raw(obj.cast::<ObjString>().as_ptr())),
+            ObjType::Array => drop(Box::from_raw(obj.cast::<ObjArray>().as_ptr())),
+            ObjType::Function => drop(Box::from_raw(obj.cast::<ObjFunction>().as_ptr())),
+            ObjType::Closure => drop(Box::from_raw(obj.cast::<ObjClosure>().as_ptr())),
+            ObjType::Upvalue => drop(Box::from_raw(obj.cast::<ObjUpvalue>().as_ptr())),
+            ObjType::Class => drop(Box::from_raw(obj.cast::<ObjClass>().as_ptr())),
+            ObjType::Instance => drop(Box::from_raw(obj.cast::<ObjInstance>().as_ptr())),
+            ObjType::BoundMethod => drop(Box::from_raw(obj.cast::<ObjBoundMethod>().as_ptr())),
+            ObjType::Native => drop(Box::from_raw(obj.cast::<ObjNative>().as_ptr())),
+        }
     }
 
     /// Release this heap.
     pub fn release(&mut self) {
-        let mut obj = self.objects;
+        let mut obj = self.objects.take();
         while let Some(o) = obj {
             unsafe {
                 obj = (*o.as_ptr()).next;
-                Self::free_object(o);
+                self.free(o);
             }
         }
+
+        self.update_next_gc(self.threshold);
     }
 }
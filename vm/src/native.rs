@@ -0,0 +1,54 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::error::RuntimeError;
+use crate::memory::Heap;
+use crate::object::ObjArray;
+use crate::value::Value;
+
+/// The signature of the native functions which can be registered with the VM.
+pub type NativeFn = fn(&mut NativeContext, &[Value]) -> Result<Value, RuntimeError>;
+
+/// A native function registered with the VM.
+#[derive(Debug, Clone)]
+pub(crate) struct Native {
+    pub name: String,
+    pub arity: u8,
+    pub function: NativeFn,
+}
+
+/// The context in which a native function is called.
+pub struct NativeContext<'inst> {
+    name: &'inst str,
+    heap: &'inst mut Heap,
+}
+
+impl NativeContext<'_> {
+    pub(crate) fn new<'a>(name: &'a str, heap: &'a mut Heap) -> NativeContext<'a> {
+        return NativeContext { name, heap };
+    }
+
+    /// Get the name of the native function being called.
+    pub fn name(&self) -> &str {
+        return self.name;
+    }
+
+    /// Create a new array with the given elements. Fails if the heap limit of the VM is
+    /// exceeded.
+    pub fn new_array(&mut self, elements: Vec<Value>) -> Result<Value, RuntimeError> {
+        let arr = ObjArray::new(elements.len(), elements);
+        return Ok(Value::from(self.heap.allocate_obj(arr)?));
+    }
+}
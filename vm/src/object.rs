@@ -13,11 +13,14 @@
  * program. If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::ptr::NonNull;
 
+use crate::error::RuntimeError;
+use crate::error::RuntimeErrorKind;
 use crate::value::Value;
 
 macro_rules! def_obj {
@@ -37,7 +40,8 @@ macro_rules! def_obj {
                 pub fn $name() -> Obj {
                     return Obj {
                         typ: ObjType::$otyp,
-                        next: None
+                        next: None,
+                        is_marked: false,
                     }
                 }
 
@@ -93,8 +97,15 @@ pub trait IObj: Debug + Display {}
 #[repr(u8)]
 #[rustfmt::skip]
 pub enum ObjType {
-    String   = 0,
-    Array    = 1,
+    String      = 0,
+    Array       = 1,
+    Function    = 2,
+    Closure     = 3,
+    Upvalue     = 4,
+    Class       = 5,
+    Instance    = 6,
+    BoundMethod = 7,
+    Native      = 8,
 }
 
 /// An object on the VM, similar to heap-allocated objects.
@@ -103,6 +114,9 @@ pub enum ObjType {
 pub struct Obj {
     pub typ: ObjType,
     pub next: Option<NonNull<Obj>>,
+
+    /// Whether the object was found to be reachable in the current garbage collection cycle.
+    pub is_marked: bool,
 }
 
 def_obj!(Array ObjArray {
@@ -114,6 +128,42 @@ def_obj!(String ObjString {
     string: String,
 });
 
+def_obj!(Function ObjFunction {
+    index: u16,
+    arity: u8,
+    name: String,
+});
+
+def_obj!(Closure ObjClosure {
+    function: NonNull<Obj>,
+    upvalues: Vec<NonNull<Obj>>,
+});
+
+def_obj!(Upvalue ObjUpvalue {
+    value: Value,
+});
+
+def_obj!(Class ObjClass {
+    name: String,
+    methods: HashMap<String, NonNull<Obj>>,
+});
+
+def_obj!(Instance ObjInstance {
+    class: NonNull<Obj>,
+    fields: HashMap<String, Value>,
+});
+
+def_obj!(BoundMethod ObjBoundMethod {
+    receiver: Value,
+    method: NonNull<Obj>,
+});
+
+def_obj!(Native ObjNative {
+    index: usize,
+    arity: u8,
+    name: String,
+});
+
 impl ObjType {
     pub fn size_of(typ: &ObjType) -> usize {
         return typ.size();
@@ -122,34 +172,58 @@ impl ObjType {
         return match self {
             ObjType::Array => ObjType::ArraySize(),
             ObjType::String => ObjType::StringSize(),
+            ObjType::Function => ObjType::FunctionSize(),
+            ObjType::Closure => ObjType::ClosureSize(),
+            ObjType::Upvalue => ObjType::UpvalueSize(),
+            ObjType::Class => ObjType::ClassSize(),
+            ObjType::Instance => ObjType::InstanceSize(),
+            ObjType::BoundMethod => ObjType::BoundMethodSize(),
+            ObjType::Native => ObjType::NativeSize(),
         };
     }
 }
 
-impl ObjArray {
-
-    #[inline(always)]
-    fn invalid_idx(&self, idx: f64) {
-        panic!("Invalid array index: {} lenght: {}", idx, self.length);
+impl Obj {
+    /// Get the number of bytes occupied by the given object, including the elements of arrays
+    /// and the contents of strings.
+    pub fn size_of(o: &NonNull<Obj>) -> usize {
+        let typ = unsafe { &o.as_ref().typ };
+        return match typ {
+            ObjType::Array => {
+                let arr = Obj::AsArrayRef(o).unwrap();
+                typ.size() + arr.elements.len() * std::mem::size_of::<Value>()
+            }
+            ObjType::String => typ.size() + Obj::AsStringRef(o).unwrap().string.len(),
+            _ => typ.size(),
+        };
     }
+}
+
+impl ObjArray {
 
     #[inline(always)]
-    fn check_idx(&self, idx: f64) {
+    fn check_idx(&self, idx: f64) -> Result<usize, RuntimeError> {
         if idx < 0.0 || idx as usize >= self.length {
-            self.invalid_idx(idx);
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::IndexOutOfBounds,
+                format!("Invalid array index: {} length: {}", idx, self.length),
+            ));
         }
+
+        Ok(idx as usize)
     }
 
     /// Get the element at the given index.
-    pub fn get(&self, idx: f64) -> &Value {
-        self.check_idx(idx);
-        self.elements.get(idx as usize).unwrap()
+    pub fn get(&self, idx: f64) -> Result<&Value, RuntimeError> {
+        let idx = self.check_idx(idx)?;
+        Ok(self.elements.get(idx).unwrap())
     }
 
     /// Set the element at the given index.
-    pub fn set(&mut self, idx: f64, value: Value) {
-        self.check_idx(idx);
-        self.elements[idx as usize] = value;
+    pub fn set(&mut self, idx: f64, value: Value) -> Result<(), RuntimeError> {
+        let idx = self.check_idx(idx)?;
+        self.elements[idx] = value;
+        Ok(())
     }
 
     /// Get the number of elements in the array.
@@ -177,3 +251,64 @@ impl Display for ObjString {
         write!(f, "{}", &self.string)
     }
 }
+
+impl Display for ObjFunction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<fun {}>", &self.name)
+    }
+}
+
+impl Display for ObjNative {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<native fun {}>", &self.name)
+    }
+}
+
+impl ObjClosure {
+    /// Get the function of this closure.
+    pub fn function(&self) -> &ObjFunction {
+        return Obj::AsFunctionRef(&self.function).expect("Expected a function");
+    }
+}
+
+impl Display for ObjClosure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.function())
+    }
+}
+
+impl Display for ObjUpvalue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", &self.value)
+    }
+}
+
+impl ObjInstance {
+    /// Get the class of this instance.
+    pub fn class(&self) -> &ObjClass {
+        return Obj::AsClassRef(&self.class).expect("Expected a class");
+    }
+}
+
+impl Display for ObjClass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<class {}>", &self.name)
+    }
+}
+
+impl Display for ObjInstance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<{} instance>", &self.class().name)
+    }
+}
+
+impl Display for ObjBoundMethod {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        unsafe {
+            match self.method.as_ref().typ {
+                ObjType::Closure => write!(f, "{}", self.method.cast::<ObjClosure>().as_ref()),
+                _ => write!(f, "{}", self.method.cast::<ObjFunction>().as_ref()),
+            }
+        }
+    }
+}
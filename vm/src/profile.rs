@@ -0,0 +1,242 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+use compiler::bytecode::attrs::Code;
+use compiler::bytecode::opcode::get_mnemonic;
+use compiler::bytecode::opcode::OpSize;
+use util::json;
+
+/// Execution statistics of an opcode.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OpcodeStats {
+    /// The number of times the opcode was executed.
+    pub count: u64,
+
+    /// The total time spent executing the opcode.
+    pub time: Duration,
+}
+
+/// The number of times an instruction was executed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PcStats {
+    /// The name of the function containing the instruction.
+    pub function: String,
+
+    /// The address of the instruction in the function.
+    pub pc: usize,
+
+    /// The number of times the instruction was executed.
+    pub count: u64,
+}
+
+/// An execution profile of a program, recorded by the VM when profiling is enabled.
+/// The number of times the instructions of a function were executed.
+#[derive(Debug, Clone)]
+struct FunctionHits {
+    /// The name of the function, used as its label in the reports.
+    name: String,
+
+    /// The number of times each instruction was executed, keyed by its address.
+    pcs: HashMap<usize, u64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    opcodes: HashMap<OpSize, OpcodeStats>,
+
+    /// The functions in the order in which they were first executed. Functions are not keyed by
+    /// their name, as different functions (lambdas, methods of different classes) can have the
+    /// same name.
+    functions: Vec<FunctionHits>,
+
+    /// The index in `functions` of the function with the given code, keyed by the address of
+    /// the code.
+    function_indices: HashMap<usize, usize>,
+    instructions: u64,
+    total_time: Duration,
+}
+
+impl Profile {
+    /// The default number of instructions listed in the hot spots of a report.
+    pub const DEFAULT_HOT_SPOTS: usize = 10;
+
+    /// Record that the instruction at the given address in the function with the given code
+    /// and name is about to be executed.
+    pub(crate) fn record_hit(&mut self, code: &Code, name: &str, pc: usize) {
+        self.instructions += 1;
+        let key = code as *const Code as usize;
+        let index = match self.function_indices.get(&key) {
+            Some(index) => *index,
+            None => {
+                self.functions.push(FunctionHits {
+                    name: name.to_string(),
+                    pcs: HashMap::new(),
+                });
+                self.function_indices.insert(key, self.functions.len() - 1);
+                self.functions.len() - 1
+            }
+        };
+
+        *self.functions[index].pcs.entry(pc).or_insert(0) += 1;
+    }
+
+    /// Record the time spent executing an instruction with the given opcode.
+    pub(crate) fn record_time(&mut self, opcode: OpSize, time: Duration) {
+        let stats = self.opcodes.entry(opcode).or_default();
+        stats.count += 1;
+        stats.time += time;
+    }
+
+    /// Add the given duration to the total execution time.
+    pub(crate) fn add_total_time(&mut self, time: Duration) {
+        self.total_time += time;
+    }
+
+    /// Get the total number of instructions executed.
+    pub fn instructions(&self) -> u64 {
+        return self.instructions;
+    }
+
+    /// Get the total time spent executing the program.
+    pub fn total_time(&self) -> Duration {
+        return self.total_time;
+    }
+
+    /// Get the statistics of the executed opcodes with their mnemonics, the most executed
+    /// opcode first.
+    pub fn opcodes(&self) -> Vec<(&'static str, OpcodeStats)> {
+        let mut opcodes: Vec<(&'static str, OpcodeStats)> = self
+            .opcodes
+            .iter()
+            .map(|(opcode, stats)| (get_mnemonic(opcode), *stats))
+            .collect();
+        opcodes.sort_by(|(a_name, a), (b_name, b)| {
+            b.count.cmp(&a.count).then_with(|| a_name.cmp(b_name))
+        });
+        return opcodes;
+    }
+
+    /// Get the (at most) `limit` most executed instructions, the most executed instruction
+    /// first.
+    pub fn hot_spots(&self, limit: usize) -> Vec<PcStats> {
+        let mut pcs: Vec<(usize, PcStats)> = self
+            .functions
+            .iter()
+            .enumerate()
+            .flat_map(|(index, function)| {
+                function.pcs.iter().map(move |(pc, count)| {
+                    let stats = PcStats {
+                        function: function.name.clone(),
+                        pc: *pc,
+                        count: *count,
+                    };
+                    (index, stats)
+                })
+            })
+            .collect();
+        pcs.sort_by(|(a_index, a), (b_index, b)| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.function.cmp(&b.function))
+                .then_with(|| a_index.cmp(b_index))
+                .then_with(|| a.pc.cmp(&b.pc))
+        });
+        pcs.truncate(limit);
+        return pcs.into_iter().map(|(_, stats)| stats).collect();
+    }
+
+    /// Format the profile as human-readable tables, listing at most `hot_spots` instructions
+    /// in the hot spots.
+    pub fn to_table(&self, hot_spots: usize) -> String {
+        let mut out = String::new();
+        let total_nanos = self.total_time.as_nanos().max(1) as f64;
+
+        writeln!(out, "Instructions executed: {}", self.instructions).unwrap();
+        writeln!(out, "Total time: {:?}", self.total_time).unwrap();
+        writeln!(out).unwrap();
+
+        writeln!(
+            out,
+            "{:<12} {:>12} {:>14} {:>8}",
+            "opcode", "count", "time", "time %"
+        )
+        .unwrap();
+        for (mnemonic, stats) in self.opcodes() {
+            writeln!(
+                out,
+                "{:<12} {:>12} {:>14} {:>7.2}%",
+                mnemonic,
+                stats.count,
+                format!("{:?}", stats.time),
+                stats.time.as_nanos() as f64 * 100.0 / total_nanos
+            )
+            .unwrap();
+        }
+        writeln!(out).unwrap();
+
+        writeln!(out, "{:<24} {:>8} {:>12}", "function", "pc", "count").unwrap();
+        for stats in self.hot_spots(hot_spots) {
+            writeln!(
+                out,
+                "{:<24} {:>8} {:>12}",
+                stats.function, stats.pc, stats.count
+            )
+            .unwrap();
+        }
+
+        return out;
+    }
+
+    /// Format the profile as a JSON object, listing at most `hot_spots` instructions in the
+    /// hot spots. The times are in nanoseconds.
+    pub fn to_json(&self, hot_spots: usize) -> String {
+        let opcodes: Vec<String> = self
+            .opcodes()
+            .iter()
+            .map(|(mnemonic, stats)| {
+                format!(
+                    "{{\"opcode\":\"{}\",\"count\":{},\"time_ns\":{}}}",
+                    mnemonic,
+                    stats.count,
+                    stats.time.as_nanos()
+                )
+            })
+            .collect();
+        let pcs: Vec<String> = self
+            .hot_spots(hot_spots)
+            .iter()
+            .map(|stats| {
+                format!(
+                    "{{\"function\":\"{}\",\"pc\":{},\"count\":{}}}",
+                    json::escape(&stats.function),
+                    stats.pc,
+                    stats.count
+                )
+            })
+            .collect();
+
+        return format!(
+            "{{\"instructions\":{},\"total_time_ns\":{},\"opcodes\":[{}],\"hot_spots\":[{}]}}",
+            self.instructions,
+            self.total_time.as_nanos(),
+            opcodes.join(","),
+            pcs.join(",")
+        );
+    }
+}
@@ -21,7 +21,9 @@ use compiler::bytecode::ConstantEntry;
 use crate::tests::util::create_vm;
 use crate::tests::util::eval_arithemetic;
 use crate::tests::util::push_constants;
+use crate::tests::util::run_both;
 use crate::tests::util::{create_constant_pool, eval_arithmetic_src};
+use crate::value::Value;
 
 #[test]
 fn test_simple_arithemetic_evaluation() {
@@ -146,3 +148,54 @@ fn test_compound_assignment_in_while_loop() {
         )
     );
 }
+
+#[test]
+fn test_folded_identities_keep_negative_zero() {
+    let cases = [
+        ("-z + 0", f64::INFINITY),
+        ("0 + -z", f64::INFINITY),
+        ("-z - -0", f64::INFINITY),
+        ("-z - 1 + 1", f64::INFINITY),
+        ("-z - 0", f64::NEG_INFINITY),
+        ("-z * 1", f64::NEG_INFINITY),
+        ("-z / 1", f64::NEG_INFINITY),
+    ];
+
+    for (expr, expected) in cases {
+        let src = format!("fun f(z) {{ return 1 / ({}); }} var r = f(0); r;", expr);
+        assert_eq!(Some(Value::Number(expected)), run_both(&src).unwrap(), "{}", expr);
+    }
+}
+
+#[test]
+fn test_folded_chains_with_string_operands() {
+    let err = run_both("fun f(s) { return s - 1 - 2; } f(\"s\");").unwrap_err();
+    assert_eq!("Cannot perform 'sub' on s and 1", err.message);
+
+    let err = run_both("fun f(s) { return s - 0; } f(\"s\");").unwrap_err();
+    assert_eq!("Cannot perform 'sub' on s and 0", err.message);
+
+    assert_eq!(
+        Some(Value::String("ss".into())),
+        run_both("fun f(s) { return s * 1 * 2; } var r = f(\"s\"); r;").unwrap()
+    );
+    assert_eq!(
+        Some(Value::String("s12".into())),
+        run_both("fun f(s) { return s + 1 + 2; } var r = f(\"s\"); r;").unwrap()
+    );
+}
+
+#[test]
+fn test_folded_chains_keep_rounding() {
+    let src = "fun f(x) { return -x + 1 + 2; } var r = f(-9007199254740992); r;";
+    assert_eq!(
+        Some(Value::Number(9007199254740994f64)),
+        run_both(src).unwrap()
+    );
+
+    let src = format!(
+        "fun f(x) {{ return -x * 10 * 0.1; }} var r = f(-1{}); r;",
+        "0".repeat(308)
+    );
+    assert_eq!(Some(Value::Number(f64::INFINITY)), run_both(&src).unwrap());
+}
@@ -0,0 +1,276 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::tests::util::eval_src;
+use crate::value::Value;
+
+#[test]
+fn test_instance_fields() {
+    assert_eq!(
+        Value::Number(3f64),
+        eval_src(
+            "class Point {}
+            var p = Point();
+            p.x = 1;
+            p.y = 2;
+            var r = p.x + p.y;
+            r;"
+        )
+    )
+}
+
+#[test]
+fn test_initializer() {
+    assert_eq!(
+        Value::Number(7f64),
+        eval_src(
+            "class Point {
+                init(x, y) {
+                    this.x = x;
+                    this.y = y;
+                }
+            }
+            var p = Point(3, 4);
+            var r = p.x + p.y;
+            r;"
+        )
+    )
+}
+
+#[test]
+fn test_method_call() {
+    assert_eq!(
+        Value::Number(25f64),
+        eval_src(
+            "class Point {
+                init(x, y) {
+                    this.x = x;
+                    this.y = y;
+                }
+                dist2() {
+                    return this.x * this.x + this.y * this.y;
+                }
+            }
+            var r = Point(3, 4).dist2();
+            r;"
+        )
+    )
+}
+
+#[test]
+fn test_bound_method() {
+    assert_eq!(
+        Value::Number(2f64),
+        eval_src(
+            "class Counter {
+                init() {
+                    this.count = 0;
+                }
+                inc() {
+                    this.count = this.count + 1;
+                    return this.count;
+                }
+            }
+            var c = Counter();
+            var inc = c.inc;
+            inc();
+            var r = inc();
+            r;"
+        )
+    )
+}
+
+#[test]
+fn test_compound_assign_field() {
+    assert_eq!(
+        Value::Number(15f64),
+        eval_src(
+            "class Box {}
+            var b = Box();
+            b.v = 5;
+            b.v += 10;
+            var r = b.v;
+            r;"
+        )
+    )
+}
+
+#[test]
+fn test_init_returns_this() {
+    assert_eq!(
+        Value::Number(1f64),
+        eval_src(
+            "class A {
+                init(v) {
+                    this.v = v;
+                    if v > 0 {
+                        return;
+                    }
+                    this.v = 0;
+                }
+            }
+            var a = A(1);
+            var r = a.init(1).v;
+            r;"
+        )
+    )
+}
+
+#[test]
+fn test_inherited_method() {
+    assert_eq!(
+        Value::Number(2f64),
+        eval_src(
+            "class A {
+                value() {
+                    return 2;
+                }
+            }
+            class B : A {}
+            var r = B().value();
+            r;"
+        )
+    )
+}
+
+#[test]
+fn test_overridden_method() {
+    assert_eq!(
+        Value::Number(3f64),
+        eval_src(
+            "class A {
+                value() {
+                    return 2;
+                }
+            }
+            class B : A {
+                value() {
+                    return 3;
+                }
+            }
+            var r = B().value();
+            r;"
+        )
+    )
+}
+
+#[test]
+fn test_super_method_call() {
+    assert_eq!(
+        Value::Number(30f64),
+        eval_src(
+            "class A {
+                init(x) {
+                    this.x = x;
+                }
+                value() {
+                    return this.x;
+                }
+            }
+            class B : A {
+                init(x) {
+                    super.init(x * 2);
+                }
+                value() {
+                    return super.value() * 3;
+                }
+            }
+            var r = B(5).value();
+            r;"
+        )
+    )
+}
+
+#[test]
+fn test_super_in_multilevel_inheritance() {
+    assert_eq!(
+        Value::Number(6f64),
+        eval_src(
+            "class A {
+                value() {
+                    return 1;
+                }
+            }
+            class B : A {
+                value() {
+                    return super.value() + 2;
+                }
+            }
+            class C : B {
+                value() {
+                    return super.value() + 3;
+                }
+            }
+            var r = C().value();
+            r;"
+        )
+    )
+}
+
+#[test]
+fn test_this_captured_by_lambda() {
+    assert_eq!(
+        Value::Number(5f64),
+        eval_src(
+            "class A {
+                init() {
+                    this.v = 5;
+                }
+                getter() {
+                    return fun() { return this.v; };
+                }
+            }
+            var get = A().getter();
+            var r = get();
+            r;"
+        )
+    )
+}
+
+#[test]
+fn test_class_in_func() {
+    assert_eq!(
+        Value::Number(11f64),
+        eval_src(
+            "fun make(base) {
+                class Adder {
+                    add(x) {
+                        return base + x;
+                    }
+                }
+                return Adder();
+            }
+            var r = make(10).add(1);
+            r;"
+        )
+    )
+}
+
+#[test]
+fn test_methods_access_globals() {
+    assert_eq!(
+        Value::Number(42f64),
+        eval_src(
+            "class A {
+                get() {
+                    return answer;
+                }
+            }
+            var answer = 42;
+            var r = A().get();
+            r;"
+        )
+    )
+}
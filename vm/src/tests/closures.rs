@@ -0,0 +1,209 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::tests::util::eval_src;
+use crate::value::Value;
+
+#[test]
+fn test_lambda_in_var() {
+    assert_eq!(
+        Value::Number(3f64),
+        eval_src("var add = fun(a, b) { return a + b; }; var r = add(1, 2); r;")
+    )
+}
+
+#[test]
+fn test_lambda_in_array() {
+    assert_eq!(
+        Value::Number(8f64),
+        eval_src("var fs = [fun(x) { return x * 2; }]; var r = fs[0](4); r;")
+    )
+}
+
+#[test]
+fn test_func_as_argument() {
+    assert_eq!(
+        Value::Number(9f64),
+        eval_src(
+            "fun apply(f, x) { return f(x); }
+            fun sq(x) { return x * x; }
+            var r = apply(sq, 3);
+            r;"
+        )
+    )
+}
+
+#[test]
+fn test_counter_closure() {
+    assert_eq!(
+        Value::Number(4f64),
+        eval_src(
+            "fun counter() {
+                var count = 0;
+                fun inc() {
+                    count = count + 1;
+                    return count;
+                }
+                return inc;
+            }
+            var a = counter();
+            var b = counter();
+            a();
+            a();
+            var r = a() + b();
+            r;"
+        )
+    )
+}
+
+#[test]
+fn test_closures_share_captured_var() {
+    assert_eq!(
+        Value::Number(7f64),
+        eval_src(
+            "fun pair() {
+                var v = 0;
+                var set = fun(x) { v = x; };
+                var get = fun() { return v; };
+                set(7);
+                return get();
+            }
+            var r = pair();
+            r;"
+        )
+    )
+}
+
+#[test]
+fn test_captured_param() {
+    assert_eq!(
+        Value::Number(8f64),
+        eval_src(
+            "fun adder(n) { return fun(x) { return x + n; }; }
+            var add5 = adder(5);
+            var r = add5(3);
+            r;"
+        )
+    )
+}
+
+#[test]
+fn test_transitive_capture() {
+    assert_eq!(
+        Value::Number(11f64),
+        eval_src(
+            "fun outer() {
+                var x = 1;
+                fun middle() {
+                    fun inner() {
+                        x = x + 10;
+                        return x;
+                    }
+                    return inner;
+                }
+                return middle();
+            }
+            var r = outer()();
+            r;"
+        )
+    )
+}
+
+#[test]
+fn test_recursive_nested_func() {
+    assert_eq!(
+        Value::Number(120f64),
+        eval_src(
+            "fun f() {
+                fun fact(n) {
+                    if n < 2 {
+                        return 1;
+                    }
+                    return n * fact(n - 1);
+                }
+                return fact(5);
+            }
+            var r = f();
+            r;"
+        )
+    )
+}
+
+#[test]
+fn test_capture_per_iteration() {
+    assert_eq!(
+        Value::Number(2f64),
+        eval_src(
+            "fun make() {
+                var fs = [0, 0, 0];
+                for (var i = 0; i < 3; i = i + 1) {
+                    var j = i;
+                    fs[i] = fun() { return j; };
+                }
+                return fs;
+            }
+            var fs = make();
+            var r = fs[0]() + fs[2]();
+            r;"
+        )
+    )
+}
+
+#[test]
+fn test_capture_indexed_read() {
+    assert_eq!(
+        Value::Number(1f64),
+        eval_src(
+            "fun mk(n) {
+                var c = [n];
+                return fun () { return c[0]; };
+            }
+            var r = mk(1)();
+            r;"
+        )
+    )
+}
+
+#[test]
+fn test_capture_indexed_write() {
+    assert_eq!(
+        Value::Number(9f64),
+        eval_src(
+            "fun mk(n) {
+                var c = [n];
+                fun set() { c[0] = 9; }
+                set();
+                return c[0];
+            }
+            var r = mk(1);
+            r;"
+        )
+    )
+}
+
+#[test]
+fn test_capture_nested_index() {
+    assert_eq!(
+        Value::Number(2f64),
+        eval_src(
+            "fun mk(n) {
+                var c = [n];
+                return fun () { return [1, 2][c[0]]; };
+            }
+            var r = mk(1)();
+            r;"
+        )
+    )
+}
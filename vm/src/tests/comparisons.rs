@@ -0,0 +1,164 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::error::RuntimeErrorKind;
+use crate::tests::util::eval_both;
+use crate::tests::util::eval_src;
+use crate::tests::util::run_both;
+use crate::value::Value;
+
+#[test]
+fn test_equality() {
+    let cases = [
+        ("\"a\" == \"a\"", true),
+        ("\"a\" == \"b\"", false),
+        ("\"a\" != \"b\"", true),
+        ("true == false", false),
+        ("true == true", true),
+        ("false != true", true),
+        ("null == null", true),
+        ("null != null", false),
+        ("1 == 1", true),
+        ("1 == \"1\"", false),
+        ("1 != \"1\"", true),
+        ("0 == false", false),
+        ("0 == null", false),
+        ("true == 1", false),
+        ("true != 1", true),
+        ("false == null", false),
+        ("\"\" == null", false),
+    ];
+
+    for (src, expected) in cases {
+        assert_eq!(Value::Bool(expected), eval_both(src), "{}", src);
+    }
+}
+
+#[test]
+fn test_string_ordering() {
+    let cases = [
+        ("\"a\" < \"b\"", true),
+        ("\"b\" < \"a\"", false),
+        ("\"a\" < \"a!\"", true),
+        ("\"a!\" <= \"a\"", false),
+        ("\"abc\" >= \"abc\"", true),
+        ("\"B\" > \"a\"", false),
+        ("\"\" < \"a\"", true),
+    ];
+
+    for (src, expected) in cases {
+        assert_eq!(Value::Bool(expected), eval_both(src), "{}", src);
+    }
+}
+
+#[test]
+fn test_variable_equality() {
+    assert_eq!(
+        Value::Bool(true),
+        eval_src("var x = null; var r = x == null; r;")
+    );
+    assert_eq!(
+        Value::Bool(false),
+        eval_src("var x = \"a\"; var r = x == null; r;")
+    );
+    assert_eq!(
+        Value::Bool(true),
+        eval_src("var x = \"a\"; var y = \"a\"; var r = x == y; r;")
+    );
+    assert_eq!(
+        Value::Bool(true),
+        eval_src("var x = \"a\"; var r = x != 0; r;")
+    );
+    assert_eq!(
+        Value::Bool(true),
+        eval_src("var x = true; var r = 1; if x == true { r = 2; } r == 2;")
+    );
+}
+
+#[test]
+fn test_reference_equality() {
+    assert_eq!(
+        Value::Bool(true),
+        eval_src("var a = [1]; var b = a; var r = a == b; r;")
+    );
+    assert_eq!(
+        Value::Bool(false),
+        eval_src("var a = [1]; var b = [1]; var r = a == b; r;")
+    );
+    assert_eq!(
+        Value::Bool(true),
+        eval_src("class A {} var a = A(); var r = a != A(); r;")
+    );
+}
+
+#[test]
+fn test_ordering_incomparable_types() {
+    let cases = [
+        "true < false",
+        "1 < \"1\"",
+        "\"a\" >= 0",
+        "null > null",
+        "null <= 1",
+    ];
+
+    for src in cases {
+        let err = run_both(&format!("var r = {};", src)).expect_err(src);
+        assert_eq!(RuntimeErrorKind::TypeError, err.kind, "{}", src);
+    }
+
+    let err = run_both("var a = [1]; var r = a < 0;").unwrap_err();
+    assert_eq!(RuntimeErrorKind::TypeError, err.kind);
+    assert_eq!(
+        "Cannot compare ObjArray{ length: 1, elements: [1] } with 0",
+        err.message
+    );
+}
+
+#[test]
+fn test_incomparable_operands_reported_in_order() {
+    let cases = [
+        ("1 < \"a\"", "Cannot compare 1 with a"),
+        ("0 < \"a\"", "Cannot compare 0 with a"),
+        ("\"a\" >= 0", "Cannot compare a with 0"),
+    ];
+
+    for (src, expected) in cases {
+        let err = run_both(&format!("var r = {};", src)).expect_err(src);
+        assert_eq!(expected, err.message, "{}", src);
+    }
+}
+
+#[test]
+fn test_nan_ordering() {
+    let cases = [
+        ("0/0 > 0", false),
+        ("0/0 < 1", false),
+        ("0/0 >= 0", false),
+        ("0/0 <= 1", false),
+        ("0 < 0/0", false),
+        ("1 > 0/0", false),
+        ("0/0 == 0/0", false),
+        ("0/0 != 0/0", true),
+        ("0/0 != 0", true),
+    ];
+
+    for (src, expected) in cases {
+        assert_eq!(Value::Bool(expected), eval_both(src), "{}", src);
+    }
+
+    let src = "fun f(x) { var r = 0; if x > 0 { r = 1; } if x <= 0 { r = r + 2; } \
+               while x < 1 { r = r + 4; x = 1; } return r; } var r = f(0/0); r;";
+    assert_eq!(Some(Value::Number(0f64)), run_both(src).unwrap(), "{}", src);
+}
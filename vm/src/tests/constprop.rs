@@ -0,0 +1,61 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use compiler::features::CompilerFeatures;
+
+use crate::tests::util::eval_toggled;
+use crate::value::Value;
+
+/// Evaluate the source with and without the constant propagation and check that both produce
+/// the same result.
+fn eval_constprop(src: &str) -> Value {
+    return eval_toggled(src, &CompilerFeatures::default(), |features| {
+        features.const_propagation = false
+    });
+}
+
+#[test]
+fn test_propagated_arithmetic() {
+    assert_eq!(
+        Value::Number(26f64),
+        eval_constprop("var n = 10; var m = n * 2; m + n - 4;")
+    );
+}
+
+#[test]
+fn test_reassigned_in_loop() {
+    assert_eq!(
+        Value::Number(10f64),
+        eval_constprop("var step = 2; var i = 0; while i < 10 { i = i + step; } i;")
+    );
+}
+
+#[test]
+fn test_captured_constant() {
+    assert_eq!(
+        Value::Number(6f64),
+        eval_constprop(
+            "fun make() { var base = 5; return fun(x) { return base + x; }; } var f = make(); var r = f(1); r;"
+        )
+    );
+}
+
+#[test]
+fn test_global_read_by_function() {
+    assert_eq!(
+        Value::Number(3f64),
+        eval_constprop("fun f() { return n + 1; } var n = 2; var r = f(); r;")
+    );
+}
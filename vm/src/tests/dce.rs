@@ -0,0 +1,79 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use compiler::features::CompilerFeatures;
+
+use crate::tests::util::eval_toggled;
+use crate::value::Value;
+
+/// Evaluate the source with and without the dead code elimination and check that both produce
+/// the same result.
+fn eval_dce(src: &str) -> Value {
+    let mut features = CompilerFeatures::default();
+    features.const_folding = false;
+    return eval_toggled(src, &features, |features| features.dead_code_elim = false);
+}
+
+#[test]
+fn test_stmts_after_break() {
+    assert_eq!(
+        Value::Number(1f64),
+        eval_dce("var a = 0; while true { a = a + 1; break; a = 10; } a;")
+    );
+}
+
+#[test]
+fn test_stmts_after_continue() {
+    assert_eq!(
+        Value::Number(3f64),
+        eval_dce(
+            "var a = 0; var b = 0; while a < 3 { a = a + 1; if a > 0 { continue; b = 1; } b = 2; } a + b;"
+        )
+    );
+}
+
+#[test]
+fn test_returning_branches() {
+    assert_eq!(
+        Value::Number(3f64),
+        eval_dce("fun f(a) { if a { return 1; } else { return 2; } print a; } f(true) + f(false);")
+    );
+}
+
+#[test]
+fn test_while_false() {
+    assert_eq!(
+        Value::Number(1f64),
+        eval_dce("var a = 1; while false { a = 2; } a;")
+    );
+}
+
+#[test]
+fn test_for_false_runs_initializer() {
+    assert_eq!(
+        Value::Number(5f64),
+        eval_dce("var a = 1; for (a = 5; false; a = a + 1) { a = 10; } a;")
+    );
+}
+
+#[test]
+fn test_labeled_loops() {
+    assert_eq!(
+        Value::Number(6f64),
+        eval_dce(
+            "var n = 0; outer: for (var i = 0; i < 3; i = i + 1) { for (var j = 0; j < 3; j = j + 1) { if j > i { continue outer; n = 100; } n = n + 1; } } n;"
+        )
+    );
+}
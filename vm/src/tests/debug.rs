@@ -0,0 +1,177 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::collections::VecDeque;
+
+use compiler::features::CompilerFeatures;
+
+use crate::debug::Breakpoint;
+use crate::debug::DebugAction;
+use crate::debug::DebugHook;
+use crate::debug::PausedState;
+use crate::error::RuntimeErrorKind;
+use crate::tests::util::compile_src;
+use crate::value::Value;
+use crate::YKVM;
+
+/// The state of the program recorded when it was paused.
+#[derive(Debug, Clone, PartialEq)]
+struct Pause {
+    function: String,
+    pc: usize,
+    line: Option<u32>,
+    depth: usize,
+    operands: Vec<Value>,
+    locals: Vec<Value>,
+}
+
+/// A debugger which resumes the program with the given actions and records each pause.
+struct ScriptedDebugger {
+    breakpoints: Vec<Breakpoint>,
+    actions: VecDeque<DebugAction>,
+    pauses: Vec<Pause>,
+}
+
+impl ScriptedDebugger {
+    fn new(breakpoints: Vec<Breakpoint>, actions: Vec<DebugAction>) -> ScriptedDebugger {
+        return ScriptedDebugger {
+            breakpoints,
+            actions: VecDeque::from(actions),
+            pauses: Vec::new(),
+        };
+    }
+}
+
+impl DebugHook for ScriptedDebugger {
+    fn breakpoints(&self) -> &[Breakpoint] {
+        return &self.breakpoints;
+    }
+
+    fn on_pause(&mut self, state: &PausedState) -> DebugAction {
+        self.pauses.push(Pause {
+            function: state.function.to_string(),
+            pc: state.pc,
+            line: state.line,
+            depth: state.depth,
+            operands: state.operands.to_vec(),
+            locals: state.locals.to_vec(),
+        });
+        return self.actions.pop_front().unwrap_or(DebugAction::Continue);
+    }
+}
+
+fn debug(src: &str, debugger: &mut ScriptedDebugger) -> Result<Option<Value>, crate::RuntimeError> {
+    let mut vm = YKVM::new();
+    let mut features = CompilerFeatures::default();
+    features.const_folding = false;
+    let file = compile_src(&vm, src, &features);
+    vm.debug(&file, debugger)
+}
+
+#[test]
+fn test_pause_at_start() {
+    let mut debugger = ScriptedDebugger::new(vec![], vec![DebugAction::Continue]);
+    let result = debug("var a = 1; a;", &mut debugger).unwrap();
+
+    assert_eq!(Some(Value::Number(1f64)), result);
+    assert_eq!(1, debugger.pauses.len());
+    assert_eq!(YKVM::TOP_LEVEL_NAME, debugger.pauses[0].function);
+    assert_eq!(0, debugger.pauses[0].pc);
+    assert_eq!(Some(1), debugger.pauses[0].line);
+}
+
+#[test]
+fn test_single_step() {
+    let mut debugger = ScriptedDebugger::new(
+        vec![],
+        vec![DebugAction::Step, DebugAction::Step, DebugAction::Continue],
+    );
+    debug("var a = 1; a;", &mut debugger).unwrap();
+
+    // ldc #1; store_0; load_0
+    let pcs: Vec<usize> = debugger.pauses.iter().map(|pause| pause.pc).collect();
+    assert_eq!(vec![0, 3, 4], pcs);
+    assert_eq!(vec![Value::Number(1f64)], debugger.pauses[1].operands);
+    assert_eq!(vec![Value::Number(1f64)], debugger.pauses[2].locals);
+    assert!(debugger.pauses[2].operands.is_empty());
+}
+
+#[test]
+fn test_line_breakpoints() {
+    let mut debugger = ScriptedDebugger::new(
+        vec![Breakpoint::Line(3)],
+        vec![
+            DebugAction::Continue,
+            DebugAction::Continue,
+            DebugAction::Continue,
+        ],
+    );
+    let result = debug(
+        "var sum = 0;
+        for (var i = 0; i < 2; i = i + 1) {
+            sum = sum + i;
+        }
+        sum;",
+        &mut debugger,
+    )
+    .unwrap();
+
+    assert_eq!(Some(Value::Number(1f64)), result);
+
+    // paused at the start and then once per iteration
+    let lines: Vec<Option<u32>> = debugger.pauses.iter().map(|pause| pause.line).collect();
+    assert_eq!(vec![Some(1), Some(3), Some(3)], lines);
+}
+
+#[test]
+fn test_pc_breakpoint_in_function() {
+    let mut debugger = ScriptedDebugger::new(
+        vec![Breakpoint::Pc {
+            function: "f".to_string(),
+            pc: 0,
+        }],
+        vec![DebugAction::Continue, DebugAction::Continue],
+    );
+    debug("fun f(a) { return a; } f(1); f(2);", &mut debugger).unwrap();
+
+    assert_eq!(3, debugger.pauses.len());
+    assert_eq!("f", debugger.pauses[1].function);
+    assert_eq!(2, debugger.pauses[1].depth);
+    assert_eq!(vec![Value::Number(1f64)], debugger.pauses[1].locals);
+    assert_eq!(vec![Value::Number(2f64)], debugger.pauses[2].locals);
+}
+
+#[test]
+fn test_step_over_call() {
+    let src = "fun f(a) { return a; } var r = f(1); r;";
+
+    let mut stepping = ScriptedDebugger::new(vec![], vec![DebugAction::Step; 16]);
+    debug(src, &mut stepping).unwrap();
+    assert!(stepping.pauses.iter().any(|pause| pause.function == "f"));
+
+    let mut stepping_over = ScriptedDebugger::new(vec![], vec![DebugAction::StepOver; 16]);
+    let result = debug(src, &mut stepping_over).unwrap();
+    assert_eq!(Some(Value::Number(1f64)), result);
+    assert!(stepping_over.pauses.iter().all(|pause| pause.depth == 1));
+    assert!(stepping_over.pauses.len() < stepping.pauses.len());
+}
+
+#[test]
+fn test_quit() {
+    let mut debugger = ScriptedDebugger::new(vec![], vec![DebugAction::Step, DebugAction::Quit]);
+    let err = debug("var a = 1; print a;", &mut debugger).unwrap_err();
+    assert_eq!(RuntimeErrorKind::Interrupted, err.kind);
+    assert_eq!(3, err.pc);
+}
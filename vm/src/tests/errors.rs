@@ -0,0 +1,156 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::error::RuntimeErrorKind;
+use crate::tests::util::eval_src_err;
+
+#[test]
+fn test_arithmetic_on_non_numbers() {
+    let err = eval_src_err("var a = true; print a - 1;");
+    assert_eq!(RuntimeErrorKind::TypeError, err.kind);
+    assert_eq!("Cannot perform 'sub' on true and 1", err.message);
+}
+
+#[test]
+fn test_array_index_out_of_bounds() {
+    let err = eval_src_err("var a = [1, 2]; print a[2];");
+    assert_eq!(RuntimeErrorKind::IndexOutOfBounds, err.kind);
+    assert_eq!("Invalid array index: 2 length: 2", err.message);
+}
+
+#[test]
+fn test_array_store_out_of_bounds() {
+    let err = eval_src_err("var a = [1, 2]; a[-1] = 3;");
+    assert_eq!(RuntimeErrorKind::IndexOutOfBounds, err.kind);
+}
+
+#[test]
+fn test_index_non_array() {
+    let err = eval_src_err("var a = 1; print a[0];");
+    assert_eq!(RuntimeErrorKind::TypeError, err.kind);
+    assert_eq!("Expected an array, found 1", err.message);
+}
+
+#[test]
+fn test_undefined_property() {
+    let err = eval_src_err("class A {} var a = A(); print a.x;");
+    assert_eq!(RuntimeErrorKind::UndefinedProperty, err.kind);
+    assert_eq!("Undefined property 'x'", err.message);
+}
+
+#[test]
+fn test_property_of_non_instance() {
+    let err = eval_src_err("var a = 1; print a.x;");
+    assert_eq!(RuntimeErrorKind::TypeError, err.kind);
+}
+
+#[test]
+fn test_call_depth_exceeded() {
+    let err = eval_src_err("fun f(n) { return f(n + 1); } f(0);");
+    assert_eq!(RuntimeErrorKind::StackOverflow, err.kind);
+    assert_eq!("Maximum call depth of 1024 exceeded", err.message);
+}
+
+#[test]
+fn test_recursive_frames_collapsed() {
+    let err = eval_src_err("fun f(n) { return f(n + 1); } f(0);");
+    assert_eq!(1024, err.stack_trace.len());
+
+    let display = format!("{}", err);
+    let lines: Vec<&str> = display.lines().collect();
+    assert_eq!(4, lines.len());
+    assert!(lines[1].starts_with("    at f (line: 1, pc: "));
+    assert_eq!("    ... previous frame repeated 1022 more times", lines[2]);
+    assert!(lines[3].starts_with("    at <script> (line: 1, pc: "));
+}
+
+#[test]
+fn test_long_stack_trace_truncated() {
+    let err = eval_src_err(
+        "fun a(n) { return b(n + 1); }
+        fun b(n) { return a(n + 1); }
+        a(0);",
+    );
+    assert_eq!(RuntimeErrorKind::StackOverflow, err.kind);
+
+    assert_eq!(1024, err.stack_trace.len());
+
+    let display = format!("{}", err);
+    let lines: Vec<&str> = display.lines().collect();
+    assert_eq!(22, lines.len());
+    assert_eq!("    ... 1004 more frames", lines[11]);
+    assert!(lines[21].starts_with("    at <script> (line: 3, pc: "));
+}
+
+#[test]
+fn test_stack_trace() {
+    let err = eval_src_err(
+        "fun inner(a) { return a + true; }
+        fun outer(a) { return inner(a) * 2; }
+        print outer(1);",
+    );
+    assert_eq!(RuntimeErrorKind::TypeError, err.kind);
+
+    let functions: Vec<&str> = err
+        .stack_trace
+        .iter()
+        .map(|element| element.function.as_str())
+        .collect();
+    assert_eq!(vec!["inner", "outer", "<script>"], functions);
+    assert_eq!(err.pc, err.stack_trace[0].pc);
+}
+
+#[test]
+fn test_display() {
+    let err = eval_src_err("fun f() { return [][0]; } f();");
+    let display = format!("{}", err);
+    assert!(display.starts_with("IndexOutOfBounds: Invalid array index: 0 length: 0"));
+    assert!(display.contains("\n    at f (line: 1, pc: "));
+    assert!(display.contains("\n    at <script> (line: 1, pc: "));
+}
+
+#[test]
+fn test_stack_trace_lines() {
+    let err = eval_src_err(
+        "fun inner(a) {
+            return a - true;
+        }
+        fun outer(a) {
+            return inner(a);
+        }
+        outer(1);",
+    );
+
+    let lines: Vec<Option<u32>> = err
+        .stack_trace
+        .iter()
+        .map(|element| element.line)
+        .collect();
+    assert_eq!(vec![Some(2), Some(5), Some(7)], lines);
+}
+
+#[test]
+fn test_vm_reusable_after_error() {
+    use crate::tests::util::run_src_with;
+    use crate::value::Value;
+    use crate::YKVM;
+
+    let mut vm = YKVM::new();
+    assert!(run_src_with(&mut vm, "print [][1];").is_err());
+    assert_eq!(
+        Some(Value::Number(2f64)),
+        run_src_with(&mut vm, "var a = 2; a;").unwrap()
+    );
+}
@@ -0,0 +1,148 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::error::RuntimeErrorKind;
+use crate::tests::util::eval_src;
+use crate::tests::util::eval_src_err;
+use crate::value::Value;
+
+#[test]
+fn test_simple_func_call() {
+    assert_eq!(
+        Value::Number(3f64),
+        eval_src("fun add(a, b) { return a + b; } var r = add(1, 2); r;")
+    )
+}
+
+#[test]
+fn test_func_call_in_expr() {
+    assert_eq!(
+        Value::Number(10f64),
+        eval_src("fun sq(a) { return a * a; } var r = sq(3) + 1; r;")
+    )
+}
+
+#[test]
+fn test_func_call_before_decl() {
+    assert_eq!(
+        Value::Number(6f64),
+        eval_src("var r = twice(3); fun twice(a) { return a * 2; } r;")
+    )
+}
+
+#[test]
+fn test_recursive_func() {
+    assert_eq!(
+        Value::Number(55f64),
+        eval_src(
+            "fun fib(n) {
+                if n < 2 {
+                    return n;
+                }
+                return fib(n - 1) + fib(n - 2);
+            }
+            var r = fib(10);
+            r;"
+        )
+    )
+}
+
+#[test]
+fn test_func_without_return() {
+    assert_eq!(
+        Value::Null,
+        eval_src("fun noop() { var a = 1; } var r = noop(); r;")
+    )
+}
+
+#[test]
+fn test_empty_return() {
+    assert_eq!(
+        Value::Null,
+        eval_src("fun f(a) { if a { return; } return 1; } var r = f(true); r;")
+    )
+}
+
+#[test]
+fn test_func_access_globals() {
+    assert_eq!(
+        Value::Number(3f64),
+        eval_src(
+            "var count = 0;
+            fun incr() { count = count + 1; }
+            incr();
+            incr();
+            incr();
+            count;"
+        )
+    )
+}
+
+#[test]
+fn test_func_locals_shadow_globals() {
+    assert_eq!(
+        Value::Number(1f64),
+        eval_src(
+            "var a = 1;
+            fun f(a) { a = a + 10; return a; }
+            f(5);
+            a;"
+        )
+    )
+}
+
+#[test]
+fn test_func_call_in_loop() {
+    assert_eq!(
+        Value::Number(110f64),
+        eval_src(
+            "fun double(a) { return a * 2; }
+            var sum = 0;
+            for (var i = 1; i <= 10; i = i + 1) {
+                sum = sum + double(i);
+            }
+            sum;"
+        )
+    )
+}
+
+#[test]
+fn test_nested_func_decl() {
+    assert_eq!(
+        Value::Number(7f64),
+        eval_src(
+            "fun outer(a) {
+                fun inner(b) { return b + 1; }
+                return inner(a) + 1;
+            }
+            var r = outer(5);
+            r;"
+        )
+    )
+}
+
+#[test]
+fn test_func_call_arity_mismatch() {
+    let err = eval_src_err("fun add(a, b) { return a + b; } add(1);");
+    assert_eq!(RuntimeErrorKind::ArityMismatch, err.kind);
+    assert_eq!("Function 'add' expects 2 arguments, but got 1", err.message);
+}
+
+#[test]
+fn test_call_non_func() {
+    let err = eval_src_err("var a = 1; a();");
+    assert_eq!(RuntimeErrorKind::TypeError, err.kind);
+    assert_eq!("Attempt to call a non-function value: 1", err.message);
+}
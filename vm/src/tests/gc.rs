@@ -0,0 +1,138 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::tests::util::eval_src_with;
+use crate::value::Value;
+use crate::YKVM;
+
+fn create_vm<'a>() -> YKVM<'a> {
+    let mut vm = YKVM::new();
+    // collect as often as possible
+    vm.set_gc_threshold(0);
+    vm
+}
+
+#[test]
+fn test_garbage_is_collected() {
+    let mut vm = YKVM::new();
+    vm.set_gc_threshold(64 * 1024);
+    assert_eq!(
+        Value::Number(2000f64),
+        eval_src_with(
+            &mut vm,
+            "var n = 0;
+            while n < 2000 {
+                var arr = [n, n, n, n, n, n, n, n];
+                n = n + 1;
+            }
+            n;"
+        )
+    );
+
+    let stats = vm.heap_stats();
+    assert!(stats.collections > 0);
+    assert!(stats.max_bytes_allocated < 2 * 64 * 1024 + 1024);
+    assert_eq!(0, stats.bytes_allocated);
+    assert_eq!(0, stats.objects);
+}
+
+#[test]
+fn test_no_collection_below_threshold() {
+    let mut vm = YKVM::new();
+    eval_src_with(&mut vm, "var arr = [1, 2, 3]; arr[0];");
+    assert_eq!(0, vm.heap_stats().collections);
+    assert!(vm.heap_stats().max_bytes_allocated > 0);
+}
+
+#[test]
+fn test_reachable_arrays_survive() {
+    let mut vm = create_vm();
+    assert_eq!(
+        Value::Number(45f64),
+        eval_src_with(
+            &mut vm,
+            "var arrs = [[0], [0], [0], [0], [0], [0], [0], [0], [0], [0]];
+            for (var i = 0; i < 10; i = i + 1) {
+                var tmp = [i, i];
+                arrs[i] = [tmp[0]];
+            }
+            var sum = 0;
+            for (var j = 0; j < 10; j = j + 1) {
+                var arr = arrs[j];
+                sum = sum + arr[0];
+            }
+            sum;"
+        )
+    );
+    assert!(vm.heap_stats().collections > 0);
+}
+
+#[test]
+fn test_reachable_closures_survive() {
+    let mut vm = create_vm();
+    assert_eq!(
+        Value::Number(3f64),
+        eval_src_with(
+            &mut vm,
+            "fun counter() {
+                var count = 0;
+                return fun() {
+                    count = count + 1;
+                    return count;
+                };
+            }
+            var c = counter();
+            var garbage = [1, 2, 3];
+            garbage = [4, 5, 6];
+            c();
+            c();
+            var r = c();
+            r;"
+        )
+    );
+    assert!(vm.heap_stats().collections > 0);
+}
+
+#[test]
+fn test_reachable_instances_survive() {
+    let mut vm = create_vm();
+    assert_eq!(
+        Value::Number(12f64),
+        eval_src_with(
+            &mut vm,
+            "class Box {
+                init(value) {
+                    this.value = [value];
+                }
+                get() {
+                    return this.value[0];
+                }
+            }
+            class Twice : Box {
+                get() {
+                    return super.get() * 2;
+                }
+            }
+            var boxes = [Box(1), Twice(2), Box(3)];
+            for (var i = 0; i < 3; i = i + 1) {
+                var garbage = Box(i);
+            }
+            var get = boxes[1].get;
+            var r = boxes[0].get() + get() + boxes[2].get() * 2 + 1;
+            r;"
+        )
+    );
+    assert!(vm.heap_stats().collections > 0);
+}
@@ -0,0 +1,112 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+use std::cell::Cell;
+
+use compiler::features::CompilerFeatures;
+
+use crate::error::RuntimeError;
+use crate::native::NativeContext;
+use crate::tests::util::eval_toggled;
+use crate::tests::util::run_src_with_features;
+use crate::value::Value;
+use crate::YKVM;
+
+/// Evaluate the source with and without the loop-invariant code motion and check that both
+/// produce the same result.
+fn eval_licm(src: &str) -> Value {
+    let mut features = CompilerFeatures::default();
+    features.const_propagation = false;
+    features.licm = true;
+    return eval_toggled(src, &features, |features| features.licm = false);
+}
+
+#[test]
+fn test_hoisted_arithmetic() {
+    assert_eq!(
+        Value::Number(30f64),
+        eval_licm("var n = 5; var s = 0; for (var i = 0; i < 3; i = i + 1) { s = s + n * 2; } s;")
+    );
+}
+
+#[test]
+fn test_hoisted_condition() {
+    assert_eq!(
+        Value::Number(10f64),
+        eval_licm("var n = 5; var i = 0; while (i < n * 2) { i = i + 1; } i;")
+    );
+}
+
+#[test]
+fn test_loop_not_entered() {
+    assert_eq!(
+        Value::Number(0f64),
+        eval_licm("var n = \"a\"; var i = 0; while (i > 0) { i = n * 2; } i;")
+    );
+}
+
+#[test]
+fn test_written_by_call() {
+    assert_eq!(
+        Value::Number(12f64),
+        eval_licm(
+            "var n = 1; fun g() { n = n + 1; } var s = 0; for (var i = 0; i < 3; i = i + 1) { s = s + n * 2; g(); } s;"
+        )
+    );
+}
+
+#[test]
+fn test_labeled_continue() {
+    assert_eq!(
+        Value::Number(18f64),
+        eval_licm(
+            "var n = 3; var s = 0; outer: for (var i = 0; i < n; i = i + 1) { var j = 0; while (j < n) { s = s + n * 2; j = j + 1; if (j == 1) { continue outer; } } } s;"
+        )
+    );
+}
+
+thread_local! {
+    static MARKS: Cell<u32> = const { Cell::new(0) };
+}
+
+fn mark(_ctx: &mut NativeContext, _args: &[Value]) -> Result<Value, RuntimeError> {
+    MARKS.with(|marks| marks.set(marks.get() + 1));
+    return Ok(Value::Null);
+}
+
+#[test]
+fn test_error_raised_in_place() {
+    let mut features = CompilerFeatures::default();
+    features.licm = true;
+
+    let mut vm = YKVM::new();
+    vm.register_native("mark", 0, mark);
+    let err = run_src_with_features(
+        &mut vm,
+        "var s = \"a\";
+        var r = 0;
+        var i = 0;
+        while i < 1 {
+            mark();
+            r = s - 1;
+            i = i + 1;
+        }",
+        &features,
+    )
+    .expect_err("Expected a runtime error");
+
+    // the statements before the failing expression are executed first
+    assert_eq!(1, MARKS.with(|marks| marks.get()));
+    assert_eq!(Some(6), err.stack_trace[0].line);
+}
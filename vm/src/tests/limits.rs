@@ -0,0 +1,210 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+use compiler::bytecode::attrs::Code;
+use compiler::bytecode::cp_info::NumberInfo;
+use compiler::bytecode::opcode::OpCode;
+use compiler::bytecode::opcode::OpSize;
+use compiler::bytecode::ConstantEntry;
+
+use crate::error::RuntimeErrorKind;
+use crate::limits::VmLimits;
+use crate::tests::util::create_constant_pool;
+use crate::tests::util::push_constants;
+use crate::tests::util::run_src_with;
+use crate::value::Value;
+use crate::YKVM;
+
+fn create_vm<'a>(limits: VmLimits) -> YKVM<'a> {
+    let mut vm = YKVM::new();
+    vm.set_limits(limits);
+    vm
+}
+
+#[test]
+fn test_instruction_limit() {
+    let mut vm = create_vm(VmLimits {
+        max_instructions: Some(1000),
+        ..VmLimits::default()
+    });
+
+    let err = run_src_with(&mut vm, "while true {}").unwrap_err();
+    assert_eq!(RuntimeErrorKind::InstructionLimitExceeded, err.kind);
+    assert_eq!("Maximum instruction count of 1000 exceeded", err.message);
+    assert_eq!(1, err.stack_trace.len());
+}
+
+#[test]
+fn test_instruction_limit_not_exceeded() {
+    let mut vm = create_vm(VmLimits {
+        max_instructions: Some(1000),
+        ..VmLimits::default()
+    });
+
+    let result = run_src_with(&mut vm, "var a = 0; while a < 10 { a = a + 1; } a;");
+    assert_eq!(Some(Value::Number(10f64)), result.unwrap());
+}
+
+#[test]
+fn test_instruction_limit_in_function() {
+    let mut vm = create_vm(VmLimits {
+        max_instructions: Some(500),
+        ..VmLimits::default()
+    });
+
+    let err = run_src_with(&mut vm, "fun f() { while true {} } f();").unwrap_err();
+    assert_eq!(RuntimeErrorKind::InstructionLimitExceeded, err.kind);
+
+    let functions: Vec<&str> = err
+        .stack_trace
+        .iter()
+        .map(|element| element.function.as_str())
+        .collect();
+    assert_eq!(vec!["f", "<script>"], functions);
+}
+
+#[test]
+fn test_heap_limit() {
+    let mut vm = create_vm(VmLimits {
+        max_heap_bytes: Some(16 * 1024),
+        ..VmLimits::default()
+    });
+
+    let err = run_src_with(&mut vm, "var a = null; while true { a = [a, 1, 2, 3]; }").unwrap_err();
+    assert_eq!(RuntimeErrorKind::HeapLimitExceeded, err.kind);
+    assert_eq!("Maximum heap size of 16384 bytes exceeded", err.message);
+    assert!(vm.heap_stats().max_bytes_allocated <= 16 * 1024);
+}
+
+#[test]
+fn test_heap_limit_string() {
+    let mut vm = create_vm(VmLimits {
+        max_heap_bytes: Some(16 * 1024),
+        ..VmLimits::default()
+    });
+
+    let err = run_src_with(&mut vm, "var s = \"ab\" * 10000;").unwrap_err();
+    assert_eq!(RuntimeErrorKind::HeapLimitExceeded, err.kind);
+
+    let err = run_src_with(&mut vm, "var s = \"ab\" * 5000; var r = s + s;").unwrap_err();
+    assert_eq!(RuntimeErrorKind::HeapLimitExceeded, err.kind);
+    assert_eq!(Some(1), err.stack_trace[0].line);
+}
+
+/// Run the code which creates an array with the given size.
+fn run_arrnew(vm: &mut YKVM, size: f64) -> RuntimeErrorKind {
+    let mut cp = create_constant_pool();
+    push_constants(&mut cp, vec![ConstantEntry::Number(NumberInfo::from(&size))]);
+
+    #[rustfmt::skip]
+    let code = Code::with_insns(1, 0, vec![
+        OpCode::Ldc as OpSize, 0x00, 0x01,
+        OpCode::ArrNew as OpSize,
+    ]);
+
+    return vm.run_code(&code, &cp).unwrap_err().kind;
+}
+
+#[test]
+fn test_heap_limit_array() {
+    let mut vm = create_vm(VmLimits {
+        max_heap_bytes: Some(16 * 1024),
+        ..VmLimits::default()
+    });
+
+    // the limit is checked before the elements are allocated
+    assert_eq!(RuntimeErrorKind::HeapLimitExceeded, run_arrnew(&mut vm, 1e6));
+    assert_eq!(RuntimeErrorKind::HeapLimitExceeded, run_arrnew(&mut vm, 1e17));
+    assert_eq!(0, vm.heap_stats().max_bytes_allocated);
+
+    let mut vm = create_vm(VmLimits::default());
+    assert_eq!(RuntimeErrorKind::InvalidArraySize, run_arrnew(&mut vm, 1e18));
+}
+
+#[test]
+fn test_heap_limit_with_garbage() {
+    let mut vm = create_vm(VmLimits {
+        max_heap_bytes: Some(16 * 1024),
+        ..VmLimits::default()
+    });
+
+    let result = run_src_with(
+        &mut vm,
+        "var n = 0; while n < 1000 { var a = [n, n, n, n]; n = n + 1; } n;",
+    );
+    assert_eq!(Some(Value::Number(1000f64)), result.unwrap());
+    assert!(vm.heap_stats().collections > 0);
+}
+
+#[test]
+fn test_call_depth_limit() {
+    let src = "fun f(n) { if n == 0 { return 0; } return f(n - 1) + 1; } var r = f(20); r;";
+
+    let mut vm = create_vm(VmLimits {
+        max_call_depth: 10,
+        ..VmLimits::default()
+    });
+    let err = run_src_with(&mut vm, src).unwrap_err();
+    assert_eq!(RuntimeErrorKind::StackOverflow, err.kind);
+    assert_eq!("Maximum call depth of 10 exceeded", err.message);
+    assert_eq!(10, err.stack_trace.len());
+
+    let mut vm = create_vm(VmLimits {
+        max_call_depth: 30,
+        ..VmLimits::default()
+    });
+    assert_eq!(
+        Some(Value::Number(20f64)),
+        run_src_with(&mut vm, src).unwrap()
+    );
+}
+
+#[test]
+fn test_operand_limit() {
+    let mut vm = create_vm(VmLimits {
+        max_operands: Some(3),
+        ..VmLimits::default()
+    });
+
+    let err = run_src_with(&mut vm, "var a = 1; var r = a + (a + (a + a)); r;").unwrap_err();
+    assert_eq!(RuntimeErrorKind::OperandLimitExceeded, err.kind);
+
+    let result = run_src_with(&mut vm, "var a = 1; var r = a + (a + a); r;");
+    assert_eq!(Some(Value::Number(3f64)), result.unwrap());
+}
+
+#[test]
+fn test_interrupt() {
+    let mut vm = YKVM::new();
+    let interrupt = vm.interrupt_flag();
+
+    let handle = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        interrupt.store(true, Ordering::Relaxed);
+    });
+
+    let err = run_src_with(&mut vm, "while true {}").unwrap_err();
+    handle.join().unwrap();
+    assert_eq!(RuntimeErrorKind::Interrupted, err.kind);
+
+    // the VM can be reused once the flag is cleared
+    vm.interrupt_flag().store(false, Ordering::Relaxed);
+    let result = run_src_with(&mut vm, "var r = 1; r;");
+    assert_eq!(Some(Value::Number(1f64)), result.unwrap());
+}
@@ -15,8 +15,24 @@
 
 mod arithemetic;
 mod branching;
+mod classes;
+mod closures;
+mod comparisons;
+mod constprop;
+mod dce;
+mod debug;
+mod errors;
+mod functions;
+mod gc;
+mod licm;
+mod limits;
 mod loops;
+mod natives;
+mod peephole;
+mod profile;
+mod strings;
 mod unary;
+mod verifier;
 mod util;
 mod variables;
 mod arrays;
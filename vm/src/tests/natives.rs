@@ -0,0 +1,137 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::error::RuntimeError;
+use crate::error::RuntimeErrorKind;
+use crate::native::NativeContext;
+use crate::tests::util::eval_src_with;
+use crate::tests::util::run_src_with;
+use crate::value::Value;
+use crate::YKVM;
+
+fn add(_ctx: &mut NativeContext, args: &[Value]) -> Result<Value, RuntimeError> {
+    match (&args[0], &args[1]) {
+        (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a + b)),
+        _ => Err(RuntimeError::new(
+            RuntimeErrorKind::Native,
+            "add: expected numbers",
+        )),
+    }
+}
+
+fn pair(ctx: &mut NativeContext, args: &[Value]) -> Result<Value, RuntimeError> {
+    return ctx.new_array(args.to_vec());
+}
+
+fn name(ctx: &mut NativeContext, _args: &[Value]) -> Result<Value, RuntimeError> {
+    return Ok(Value::String(ctx.name().to_string()));
+}
+
+fn create_vm<'a>() -> YKVM<'a> {
+    let mut vm = YKVM::new();
+    vm.register_native("add", 2, add);
+    vm.register_native("pair", 2, pair);
+    vm.register_native("name", 0, name);
+    vm
+}
+
+#[test]
+fn test_native_call() {
+    let mut vm = create_vm();
+    assert_eq!(Value::Number(3f64), eval_src_with(&mut vm, "var r = add(1, 2); r;"));
+}
+
+#[test]
+fn test_native_call_from_func() {
+    let mut vm = create_vm();
+    assert_eq!(
+        Value::Number(6f64),
+        eval_src_with(
+            &mut vm,
+            "fun f(x) { return add(x, add(x, x)); } var r = f(2); r;"
+        )
+    );
+}
+
+#[test]
+fn test_native_as_value() {
+    let mut vm = create_vm();
+    assert_eq!(
+        Value::Number(5f64),
+        eval_src_with(
+            &mut vm,
+            "fun apply(f) { return f(2, 3); } var r = apply(add); r;"
+        )
+    );
+}
+
+#[test]
+fn test_native_returns_array() {
+    let mut vm = create_vm();
+    assert_eq!(
+        Value::Number(4f64),
+        eval_src_with(&mut vm, "var p = pair(3, 4); var r = p[1]; r;")
+    );
+}
+
+#[test]
+fn test_native_context_name() {
+    let mut vm = create_vm();
+    assert_eq!(
+        Value::String(String::from("name")),
+        eval_src_with(&mut vm, "var r = name(); r;")
+    );
+}
+
+#[test]
+fn test_native_shadowed_by_var() {
+    let mut vm = create_vm();
+    assert_eq!(
+        Value::Number(2f64),
+        eval_src_with(
+            &mut vm,
+            "fun add(a, b) { return a * b; } var r = add(1, 2); r;"
+        )
+    );
+}
+
+#[test]
+fn test_register_native_replaces_existing() {
+    let mut vm = create_vm();
+    vm.register_native("add", 2, pair);
+    assert_eq!(vec!["add", "pair", "name"], vm.natives());
+    assert_eq!(
+        Value::Number(2f64),
+        eval_src_with(&mut vm, "var r = add(1, 2)[1]; r;")
+    );
+}
+
+#[test]
+fn test_native_error() {
+    let mut vm = create_vm();
+    let err = run_src_with(&mut vm, "fun f() { return add(1, true); } f();").unwrap_err();
+    assert_eq!(RuntimeErrorKind::Native, err.kind);
+    assert_eq!("add: expected numbers", err.message);
+    assert_eq!(2, err.stack_trace.len());
+    assert_eq!("f", err.stack_trace[0].function);
+}
+
+#[test]
+fn test_native_arity_mismatch() {
+    let mut vm = create_vm();
+    let err = run_src_with(&mut vm, "add(1);").unwrap_err();
+    assert_eq!(RuntimeErrorKind::ArityMismatch, err.kind);
+    assert_eq!("Function 'add' expects 2 arguments, but got 1", err.message);
+}
@@ -0,0 +1,72 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use compiler::features::CompilerFeatures;
+
+use crate::tests::util::eval_toggled;
+use crate::value::Value;
+
+/// Evaluate the source with and without the peephole optimizer and check that both produce the
+/// same result.
+fn eval_peephole(src: &str) -> Value {
+    let mut features = CompilerFeatures::default();
+    features.const_folding = false;
+    features.const_propagation = false;
+    return eval_toggled(src, &features, |features| features.peephole = false);
+}
+
+#[test]
+fn test_store_load() {
+    assert_eq!(
+        Value::Number(8f64),
+        eval_peephole("var a = 2; var r = a * (a + a); r;")
+    );
+}
+
+#[test]
+fn test_negated_conditions() {
+    assert_eq!(
+        Value::Number(1f64),
+        eval_peephole("var a = false; var r = 0; if !a { r = 1; } else { r = 2; } r;")
+    );
+    assert_eq!(
+        Value::Number(3f64),
+        eval_peephole("var i = 0; while !(i == 3) { i = i + 1; } i;")
+    );
+}
+
+#[test]
+fn test_negated_short_circuit() {
+    assert_eq!(
+        Value::Bool(false),
+        eval_peephole("var a = true; var r = !a and a; r;")
+    );
+    assert_eq!(
+        Value::Bool(true),
+        eval_peephole("var a = false; var r = !a or a; r;")
+    );
+}
+
+#[test]
+fn test_constant_conditions() {
+    assert_eq!(
+        Value::Number(5f64),
+        eval_peephole("var i = 0; while true { i = i + 1; if i == 5 { break; } } i;")
+    );
+    assert_eq!(Value::Number(3f64), eval_peephole("var r = true and 3; r;"));
+    assert_eq!(Value::Number(4f64), eval_peephole("var r = null or 4; r;"));
+    assert_eq!(Value::Bool(false), eval_peephole("var r = false and 4; r;"));
+    assert_eq!(Value::Bool(true), eval_peephole("var r = true or 4; r;"));
+}
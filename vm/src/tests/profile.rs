@@ -0,0 +1,134 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::tests::util::run_src_with;
+use crate::YKVM;
+
+#[test]
+fn test_no_profile_by_default() {
+    let mut vm = YKVM::new();
+    run_src_with(&mut vm, "var a = 1; a;").unwrap();
+    assert!(vm.profile().is_none());
+}
+
+#[test]
+fn test_opcode_counts() {
+    let mut vm = YKVM::new();
+    vm.set_profiling(true);
+    run_src_with(&mut vm, "var a = 1; a;").unwrap();
+
+    let profile = vm.profile().unwrap();
+    assert_eq!(3, profile.instructions());
+
+    let counts: Vec<(&str, u64)> = profile
+        .opcodes()
+        .iter()
+        .map(|(mnemonic, stats)| (*mnemonic, stats.count))
+        .collect();
+    assert_eq!(3, counts.len());
+    assert!(counts.contains(&("ldc", 1)));
+    assert!(counts.contains(&("store_0", 1)));
+    assert!(counts.contains(&("load_0", 1)));
+}
+
+#[test]
+fn test_hot_spots() {
+    let mut vm = YKVM::new();
+    vm.set_profiling(true);
+    run_src_with(
+        &mut vm,
+        "fun f(a) { return a; }
+        for (var i = 0; i < 10; i = i + 1) {
+            f(i);
+        }",
+    )
+    .unwrap();
+
+    let profile = vm.profile().unwrap();
+    let hot_spots = profile.hot_spots(3);
+    assert_eq!(3, hot_spots.len());
+
+    // the loop condition is executed once more than the loop body
+    assert_eq!(11, hot_spots[0].count);
+    assert_eq!(YKVM::TOP_LEVEL_NAME, hot_spots[0].function);
+
+    let in_function: Vec<u64> = profile
+        .hot_spots(usize::MAX)
+        .iter()
+        .filter(|stats| stats.function == "f")
+        .map(|stats| stats.count)
+        .collect();
+    assert!(!in_function.is_empty());
+    assert!(in_function.iter().all(|count| *count == 10));
+
+    let total: u64 = profile.opcodes().iter().map(|(_, stats)| stats.count).sum();
+    assert_eq!(profile.instructions(), total);
+    assert!(profile.total_time() >= profile.opcodes()[0].1.time);
+}
+
+#[test]
+fn test_hot_spots_of_functions_with_same_name() {
+    let mut vm = YKVM::new();
+    vm.set_profiling(true);
+    run_src_with(
+        &mut vm,
+        "class A { m() { return 1; } }
+        class B { m() { return 2; } }
+        var a = A();
+        var b = B();
+        for (var i = 0; i < 3; i = i + 1) {
+            a.m();
+            b.m();
+            b.m();
+        }",
+    )
+    .unwrap();
+
+    let mut counts: Vec<u64> = vm
+        .profile()
+        .unwrap()
+        .hot_spots(usize::MAX)
+        .iter()
+        .filter(|stats| stats.function == "m" && stats.pc == 0)
+        .map(|stats| stats.count)
+        .collect();
+    counts.sort();
+    assert_eq!(vec![3, 6], counts);
+}
+
+#[test]
+fn test_profile_after_error() {
+    let mut vm = YKVM::new();
+    vm.set_profiling(true);
+    assert!(run_src_with(&mut vm, "var a = 1; print a - true;").is_err());
+    assert!(vm.profile().unwrap().instructions() > 0);
+}
+
+#[test]
+fn test_reports() {
+    let mut vm = YKVM::new();
+    vm.set_profiling(true);
+    run_src_with(&mut vm, "var a = 1; a;").unwrap();
+
+    let profile = vm.profile().unwrap();
+    let table = profile.to_table(10);
+    assert!(table.contains("Instructions executed: 3"));
+    assert!(table.contains("store_0"));
+
+    let json = profile.to_json(10);
+    assert!(json.starts_with("{\"instructions\":3,"));
+    assert!(json.contains("{\"opcode\":\"ldc\",\"count\":1,"));
+    assert!(json.contains("{\"function\":\"<script>\",\"pc\":0,\"count\":1}"));
+}
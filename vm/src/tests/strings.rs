@@ -0,0 +1,102 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::error::RuntimeErrorKind;
+use crate::tests::util::eval_both;
+use crate::tests::util::eval_src;
+use crate::tests::util::eval_src_err;
+use crate::tests::util::run_both;
+use crate::value::Value;
+
+fn string(str: &str) -> Value {
+    Value::String(str.to_string())
+}
+
+#[test]
+fn test_string_concatenation() {
+    assert_eq!(string("ab"), eval_both("\"a\" + \"b\""));
+    assert_eq!(string("abc"), eval_both("\"a\" + \"b\" + \"c\""));
+    assert_eq!(string(""), eval_both("\"\" + \"\""));
+}
+
+#[test]
+fn test_string_number_concatenation() {
+    assert_eq!(string("n=1"), eval_both("\"n=\" + 1"));
+    assert_eq!(string("n=1.5"), eval_both("\"n=\" + 1.5"));
+    assert_eq!(string("-2 apples"), eval_both("-2 + \" apples\""));
+    assert_eq!(string("a12"), eval_both("\"a\" + 1 + 2"));
+    assert_eq!(string("3a"), eval_both("1 + 2 + \"a\""));
+}
+
+#[test]
+fn test_string_variable_concatenation() {
+    assert_eq!(
+        string("Hello, World!"),
+        eval_src("var a = \"Hello\"; var b = \"World\"; var r = a + \", \" + b + \"!\"; r;")
+    );
+    assert_eq!(
+        string("i=3"),
+        eval_src("var r = \"i=\"; var i = 3; r = r + i; r;")
+    );
+}
+
+#[test]
+fn test_string_repetition() {
+    assert_eq!(string("ababab"), eval_both("\"ab\" * 3"));
+    assert_eq!(string("ababab"), eval_both("3 * \"ab\""));
+    assert_eq!(string(""), eval_both("\"ab\" * 0"));
+    assert_eq!(
+        string("xxxx"),
+        eval_src("var s = \"x\"; var n = 4; var r = s * n; r;")
+    );
+}
+
+#[test]
+fn test_string_too_long() {
+    let cases = [
+        "\"ab\" * 1000000000000000000",
+        "1073741825 * \"a\"",
+        "\"ab\" * 536870913",
+    ];
+
+    for src in cases {
+        let err = run_both(&format!("var r = {};", src)).expect_err(src);
+        assert_eq!(RuntimeErrorKind::StringLengthExceeded, err.kind, "{}", src);
+    }
+}
+
+#[test]
+fn test_invalid_string_operations() {
+    let cases = [
+        "\"a\" - \"b\"",
+        "\"a\" / 2",
+        "\"a\" * \"b\"",
+        "\"a\" * -1",
+        "\"a\" * 1.5",
+        "\"a\" + true",
+        "null + \"a\"",
+    ];
+
+    for src in cases {
+        let err = run_both(&format!("var r = {};", src)).expect_err(src);
+        assert_eq!(RuntimeErrorKind::TypeError, err.kind, "{}", src);
+    }
+
+    let err = eval_src_err("var a = \"a\"; var r = a + [1];");
+    assert_eq!(
+        "Cannot perform 'add' on a and ObjArray{ length: 1, elements: [1] }",
+        err.message
+    );
+}
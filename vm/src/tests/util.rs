@@ -19,9 +19,11 @@ use compiler::bytecode::attrs::Code;
 use compiler::bytecode::opcode::OpSize;
 use compiler::bytecode::ConstantEntry;
 use compiler::bytecode::ConstantPool;
+use compiler::bytecode::YKBFile;
 use compiler::comp::YKCompiler;
 use compiler::features::CompilerFeatures;
 
+use crate::error::RuntimeError;
 use crate::value::Value;
 use crate::YKVM;
 
@@ -69,21 +71,115 @@ pub fn eval_arithmetic_src(src: &str) -> f64 {
 
 pub fn eval_src(src: &str) -> Value {
     let mut vm = YKVM::new();
-    let mut compiler = YKCompiler::new();
+    eval_src_with(&mut vm, src)
+}
+
+/// Evaluate the source with the given VM. The native functions registered with the VM are
+/// declared to the compiler.
+pub fn eval_src_with(vm: &mut YKVM, src: &str) -> Value {
+    run_src_with(vm, src)
+        .unwrap()
+        .expect("Expected result")
+}
+
+/// Evaluate the source and return the runtime error which occurs during execution.
+pub fn eval_src_err(src: &str) -> RuntimeError {
+    let mut vm = YKVM::new();
+    run_src_with(&mut vm, src).expect_err("Expected a runtime error")
+}
+
+/// Compile the source and run it with the given VM.
+pub fn run_src_with(vm: &mut YKVM, src: &str) -> Result<Option<Value>, RuntimeError> {
     let mut features = CompilerFeatures::default();
     features.const_folding = false;
+    features.const_propagation = false;
+    features.peephole = false;
+    run_src_with_features(vm, src, &features)
+}
+
+/// Compile the source with the given compiler features and run it with the given VM.
+pub fn run_src_with_features(
+    vm: &mut YKVM,
+    src: &str,
+    features: &CompilerFeatures,
+) -> Result<Option<Value>, RuntimeError> {
+    let file = compile_src(vm, src, features);
+    vm.run(&file)
+}
+
+/// Compile the source with the given compiler features. The native functions registered with
+/// the VM are declared to the compiler.
+pub fn compile_src(vm: &YKVM, src: &str, features: &CompilerFeatures) -> YKBFile {
+    let mut compiler = YKCompiler::new();
+    for native in vm.natives() {
+        compiler.declare_native(native);
+    }
 
     let (mut program, has_errors) = compiler
         .parse(Cursor::new(src))
         .expect("Failed to parse source");
     assert!(!has_errors);
-    assert!(!compiler.attr(&mut program, &features));
+    assert!(!compiler.attr(&mut program, features));
 
     // let mut out = String::new();
     // let mut printer = ASTPrinter::new(&mut out, true);
     // program.accept(&mut printer, &mut 0);
     // println!("Evaluating with VM: {}", out);
 
-    let file = compiler.ir(&mut program, &features);
-    vm.run(&file).unwrap().expect("Expected result")
+    compiler
+        .ir(&mut program, features)
+        .expect("Failed to generate bytecode")
+}
+
+/// Run the source with and without constant folding and assert that both the results agree.
+pub fn run_both(src: &str) -> Result<Option<Value>, RuntimeError> {
+    let mut features = CompilerFeatures::default();
+
+    features.const_folding = true;
+    let folded = run_src_with_features(&mut YKVM::new(), src, &features);
+
+    features.const_folding = false;
+    let unfolded = run_src_with_features(&mut YKVM::new(), src, &features);
+
+    match (&folded, &unfolded) {
+        (Ok(folded), Ok(unfolded)) => assert_eq!(folded, unfolded, "{}", src),
+        (Err(folded), Err(unfolded)) => {
+            assert_eq!(folded.kind, unfolded.kind, "{}", src);
+            assert_eq!(folded.message, unfolded.message, "{}", src);
+        }
+        _ => panic!(
+            "Folded and unfolded results differ for '{}': {:?} and {:?}",
+            src, folded, unfolded
+        ),
+    }
+
+    unfolded
+}
+
+/// Evaluate the given expression with and without constant folding.
+pub fn eval_both(src: &str) -> Value {
+    run_both(&format!("var r = {}; r;", src))
+        .unwrap()
+        .expect("Expected result")
+}
+
+/// Evaluate the source with the given compiler features, and again with the features changed by
+/// `toggle`, and assert that both the results agree.
+pub fn eval_toggled(
+    src: &str,
+    features: &CompilerFeatures,
+    toggle: fn(&mut CompilerFeatures),
+) -> Value {
+    let enabled = run_src_with_features(&mut YKVM::new(), src, features)
+        .unwrap()
+        .expect("Expected result");
+
+    let mut features = features.clone();
+    toggle(&mut features);
+    let disabled = run_src_with_features(&mut YKVM::new(), src, &features)
+        .unwrap()
+        .expect("Expected result");
+
+    assert_eq!(disabled, enabled, "{}", src);
+    enabled
 }
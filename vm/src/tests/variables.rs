@@ -22,6 +22,8 @@ use crate::tests::util::create_constant_pool;
 use crate::tests::util::create_vm;
 use crate::tests::util::eval_arithemetic;
 use crate::tests::util::push_constants;
+use crate::tests::util::run_both;
+use crate::value::Value;
 
 #[test]
 fn test_simple_var_decls() {
@@ -100,3 +102,31 @@ fn test_simple_variable_reassignment() {
         )
     )
 }
+
+#[test]
+fn test_const_decls() {
+    let src = "const a = 10; const b = a * 2; var c = 3; const d = c;
+        fun f(x) { return x + b; }
+        var g = fun() { return a + d; };
+        var r = 0;
+        for (const i = 1; r < 100; r = r + i) { r = r + f(a) + g(); }
+        r;";
+    assert_eq!(
+        Value::Number(132f64),
+        run_both(src).unwrap().expect("Expected result")
+    );
+}
+
+#[test]
+fn test_const_read_before_declaration() {
+    // `f` is called before `N` is initialized
+    let src = "fun f() { return N; }
+        var a = f();
+        const N = 3;
+        var r = a == null and f() == 3;
+        r;";
+    assert_eq!(
+        Value::Bool(true),
+        run_both(src).unwrap().expect("Expected result")
+    );
+}
@@ -0,0 +1,354 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use compiler::bytecode::attrs::Attr;
+use compiler::bytecode::attrs::Code;
+use compiler::bytecode::cp_info::NumberInfo;
+use compiler::bytecode::decls::FuncDecl;
+use compiler::bytecode::opcode;
+use compiler::bytecode::ConstantEntry;
+use compiler::bytecode::YKBFile;
+use compiler::bytecode::YKBVersion;
+use compiler::features::CompilerFeatures;
+
+use crate::error::RuntimeError;
+use crate::error::RuntimeErrorKind;
+use crate::tests::util::compile_src;
+use crate::verify;
+use crate::YKVM;
+
+/// Create a file with the given top-level code and a constant pool containing a number at index
+/// 1 and a Utf8 entry at index 2.
+fn file_with_code(max_stack: u16, max_locals: u16, insns: Vec<u8>) -> YKBFile {
+    let mut file = YKBFile::new(YKBVersion::LATEST.clone());
+    file.constant_pool_mut()
+        .push(ConstantEntry::Number(NumberInfo::from(&1f64)));
+    file.constant_pool_mut().push_str("name");
+    file.attributes_mut()
+        .push(Attr::Code(Code::with_insns(max_stack, max_locals, insns)));
+    return file;
+}
+
+fn verify_err(file: &YKBFile) -> RuntimeError {
+    let err = verify(file).expect_err("Expected the verification to fail");
+    assert_eq!(RuntimeErrorKind::InvalidBytecode, err.kind);
+    return err;
+}
+
+#[test]
+fn test_compiled_programs_are_valid() {
+    let vm = YKVM::new();
+    let file = compile_src(
+        &vm,
+        "class A { init(a) { this.a = a; } get() { return this.a; } }
+        class B : A { get() { return super.get() + 1; } }
+        fun counter() {
+            var count = 0;
+            fun incr() { count = count + 1; return count; }
+            return incr;
+        }
+        var arr = [1, 2, 3];
+        for (var i = 0; i < 3 and arr[i] != 2; i = i + 1) {
+            print counter()() + B(i).get();
+        }",
+        &CompilerFeatures::default(),
+    );
+    verify(&file).unwrap();
+}
+
+#[test]
+fn test_valid_code() {
+    let file = file_with_code(
+        2,
+        1,
+        vec![
+            opcode::Ldc,
+            0,
+            1,
+            opcode::Store0,
+            opcode::Load0,
+            opcode::IfFalsy,
+            0,
+            1,
+            opcode::Nop,
+            opcode::Pop,
+        ],
+    );
+    verify(&file).unwrap();
+}
+
+#[test]
+fn test_invalid_opcode() {
+    let err = verify_err(&file_with_code(1, 0, vec![opcode::BPush1, 0xFF]));
+    assert_eq!(1, err.pc);
+    assert!(err.message.contains("Invalid opcode"));
+}
+
+#[test]
+fn test_truncated_operands() {
+    let err = verify_err(&file_with_code(1, 0, vec![opcode::Ldc, 0]));
+    assert_eq!(0, err.pc);
+}
+
+#[test]
+fn test_jump_targets() {
+    // into the operands of ldc
+    let err = verify_err(&file_with_code(
+        1,
+        0,
+        vec![opcode::Jmp, 0, 1, opcode::Ldc, 0, 1],
+    ));
+    assert!(err.message.contains("Invalid jump target 4"));
+
+    // past the end of the code
+    verify_err(&file_with_code(1, 0, vec![opcode::Jmp, 0, 100]));
+
+    // before the start of the code
+    verify_err(&file_with_code(1, 0, vec![opcode::Jmp, 0xFF, 0xF0]));
+
+    // to the end of the code
+    verify(&file_with_code(1, 0, vec![opcode::Jmp, 0, 1, opcode::Nop])).unwrap();
+}
+
+#[test]
+fn test_constant_pool_indices() {
+    // missing entry
+    verify_err(&file_with_code(1, 0, vec![opcode::Ldc, 0x03, 0xE7]));
+
+    // Utf8 entries cannot be loaded
+    verify_err(&file_with_code(1, 0, vec![opcode::Ldc, 0, 2]));
+
+    // names must be Utf8 entries
+    verify_err(&file_with_code(1, 0, vec![opcode::NatLoad, 0, 1]));
+    verify(&file_with_code(1, 0, vec![opcode::NatLoad, 0, 2])).unwrap();
+}
+
+#[test]
+fn test_local_indices() {
+    verify_err(&file_with_code(1, 1, vec![opcode::Load1]));
+    verify_err(&file_with_code(1, 1, vec![opcode::Load, 0, 5]));
+    verify_err(&file_with_code(
+        1,
+        1,
+        vec![opcode::BPush1, opcode::GStore, 0, 1],
+    ));
+    verify(&file_with_code(1, 2, vec![opcode::Load1])).unwrap();
+}
+
+#[test]
+fn test_declaration_indices() {
+    verify_err(&file_with_code(1, 0, vec![opcode::FnLoad, 0, 0]));
+    verify_err(&file_with_code(1, 0, vec![opcode::ClsNew, 0, 0]));
+
+    // the top-level code has no upvalues
+    verify_err(&file_with_code(1, 0, vec![opcode::UpvLoad, 0, 0]));
+}
+
+#[test]
+fn test_stack_underflow() {
+    let err = verify_err(&file_with_code(1, 0, vec![opcode::BPush1, opcode::Add]));
+    assert_eq!(1, err.pc);
+    assert!(err.message.contains("underflow"));
+
+    // the callee and the arguments are popped
+    verify_err(&file_with_code(2, 0, vec![opcode::NPush, opcode::Call, 1]));
+}
+
+#[test]
+fn test_stack_overflow() {
+    let err = verify_err(&file_with_code(1, 0, vec![opcode::BPush1, opcode::Dup]));
+    assert_eq!(1, err.pc);
+    assert!(err.message.contains("overflow"));
+}
+
+#[test]
+fn test_inconsistent_stack_depth() {
+    // the value pushed at pc 4 is only on the stack when the jump is not taken
+    let err = verify_err(&file_with_code(
+        2,
+        0,
+        vec![
+            opcode::BPush1,
+            opcode::IfFalsy,
+            0,
+            1,
+            opcode::BPush1,
+            opcode::Pop,
+        ],
+    ));
+    assert!(err
+        .message
+        .contains("Inconsistent operand stack depth at pc 5"));
+}
+
+#[test]
+fn test_function_code_is_verified() {
+    let mut vm = YKVM::new();
+    let mut file = compile_src(
+        &vm,
+        "fun f(a) { return a; } print f(1);",
+        &CompilerFeatures::default(),
+    );
+
+    {
+        let mut decls = file.declarations_mut();
+        let index = decls
+            .iter()
+            .position(|decl| decl.as_func().is_some())
+            .unwrap();
+        let mut func: FuncDecl = decls[index].as_func().unwrap().clone();
+        let max_locals = func.code().max_locals();
+        func.attributes = vec![Attr::Code(Code::with_insns(
+            1,
+            max_locals,
+            vec![opcode::Load1, opcode::Ret],
+        ))];
+        decls[index] = Box::new(func);
+    }
+
+    let err = verify_err(&file);
+    assert_eq!("f", err.stack_trace[0].function);
+
+    // the program is not executed
+    let err = vm.run(&file).unwrap_err();
+    assert_eq!(RuntimeErrorKind::InvalidBytecode, err.kind);
+}
+
+/// Verify and run the given file, which is expected to fail at runtime.
+fn run_err(file: &YKBFile) -> RuntimeError {
+    verify(file).unwrap();
+    return YKVM::new()
+        .run(file)
+        .expect_err("Expected the execution to fail");
+}
+
+#[test]
+fn test_operands_of_wrong_type() {
+    // the verifier only checks the depth of the operand stack, so the types are checked when
+    // the instructions are executed
+    let err = run_err(&file_with_code(
+        2,
+        0,
+        vec![
+            opcode::Ldc,
+            0,
+            1,
+            opcode::Ldc,
+            0,
+            1,
+            opcode::GetSuper,
+            0,
+            2,
+            opcode::Pop,
+        ],
+    ));
+    assert_eq!(RuntimeErrorKind::TypeError, err.kind);
+    assert_eq!("Superclass must be a class, found 1", err.message);
+
+    let err = run_err(&file_with_code(
+        2,
+        0,
+        vec![
+            opcode::Ldc,
+            0,
+            1,
+            opcode::Ldc,
+            0,
+            1,
+            opcode::Method,
+            opcode::Pop,
+        ],
+    ));
+    assert_eq!(RuntimeErrorKind::TypeError, err.kind);
+    assert_eq!("Expected a function or a closure, found 1", err.message);
+
+    let mut file = file_with_code(
+        2,
+        0,
+        vec![
+            opcode::Ldc,
+            0,
+            1,
+            opcode::FnLoad,
+            0,
+            0,
+            opcode::Method,
+            opcode::Pop,
+        ],
+    );
+    let code = Code::with_insns(1, 0, vec![opcode::NPush, opcode::Ret]);
+    file.declarations_mut().push(Box::new(FuncDecl::new(
+        2,
+        vec![],
+        vec![],
+        vec![Attr::Code(code)],
+    )));
+    let err = run_err(&file);
+    assert_eq!(RuntimeErrorKind::TypeError, err.kind);
+    assert_eq!("Expected a class, found 1", err.message);
+
+    let err = run_err(&file_with_code(
+        1,
+        1,
+        vec![
+            opcode::Ldc,
+            0,
+            1,
+            opcode::Store0,
+            opcode::CellLoad,
+            0,
+            0,
+            opcode::Pop,
+        ],
+    ));
+    assert_eq!(RuntimeErrorKind::InvalidBytecode, err.kind);
+}
+
+#[test]
+fn test_function_loaded_without_closure() {
+    let vm = YKVM::new();
+    let mut file = compile_src(
+        &vm,
+        "fun mk() { var c = 0; fun inc() { c = c + 1; return c; } return inc; } print mk()();",
+        &CompilerFeatures::default(),
+    );
+
+    // load `inc` with `fnload` instead of `closure`, which have the same operands
+    {
+        let mut decls = file.declarations_mut();
+        let index = decls
+            .iter()
+            .position(|decl| {
+                decl.as_func()
+                    .is_some_and(|func| func.code().instructions().contains(&opcode::Closure))
+            })
+            .unwrap();
+        let mut func: FuncDecl = decls[index].as_func().unwrap().clone();
+        let code = func.code();
+        let mut insns = code.instructions().clone();
+        let pc = insns
+            .iter()
+            .position(|insn| *insn == opcode::Closure)
+            .unwrap();
+        insns[pc] = opcode::FnLoad;
+        func.attributes[0] =
+            Attr::Code(Code::with_insns(code.max_stack(), code.max_locals(), insns));
+        decls[index] = Box::new(func);
+    }
+
+    let err = run_err(&file);
+    assert_eq!(RuntimeErrorKind::InvalidBytecode, err.kind);
+    assert_eq!("Expected an upvalue at index 0", err.message);
+}
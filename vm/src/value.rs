@@ -13,6 +13,7 @@
  * program. If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::cmp::Ordering;
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::ops::Add;
@@ -22,7 +23,8 @@ use std::ops::Sub;
 use std::ptr::NonNull;
 
 use crate::enum_casts;
-use crate::object::{Obj, ObjArray, ObjString, ObjType};
+use crate::object::{Obj, ObjArray, ObjClosure, ObjFunction, ObjString, ObjType, ObjUpvalue};
+use crate::object::{ObjBoundMethod, ObjClass, ObjInstance, ObjNative};
 
 enum_casts!(Value, (Ref:NonNull<Obj>), (String:String), (Number:f64), (Bool:bool));
 
@@ -36,6 +38,9 @@ pub enum Value {
 }
 
 impl Value {
+    /// The maximum length of the strings created by concatenating or repeating strings, in bytes.
+    pub const MAX_STRING_LEN: usize = 1 << 30;
+
     /// Returns whether the value is truthy.
     pub fn is_truthy(&self) -> bool {
         match self {
@@ -49,6 +54,24 @@ impl Value {
     pub fn is_falsy(&self) -> bool {
         !self.is_truthy()
     }
+
+    /// Compare the value with the given value. Only numbers and strings can be ordered, strings
+    /// are ordered lexicographically. Returns [None] if the values cannot be ordered.
+    pub fn partial_compare(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Number(n1), Value::Number(n2)) => n1.partial_cmp(n2),
+            (Value::String(s1), Value::String(s2)) => Some(s1.cmp(s2)),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the values can be ordered with [Value::partial_compare].
+    pub fn is_comparable(&self, other: &Value) -> bool {
+        matches!(
+            (self, other),
+            (Value::Number(_), Value::Number(_)) | (Value::String(_), Value::String(_))
+        )
+    }
 }
 
 impl Display for Value {
@@ -58,6 +81,15 @@ impl Display for Value {
                 match reff.as_ref().typ {
                     ObjType::Array => write!(f, "{}", reff.cast::<ObjArray>().as_ref()),
                     ObjType::String => write!(f, "{}", reff.cast::<ObjString>().as_ref()),
+                    ObjType::Function => write!(f, "{}", reff.cast::<ObjFunction>().as_ref()),
+                    ObjType::Closure => write!(f, "{}", reff.cast::<ObjClosure>().as_ref()),
+                    ObjType::Upvalue => write!(f, "{}", reff.cast::<ObjUpvalue>().as_ref()),
+                    ObjType::Class => write!(f, "{}", reff.cast::<ObjClass>().as_ref()),
+                    ObjType::Instance => write!(f, "{}", reff.cast::<ObjInstance>().as_ref()),
+                    ObjType::BoundMethod => {
+                        write!(f, "{}", reff.cast::<ObjBoundMethod>().as_ref())
+                    }
+                    ObjType::Native => write!(f, "{}", reff.cast::<ObjNative>().as_ref()),
                 }
             },
             Value::String(str) => write!(f, "{}", str),
@@ -71,7 +103,7 @@ impl Display for Value {
 impl PartialEq for Value {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
-            (Value::Ref(r1), Value::Ref(r2)) => unsafe { r1.as_ref() == r2.as_ref() },
+            (Value::Ref(r1), Value::Ref(r2)) => r1 == r2,
             (Value::String(f), Value::String(s)) => f == s,
             (Value::Number(f), Value::Number(s)) => f == s,
             (Value::Bool(f), Value::Bool(s)) => f == s,
@@ -103,7 +135,10 @@ impl Add for Value {
     fn add(self, rhs: Self) -> Self::Output {
         match (&self, &rhs) {
             (Self::Number(n1), Self::Number(n2)) => Ok(Self::Number(n1 + n2)),
-            (Self::String(n1), Self::String(n2)) => Ok(Self::String(format!("{}{}", n1, n2))),
+
+            // a number is converted to its string representation when added to a string
+            (Self::String(_), Self::String(_) | Self::Number(_))
+            | (Self::Number(_), Self::String(_)) => Ok(Self::String(format!("{}{}", self, rhs))),
             (_, _) => Err(format!("Cannot perform addition on {} and {}", self, rhs)),
         }
     }
@@ -129,6 +164,19 @@ impl Mul for Value {
     fn mul(self, rhs: Self) -> Self::Output {
         match (&self, &rhs) {
             (Self::Number(n1), Self::Number(n2)) => Ok(Self::Number(n1 * n2)),
+
+            // a string multiplied by a non-negative integer is repeated that many times, as long as
+            // the result is not too long
+            (Self::String(str), Self::Number(count)) | (Self::Number(count), Self::String(str))
+                if *count >= 0.0
+                    && count.fract() == 0.0
+                    && str
+                        .len()
+                        .checked_mul(*count as usize)
+                        .is_some_and(|len| len <= Self::MAX_STRING_LEN) =>
+            {
+                Ok(Self::String(str.repeat(*count as usize)))
+            }
             (_, _) => Err(format!(
                 "Cannot perform multiplication on {} and {}",
                 self, rhs
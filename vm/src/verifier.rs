@@ -0,0 +1,504 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashSet;
+
+use compiler::bytecode::attrs;
+use compiler::bytecode::attrs::Code;
+use compiler::bytecode::attrs::LineNumberTable;
+use compiler::bytecode::bytes::AssertingByteConversions;
+use compiler::bytecode::decls::FuncDecl;
+use compiler::bytecode::decls::YKBDecl;
+use compiler::bytecode::opcode::try_get_opcode;
+use compiler::bytecode::opcode::OpCode;
+use compiler::bytecode::opcode::OpCodeExt;
+use compiler::bytecode::ConstantEntry;
+use compiler::bytecode::ConstantPool;
+use compiler::bytecode::CpSize;
+use compiler::bytecode::YKBFile;
+
+use crate::error::RuntimeError;
+use crate::error::RuntimeErrorKind;
+use crate::error::StackTraceElement;
+use crate::YKVM;
+
+/// Verify the bytecode of the given file before it is executed. The top-level code and the code
+/// of every function declaration are checked, so that a malformed file results in an error
+/// instead of a panic or an out-of-bounds access while the program runs.
+pub fn verify(file: &YKBFile) -> Result<(), RuntimeError> {
+    let attrs = file.attributes();
+    let code = match attrs.iter().find_map(|attr| attr.Code()) {
+        Some(code) => code,
+        None => {
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::InvalidBytecode,
+                format!("Missing {} attribute", attrs::CODE),
+            ))
+        }
+    };
+
+    let line_numbers = attrs.iter().find_map(|attr| attr.LineNumberTable());
+    let decls = file.declarations();
+    let verifier = Verifier::new(file.constant_pool(), &decls, code.max_locals() as usize)?;
+    verifier.verify_code(&CodeInfo {
+        name: YKVM::TOP_LEVEL_NAME.to_string(),
+        code,
+        line_numbers,
+        locals: code.max_locals() as usize,
+        upvalues: None,
+    })?;
+
+    for decl in decls.iter() {
+        if let Some(func) = decl.as_func() {
+            verifier.verify_func(func)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Verifies the code of a YKB file.
+struct Verifier<'a> {
+    constant_pool: &'a ConstantPool,
+    declarations: &'a Vec<Box<dyn YKBDecl>>,
+
+    /// The indices of the function declarations which are methods of a class. Methods receive
+    /// the receiver as an additional argument.
+    methods: HashSet<u16>,
+
+    /// The number of global variables, i.e. the local variables of the top-level code.
+    globals: usize,
+}
+
+/// The code being verified.
+struct CodeInfo<'a> {
+    /// The name of the function containing the code.
+    name: String,
+    code: &'a Code,
+    line_numbers: Option<&'a LineNumberTable>,
+
+    /// The number of local variables available to the code.
+    locals: usize,
+
+    /// The number of upvalues available to the code, or [None] for the top-level code.
+    upvalues: Option<usize>,
+}
+
+/// A decoded instruction.
+struct Insn {
+    pc: usize,
+    opcode: OpCode,
+    operand: u16,
+}
+
+impl<'a> Verifier<'a> {
+    fn new(
+        constant_pool: &'a ConstantPool,
+        declarations: &'a Vec<Box<dyn YKBDecl>>,
+        globals: usize,
+    ) -> Result<Verifier<'a>, RuntimeError> {
+        let mut verifier = Verifier {
+            constant_pool,
+            declarations,
+            methods: HashSet::new(),
+            globals,
+        };
+
+        for decl in declarations {
+            let name_index = *decl.name_index();
+            verifier.check_utf8(name_index, "declaration name")?;
+
+            if let Some(class) = decl.as_class() {
+                if class.has_super() {
+                    verifier.check_utf8(class.super_name_index, "superclass name")?;
+                }
+
+                for method in &class.methods {
+                    verifier.check_func_decl(*method)?;
+                    verifier.methods.insert(*method);
+                }
+            }
+
+            if let Some(func) = decl.as_func() {
+                for param in &func.param_names {
+                    verifier.check_utf8(*param, "parameter name")?;
+                }
+
+                if func
+                    .attributes
+                    .iter()
+                    .find_map(|attr| attr.Code())
+                    .is_none()
+                {
+                    return Err(invalid(format!(
+                        "Missing {} attribute in function declaration",
+                        attrs::CODE
+                    )));
+                }
+            }
+        }
+
+        return Ok(verifier);
+    }
+
+    fn verify_func(&self, func: &FuncDecl) -> Result<(), RuntimeError> {
+        let index = self
+            .declarations
+            .iter()
+            .position(|decl| decl.as_func().is_some_and(|decl| std::ptr::eq(decl, func)))
+            .unwrap() as u16;
+
+        let code = func.code();
+        let mut args = func.arity() as usize;
+        if self.methods.contains(&index) {
+            args += 1;
+        }
+
+        let name = self
+            .constant_pool
+            .get(func.name_index)
+            .and_then(|entry| entry.as_utf8())
+            .map(|utf8| utf8.to_string())
+            .unwrap_or_default();
+
+        // the VM allocates at least one local variable for each argument
+        return self.verify_code(&CodeInfo {
+            name,
+            code,
+            line_numbers: func.line_numbers(),
+            locals: (code.max_locals() as usize).max(args),
+            upvalues: Some(func.upvalues.len()),
+        });
+    }
+
+    /// Verify the given code. The instructions are decoded and their operands are checked first,
+    /// then the depth of the operand stack is computed along every path through the code.
+    fn verify_code(&self, info: &CodeInfo) -> Result<(), RuntimeError> {
+        let insns = self.decode(info)?;
+        for insn in &insns {
+            self.check_operand(info, insn)
+                .map_err(|err| at(err, info, insn.pc))?;
+        }
+
+        return self.check_stack(info, &insns);
+    }
+
+    /// Decode the instructions of the code, checking that each instruction has a valid opcode and
+    /// that its operands are within the code.
+    fn decode(&self, info: &CodeInfo) -> Result<Vec<Insn>, RuntimeError> {
+        let bytes = info.code.instructions();
+        let mut insns = Vec::new();
+        let mut pc = 0;
+        while pc < bytes.len() {
+            let opcode = match try_get_opcode(bytes[pc]) {
+                Some(opcode) => opcode,
+                None => {
+                    return Err(at(
+                        invalid(format!("Invalid opcode: {:#04x}", bytes[pc])),
+                        info,
+                        pc,
+                    ))
+                }
+            };
+
+            let size = opcode.operand_size() as usize;
+            if pc + 1 + size > bytes.len() {
+                return Err(at(
+                    invalid(format!("Truncated operands of '{}'", opcode)),
+                    info,
+                    pc,
+                ));
+            }
+
+            let operand = match size {
+                0 => 0,
+                1 => bytes[pc + 1] as u16,
+                _ => (bytes[pc + 1].as_u16() << 8) | bytes[pc + 2].as_u16(),
+            };
+
+            insns.push(Insn {
+                pc,
+                opcode,
+                operand,
+            });
+            pc += 1 + size;
+        }
+
+        return Ok(insns);
+    }
+
+    /// Check the operand of the given instruction.
+    fn check_operand(&self, info: &CodeInfo, insn: &Insn) -> Result<(), RuntimeError> {
+        let operand = insn.operand;
+        match insn.opcode {
+            OpCode::Ldc => match self.constant_pool.get(operand) {
+                Some(ConstantEntry::Number(_)) => {}
+                Some(ConstantEntry::String(_))
+                    if self.constant_pool.get_string(operand).is_some() => {}
+                _ => {
+                    return Err(invalid(format!(
+                        "Expected a number or a string constant at index {}",
+                        operand
+                    )))
+                }
+            },
+            OpCode::GetField | OpCode::PutField | OpCode::GetSuper | OpCode::NatLoad => {
+                self.check_utf8(operand, "name")?;
+            }
+            OpCode::Load
+            | OpCode::Store
+            | OpCode::CellNew
+            | OpCode::CellLoad
+            | OpCode::CellStore => {
+                self.check_local(info, operand as usize)?;
+            }
+            OpCode::Load0 | OpCode::Store0 => self.check_local(info, 0)?,
+            OpCode::Load1 | OpCode::Store1 => self.check_local(info, 1)?,
+            OpCode::Load2 | OpCode::Store2 => self.check_local(info, 2)?,
+            OpCode::Load3 | OpCode::Store3 => self.check_local(info, 3)?,
+            OpCode::GLoad | OpCode::GStore => {
+                if operand as usize >= self.globals {
+                    return Err(invalid(format!(
+                        "Global variable index {} out of bounds, expected less than {}",
+                        operand, self.globals
+                    )));
+                }
+            }
+            OpCode::UpvLoad | OpCode::UpvStore => self.check_upvalue(info, operand)?,
+            OpCode::FnLoad => {
+                self.check_func_decl(operand)?;
+            }
+            OpCode::Closure => {
+                let func = self.check_func_decl(operand)?;
+                for upvalue in &func.upvalues {
+                    if upvalue.is_local {
+                        self.check_local(info, upvalue.index as usize)?;
+                    } else {
+                        self.check_upvalue(info, upvalue.index)?;
+                    }
+                }
+            }
+            OpCode::ClsNew => {
+                let is_class = self
+                    .declarations
+                    .get(operand as usize)
+                    .is_some_and(|decl| decl.as_class().is_some());
+                if !is_class {
+                    return Err(invalid(format!(
+                        "Expected a class declaration at index {}",
+                        operand
+                    )));
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Check that the given operand stack depth is consistent along every path through the code,
+    /// and that the operand stack neither underflows nor exceeds the `max_stack` of the code.
+    fn check_stack(&self, info: &CodeInfo, insns: &[Insn]) -> Result<(), RuntimeError> {
+        let len = info.code.instructions().len();
+        let max_stack = info.code.max_stack() as usize;
+
+        // the index of the instruction at each address, if an instruction starts there
+        let mut indices = vec![None; len];
+        for (index, insn) in insns.iter().enumerate() {
+            indices[insn.pc] = Some(index);
+        }
+
+        let mut depths: Vec<Option<usize>> = vec![None; insns.len()];
+        let mut pending = Vec::new();
+        if !insns.is_empty() {
+            depths[0] = Some(0);
+            pending.push(0);
+        }
+
+        while let Some(index) = pending.pop() {
+            let insn = &insns[index];
+            let depth = depths[index].unwrap();
+            let (pops, pushes) = stack_effect(insn);
+
+            if depth < pops {
+                return Err(at(
+                    invalid(format!(
+                        "Operand stack underflow: '{}' expects {} operand(s), found {}",
+                        insn.opcode, pops, depth
+                    )),
+                    info,
+                    insn.pc,
+                ));
+            }
+
+            let depth = depth - pops + pushes;
+            if depth > max_stack {
+                return Err(at(
+                    invalid(format!(
+                        "Operand stack overflow: depth {} exceeds max_stack={}",
+                        depth, max_stack
+                    )),
+                    info,
+                    insn.pc,
+                ));
+            }
+
+            let next = insn.pc + 1 + insn.opcode.operand_size() as usize;
+            let mut successors = Vec::with_capacity(2);
+            match insn.opcode {
+                OpCode::Halt | OpCode::Ret => {}
+                OpCode::Jmp => successors.push(self.jump_target(info, insn, &indices)?),
+                opcode if opcode.is_jmp() => {
+                    successors.push(next);
+                    successors.push(self.jump_target(info, insn, &indices)?);
+                }
+                _ => successors.push(next),
+            }
+
+            for successor in successors {
+                // the execution ends when it reaches the end of the code
+                if successor == len {
+                    continue;
+                }
+
+                let successor = indices[successor].unwrap();
+                match depths[successor] {
+                    None => {
+                        depths[successor] = Some(depth);
+                        pending.push(successor);
+                    }
+                    Some(expected) if expected != depth => {
+                        return Err(at(
+                            invalid(format!(
+                                "Inconsistent operand stack depth at pc {}: {} and {}",
+                                insns[successor].pc, expected, depth
+                            )),
+                            info,
+                            insn.pc,
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the address the given jump instruction jumps to. The address must be the start of an
+    /// instruction or the end of the code.
+    fn jump_target(
+        &self,
+        info: &CodeInfo,
+        insn: &Insn,
+        indices: &[Option<usize>],
+    ) -> Result<usize, RuntimeError> {
+        let next = insn.pc + 1 + insn.opcode.operand_size() as usize;
+        let target = next as isize + insn.operand as i16 as isize;
+        let is_valid = target >= 0
+            && (target as usize == indices.len()
+                || indices
+                    .get(target as usize)
+                    .is_some_and(|index| index.is_some()));
+
+        if !is_valid {
+            return Err(at(
+                invalid(format!(
+                    "Invalid jump target {}: not the start of an instruction",
+                    target
+                )),
+                info,
+                insn.pc,
+            ));
+        }
+
+        return Ok(target as usize);
+    }
+
+    fn check_utf8(&self, index: CpSize, what: &str) -> Result<(), RuntimeError> {
+        if self
+            .constant_pool
+            .get(index)
+            .and_then(|entry| entry.as_utf8())
+            .is_none()
+        {
+            return Err(invalid(format!(
+                "Expected a Utf8 constant for the {} at index {}",
+                what, index
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn check_func_decl(&self, index: u16) -> Result<&'a FuncDecl, RuntimeError> {
+        return match self
+            .declarations
+            .get(index as usize)
+            .and_then(|decl| decl.as_func())
+        {
+            Some(func) => Ok(func),
+            None => Err(invalid(format!(
+                "Expected a function declaration at index {}",
+                index
+            ))),
+        };
+    }
+
+    fn check_local(&self, info: &CodeInfo, index: usize) -> Result<(), RuntimeError> {
+        if index >= info.locals {
+            return Err(invalid(format!(
+                "Local variable index {} out of bounds, expected less than {}",
+                index, info.locals
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn check_upvalue(&self, info: &CodeInfo, index: u16) -> Result<(), RuntimeError> {
+        return match info.upvalues {
+            Some(upvalues) if (index as usize) < upvalues => Ok(()),
+            Some(upvalues) => Err(invalid(format!(
+                "Upvalue index {} out of bounds, expected less than {}",
+                index, upvalues
+            ))),
+            None => Err(invalid("Upvalues are not available in the top-level code")),
+        };
+    }
+}
+
+/// Get the number of operands popped and pushed by the given instruction, as executed by the VM.
+fn stack_effect(insn: &Insn) -> (usize, usize) {
+    return insn.opcode.stack_usage(insn.operand);
+}
+
+fn invalid(message: impl Into<String>) -> RuntimeError {
+    return RuntimeError::new(RuntimeErrorKind::InvalidBytecode, message);
+}
+
+/// Fill in the location of the given error, which was found in the instruction at the given
+/// address.
+fn at(mut err: RuntimeError, info: &CodeInfo, pc: usize) -> RuntimeError {
+    err.pc = pc;
+    err.stack_trace = vec![StackTraceElement {
+        function: info.name.clone(),
+        pc,
+        line: info
+            .line_numbers
+            .and_then(|line_numbers| line_numbers.line_at(pc.as_code_size())),
+    }];
+    return err;
+}
@@ -14,7 +14,13 @@
  */
 
 use std::cmp::max;
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::ptr::NonNull;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering as AtomicOrdering;
+use std::sync::Arc;
+use std::time::Instant;
 
 use log::error;
 use log::log_enabled;
@@ -22,20 +28,47 @@ use log::trace;
 use log::warn;
 use log::Level::Trace;
 
+use crate::debug::Breakpoint;
+use crate::debug::DebugAction;
+use crate::debug::DebugHook;
+use crate::debug::PausedState;
+use crate::error::RuntimeError;
+use crate::error::RuntimeErrorKind;
+use crate::error::StackTraceElement;
+use crate::limits::VmLimits;
 use crate::memory::Heap;
+use crate::memory::HeapStats;
+use crate::native::Native;
+use crate::native::NativeContext;
+use crate::native::NativeFn;
+use crate::profile::Profile;
+use crate::verifier::verify;
 use compiler::bytecode::attrs;
 use compiler::bytecode::attrs::Attr;
 use compiler::bytecode::attrs::Code;
+use compiler::bytecode::attrs::LineNumberTable;
 use compiler::bytecode::bytes::AssertingByteConversions;
+use compiler::bytecode::decls::ClassDecl;
+use compiler::bytecode::decls::FuncDecl;
+use compiler::bytecode::decls::YKBDecl;
 use compiler::bytecode::opcode;
+use compiler::bytecode::opcode::OpCode;
+use compiler::bytecode::opcode::OpCodeExt;
 use compiler::bytecode::opcode::OpSize;
-use compiler::bytecode::opcode::{get_mnemonic, get_opcode};
+use compiler::bytecode::opcode::get_mnemonic;
 use compiler::bytecode::ConstantEntry;
 use compiler::bytecode::ConstantPool;
 use compiler::bytecode::CpSize;
 use compiler::bytecode::YKBFile;
 
 use crate::object::Obj;
+use crate::object::ObjBoundMethod;
+use crate::object::ObjClass;
+use crate::object::ObjClosure;
+use crate::object::ObjFunction;
+use crate::object::ObjInstance;
+use crate::object::ObjNative;
+use crate::object::ObjUpvalue;
 use crate::object::{ObjArray, ObjType};
 use crate::value::Value;
 
@@ -43,49 +76,190 @@ use crate::value::Value;
 #[allow(unused)]
 pub struct YKVM<'inst> {
     heap: Heap,
+    natives: Vec<Native>,
+    limits: VmLimits,
+    interrupt: Arc<AtomicBool>,
+    profiling: bool,
+    profile: Option<Profile>,
     _s: &'inst str,
 }
 
 impl<'inst> YKVM<'inst> {
+    /// The name of the top-level code of a program, as shown in stack traces.
+    pub const TOP_LEVEL_NAME: &'static str = "<script>";
+
     pub fn new<'a>() -> YKVM<'a> {
         return YKVM {
             heap: Heap::new(),
+            natives: Vec::with_capacity(0),
+            limits: VmLimits::default(),
+            interrupt: Arc::new(AtomicBool::new(false)),
+            profiling: false,
+            profile: None,
             _s: "",
         };
     }
+
+    /// Register a native function with the given name and arity. Programs can call the native
+    /// function like any other function, provided that its name was declared to the compiler.
+    /// Registering a native function with the name of an existing native function replaces it.
+    pub fn register_native(&mut self, name: &str, arity: u8, function: NativeFn) {
+        let native = Native {
+            name: name.to_string(),
+            arity,
+            function,
+        };
+
+        match self.natives.iter_mut().find(|native| native.name == name) {
+            Some(existing) => *existing = native,
+            None => self.natives.push(native),
+        }
+    }
+
+    /// Get the names of the native functions registered with this VM.
+    pub fn natives(&self) -> Vec<&str> {
+        return self
+            .natives
+            .iter()
+            .map(|native| native.name.as_str())
+            .collect();
+    }
+
+    /// Get the memory usage statistics of this VM.
+    pub fn heap_stats(&self) -> HeapStats {
+        return self.heap.stats();
+    }
+
+    /// Get the resource limits of this VM.
+    pub fn limits(&self) -> &VmLimits {
+        return &self.limits;
+    }
+
+    /// Set the resource limits of this VM.
+    pub fn set_limits(&mut self, limits: VmLimits) {
+        self.heap.set_max_bytes(limits.max_heap_bytes);
+        self.limits = limits;
+    }
+
+    /// Get the interrupt flag of this VM. Setting the flag, possibly from another thread, aborts
+    /// the running program with a [RuntimeErrorKind::Interrupted] error. The flag is not cleared
+    /// by the VM, it must be cleared before running another program.
+    pub fn interrupt_flag(&self) -> Arc<AtomicBool> {
+        return self.interrupt.clone();
+    }
+
+    /// Enable or disable profiling. When enabled, each run of a program records the number of
+    /// times each opcode and each instruction was executed, and the time spent executing them.
+    pub fn set_profiling(&mut self, profiling: bool) {
+        self.profiling = profiling;
+    }
+
+    /// Get the profile recorded by the last run of a program, if profiling was enabled.
+    pub fn profile(&self) -> Option<&Profile> {
+        return self.profile.as_ref();
+    }
+
+    /// Set the minimum number of bytes which must be allocated before the garbage collector runs.
+    /// Defaults to 1 MiB.
+    pub fn set_gc_threshold(&mut self, bytes: usize) {
+        self.heap.set_gc_threshold(bytes);
+    }
 }
 
 impl<'inst> YKVM<'inst> {
-    pub fn run<'a>(&mut self, file: &YKBFile) -> Result<Option<Value>, String> {
+    pub fn run<'a>(&mut self, file: &YKBFile) -> Result<Option<Value>, RuntimeError> {
+        self.run_file(file, None)
+    }
+
+    /// Run the program under the control of the given debugger. The execution is paused before
+    /// the first instruction is executed.
+    pub fn debug(
+        &mut self,
+        file: &YKBFile,
+        debugger: &mut dyn DebugHook,
+    ) -> Result<Option<Value>, RuntimeError> {
+        self.run_file(file, Some(debugger))
+    }
+
+    fn run_file(
+        &mut self,
+        file: &YKBFile,
+        debugger: Option<&mut dyn DebugHook>,
+    ) -> Result<Option<Value>, RuntimeError> {
+        verify(file)?;
+
         let attrs = file.attributes();
         let code = attrs.iter().find(|attr| attr.name() == attrs::CODE);
         if code.is_none() {
-            return Err(String::from("Missing code attribute"));
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::InvalidBytecode,
+                "Missing code attribute",
+            ));
         }
 
         let attr = code.unwrap();
         let code = if let Attr::Code(code) = attr {
             code
         } else {
-            return Err(format!(
-                "Invalid attribute with name {}. Expected Code.",
-                attrs::CODE
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::InvalidBytecode,
+                format!("Invalid attribute with name {}. Expected Code.", attrs::CODE),
             ));
         };
 
-        self.run_code(code, file.constant_pool())
+        let line_numbers = attrs.iter().find_map(|attr| attr.LineNumberTable());
+        let decls = file.declarations();
+        self.run_code0(
+            code,
+            line_numbers,
+            file.constant_pool(),
+            Some(&decls),
+            debugger,
+        )
     }
 
     /// Execute the instructions in the [Code] and returns the value at the top of the stack
-    /// after execution.
-    pub fn run_code<'cpinst>(
+    /// after execution. The code is not verified, so this is only available to run the
+    /// hand-written code in the tests. Use [YKVM::run] to run a bytecode file.
+    #[cfg(test)]
+    pub(crate) fn run_code(
+        &mut self,
+        code: &Code,
+        constant_pool: &ConstantPool,
+    ) -> Result<Option<Value>, RuntimeError> {
+        self.run_code0(code, None, constant_pool, None, None)
+    }
+
+    fn run_code0(
         &mut self,
         code: &Code,
-        constant_pool: &'cpinst ConstantPool,
-    ) -> Result<Option<Value>, String> {
-        let mut executor = CodeExecutor::new(&mut self.heap, Some(constant_pool));
-        let result = executor.execute(code).map(|r| r.map(|r| r.clone()));
+        line_numbers: Option<&LineNumberTable>,
+        constant_pool: &ConstantPool,
+        declarations: Option<&Vec<Box<dyn YKBDecl>>>,
+        debugger: Option<&mut dyn DebugHook>,
+    ) -> Result<Option<Value>, RuntimeError> {
+        let mut executor = CodeExecutor::new(
+            &mut self.heap,
+            &self.natives,
+            &self.limits,
+            &self.interrupt,
+            Some(constant_pool),
+            declarations,
+        );
+        executor.debugger = debugger.map(|debugger| debugger as &mut dyn DebugHook);
+        executor.profile = self.profiling.then(Profile::default);
+
+        let start = Instant::now();
+        let result = executor
+            .execute(code, line_numbers)
+            .map(|r| r.map(|r| r.clone()));
+
+        let mut profile = executor.profile.take();
+        if let Some(profile) = &mut profile {
+            profile.add_total_time(start.elapsed());
+        }
 
+        self.profile = profile;
         self.release();
 
         result
@@ -99,11 +273,61 @@ impl<'inst> YKVM<'inst> {
 /// The code executor responsible for executing the code.
 pub struct CodeExecutor<'inst> {
     constant_pool: Option<&'inst ConstantPool>,
+    declarations: Option<&'inst Vec<Box<dyn YKBDecl>>>,
     heap: &'inst mut Heap,
-    variables: Vec<Value>,
+    natives: &'inst Vec<Native>,
+    limits: &'inst VmLimits,
+    interrupt: &'inst AtomicBool,
+    frames: Vec<CallFrame<'inst>>,
     operands: Vec<Value>,
-    max_stack: u16,
-    max_locals: u16,
+    debugger: Option<&'inst mut dyn DebugHook>,
+    debug_mode: DebugMode,
+    profile: Option<Profile>,
+}
+
+/// Determines when the execution is paused, if a debugger is attached.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DebugMode {
+    /// Pause only at breakpoints.
+    Continue,
+
+    /// Pause before the next instruction.
+    Step,
+
+    /// Pause before the next instruction executed with at most the given number of active call
+    /// frames.
+    StepOver(usize),
+}
+
+/// A call frame, created for the top-level code and for each function call.
+struct CallFrame<'inst> {
+    /// The code being executed in this frame.
+    code: &'inst Code,
+
+    /// The address of the instruction to continue the execution from, when this frame becomes
+    /// the current frame again.
+    pc: usize,
+
+    /// The local variables of this frame.
+    locals: Vec<Value>,
+
+    /// The index in the operand stack where the operands of this frame begin.
+    stack_base: usize,
+
+    /// The closure being executed in this frame, if any. The upvalues of the closure are
+    /// accessible to the code in this frame.
+    closure: Option<NonNull<Obj>>,
+
+    /// The name of the function being executed in this frame.
+    name: String,
+
+    /// The source lines of the code being executed in this frame, if available.
+    line_numbers: Option<&'inst LineNumberTable>,
+}
+
+impl CallFrame<'_> {
+    /// The name of the call frame for the top-level code.
+    const TOP_LEVEL_NAME: &'static str = YKVM::TOP_LEVEL_NAME;
 }
 
 macro_rules! read1 {
@@ -123,14 +347,26 @@ macro_rules! read2 {
 }
 
 impl<'inst> CodeExecutor<'inst> {
-    fn new<'i>(heap: &'i mut Heap, constant_pool: Option<&'i ConstantPool>) -> CodeExecutor<'i> {
+    fn new<'i>(
+        heap: &'i mut Heap,
+        natives: &'i Vec<Native>,
+        limits: &'i VmLimits,
+        interrupt: &'i AtomicBool,
+        constant_pool: Option<&'i ConstantPool>,
+        declarations: Option<&'i Vec<Box<dyn YKBDecl>>>,
+    ) -> CodeExecutor<'i> {
         CodeExecutor {
             heap,
+            natives,
+            limits,
+            interrupt,
             constant_pool,
-            variables: Vec::with_capacity(0),
+            declarations,
+            frames: Vec::with_capacity(0),
             operands: Vec::with_capacity(0),
-            max_stack: 0,
-            max_locals: 0,
+            debugger: None,
+            debug_mode: DebugMode::Step,
+            profile: None,
         }
     }
 
@@ -144,10 +380,75 @@ impl<'inst> CodeExecutor<'inst> {
         }
 
         self.constant_pool = None;
-        self.variables.clear();
+        self.declarations = None;
+        self.frames.clear();
         self.operands.clear();
-        self.max_stack = 0;
-        self.max_locals = 0;
+    }
+
+    fn frame(&self) -> &CallFrame<'inst> {
+        return self.frames.last().expect("Expected a call frame");
+    }
+
+    fn frame_mut(&mut self) -> &mut CallFrame<'inst> {
+        return self.frames.last_mut().expect("Expected a call frame");
+    }
+
+    fn push_frame(
+        &mut self,
+        code: &'inst Code,
+        line_numbers: Option<&'inst LineNumberTable>,
+        mut args: Vec<Value>,
+        closure: Option<NonNull<Obj>>,
+        name: String,
+    ) -> Result<(), RuntimeError> {
+        let max_call_depth = self.limits.max_call_depth;
+        if self.frames.len() >= max_call_depth {
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::StackOverflow,
+                format!("Maximum call depth of {} exceeded", max_call_depth),
+            ));
+        }
+
+        let locals = max(code.max_locals() as usize, args.len());
+        args.resize_with(locals, &|| Value::Null);
+
+        self.frames.push(CallFrame {
+            code,
+            pc: 0,
+            locals: args,
+            stack_base: self.operands.len(),
+            closure,
+            name,
+            line_numbers,
+        });
+
+        Ok(())
+    }
+
+    fn get_func_decl(&self, index: u16) -> &'inst FuncDecl {
+        return self
+            .declarations
+            .and_then(|decls| decls.get(index as usize))
+            .and_then(|decl| decl.as_func())
+            .expect(&format!("Expected a function declaration at index {}", index));
+    }
+
+    fn get_class_decl(&self, index: u16) -> &'inst ClassDecl {
+        return self
+            .declarations
+            .and_then(|decls| decls.get(index as usize))
+            .and_then(|decl| decl.as_class())
+            .expect(&format!("Expected a class declaration at index {}", index));
+    }
+
+    /// Get the UTF-8 constant at the given index in the constant pool.
+    fn get_utf8(&self, index: CpSize) -> String {
+        return self
+            .constant_pool()
+            .get(index)
+            .and_then(|entry| entry.as_utf8())
+            .map(|utf8| utf8.to_string())
+            .expect(&format!("Expected a Utf8 constant at index {}", index));
     }
 
     fn try_peek_operand(&mut self) -> Option<&Value> {
@@ -169,6 +470,11 @@ impl<'inst> CodeExecutor<'inst> {
     }
 
     fn try_pop_operand(&mut self) -> Option<Value> {
+        // the operands of the caller frames are not accessible
+        if self.operands.len() <= self.frame().stack_base {
+            return None;
+        }
+
         self.operands.pop()
     }
 
@@ -186,41 +492,50 @@ impl<'inst> CodeExecutor<'inst> {
         op
     }
 
-    fn pop_num(&mut self) -> f64 {
-        self.pop_operand()
-            .take_Number()
-            .expect("Expected a number in stack")
-    }
-
-    fn pop_bool(&mut self) -> bool {
-        self.pop_operand()
-            .take_Bool()
-            .expect("Expected a boolean in stack")
-    }
-
-    fn pop_ref(&mut self) -> NonNull<Obj> {
-        self.pop_operand()
-            .take_Ref()
-            .expect("Expected a Ref object in stack")
+    fn pop_num(&mut self) -> Result<f64, RuntimeError> {
+        let value = self.pop_operand();
+        return value.Number().cloned().ok_or_else(|| {
+            RuntimeError::new(
+                RuntimeErrorKind::TypeError,
+                format!("Expected a number, found {}", value),
+            )
+        });
     }
 
-    fn pop_arr(&mut self) -> &mut ObjArray {
-        unsafe {
-            let mut arr = self.pop_ref();
-            match arr.as_ref().typ {
-                ObjType::Array => Obj::AsArrayRef_mut(&mut arr).expect("Critical: Obj.typ == Array, but Obj.AsArrayRef_mut() failed."),
-                _ => panic!("Attempt to load from a non-array object."),
+    fn pop_arr(&mut self) -> Result<&mut ObjArray, RuntimeError> {
+        let value = self.pop_operand();
+        return match value {
+            Value::Ref(mut arr) if Obj::AsArrayRef(&arr).is_some() => {
+                Ok(Obj::AsArrayRef_mut(&mut arr).unwrap())
             }
-        }
+            _ => Err(RuntimeError::new(
+                RuntimeErrorKind::TypeError,
+                format!("Expected an array, found {}", value),
+            )),
+        };
     }
 
-    fn push_operand(&mut self, value: Value) {
+    fn push_operand(&mut self, value: Value) -> Result<(), RuntimeError> {
         if log_enabled!(Trace) {
             trace!("VM::push_operand({:?})", value);
         }
 
-        if self.max_stack != 0 && self.operands.len() >= self.max_stack as usize {
-            panic!("Critical: Operand stack overflow! max_stack={}. Did the compiler compute invalid stack depth?", self.max_stack);
+        let frame = self.frame();
+        let max_stack = frame.code.max_stack();
+        if max_stack != 0 && self.operands.len() - frame.stack_base >= max_stack as usize {
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::StackOverflow,
+                format!("Operand stack overflow, max_stack={}", max_stack),
+            ));
+        }
+
+        if let Some(max_operands) = self.limits.max_operands {
+            if self.operands.len() >= max_operands {
+                return Err(RuntimeError::new(
+                    RuntimeErrorKind::OperandLimitExceeded,
+                    format!("Maximum operand stack size of {} exceeded", max_operands),
+                ));
+            }
         }
 
         self.operands.push(value);
@@ -231,16 +546,17 @@ impl<'inst> CodeExecutor<'inst> {
                 self.operands.len()
             );
         }
+
+        Ok(())
     }
 
-    fn load_constant(&mut self, index: CpSize) {
+    fn load_constant(&mut self, index: CpSize) -> Result<(), RuntimeError> {
         if log_enabled!(Trace) {
             trace!("VM::load_constant({})", index);
         }
 
         if let Some(str) = self.constant_pool().get_string(index) {
-            self.push_operand(Value::String(str));
-            return;
+            return self.push_operand(Value::String(str));
         }
 
         let constant = self
@@ -255,6 +571,7 @@ impl<'inst> CodeExecutor<'inst> {
                     "Unsupported constant type: {:?}, index: {}, ignoring.",
                     constant, index
                 );
+                Ok(())
             }
         }
     }
@@ -265,273 +582,1010 @@ impl<'inst> CodeExecutor<'inst> {
         }
 
         let value = self.try_pop_operand().unwrap_or(Value::Null);
-        self.variables[index as usize] = value.clone();
+        self.frame_mut().locals[index as usize] = value;
     }
 
-    pub fn load_var(&mut self, index: u16) {
+    pub fn load_var(&mut self, index: u16) -> Result<(), RuntimeError> {
         if log_enabled!(Trace) {
             trace!("VM::load_var({})", index);
         }
 
-        let value = self.variables[index as usize].clone();
-        self.push_operand(value);
+        let value = self.frame().locals[index as usize].clone();
+        self.push_operand(value)
     }
 
-    pub fn execute(&mut self, code: &Code) -> Result<Option<Value>, String> {
-        self.max_stack = code.max_stack();
-        self.max_locals = code.max_locals();
+    /// Store the value at the top of the stack to the variable at the given index in the
+    /// top-level frame.
+    pub fn store_global(&mut self, index: u16) {
+        if log_enabled!(Trace) {
+            trace!("VM::store_global({})", index);
+        }
 
+        let value = self.pop_operand();
+        self.frames[0].locals[index as usize] = value;
+    }
+
+    /// Load the variable at the given index in the top-level frame.
+    pub fn load_global(&mut self, index: u16) -> Result<(), RuntimeError> {
+        if log_enabled!(Trace) {
+            trace!("VM::load_global({})", index);
+        }
+
+        let value = self.frames[0].locals[index as usize].clone();
+        self.push_operand(value)
+    }
+
+    pub fn execute(
+        &mut self,
+        code: &'inst Code,
+        line_numbers: Option<&'inst LineNumberTable>,
+    ) -> Result<Option<Value>, RuntimeError> {
         if log_enabled!(Trace) {
             trace!(
                 "VM::execute(max_stack={}, max_locals={})",
-                self.max_stack,
-                self.max_locals
+                code.max_stack(),
+                code.max_locals()
             );
         }
 
-        self.operands = Vec::with_capacity(max(0, self.max_stack) as usize);
-
-        self.variables.clear();
-        self.variables
-            .resize_with(max(0, self.max_locals) as usize, &|| Value::Null);
+        self.operands = Vec::with_capacity(code.max_stack() as usize);
+
+        self.frames.clear();
+        if let Err(err) = self.push_frame(
+            code,
+            line_numbers,
+            Vec::with_capacity(0),
+            None,
+            String::from(CallFrame::TOP_LEVEL_NAME),
+        ) {
+            self.release();
+            return Err(err);
+        }
 
-        let insns = code.instructions();
+        let mut insns = code.instructions();
         if log_enabled!(Trace) {
             trace!("VM::execute(insns.len()={})", insns.len());
         }
 
         let mut pc = 0;
         let mut is_halted = false;
+        let mut executed: u64 = 0;
 
-        'insn: while pc < insns.len() {
-            let insn = read1!(insns, pc).as_op_size();
-            if log_enabled!(Trace) {
-                trace!(
-                    "VM::execute(pc={}, instruction={}, opcode={:?})",
-                    pc,
-                    insn,
-                    get_mnemonic(&insn)
-                );
+        while pc < insns.len() {
+            // all the live objects are reachable from the call frames between two instructions
+            if self.heap.should_collect() {
+                self.collect_garbage();
             }
 
-            match insn {
-                opcode::Nop => {}
-                opcode::Halt => {
+            let insn_pc = pc;
+            let result = self
+                .check_limits(executed)
+                .and_then(|_| self.debug_pause(insn_pc))
+                .and_then(|_| self.exec_next(&mut insns, &mut pc));
+            executed += 1;
+
+            match result {
+                Ok(false) => {}
+                Ok(true) => {
                     is_halted = true;
-                    break 'insn;
+                    break;
                 }
-                opcode::Add | opcode::Sub | opcode::Mult | opcode::Div => {
-                    self.exec_arithmetic(&insn);
+                Err(err) => {
+                    let err = self.with_stack_trace(err, insn_pc);
+                    self.release();
+                    return Err(err);
                 }
-                opcode::Print => {
-                    let value = self.pop_operand();
-                    println!("{}", value);
-                }
-                opcode::Ldc => {
-                    let const_idx = read2!(insns, pc);
-                    self.load_constant(const_idx);
+            }
+        }
+
+        if pc != insns.len() && !is_halted {
+            error!(
+                "Expected all instructions to be executed, but {} bytes are remaining",
+                insns.len() - pc
+            );
+        }
+
+        trace!("VM::execute(): pc: {}, is_halted: {}", pc, is_halted);
+
+        let result = self.try_pop_operand();
+        if result.is_some() {
+            trace!("VM::execute(): result: {:?}", result);
+        }
+
+        self.release();
+
+        // Return the result at the top of the stack
+        Ok(result)
+    }
+
+    /// Check whether the execution must be aborted before executing the next instruction, given
+    /// the number of instructions executed so far.
+    fn check_limits(&self, executed: u64) -> Result<(), RuntimeError> {
+        if let Some(max_instructions) = self.limits.max_instructions {
+            if executed >= max_instructions {
+                return Err(RuntimeError::new(
+                    RuntimeErrorKind::InstructionLimitExceeded,
+                    format!("Maximum instruction count of {} exceeded", max_instructions),
+                ));
+            }
+        }
+
+        if self.interrupt.load(AtomicOrdering::Relaxed) {
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::Interrupted,
+                "Execution interrupted",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Run the garbage collector, with the operands, the local variables and the closures of
+    /// the active call frames as the roots.
+    fn collect_garbage(&mut self) {
+        let values = self
+            .operands
+            .iter()
+            .chain(self.frames.iter().flat_map(|frame| frame.locals.iter()))
+            .filter_map(|value| value.Ref());
+        let closures = self.frames.iter().filter_map(|frame| frame.closure.as_ref());
+        self.heap.collect(values.chain(closures));
+    }
+
+    /// Fill in the address and the stack trace of the given error, which occurred while
+    /// executing the instruction at the given address in the current call frame.
+    fn with_stack_trace(&self, mut err: RuntimeError, pc: usize) -> RuntimeError {
+        err.pc = pc;
+        err.stack_trace = self.stack_trace(pc);
+        return err;
+    }
+
+    /// Get the call stack, given the address of the instruction being executed in the current
+    /// call frame.
+    fn stack_trace(&self, pc: usize) -> Vec<StackTraceElement> {
+        // the callers have stored the address of the instruction following the call instruction
+        let call_size = 1 + OpCode::Call.operand_size() as usize;
+
+        return self
+            .frames
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, frame)| {
+                let pc = if i == 0 { pc } else { frame.pc - call_size };
+                StackTraceElement {
+                    function: frame.name.clone(),
+                    pc,
+                    line: frame
+                        .line_numbers
+                        .and_then(|line_numbers| line_numbers.line_at(pc.as_code_size())),
                 }
-                opcode::BPush0 => self.push_operand(Value::Bool(false)),
-                opcode::BPush1 => self.push_operand(Value::Bool(true)),
-                opcode::Store0 => self.store_var(0),
-                opcode::Store1 => self.store_var(1),
-                opcode::Store2 => self.store_var(2),
-                opcode::Store3 => self.store_var(3),
-                opcode::Store => {
-                    let var_idx = read2!(insns, pc);
-                    self.store_var(var_idx);
+            })
+            .collect();
+    }
+
+    /// Pause the execution before the instruction at the given address in the current call
+    /// frame, if a debugger is attached and it requested to pause at the instruction.
+    fn debug_pause(&mut self, pc: usize) -> Result<(), RuntimeError> {
+        let debugger = match self.debugger.take() {
+            Some(debugger) => debugger,
+            None => return Ok(()),
+        };
+
+        let result = self.debug_pause0(&mut *debugger, pc);
+        self.debugger = Some(debugger);
+        result
+    }
+
+    fn debug_pause0(
+        &mut self,
+        debugger: &mut dyn DebugHook,
+        pc: usize,
+    ) -> Result<(), RuntimeError> {
+        let depth = self.frames.len();
+        let frame = self.frame();
+
+        // the line at which the instruction is the first instruction of the line
+        let line_start = frame.line_numbers.and_then(|line_numbers| {
+            line_numbers
+                .entries()
+                .iter()
+                .find(|entry| entry.start_pc as usize == pc)
+                .map(|entry| entry.line)
+        });
+
+        let pause = match self.debug_mode {
+            DebugMode::Continue => false,
+            DebugMode::Step => true,
+            DebugMode::StepOver(max_depth) => depth <= max_depth,
+        } || debugger
+            .breakpoints()
+            .iter()
+            .any(|breakpoint| match breakpoint {
+                Breakpoint::Pc {
+                    function,
+                    pc: bp_pc,
+                } => *bp_pc == pc && *function == frame.name,
+                Breakpoint::Line(line) => line_start == Some(*line),
+            });
+
+        if !pause {
+            return Ok(());
+        }
+
+        let state = PausedState {
+            function: &frame.name,
+            pc,
+            line: frame
+                .line_numbers
+                .and_then(|line_numbers| line_numbers.line_at(pc.as_code_size())),
+            depth,
+            operands: &self.operands[frame.stack_base..],
+            locals: &frame.locals,
+            code: frame.code,
+            constant_pool: self.constant_pool(),
+            stack_trace: self.stack_trace(pc),
+        };
+
+        self.debug_mode = match debugger.on_pause(&state) {
+            DebugAction::Continue => DebugMode::Continue,
+            DebugAction::Step => DebugMode::Step,
+            DebugAction::StepOver => DebugMode::StepOver(depth),
+            DebugAction::Quit => {
+                return Err(RuntimeError::new(
+                    RuntimeErrorKind::Interrupted,
+                    "Execution aborted by the debugger",
+                ))
+            }
+        };
+
+        Ok(())
+    }
+
+    /// Execute the next instruction, recording it in the profile if profiling is enabled.
+    fn exec_next(
+        &mut self,
+        insns: &mut &'inst Vec<u8>,
+        pc: &mut usize,
+    ) -> Result<bool, RuntimeError> {
+        let profile = match self.profile.as_mut() {
+            Some(profile) => profile,
+            None => return self.exec_insn(insns, pc),
+        };
+
+        let frame = self.frames.last().expect("Expected a call frame");
+        profile.record_hit(frame.code, &frame.name, *pc);
+
+        let opcode = insns[*pc];
+        let start = Instant::now();
+        let result = self.exec_insn(insns, pc);
+        let time = start.elapsed();
+
+        if let Some(profile) = self.profile.as_mut() {
+            profile.record_time(opcode, time);
+        }
+
+        result
+    }
+
+    /// Execute the instruction at the given address. Returns whether the execution should be
+    /// halted.
+    fn exec_insn(
+        &mut self,
+        insns: &mut &'inst Vec<u8>,
+        pc: &mut usize,
+    ) -> Result<bool, RuntimeError> {
+        let insn = read1!(insns, *pc).as_op_size();
+        if log_enabled!(Trace) {
+            trace!(
+                "VM::execute(pc={}, instruction={}, opcode={:?})",
+                pc,
+                insn,
+                get_mnemonic(&insn)
+            );
+        }
+
+        match insn {
+            opcode::Nop => {}
+            opcode::Halt => return Ok(true),
+            opcode::Add | opcode::Sub | opcode::Mult | opcode::Div => {
+                self.exec_arithmetic(&insn)?;
+            }
+            opcode::Print => {
+                let value = self.pop_operand();
+                println!("{}", value);
+            }
+            opcode::Ldc => {
+                let const_idx = read2!(insns, *pc);
+                self.load_constant(const_idx)?;
+            }
+            opcode::BPush0 => self.push_operand(Value::Bool(false))?,
+            opcode::BPush1 => self.push_operand(Value::Bool(true))?,
+            opcode::Store0 => self.store_var(0),
+            opcode::Store1 => self.store_var(1),
+            opcode::Store2 => self.store_var(2),
+            opcode::Store3 => self.store_var(3),
+            opcode::Store => {
+                let var_idx = read2!(insns, *pc);
+                self.store_var(var_idx);
+            }
+            opcode::Load0 => self.load_var(0)?,
+            opcode::Load1 => self.load_var(1)?,
+            opcode::Load2 => self.load_var(2)?,
+            opcode::Load3 => self.load_var(3)?,
+            opcode::Load => {
+                let var_idx = read2!(insns, *pc);
+                self.load_var(var_idx)?;
+            }
+
+            opcode::IfTruthy | opcode::IfFalsy => {
+                let addr = read2!(insns, *pc) as i16;
+                let value = self.peek_operand();
+
+                if (insn == opcode::IfTruthy && value.is_truthy())
+                    || (insn == opcode::IfFalsy && value.is_falsy())
+                {
+                    // jump to the specified address
+                    jmp(pc, addr);
+                    if log_enabled!(Trace) {
+                        trace!("VM::execute::jmp(pc={})", pc);
+                    }
                 }
-                opcode::Load0 => self.load_var(0),
-                opcode::Load1 => self.load_var(1),
-                opcode::Load2 => self.load_var(2),
-                opcode::Load3 => self.load_var(3),
-                opcode::Load => {
-                    let var_idx = read2!(insns, pc);
-                    self.load_var(var_idx);
+            }
+
+            opcode::IfEq
+            | opcode::IfNe
+            | opcode::IfLt
+            | opcode::IfGt
+            | opcode::IfLe
+            | opcode::IfGe => {
+                let addr = read2!(insns, *pc) as i16;
+                if self.cmp(&insn)? {
+                    jmp(pc, addr);
                 }
+            }
 
-                opcode::IfTruthy | opcode::IfFalsy => {
-                    let addr = read2!(insns, pc) as i16;
-                    let value = self.peek_operand();
-
-                    if (insn == opcode::IfTruthy && value.is_truthy())
-                        || (insn == opcode::IfFalsy && value.is_falsy())
-                    {
-                        // jump to the specified address
-                        jmp(&mut pc, addr);
-                        if log_enabled!(Trace) {
-                            trace!("VM::execute::jmp(pc={})", pc);
-                        }
-                    }
+            opcode::IfEqZ
+            | opcode::IfNeZ
+            | opcode::IfLtZ
+            | opcode::IfGtZ
+            | opcode::IfLeZ
+            | opcode::IfGeZ => {
+                let addr = read2!(insns, *pc) as i16;
+                if self.cmpz(&insn)? {
+                    jmp(pc, addr);
                 }
+            }
 
-                opcode::IfEq
-                | opcode::IfNe
-                | opcode::IfLt
-                | opcode::IfGt
-                | opcode::IfLe
-                | opcode::IfGe => {
-                    let addr = read2!(insns, pc) as i16;
-                    if self.cmp(&insn) {
-                        jmp(&mut pc, addr);
-                    }
+            opcode::Jmp => {
+                let addr = read2!(insns, *pc) as i16;
+                jmp(pc, addr);
+                if log_enabled!(Trace) {
+                    trace!("VM::execute::jmp(pc={})", pc);
                 }
+            }
 
-                opcode::IfEqZ
-                | opcode::IfNeZ
-                | opcode::IfLtZ
-                | opcode::IfGtZ
-                | opcode::IfLeZ
-                | opcode::IfGeZ => {
-                    let addr = read2!(insns, pc) as i16;
-                    if self.cmpz(&insn) {
-                        jmp(&mut pc, addr);
-                    }
+            opcode::Pop => {
+                if log_enabled!(Trace) {
+                    trace!("VM::execute::pop()");
                 }
+                self.pop_operand();
+            }
 
-                opcode::Jmp => {
-                    let addr = read2!(insns, pc) as i16;
-                    jmp(&mut pc, addr);
-                    if log_enabled!(Trace) {
-                        trace!("VM::execute::jmp(pc={})", pc);
+            opcode::Neg => {
+                let value = self.pop_operand();
+                self.push_operand(match value {
+                    Value::Number(num) => Value::Number(-num),
+                    _ => {
+                        // TODO: Should we warn the user?
+                        Value::Number(0.0)
                     }
-                }
+                })?;
+            }
 
-                opcode::Pop => {
-                    if log_enabled!(Trace) {
-                        trace!("VM::execute::pop()");
+            opcode::Not => {
+                let value = self.pop_operand();
+                self.push_operand(match value {
+                    Value::Bool(bool) => Value::Bool(!bool),
+                    _ => {
+                        // TODO: Should we warn the user?
+                        Value::Bool(false)
                     }
-                    self.pop_operand();
-                }
+                })?;
+            }
 
-                opcode::Neg => {
-                    let value = self.pop_operand();
-                    self.push_operand(match value {
-                        Value::Number(num) => Value::Number(-num),
-                        _ => {
-                            // TODO: Should we warn the user?
-                            Value::Number(0.0)
-                        }
-                    });
+            opcode::Dup => {
+                let value = self.peek_operand().clone();
+                self.push_operand(value)?;
+            }
+
+            opcode::ArrNew => self.arrnew()?,
+            opcode::ArrLd => self.arrld()?,
+            opcode::ArrPut => self.arrput()?,
+
+            opcode::NPush => self.push_operand(Value::Null)?,
+            opcode::GLoad => {
+                let var_idx = read2!(insns, *pc);
+                self.load_global(var_idx)?;
+            }
+            opcode::GStore => {
+                let var_idx = read2!(insns, *pc);
+                self.store_global(var_idx);
+            }
+            opcode::FnLoad => {
+                let decl_idx = read2!(insns, *pc);
+                self.fnload(decl_idx)?;
+            }
+            opcode::Closure => {
+                let decl_idx = read2!(insns, *pc);
+                self.closure(decl_idx)?;
+            }
+            opcode::UpvLoad => {
+                let upv_idx = read2!(insns, *pc);
+                let upvalue = self.get_upvalue(upv_idx)?;
+                self.push_operand(Self::cell_ref(&upvalue).value.clone())?;
+            }
+            opcode::UpvStore => {
+                let upv_idx = read2!(insns, *pc);
+                let value = self.pop_operand();
+                let mut upvalue = self.get_upvalue(upv_idx)?;
+                Self::cell_mut(&mut upvalue).value = value;
+            }
+            opcode::CellNew => {
+                let var_idx = read2!(insns, *pc);
+                let value = self.pop_operand();
+                let cell = self.heap.allocate_obj(ObjUpvalue::new(value))?;
+                self.frame_mut().locals[var_idx as usize] = Value::from(cell);
+            }
+            opcode::CellLoad => {
+                let var_idx = read2!(insns, *pc);
+                let cell = self.get_cell(var_idx)?;
+                self.push_operand(Self::cell_ref(&cell).value.clone())?;
+            }
+            opcode::CellStore => {
+                let var_idx = read2!(insns, *pc);
+                let value = self.pop_operand();
+                let mut cell = self.get_cell(var_idx)?;
+                Self::cell_mut(&mut cell).value = value;
+            }
+            opcode::Call => {
+                let argc = read1!(insns, *pc);
+                self.frame_mut().pc = *pc;
+
+                if let Some(code) = self.call(argc)? {
+                    *insns = code.instructions();
+                    *pc = 0;
+                }
+            }
+            opcode::ClsNew => {
+                let decl_idx = read2!(insns, *pc);
+                self.clsnew(decl_idx)?;
+            }
+            opcode::Inherit => self.inherit()?,
+            opcode::Method => self.method()?,
+            opcode::GetField => {
+                let name_idx = read2!(insns, *pc);
+                self.getfield(name_idx)?;
+            }
+            opcode::PutField => {
+                let name_idx = read2!(insns, *pc);
+                self.putfield(name_idx)?;
+            }
+            opcode::GetSuper => {
+                let name_idx = read2!(insns, *pc);
+                self.getsuper(name_idx)?;
+            }
+            opcode::NatLoad => {
+                let name_idx = read2!(insns, *pc);
+                self.natload(name_idx)?;
+            }
+            opcode::Ret => {
+                let value = self.pop_operand();
+                if self.frames.len() == 1 {
+                    // returning from the top-level code
+                    self.push_operand(value)?;
+                    return Ok(true);
                 }
 
-                opcode::Not => {
-                    let value = self.pop_operand();
-                    self.push_operand(match value {
-                        Value::Bool(bool) => Value::Bool(!bool),
-                        _ => {
-                            // TODO: Should we warn the user?
-                            Value::Bool(false)
+                let frame = self.frames.pop().unwrap();
+                self.operands.truncate(frame.stack_base);
+
+                let caller = self.frame();
+                *insns = caller.code.instructions();
+                *pc = caller.pc;
+
+                self.push_operand(value)?;
+            }
+
+            _ => {
+                return Err(RuntimeError::new(
+                    RuntimeErrorKind::InvalidBytecode,
+                    format!("Unexpected instruction: {:#04x}", insn),
+                ))
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Create a function object for the function declaration at the given index.
+    fn new_function(&mut self, index: u16) -> Result<NonNull<Obj>, RuntimeError> {
+        let decl = self.get_func_decl(index);
+        let name = self
+            .constant_pool()
+            .get(decl.name_index)
+            .and_then(|entry| entry.as_utf8())
+            .map(|utf8| utf8.to_string())
+            .unwrap_or_default();
+
+        let func = ObjFunction::new(index, decl.arity(), name);
+        return self.heap.allocate_obj(func);
+    }
+
+    fn fnload(&mut self, index: u16) -> Result<(), RuntimeError> {
+        let obj = self.new_function(index)?;
+        self.push_operand(Value::from(obj))
+    }
+
+    /// Load the native function with the name at the given index in the constant pool.
+    fn natload(&mut self, name_idx: CpSize) -> Result<(), RuntimeError> {
+        let name = self.get_utf8(name_idx);
+        let index = match self.natives.iter().position(|native| native.name == name) {
+            Some(index) => index,
+            None => {
+                return Err(RuntimeError::new(
+                    RuntimeErrorKind::UndefinedNative,
+                    format!("Undefined native function '{}'", name),
+                ))
+            }
+        };
+
+        let native = &self.natives[index];
+        let obj = ObjNative::new(index, native.arity, name);
+        let obj = self.heap.allocate_obj(obj)?;
+        self.push_operand(Value::from(obj))
+    }
+
+    /// Create a closure for the function declaration at the given index. The upvalues of the
+    /// closure are captured from the current call frame.
+    fn closure(&mut self, index: u16) -> Result<(), RuntimeError> {
+        let decl = self.get_func_decl(index);
+        let function = self.new_function(index)?;
+
+        let mut upvalues = Vec::with_capacity(decl.upvalues.len());
+        for upvalue in &decl.upvalues {
+            let cell = if upvalue.is_local {
+                self.get_cell(upvalue.index)?
+            } else {
+                self.get_upvalue(upvalue.index)?
+            };
+            upvalues.push(cell);
+        }
+
+        let closure = self.heap.allocate_obj(ObjClosure::new(function, upvalues))?;
+        self.push_operand(Value::from(closure))
+    }
+
+    /// Get the upvalue cell stored in the local variable at the given index. The compiler only
+    /// reads the cells of the variables it wrapped in a cell, so a malformed file may read
+    /// another value.
+    fn get_cell(&self, index: u16) -> Result<NonNull<Obj>, RuntimeError> {
+        return match &self.frame().locals[index as usize] {
+            Value::Ref(obj) if Obj::AsUpvalueRef(obj).is_some() => Ok(obj.clone()),
+            value => Err(RuntimeError::new(
+                RuntimeErrorKind::InvalidBytecode,
+                format!("Expected an upvalue cell in local {}, found {}", index, value),
+            )),
+        };
+    }
+
+    /// Get the upvalue at the given index of the closure in the current call frame. A function
+    /// which captures upvalues may be loaded without its closure by a malformed file.
+    fn get_upvalue(&self, index: u16) -> Result<NonNull<Obj>, RuntimeError> {
+        let upvalue = self
+            .frame()
+            .closure
+            .as_ref()
+            .and_then(|closure| Obj::AsClosureRef(closure))
+            .and_then(|closure| closure.upvalues.get(index as usize));
+
+        return match upvalue {
+            Some(upvalue) => Ok(upvalue.clone()),
+            None => Err(RuntimeError::new(
+                RuntimeErrorKind::InvalidBytecode,
+                format!("Expected an upvalue at index {}", index),
+            )),
+        };
+    }
+
+    fn cell_ref<'a>(cell: &NonNull<Obj>) -> &'a ObjUpvalue {
+        return Obj::AsUpvalueRef(cell).expect("Expected an upvalue cell");
+    }
+
+    fn cell_mut<'a>(cell: &mut NonNull<Obj>) -> &'a mut ObjUpvalue {
+        return Obj::AsUpvalueRef_mut(cell).expect("Expected an upvalue cell");
+    }
+
+    /// Call the value with the given number of arguments. The callee and the arguments are
+    /// popped from the operand stack. Functions, closures and bound methods are called in a new
+    /// call frame, in which case the [Code] of the callee is returned. Native functions are
+    /// called directly. Calling a class creates a new instance of the class and calls its
+    /// initializer, if there is one.
+    fn call(&mut self, argc: u8) -> Result<Option<&'inst Code>, RuntimeError> {
+        if self.operands.len() < self.frame().stack_base + argc as usize + 1 {
+            panic!("Expected a callee and {} arguments in stack", argc);
+        }
+
+        let args = self.operands.split_off(self.operands.len() - argc as usize);
+        let callee = self.pop_operand();
+        let obj = match callee.Ref() {
+            Some(obj) => obj.clone(),
+            None => return Err(Self::not_callable(&callee)),
+        };
+
+        return match unsafe { &obj.as_ref().typ } {
+            ObjType::Function | ObjType::Closure => self.call_func(obj, None, args).map(Some),
+            ObjType::BoundMethod => {
+                let bound = Obj::AsBoundMethodRef(&obj).unwrap();
+                self.call_func(bound.method, Some(bound.receiver.clone()), args)
+                    .map(Some)
+            }
+            ObjType::Native => {
+                let native = Obj::AsNativeRef(&obj).unwrap();
+                self.call_native(native, args)?;
+                Ok(None)
+            }
+            ObjType::Class => {
+                let class = Obj::AsClassRef(&obj).unwrap();
+                let instance = self
+                    .heap
+                    .allocate_obj(ObjInstance::new(obj, HashMap::new()))?;
+
+                match class.methods.get(ClassDecl::INIT_NAME) {
+                    Some(init) => self
+                        .call_func(*init, Some(Value::from(instance)), args)
+                        .map(Some),
+                    None => {
+                        if argc != 0 {
+                            return Err(Self::arity_mismatch(&class.name, 0, argc as usize));
                         }
-                    });
-                }
 
-                opcode::Dup => {
-                    let value = self.peek_operand().clone();
-                    self.push_operand(value);
+                        self.push_operand(Value::from(instance))?;
+                        Ok(None)
+                    }
                 }
+            }
+            _ => Err(Self::not_callable(&callee)),
+        };
+    }
 
-                opcode::ArrNew => self.arrnew(),
-                opcode::ArrLd => self.arrld(),
-                opcode::ArrPut => self.arrput(),
+    fn not_callable(callee: &Value) -> RuntimeError {
+        return RuntimeError::new(
+            RuntimeErrorKind::TypeError,
+            format!("Attempt to call a non-function value: {}", callee),
+        );
+    }
 
-                _ => panic!("Unexpected instruction: {:?}", get_opcode(insn)),
-            }
+    fn arity_mismatch(name: &str, arity: u8, argc: usize) -> RuntimeError {
+        return RuntimeError::new(
+            RuntimeErrorKind::ArityMismatch,
+            format!(
+                "Function '{}' expects {} arguments, but got {}",
+                name, arity, argc
+            ),
+        );
+    }
+
+    /// Call the given function or closure. The receiver, if any, is passed as the first argument
+    /// to the function. Returns the [Code] of the function.
+    fn call_func(
+        &mut self,
+        callee: NonNull<Obj>,
+        receiver: Option<Value>,
+        args: Vec<Value>,
+    ) -> Result<&'inst Code, RuntimeError> {
+        let (func, closure) = match Obj::AsClosureRef(&callee) {
+            Some(closure) => (closure.function(), Some(callee)),
+            None => (
+                Obj::AsFunctionRef(&callee).expect("Expected a function or a closure"),
+                None,
+            ),
+        };
+
+        if func.arity as usize != args.len() {
+            return Err(Self::arity_mismatch(&func.name, func.arity, args.len()));
         }
 
-        if pc != insns.len() && !is_halted {
-            error!(
-                "Expected all instructions to be executed, but {} bytes are remaining",
-                insns.len() - pc
-            );
+        let args = match receiver {
+            Some(receiver) => std::iter::once(receiver).chain(args).collect(),
+            None => args,
+        };
+
+        let decl = self.get_func_decl(func.index);
+        self.push_frame(
+            decl.code(),
+            decl.line_numbers(),
+            args,
+            closure,
+            func.name.clone(),
+        )?;
+        return Ok(decl.code());
+    }
+
+    /// Call the given native function and push its result onto the operand stack.
+    fn call_native(&mut self, native: &ObjNative, args: Vec<Value>) -> Result<(), RuntimeError> {
+        if native.arity as usize != args.len() {
+            return Err(Self::arity_mismatch(&native.name, native.arity, args.len()));
         }
 
-        trace!("VM::execute(): pc: {}, is_halted: {}", pc, is_halted);
+        let function = self.natives[native.index].function;
+        let mut context = NativeContext::new(&native.name, self.heap);
+        let value = function(&mut context, &args)?;
+        self.push_operand(value)
+    }
 
-        let result = self.try_pop_operand();
-        if result.is_some() {
-            trace!("VM::execute(): result: {:?}", result);
+    fn clsnew(&mut self, index: u16) -> Result<(), RuntimeError> {
+        let decl = self.get_class_decl(index);
+        let name = self.get_utf8(decl.name_index);
+        let class = self.heap.allocate_obj(ObjClass::new(name, HashMap::new()))?;
+        self.push_operand(Value::from(class))
+    }
+
+    /// Copy the methods of the superclass at the top of the stack to the class below it.
+    fn inherit(&mut self) -> Result<(), RuntimeError> {
+        let supercls = self.pop_operand();
+        let supercls = match supercls.Ref().and_then(|obj| Obj::AsClassRef(obj)) {
+            Some(supercls) => supercls,
+            None => {
+                return Err(RuntimeError::new(
+                    RuntimeErrorKind::TypeError,
+                    format!("Superclass must be a class, found {}", supercls),
+                ))
+            }
+        };
+
+        let mut class = self.peek_class()?;
+        let class = Obj::AsClassRef_mut(&mut class).unwrap();
+        for (name, method) in &supercls.methods {
+            class.methods.insert(name.clone(), *method);
         }
 
-        self.release();
+        Ok(())
+    }
 
-        // Return the result at the top of the stack
-        Ok(result)
+    /// Add the method at the top of the stack to the class below it.
+    fn method(&mut self) -> Result<(), RuntimeError> {
+        let value = self.pop_operand();
+        let (method, name) = match value.Ref() {
+            Some(obj) => match Obj::AsClosureRef(obj) {
+                Some(closure) => (*obj, closure.function().name.clone()),
+                None => match Obj::AsFunctionRef(obj) {
+                    Some(function) => (*obj, function.name.clone()),
+                    None => return Err(Self::not_a_method(&value)),
+                },
+            },
+            None => return Err(Self::not_a_method(&value)),
+        };
+
+        let mut class = self.peek_class()?;
+        let class = Obj::AsClassRef_mut(&mut class).unwrap();
+        class.methods.insert(name, method);
+        Ok(())
+    }
+
+    fn not_a_method(value: &Value) -> RuntimeError {
+        return RuntimeError::new(
+            RuntimeErrorKind::TypeError,
+            format!("Expected a function or a closure, found {}", value),
+        );
+    }
+
+    fn peek_class(&mut self) -> Result<NonNull<Obj>, RuntimeError> {
+        return match self.peek_operand() {
+            Value::Ref(obj) if Obj::AsClassRef(obj).is_some() => Ok(obj.clone()),
+            value => Err(RuntimeError::new(
+                RuntimeErrorKind::TypeError,
+                format!("Expected a class, found {}", value),
+            )),
+        };
+    }
+
+    fn pop_instance(&mut self) -> Result<NonNull<Obj>, RuntimeError> {
+        let value = self.pop_operand();
+        return match value.Ref() {
+            Some(obj) if Obj::AsInstanceRef(obj).is_some() => Ok(obj.clone()),
+            _ => Err(RuntimeError::new(
+                RuntimeErrorKind::TypeError,
+                format!("Only instances have properties, found {}", value),
+            )),
+        };
+    }
+
+    /// Load the field or the method with the given name of the instance at the top of the stack.
+    fn getfield(&mut self, name_idx: CpSize) -> Result<(), RuntimeError> {
+        let name = self.get_utf8(name_idx);
+        let instance = self.pop_instance()?;
+        let inst = Obj::AsInstanceRef(&instance).unwrap();
+
+        if let Some(value) = inst.fields.get(&name) {
+            let value = value.clone();
+            return self.push_operand(value);
+        }
+
+        match inst.class().methods.get(&name) {
+            Some(method) => self.bind_method(Value::from(instance), *method),
+            None => Err(RuntimeError::new(
+                RuntimeErrorKind::UndefinedProperty,
+                format!("Undefined property '{}'", name),
+            )),
+        }
+    }
+
+    /// Store the value at the top of the stack to the field with the given name of the instance
+    /// below it.
+    fn putfield(&mut self, name_idx: CpSize) -> Result<(), RuntimeError> {
+        let name = self.get_utf8(name_idx);
+        let value = self.pop_operand();
+        let mut instance = self.pop_instance()?;
+        let inst = Obj::AsInstanceRef_mut(&mut instance).unwrap();
+        inst.fields.insert(name, value);
+        Ok(())
     }
 
-    fn arrnew(&mut self) {
-        let size = self.pop_num();
+    /// Load the method with the given name of the superclass at the top of the stack, bound to
+    /// the receiver below it.
+    fn getsuper(&mut self, name_idx: CpSize) -> Result<(), RuntimeError> {
+        let name = self.get_utf8(name_idx);
+        let supercls = self.pop_operand();
+        let receiver = self.pop_operand();
+        let supercls = match supercls.Ref().and_then(|obj| Obj::AsClassRef(obj)) {
+            Some(supercls) => supercls,
+            None => {
+                return Err(RuntimeError::new(
+                    RuntimeErrorKind::TypeError,
+                    format!("Superclass must be a class, found {}", supercls),
+                ))
+            }
+        };
+
+        match supercls.methods.get(&name) {
+            Some(method) => self.bind_method(receiver, *method),
+            None => Err(RuntimeError::new(
+                RuntimeErrorKind::UndefinedProperty,
+                format!("Undefined superclass method '{}'", name),
+            )),
+        }
+    }
+
+    fn bind_method(&mut self, receiver: Value, method: NonNull<Obj>) -> Result<(), RuntimeError> {
+        let bound = self
+            .heap
+            .allocate_obj(ObjBoundMethod::new(receiver, method))?;
+        self.push_operand(Value::from(bound))
+    }
+
+    fn arrnew(&mut self) -> Result<(), RuntimeError> {
+        let size = self.pop_num()?;
         if size < 0.0 {
-            panic!("Array size cannot be negative");
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::InvalidArraySize,
+                format!("Array size cannot be negative: {}", size),
+            ));
         }
         
         let size = size as usize;
-        
+
+        // the size of the array is checked before its elements are allocated
+        let bytes = size
+            .checked_mul(std::mem::size_of::<Value>())
+            .and_then(|bytes| bytes.checked_add(ObjType::ArraySize()));
+        match bytes {
+            Some(bytes) => self.heap.check_alloc(bytes)?,
+            None => return Err(Self::array_too_large(size)),
+        }
+
         let mut elements = Vec::new();
+        if elements.try_reserve_exact(size).is_err() {
+            return Err(Self::array_too_large(size));
+        }
         elements.resize(size, Value::Null);
         
         let arr = ObjArray::new(size, elements);
         let obj = self
             .heap
-            .allocate_obj(arr);
+            .allocate_obj(arr)?;
 
-        self.push_operand(Value::from(obj));
+        self.push_operand(Value::from(obj))
     }
 
-    fn arrld(&mut self) {
-        let index = self.pop_num();
-        let arr = self.pop_arr();
-        let val = arr.get(index).clone();
-        self.push_operand(val);
+    fn array_too_large(size: usize) -> RuntimeError {
+        return RuntimeError::new(
+            RuntimeErrorKind::InvalidArraySize,
+            format!("Array size is too large: {}", size),
+        );
     }
 
-    fn arrput(&mut self) {
+    fn arrld(&mut self) -> Result<(), RuntimeError> {
+        let index = self.pop_num()?;
+        let arr = self.pop_arr()?;
+        let val = arr.get(index)?.clone();
+        self.push_operand(val)
+    }
+
+    fn arrput(&mut self) -> Result<(), RuntimeError> {
         let value = self.pop_operand();
-        let index = self.pop_num();
-        let arr = self.pop_arr();
-        arr.set(index, value);
+        let index = self.pop_num()?;
+        let arr = self.pop_arr()?;
+        arr.set(index, value)
     }
 
-    fn cmp(&mut self, op: &OpSize) -> bool {
+    fn cmp(&mut self, op: &OpSize) -> Result<bool, RuntimeError> {
         let op2 = self.pop_operand();
         let op1 = self.pop_operand();
+        Self::compare(op, &op1, &op2)
+    }
 
-        if let (Some(n2), Some(n1)) = (op2.Number(), op1.Number()) {
-            return match op {
-                &opcode::IfEq => n1 == n2,
-                &opcode::IfNe => n1 != n2,
-                &opcode::IfLt => n1 < n2,
-                &opcode::IfLe => n1 <= n2,
-                &opcode::IfGt => n1 > n2,
-                &opcode::IfGe => n1 >= n2,
-                _ => unreachable!("cmp is not implemented for {:?}", op),
-            };
-        }
+    fn cmpz(&mut self, op_code: &OpSize) -> Result<bool, RuntimeError> {
+        let op = self.pop_operand();
+        let op_code = match op_code {
+            &opcode::IfEqZ => opcode::IfEq,
+            &opcode::IfNeZ => opcode::IfNe,
+            &opcode::IfLtZ => opcode::IfLt,
+            &opcode::IfLeZ => opcode::IfLe,
+            &opcode::IfGtZ => opcode::IfGt,
+            &opcode::IfGeZ => opcode::IfGe,
+            _ => unreachable!("cmpz is not implemented for {:?}", op_code),
+        };
 
-        // TODO: implement comparison between other types
-        false
+        Self::compare(&op_code, &op, &Value::Number(0f64))
     }
 
-    fn cmpz(&mut self, op_code: &OpSize) -> bool {
-        let op = self.pop_operand();
-        if let Some(n) = op.Number() {
-            return match op_code {
-                &opcode::IfEqZ => n == &0f64,
-                &opcode::IfNeZ => n != &0f64,
-                &opcode::IfLtZ => n < &0f64,
-                &opcode::IfLeZ => n <= &0f64,
-                &opcode::IfGtZ => n > &0f64,
-                &opcode::IfGeZ => n >= &0f64,
-                _ => unreachable!("cmp is not implemented for {:?}", op),
-            };
+    /// Compare the given values. Values of any type can be checked for equality, but only
+    /// numbers and strings can be ordered.
+    fn compare(op: &OpSize, op1: &Value, op2: &Value) -> Result<bool, RuntimeError> {
+        match op {
+            &opcode::IfEq => return Ok(op1 == op2),
+            &opcode::IfNe => return Ok(op1 != op2),
+            _ => {}
+        }
+
+        if !op1.is_comparable(op2) {
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::TypeError,
+                format!("Cannot compare {} with {}", op1, op2),
+            ));
         }
 
-        // TODO: implement comparison between other types
-        false
+        let ordering = op1.partial_compare(op2);
+        return Ok(match op {
+            &opcode::IfLt => ordering == Some(Ordering::Less),
+            &opcode::IfLe => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            &opcode::IfGt => ordering == Some(Ordering::Greater),
+            &opcode::IfGe => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+            _ => unreachable!("cmp is not implemented for {:?}", op),
+        });
     }
 
-    fn exec_arithmetic(&mut self, op: &OpSize) {
+    fn exec_arithmetic(&mut self, op: &OpSize) -> Result<(), RuntimeError> {
         let op2 = self.pop_operand();
+
+        // the length of the string resulting from a concatenation or a repetition is checked
+        // before it is allocated, the strings are not counted by the heap but must fit in it
+        if let Some(len) = Self::str_len(op, self.peek_operand(), &op2) {
+            if len > Value::MAX_STRING_LEN {
+                return Err(RuntimeError::new(
+                    RuntimeErrorKind::StringLengthExceeded,
+                    format!(
+                        "Cannot perform '{}' on strings: the result would be longer than {} bytes",
+                        get_mnemonic(op),
+                        Value::MAX_STRING_LEN
+                    ),
+                ));
+            }
+
+            self.heap.check_alloc(len)?;
+        }
+
         let op1 = self.peek_operand_mut();
 
         match (op1, op2) {
@@ -544,7 +1598,54 @@ impl<'inst> CodeExecutor<'inst> {
                     _ => panic!("Expected a binary numeric operator"),
                 };
             }
-            _ => panic!("Expected numeric operands"),
+            (op1, op2) => {
+                // strings can be concatenated and repeated
+                let result = match op {
+                    &opcode::Add => op1.clone() + op2.clone(),
+                    &opcode::Mult => op1.clone() * op2.clone(),
+                    _ => Err(String::new()),
+                };
+
+                match result {
+                    Ok(result) => *op1 = result,
+                    Err(_) => {
+                        return Err(RuntimeError::new(
+                            RuntimeErrorKind::TypeError,
+                            format!(
+                                "Cannot perform '{}' on {} and {}",
+                                get_mnemonic(op),
+                                op1,
+                                op2
+                            ),
+                        ))
+                    }
+                }
+            }
+        };
+
+        Ok(())
+    }
+
+    /// Get the length of the string which results from concatenating or repeating the given
+    /// operands, if the result is a string. The length saturates at [usize::MAX].
+    fn str_len(op: &OpSize, op1: &Value, op2: &Value) -> Option<usize> {
+        let display_len = |value: &Value| match value {
+            Value::String(str) => str.len(),
+            _ => value.to_string().len(),
+        };
+
+        return match (op, op1, op2) {
+            (&opcode::Add, Value::String(_), Value::String(_) | Value::Number(_))
+            | (&opcode::Add, Value::Number(_), Value::String(_)) => {
+                Some(display_len(op1).saturating_add(display_len(op2)))
+            }
+            (&opcode::Mult, Value::String(str), Value::Number(count))
+            | (&opcode::Mult, Value::Number(count), Value::String(str))
+                if *count >= 0.0 && count.fract() == 0.0 =>
+            {
+                Some(str.len().saturating_mul(*count as usize))
+            }
+            _ => None,
         };
     }
 }
@@ -18,6 +18,7 @@ use clap::Subcommand;
 
 use compiler::args::CompileArgs;
 use compiler::args::DisassembleArgs;
+use vm::args::DebugArgs;
 use vm::args::RunArgs;
 
 #[derive(Parser, Debug)]
@@ -61,6 +62,9 @@ pub enum SubCommand {
 
     /// Disassemble the compiled bytecode.
     Disassemble(DisassembleArgs),
+
+    /// Run the compiled bytecode in an interactive debugger.
+    Debug(DebugArgs),
 }
 
 impl SubCommand {
@@ -69,6 +73,7 @@ impl SubCommand {
             SubCommand::Compile(_) => "compile",
             SubCommand::Run(_) => "run",
             SubCommand::Disassemble(_) => "disassemble",
+            SubCommand::Debug(_) => "debug",
         }
     }
 }
@@ -13,27 +13,34 @@
  * program. If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::process::ExitCode;
+
 use log::{info, trace};
 
 use compiler::args::CompileArgs;
 use compiler::comp::YKCompiler;
 use compiler::features::CompilerFeatures;
 
-pub fn do_compile(args: &mut CompileArgs) -> Result<(), ()> {
+pub fn do_compile(args: &mut CompileArgs) -> Result<ExitCode, ()> {
     if args.files.is_empty() {
         info!("No files to compile...!");
         return Err(());
     }
 
-    perform_compilation(args)?;
-
-    Ok(())
+    // the errors have already been reported by the compiler, the usage would only hide them
+    return match perform_compilation(args) {
+        Ok(_) => Ok(ExitCode::SUCCESS),
+        Err(_) => Ok(ExitCode::FAILURE),
+    };
 }
 
 fn perform_compilation(args: &CompileArgs) -> Result<(), ()> {
     trace!("Compiler args: {:?}", args);
 
     let mut features = CompilerFeatures::default();
+    for feature in &args.enable_features {
+        features.set(&feature, true);
+    }
     for feature in &args.disable_features {
         features.set(&feature, false);
     }
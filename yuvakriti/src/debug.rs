@@ -0,0 +1,253 @@
+/*
+ * Copyright (c) 2024 Akash Yadav
+ *
+ * This program is free software: you can redistribute it and/or modify it under the
+ *  terms of the GNU General Public License as published by the Free Software
+ *  Foundation, version 3.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with this
+ * program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::io::stdin;
+use std::io::stdout;
+use std::io::BufRead;
+use std::io::Write;
+use std::process::ExitCode;
+
+use log::error;
+
+use compiler::bytecode::disassemble_insn;
+use vm::args::DebugArgs;
+use vm::Breakpoint;
+use vm::DebugAction;
+use vm::DebugHook;
+use vm::PausedState;
+use vm::Value;
+use vm::YKVM;
+
+use crate::run::read_ykb_file;
+
+const HELP: &str = "Commands:
+  break <pc>             Set a breakpoint at the address in the current function (alias: b)
+  break <function>:<pc>  Set a breakpoint at the address in the given function
+  break line <line>      Set a breakpoint at the given source line
+  delete <n>             Delete the breakpoint with the given number
+  breakpoints            List the breakpoints
+  step                   Execute the next instruction (alias: s)
+  next                   Execute the next instruction, stepping over calls (alias: n)
+  continue               Continue until the next breakpoint is hit (alias: c)
+  stack                  Print the operand stack
+  locals                 Print the local variables
+  backtrace              Print the call stack (alias: bt)
+  disassemble [n]        Disassemble n instructions around the current address (alias: d)
+  quit                   Abort the program (alias: q)
+  help                   Print this help";
+
+/// Number of instructions disassembled before and after the current address by default.
+const DEFAULT_DISASSEMBLE_COUNT: usize = 5;
+
+/// Run the bytecode file in an interactive debugger which reads the commands from the standard
+/// input. Returns the exit code of the program, or an error if the arguments are invalid.
+pub fn do_debug(args: &mut DebugArgs) -> Result<ExitCode, ()> {
+    if !args.path.exists() {
+        error!("File does not exist: {}", args.path.display());
+        return Err(());
+    }
+
+    let file = read_ykb_file(&args.path)?;
+    let mut vm = YKVM::new();
+    let mut debugger = Debugger::new();
+
+    println!("Type 'help' for a list of commands.");
+    match vm.debug(&file, &mut debugger) {
+        Ok(_) => Ok(ExitCode::SUCCESS),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            Ok(ExitCode::FAILURE)
+        }
+    }
+}
+
+/// A debugger which reads the commands from the standard input.
+struct Debugger {
+    breakpoints: Vec<Breakpoint>,
+}
+
+impl Debugger {
+    fn new() -> Debugger {
+        return Debugger {
+            breakpoints: Vec::new(),
+        };
+    }
+
+    /// Execute the given command. Returns the action to resume the program with, or [None] if
+    /// the debugger should wait for the next command.
+    fn command(&mut self, line: &str, state: &PausedState) -> Option<DebugAction> {
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command,
+            None => return None,
+        };
+        let args: Vec<&str> = words.collect();
+
+        match command {
+            "step" | "s" => return Some(DebugAction::Step),
+            "next" | "n" => return Some(DebugAction::StepOver),
+            "continue" | "c" => return Some(DebugAction::Continue),
+            "quit" | "q" => return Some(DebugAction::Quit),
+            "break" | "b" => self.add_breakpoint(&args, state),
+            "delete" => self.delete_breakpoint(&args),
+            "breakpoints" => self.print_breakpoints(),
+            "stack" => print_values("operand", state.operands),
+            "locals" => print_values("local", state.locals),
+            "backtrace" | "bt" => {
+                for element in &state.stack_trace {
+                    println!("  {}", element);
+                }
+            }
+            "disassemble" | "d" => match args.first().map(|count| count.parse()) {
+                None => disassemble(state, DEFAULT_DISASSEMBLE_COUNT),
+                Some(Ok(count)) => disassemble(state, count),
+                Some(Err(_)) => println!("Invalid instruction count: {}", args[0]),
+            },
+            "help" | "h" => println!("{}", HELP),
+            _ => println!(
+                "Unknown command: {}. Type 'help' for a list of commands.",
+                command
+            ),
+        }
+
+        return None;
+    }
+
+    fn add_breakpoint(&mut self, args: &[&str], state: &PausedState) {
+        let breakpoint = match args {
+            ["line", line] => line.parse().ok().map(Breakpoint::Line),
+            [location] => match location.rsplit_once(':') {
+                Some((function, pc)) => pc.parse().ok().map(|pc| Breakpoint::Pc {
+                    function: function.to_string(),
+                    pc,
+                }),
+                None => location.parse().ok().map(|pc| Breakpoint::Pc {
+                    function: state.function.to_string(),
+                    pc,
+                }),
+            },
+            _ => None,
+        };
+
+        match breakpoint {
+            Some(breakpoint) => {
+                println!(
+                    "Breakpoint {}: {}",
+                    self.breakpoints.len() + 1,
+                    describe(&breakpoint)
+                );
+                self.breakpoints.push(breakpoint);
+            }
+            None => println!("Usage: break <pc> | break <function>:<pc> | break line <line>"),
+        }
+    }
+
+    fn delete_breakpoint(&mut self, args: &[&str]) {
+        match args.first().and_then(|n| n.parse::<usize>().ok()) {
+            Some(n) if n > 0 && n <= self.breakpoints.len() => {
+                let breakpoint = self.breakpoints.remove(n - 1);
+                println!("Deleted breakpoint {}: {}", n, describe(&breakpoint));
+            }
+            _ => println!("Usage: delete <n>, where n is the number of the breakpoint"),
+        }
+    }
+
+    fn print_breakpoints(&self) {
+        if self.breakpoints.is_empty() {
+            println!("No breakpoints");
+        }
+
+        for (index, breakpoint) in self.breakpoints.iter().enumerate() {
+            println!("  {}: {}", index + 1, describe(breakpoint));
+        }
+    }
+}
+
+impl DebugHook for Debugger {
+    fn breakpoints(&self) -> &[Breakpoint] {
+        return &self.breakpoints;
+    }
+
+    fn on_pause(&mut self, state: &PausedState) -> DebugAction {
+        match state.line {
+            Some(line) => println!(
+                "Paused in {} at pc {} (line {})",
+                state.function, state.pc, line
+            ),
+            None => println!("Paused in {} at pc {}", state.function, state.pc),
+        }
+        disassemble(state, 0);
+
+        let stdin = stdin();
+        let mut line = String::new();
+        loop {
+            print!("(ykdb) ");
+            stdout().flush().unwrap();
+
+            line.clear();
+            match stdin.lock().read_line(&mut line) {
+                // end of input
+                Ok(0) | Err(_) => return DebugAction::Quit,
+                Ok(_) => {}
+            }
+
+            if let Some(action) = self.command(&line, state) {
+                return action;
+            }
+        }
+    }
+}
+
+fn describe(breakpoint: &Breakpoint) -> String {
+    return match breakpoint {
+        Breakpoint::Pc { function, pc } => format!("{} pc {}", function, pc),
+        Breakpoint::Line(line) => format!("line {}", line),
+    };
+}
+
+fn print_values(kind: &str, values: &[Value]) {
+    if values.is_empty() {
+        println!("No {}s", kind);
+    }
+
+    for (index, value) in values.iter().enumerate() {
+        println!("  #{}: {}", index, value);
+    }
+}
+
+/// Print the instructions of the paused function, from `count` instructions before the current
+/// address to `count` instructions after it. The current instruction is marked with an arrow.
+fn disassemble(state: &PausedState, count: usize) {
+    let insns = state.code.instructions();
+    let mut lines = Vec::new();
+    let mut current = 0;
+    let mut index = 0;
+    while index < insns.len() {
+        if index == state.pc {
+            current = lines.len();
+        }
+
+        let (line, next) = disassemble_insn(insns, index, state.constant_pool);
+        lines.push((index, line));
+        index = next;
+    }
+
+    let start = current.saturating_sub(count);
+    let end = (current + count + 1).min(lines.len());
+    for (index, line) in &lines[start..end] {
+        let marker = if *index == state.pc { "=>" } else { "  " };
+        println!("{}{}", marker, line);
+    }
+}
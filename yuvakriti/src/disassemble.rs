@@ -31,11 +31,20 @@ pub fn do_disassemble(args: &mut DisassembleArgs) -> Result<(), ()> {
 }
 
 fn perform_disassembly(args: &DisassembleArgs) -> Result<(), ()> {
-    let file = File::open(&args.file).unwrap();
+    let file = match File::open(&args.file) {
+        Ok(file) => file,
+        Err(err) => {
+            println!("Unable to open {}: {}", args.file.display(), err);
+            return Err(());
+        }
+    };
     let input = ByteInput::new(file);
     let mut out = String::new();
     let mut disassembler = YKBDisassembler::new(input, &mut out);
-    disassembler.disassemble().unwrap();
+    if let Err(err) = disassembler.disassemble() {
+        println!("Unable to read {}: {}", args.file.display(), err);
+        return Err(());
+    }
 
     println!("{}", out);
 
@@ -6,11 +6,13 @@ use clap::FromArgMatches;
 use crate::args::SubCommand;
 use crate::args::YkArgs;
 use crate::compile::do_compile;
+use crate::debug::do_debug;
 use crate::disassemble::do_disassemble;
 use crate::run::do_run;
 
 mod args;
 mod compile;
+mod debug;
 mod disassemble;
 mod run;
 
@@ -34,9 +36,10 @@ fn main() -> ExitCode {
         match match subcommand {
             SubCommand::Compile(args) => do_compile(args),
             SubCommand::Run(args) => do_run(args),
-            SubCommand::Disassemble(args) => do_disassemble(args),
+            SubCommand::Disassemble(args) => do_disassemble(args).map(|_| ExitCode::SUCCESS),
+            SubCommand::Debug(args) => do_debug(args),
         } {
-            Ok(_) => {}
+            Ok(code) => return code,
             Err(_) => {
                 let sub = command.find_subcommand_mut(subcommand.name()).unwrap();
                 let command_help = sub.render_long_help();
@@ -14,25 +14,66 @@
  */
 
 use std::fs::File;
+use std::path::Path;
+use std::process::ExitCode;
 
 use log::error;
 
 use compiler::bytecode::bytes::ByteInput;
+use compiler::bytecode::YKBFile;
 use compiler::bytecode::YKBFileReader;
+use vm::args::ProfileFormat;
 use vm::args::RunArgs;
 use vm::YKVM;
 
-pub fn do_run(args: &mut RunArgs) -> Result<(), ()> {
+/// Run the bytecode file. Returns the exit code of the program, or an error if the arguments
+/// are invalid.
+pub fn do_run(args: &mut RunArgs) -> Result<ExitCode, ()> {
     if !args.path.exists() {
         error!("File does not exist: {}", args.path.display());
         return Err(());
     }
 
-    let mut reader = YKBFileReader::new(ByteInput::new(File::open(&args.path).unwrap()));
-    let mut file = reader.read_file().unwrap();
+    let mut file = read_ykb_file(&args.path)?;
     let mut vm = YKVM::new();
+    vm.set_profiling(args.profile);
 
-    vm.run(&mut file)
-        .map_err(|err| error!("{}", err))
-        .map(|_| ())
+    let result = vm.run(&mut file);
+
+    // the report is written to stderr so that it does not mix with the output of the program
+    if let Some(profile) = vm.profile() {
+        match args.profile_format {
+            ProfileFormat::Table => eprint!("{}", profile.to_table(args.profile_hot_spots)),
+            ProfileFormat::Json => eprintln!("{}", profile.to_json(args.profile_hot_spots)),
+        }
+    }
+
+    match result {
+        Ok(_) => Ok(ExitCode::SUCCESS),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            Ok(ExitCode::FAILURE)
+        }
+    }
+}
+
+/// Read the bytecode file at the given path. Logs an error if the file cannot be read or is
+/// malformed.
+pub fn read_ykb_file(path: &Path) -> Result<YKBFile, ()> {
+    let input = match File::open(path) {
+        Ok(input) => input,
+        Err(err) => {
+            error!("Unable to open {}: {}", path.display(), err);
+            return Err(());
+        }
+    };
+
+    let mut reader = YKBFileReader::new(ByteInput::new(input));
+    return match reader.read_file() {
+        Ok(file) => Ok(file),
+        Err(err) => {
+            error!("Unable to read {}: {}", path.display(), err);
+            Err(())
+        }
+    };
 }